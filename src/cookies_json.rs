@@ -2,6 +2,8 @@ extern crate json;
 extern crate thirtyfour;
 extern crate urlencoding;
 
+use crate::error::Error;
+use crate::error::Result;
 use crate::i18n::gettext;
 use crate::utils::convert::ToStr;
 use crate::utils::path::get_exe_path;
@@ -16,6 +18,7 @@ use std::fs::File;
 use std::io::Read;
 use std::io::Write;
 use std::path::Path;
+use std::path::PathBuf;
 use thirtyfour::common::cookie::Cookie as TFCookie;
 
 #[derive(Debug, PartialEq)]
@@ -200,7 +203,7 @@ impl Cookie {
             Some(dm) => match obj.insert("domain", dm.as_str()) {
                 Ok(_) => {}
                 Err(_) => {
-                    println!(
+                    eprintln!(
                         "{}",
                         gettext("Can not insert domain to cookie's json object.")
                     );
@@ -213,7 +216,7 @@ impl Cookie {
             Some(p) => match obj.insert("path", p.as_str()) {
                 Ok(_) => {}
                 Err(_) => {
-                    println!(
+                    eprintln!(
                         "{}",
                         gettext("Can not insert path to cookie's json object.")
                     );
@@ -273,7 +276,7 @@ impl CookiesJar {
                 Some(obj) => match arr.push(obj) {
                     Ok(_) => {}
                     Err(_) => {
-                        println!("{}", gettext("Can not append a cookie to cookies jar."));
+                        eprintln!("{}", gettext("Can not append a cookie to cookies jar."));
                         return None;
                     }
                 },
@@ -287,7 +290,7 @@ impl CookiesJar {
             match obj.insert("cookies", arr) {
                 Ok(_) => {}
                 Err(e) => {
-                    println!("{}", e);
+                    eprintln!("{}", e);
                     return None;
                 }
             }
@@ -296,7 +299,7 @@ impl CookiesJar {
                 match ex.insert(k, v.clone()) {
                     Ok(_) => {}
                     Err(e) => {
-                        println!("{}", e);
+                        eprintln!("{}", e);
                         return None;
                     }
                 }
@@ -304,7 +307,7 @@ impl CookiesJar {
             match obj.insert("extras", ex) {
                 Ok(_) => {}
                 Err(e) => {
-                    println!("{}", e);
+                    eprintln!("{}", e);
                     return None;
                 }
             }
@@ -437,22 +440,28 @@ impl CookiesJar {
 
     /// Load from netscape cookie file
     /// * `p` - The path to file
-    pub fn from_netscape_cookie_file<P: AsRef<Path>>(p: P) -> Option<Self> {
-        let f = File::open(p);
-        if f.is_err() {
-            println!("{}", f.unwrap_err());
-            return None;
-        }
-        let mut f = f.unwrap();
+    pub fn from_netscape_cookie_file<P: AsRef<Path>>(p: P) -> Result<Self> {
+        let path = path_to_str(p.as_ref());
+        let mut f = match File::open(p.as_ref()) {
+            Ok(f) => f,
+            Err(e) => {
+                return Err(Error::io(path, e));
+            }
+        };
         let mut s = String::from("");
         match f.read_to_string(&mut s) {
             Ok(_) => {}
             Err(e) => {
-                println!("{}", e);
-                return None;
+                return Err(Error::io(path, e));
             }
         }
-        Self::from_netscape_cookie(&s)
+        match Self::from_netscape_cookie(&s) {
+            Some(j) => Ok(j),
+            None => Err(Error::Parse {
+                source: String::from(path),
+                msg: String::from(gettext("Invalid netscape cookie file.")),
+            }),
+        }
     }
 }
 
@@ -497,211 +506,132 @@ impl CookiesJson {
         self.cookies.get(key)
     }
 
-    pub fn read(&mut self, file_name: Option<String>) -> bool {
+    pub fn read(&mut self, file_name: Option<String>) -> Result<()> {
         self.cookies.clear();
-        match file_name {
-            Some(f) => {
-                let re = self.read_internal(Path::new(f.as_str()));
-                if !re {
-                    println!("{}\"{}\"", gettext("Can not load custom cookies file: "), f);
-                    self.cookies.clear();
-                }
-                re
-            }
+        let path = match file_name {
+            Some(f) => PathBuf::from(f),
             None => {
                 let re = get_exe_path();
-                match re {
-                    Some(pb) => {
-                        let mut tpb = pb;
-                        tpb.push("bili.cookies.json");
-                        let r = self.read_internal(tpb.as_path());
-                        if !r {
-                            self.cookies.clear();
-                        }
-                        r
-                    }
-                    None => false,
+                if re.is_none() {
+                    return Err(Error::Config(String::from(gettext("Can not get the path of cookies file."))));
                 }
+                let mut tpb = re.unwrap();
+                tpb.push("bili.cookies.json");
+                tpb
             }
+        };
+        let re = self.read_internal(path.as_path());
+        if re.is_err() {
+            self.cookies.clear();
         }
+        re
     }
 
-    fn read_internal(&mut self, path: &Path) -> bool {
+    fn read_internal(&mut self, path: &Path) -> Result<()> {
+        let io_err = |msg: &str| Error::Io {
+            path: String::from(path_to_str(path)),
+            msg: String::from(msg),
+        };
+        let parse_err = |msg: &str| Error::Parse {
+            source: String::from(path_to_str(path)),
+            msg: String::from(msg),
+        };
         if !path.exists() {
-            return false;
-        }
-        let re = File::open(path);
-        match re {
-            Ok(_) => {}
-            Err(_) => {
-                println!(
-                    "{}\"{}\"",
-                    gettext("Can not open cookies file: "),
-                    path_to_str(path)
-                );
-                return false;
-            }
+            return Err(io_err(gettext("Cookies file not found.")));
         }
-        let mut f = re.unwrap();
+        let mut f = match File::open(path) {
+            Ok(f) => f,
+            Err(e) => return Err(io_err(format!("{}", e).as_str())),
+        };
         let mut s = String::from("");
         let r = f.read_to_string(&mut s);
         match r {
             Ok(le) => {
                 if le == 0 {
-                    println!(
-                        "{}\"{}\"",
-                        gettext("Cookies file is empty: "),
-                        path_to_str(path)
-                    );
-                    return false;
+                    return Err(io_err(gettext("Cookies file is empty.")));
                 }
             }
-            Err(_) => {
-                println!(
-                    "{}\"{}\"",
-                    gettext("Can not read from cookies file: "),
-                    path_to_str(path)
-                );
-                return false;
-            }
-        }
-        let re = json::parse(s.as_str());
-        match re {
-            Ok(_) => {}
-            Err(_) => {
-                println!(
-                    "{}\"{}\"",
-                    gettext("Can not parse cookies file: "),
-                    path_to_str(path)
-                );
-                return false;
+            Err(e) => {
+                return Err(io_err(format!("{}", e).as_str()));
             }
         }
-        let obj = re.unwrap();
+        let obj = match json::parse(s.as_str()) {
+            Ok(obj) => obj,
+            Err(e) => return Err(parse_err(format!("{}", e).as_str())),
+        };
         if obj.is_object() == false {
-            println!(
-                "{}\"{}\"",
-                gettext("Unknown cookies file: "),
-                path_to_str(path)
-            );
-            return false;
+            return Err(parse_err(gettext("Unknown cookies file.")));
         }
         let mut ent = obj.entries();
         let mut en = ent.next();
         while !en.is_none() {
             let e = en.unwrap();
             if self.cookies.contains_key(e.0) {
-                println!(
-                    "{}\"{}\"",
-                    gettext("Cookies file contains two same keys: "),
-                    path_to_str(path)
-                );
-                return false;
+                return Err(parse_err(gettext("Cookies file contains two same keys.")));
             }
             if !e.1.is_array() {
-                println!(
-                    "{}\"{}\"",
-                    gettext("Unknown cookies file: "),
-                    path_to_str(path)
-                );
-                return false;
+                return Err(parse_err(gettext("Unknown cookies file.")));
             }
             let key = e.0;
             if key.len() == 0 {
-                println!(
-                    "{}\"{}\"",
-                    gettext("The provider name shoule not be empty in cookies file: "),
-                    path_to_str(path)
-                );
-                return false;
-            }
-            let jar = CookiesJar::from_json(&e.1);
+                return Err(parse_err(gettext("The provider name shoule not be empty in cookies file.")));
+            }
+            let jar = CookiesJar::from_json(e.1);
             if jar.is_none() {
-                println!(
-                    "{}\"{}\"",
-                    gettext("Unknown cookies file: "),
-                    path_to_str(path)
-                );
-                return false;
+                return Err(parse_err(gettext("Unknown cookies file.")));
             }
             self.add(key, jar.unwrap());
             en = ent.next();
         }
-        return true;
+        Ok(())
     }
 
-    pub fn save(&self, file_name: Option<String>) -> bool {
+    pub fn save(&self, file_name: Option<String>) -> Result<()> {
         let s = self.to_str();
-        match s {
-            Some(_) => {}
-            None => {
-                return false;
-            }
+        if s.is_none() {
+            return Err(Error::Config(String::from(gettext("Can not convert cookies to JSON."))));
         }
         let s = s.unwrap();
         match file_name {
-            Some(f) => {
-                let re = self.save_internal(s, Path::new(f.as_str()));
-                re
-            }
-            None => {
-                let re = get_exe_path();
-                match re {
-                    Some(pb) => {
-                        let mut tpb = pb;
-                        tpb.push("bili.cookies.json");
-                        let r = self.save_internal(s, tpb.as_path());
-                        r
-                    }
-                    None => false,
+            Some(f) => self.save_internal(s, Path::new(f.as_str())),
+            None => match get_exe_path() {
+                Some(pb) => {
+                    let mut tpb = pb;
+                    tpb.push("bili.cookies.json");
+                    self.save_internal(s, tpb.as_path())
                 }
-            }
+                None => Err(Error::Config(String::from(gettext("Can not get the path of cookies file.")))),
+            },
         }
     }
 
-    fn save_internal(&self, s: String, path: &Path) -> bool {
+    fn save_internal(&self, s: String, path: &Path) -> Result<()> {
+        let p = path_to_str(path);
         if path.exists() {
             match remove_file(path) {
                 Ok(_) => {}
-                Err(_) => {
-                    println!(
-                        "{}\"{}\"",
-                        gettext("Can not remove file: "),
-                        path_to_str(path)
-                    );
-                    return false;
+                Err(e) => {
+                    return Err(Error::io(p, e));
                 }
             }
         }
-        let r = File::create(path);
-        match r {
-            Ok(_) => {}
-            Err(_) => {
-                println!(
-                    "{}\"{}\"",
-                    gettext("Can not save to cookie file: "),
-                    path_to_str(path)
-                );
-                return false;
+        let mut f = match File::create(path) {
+            Ok(f) => f,
+            Err(e) => {
+                return Err(Error::io(p, e));
             }
-        }
-        let mut f = r.unwrap();
-        match f.write(s.as_bytes()) {
+        };
+        match f.write_all(s.as_bytes()) {
             Ok(_) => {}
-            Err(_) => {
-                println!(
-                    "{}\"{}\"",
-                    gettext("Can not write data to cookie file: "),
-                    path_to_str(path)
-                );
-                return false;
+            Err(e) => {
+                return Err(Error::io(p, e));
             }
         }
         match f.flush() {
             Ok(_) => {}
             Err(_) => {}
         }
-        return true;
+        Ok(())
     }
 
     pub fn to_json(&self) -> Option<JsonValue> {
@@ -716,7 +646,7 @@ impl CookiesJson {
             match r {
                 Ok(_) => {}
                 Err(_) => {
-                    println!("{}", gettext("Can not add a cookie jar to cookies object."));
+                    eprintln!("{}", gettext("Can not add a cookie jar to cookies object."));
                     return None;
                 }
             }
@@ -3,7 +3,7 @@ extern crate subprocess;
 
 use crate::utils::convert::ToStr;
 use crate::utils::number::ToUsize;
-use crate::utils::path::filter_file_name;
+use crate::utils::path::path_to_str;
 use crate::utils::size::ToSize;
 use core::time::Duration;
 use reqwest::IntoUrl;
@@ -11,6 +11,7 @@ use std::clone::Clone;
 use std::collections::HashMap;
 use std::convert::Into;
 use std::convert::TryFrom;
+use std::path::Path;
 use subprocess::ExitStatus;
 use subprocess::Popen;
 use subprocess::PopenConfig;
@@ -223,19 +224,31 @@ impl Aria2c {
         let t = format!("{}", self.max_connection_per_server);
         li.push(String::from("-x"));
         li.push(t);
-        if self.output.is_some() {
-            li.push(String::from("-o"));
-            li.push(self.output.as_ref().unwrap().clone());
+        if let Some(output) = &self.output {
+            let o = Path::new(output);
+            match (o.parent(), o.file_name()) {
+                (Some(dir), Some(name)) if !dir.as_os_str().is_empty() => {
+                    li.push(String::from("-d"));
+                    li.push(String::from(path_to_str(dir)));
+                    li.push(String::from("-o"));
+                    li.push(String::from(name.to_str().unwrap_or_default()));
+                }
+                _ => {
+                    li.push(String::from("-o"));
+                    li.push(output.clone());
+                }
+            }
         }
         li.push(String::from(url.as_str()));
         li.push(String::from("--auto-file-renaming"));
         li.push(String::from("false"));
-        println!("{:?}", &li);
+        li.push(String::from("--continue"));
+        li.push(String::from("true"));
         let r = Popen::create(&li, PopenConfig::default());
         match r {
             Ok(_) => {}
             Err(e) => {
-                println!("{}", e);
+                eprintln!("{}", e);
                 return None;
             }
         }
@@ -253,7 +266,7 @@ impl Aria2c {
                 }
             }
             Err(e) => {
-                println!("{}", e);
+                eprintln!("{}", e);
                 None
             }
         }
@@ -309,21 +322,17 @@ impl Aria2c {
     }
 
     pub fn set_output<U: ToStr>(&mut self, inp: Option<&U>) -> bool {
-        if inp.is_none() {
-            self.output = None;
-            true
-        } else {
-            let s = inp.unwrap().to_str();
-            if s.is_none() {
-                false
-            } else {
-                let f = filter_file_name(&s.unwrap());
-                if f.is_some() {
-                    self.output = f;
+        match inp {
+            Some(inp) => match inp.to_str() {
+                Some(s) => {
+                    self.output = Some(String::from(s));
                     true
-                } else {
-                    false
                 }
+                None => false,
+            },
+            None => {
+                self.output = None;
+                true
             }
         }
     }
@@ -0,0 +1,69 @@
+use crate::downloader::downloader::get_bool_option;
+use crate::error::Error;
+use crate::error::Result;
+use crate::getopt::OptStore;
+use crate::metadata::Chapter;
+use crate::metadata::VideoMetadata;
+use crate::settings::SettingStore;
+use std::fs::File;
+use std::io::Write;
+
+/// Check whether to save chapters to a standalone file. Disabled by default.
+/// * `opt` - Options
+/// * `se` - Settings
+pub fn enable_save_chapters(opt: &OptStore, se: &SettingStore) -> bool {
+    get_bool_option(opt, se, "save-chapters", false)
+}
+
+/// Convert milliseconds to `hh:mm:ss.mmm`
+/// * `ms` - Time in milliseconds
+fn format_time(ms: usize) -> String {
+    format!(
+        "{:02}:{:02}:{:02}.{:03}",
+        ms / 3600000,
+        ms / 60000 % 60,
+        ms / 1000 % 60,
+        ms % 1000
+    )
+}
+
+/// Generate a chapters file in OGM format. It is supported by mkvmerge, MP4Box and many players.
+/// * `chapters` - Chapters
+pub fn gen_chapters_file(chapters: &[Chapter]) -> String {
+    let mut s = String::new();
+    for (i, c) in chapters.iter().enumerate() {
+        s += format!("CHAPTER{:02}={}\n", i + 1, format_time(c.start)).as_str();
+        s += format!("CHAPTER{:02}NAME={}\n", i + 1, c.title.replace("\n", " ")).as_str();
+    }
+    s
+}
+
+/// Save chapters next to the video according to options and settings.
+/// The file will be saved to `<base>.chapters.txt`.
+/// * `meta` - Video metadata
+/// * `opt` - Options
+/// * `se` - Settings
+/// * `base` - The location of output file without extension
+pub fn handle_chapters(meta: &VideoMetadata, opt: &OptStore, se: &SettingStore, base: &str) -> Result<()> {
+    if meta.chapters.is_empty() || !enable_save_chapters(opt, se) {
+        return Ok(());
+    }
+    let p = format!("{}.chapters.txt", base);
+    let r = match File::create(p.as_str()) {
+        Ok(mut f) => f.write_all(gen_chapters_file(&meta.chapters).as_bytes()),
+        Err(e) => Err(e),
+    };
+    match r {
+        Ok(_) => Ok(()),
+        Err(e) => Err(Error::io(p.as_str(), e)),
+    }
+}
+
+#[test]
+fn test_gen_chapters_file() {
+    let l = vec![Chapter::new(0, 1000, "a"), Chapter::new(3723004, 3800000, "b\nc")];
+    assert_eq!(
+        "CHAPTER01=00:00:00.000\nCHAPTER01NAME=a\nCHAPTER02=01:02:03.004\nCHAPTER02NAME=b c\n",
+        gen_chapters_file(&l)
+    );
+}
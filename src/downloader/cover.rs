@@ -0,0 +1,213 @@
+use crate::downloader::aria2c::Aria2c;
+use crate::downloader::downloader::download_file;
+use crate::downloader::downloader::get_bool_option;
+use crate::downloader::ffmpeg::ffmpeg::FFmpeg;
+use crate::downloader::http::HttpDownloader;
+use crate::error::Error;
+use crate::error::Result;
+use crate::getopt::OptStore;
+use crate::i18n::gettext;
+use crate::metadata::VideoInfo;
+use crate::settings::SettingStore;
+use crate::utils::runtime::spawn_blocking;
+use std::fs::remove_file;
+use std::fs::rename;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+/// Detect image type from the header of file.
+/// * `data` - The first bytes of file
+///
+/// Returns the extension of image type.
+fn detect_image_type(data: &[u8]) -> Option<&'static str> {
+    if data.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        Some("jpg")
+    } else if data.starts_with(&[0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A]) {
+        Some("png")
+    } else if data.len() >= 12 && &data[0..4] == b"RIFF" && &data[8..12] == b"WEBP" {
+        Some("webp")
+    } else if data.starts_with(b"GIF8") {
+        Some("gif")
+    } else {
+        None
+    }
+}
+
+/// Detect image type of a file.
+/// * `path` - The location of file
+///
+/// Returns None if the type is unknown.
+fn detect_image_file_type(path: &str) -> Result<Option<&'static str>> {
+    let mut buf = [0u8; 16];
+    let r = match File::open(path) {
+        Ok(mut f) => f.read(&mut buf),
+        Err(e) => Err(e),
+    };
+    match r {
+        Ok(n) => Ok(detect_image_type(&buf[..n])),
+        Err(e) => Err(Error::io(path, e)),
+    }
+}
+
+/// Check whether to save cover next to the video. Disabled by default.
+/// * `opt` - Options
+/// * `se` - Settings
+pub fn enable_save_cover(opt: &OptStore, se: &SettingStore) -> bool {
+    get_bool_option(opt, se, "save-cover", false)
+}
+
+/// Check whether to embed cover to output file. Disabled by default.
+/// * `opt` - Options
+/// * `se` - Settings
+pub fn enable_embed_cover(opt: &OptStore, se: &SettingStore) -> bool {
+    get_bool_option(opt, se, "embed-cover", false)
+}
+
+/// Remove a file and ignore errors.
+fn remove_temp_file(p: &str) {
+    let _ = remove_file(p);
+}
+
+/// Download the cover of a video.
+/// * `vi` - Video information
+/// * `a2` - Aria2c interface
+/// * `hd` - Built-in HTTP downloader
+/// * `base` - The location of output file without extension
+/// * `save` - Whether to keep the cover. If false, the cover is saved to a temporary file.
+///
+/// Returns the location of cover and its type.
+async fn download_cover(
+    vi: &VideoInfo,
+    a2: Option<&mut Aria2c>,
+    hd: Option<&mut HttpDownloader>,
+    base: &str,
+    save: bool,
+) -> Result<(String, &'static str)> {
+    let url = vi.cover.as_ref().unwrap();
+    let tmp = format!("{}.cover.tmp", base);
+    if Path::new(tmp.as_str()).exists() {
+        remove_temp_file(tmp.as_str());
+    }
+    if let Err(e) = download_file(vi, a2, hd, url.as_str(), tmp.as_str()).await {
+        remove_temp_file(tmp.as_str());
+        return Err(e);
+    }
+    let typ = match detect_image_file_type(tmp.as_str()) {
+        Ok(Some(t)) => t,
+        Ok(None) => {
+            remove_temp_file(tmp.as_str());
+            return Err(Error::Download(String::from(gettext("Unknown cover image type."))));
+        }
+        Err(e) => {
+            remove_temp_file(tmp.as_str());
+            return Err(e);
+        }
+    };
+    let p = if save {
+        format!("{}.{}", base, typ)
+    } else {
+        format!("{}.cover.{}", base, typ)
+    };
+    match rename(tmp.as_str(), p.as_str()) {
+        Ok(_) => Ok((p, typ)),
+        Err(e) => {
+            remove_temp_file(tmp.as_str());
+            Err(Error::io(tmp.as_str(), e))
+        }
+    }
+}
+
+/// Save cover next to the video and/or embed cover to the output file according to options and settings.
+/// * `vi` - Video information
+/// * `opt` - Options
+/// * `se` - Settings
+/// * `a2` - Aria2c interface
+/// * `hd` - Built-in HTTP downloader
+/// * `ff` - Ffmpeg interface
+/// * `base` - The location of output file without extension
+/// * `output` - The location of output file
+#[allow(clippy::too_many_arguments)]
+pub async fn handle_cover(
+    vi: &VideoInfo,
+    opt: &OptStore,
+    se: &SettingStore,
+    a2: Option<&mut Aria2c>,
+    hd: Option<&mut HttpDownloader>,
+    ff: Option<&FFmpeg>,
+    base: &str,
+    output: &str,
+) -> Result<()> {
+    if vi.cover.is_none() {
+        return Ok(());
+    }
+    let save = enable_save_cover(opt, se);
+    let embed = enable_embed_cover(opt, se);
+    if !save && !embed {
+        return Ok(());
+    }
+    let ext = match output.rfind('.') {
+        Some(i) => output[i + 1..].to_lowercase(),
+        None => String::from(""),
+    };
+    let mp4 = ext == "mp4" || ext == "m4a" || ext == "m4v" || ext == "mov";
+    let mkv = ext == "mkv" || ext == "mka";
+    let mp3 = ext == "mp3";
+    let embed = if embed && !mp4 && !mkv && !mp3 {
+        eprintln!("{}", gettext("Embedding cover is not supported by this container."));
+        false
+    } else if embed && ff.is_none() {
+        eprintln!("{}", gettext("Ffmpeg is not available. Cover will not be embedded."));
+        false
+    } else {
+        embed
+    };
+    if !save && !embed {
+        return Ok(());
+    }
+    let (cover, typ) = download_cover(vi, a2, hd, base, save).await?;
+    if !embed {
+        return Ok(());
+    }
+    let ff = ff.unwrap().clone();
+    let mut temp_files: Vec<String> = Vec::new();
+    if !save {
+        temp_files.push(cover.clone());
+    }
+    // Attached picture in mp4 container and ID3 tag must be JPEG or PNG.
+    let cover = if (mp4 || mp3) && typ != "jpg" && typ != "png" {
+        let p = format!("{}.cover.conv.jpg", base);
+        let (f, i, o) = (ff.clone(), cover.clone(), p.clone());
+        if !spawn_blocking(move || f.convert_image(i.as_str(), o.as_str())).await {
+            for f in temp_files.iter() {
+                remove_temp_file(f.as_str());
+            }
+            return Err(Error::Download(String::from(gettext("Can not convert cover to JPEG."))));
+        }
+        temp_files.push(p.clone());
+        p
+    } else {
+        cover
+    };
+    let tmp = format!("{}.embed.{}", base, ext);
+    let (i, o) = (String::from(output), tmp.clone());
+    let r = spawn_blocking(move || ff.embed_cover(i.as_str(), cover.as_str(), o.as_str())).await;
+    for f in temp_files.iter() {
+        remove_temp_file(f.as_str());
+    }
+    if !r {
+        remove_temp_file(tmp.as_str());
+        return Err(Error::Download(String::from(gettext("Can not embed cover to output file."))));
+    }
+    match rename(tmp.as_str(), output) {
+        Ok(_) => Ok(()),
+        Err(e) => Err(Error::io(tmp.as_str(), e)),
+    }
+}
+
+#[test]
+fn test_detect_image_type() {
+    assert_eq!(Some("jpg"), detect_image_type(&[0xFF, 0xD8, 0xFF, 0xE0]));
+    assert_eq!(Some("webp"), detect_image_type(b"RIFF\x10\x00\x00\x00WEBPVP8 "));
+    assert_eq!(None, detect_image_type(b"<html>"));
+}
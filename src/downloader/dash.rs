@@ -0,0 +1,176 @@
+use crate::downloader::aria2c::Aria2c;
+use crate::downloader::chapters::handle_chapters;
+use crate::downloader::cover::handle_cover;
+use crate::downloader::downloader::download_file;
+use crate::downloader::downloader::enable_add_metadata;
+use crate::downloader::downloader::finish_temp_output;
+use crate::downloader::downloader::is_downloaded;
+use crate::downloader::downloader::temp_output_name;
+use crate::downloader::downloader::Downloader;
+use crate::downloader::downloader::DownloaderType;
+use crate::downloader::downloader::VideoDownloader;
+use crate::downloader::ffmpeg::ffmetafile::FFMetaFile;
+use crate::downloader::ffmpeg::ffmpeg::FFmpeg;
+use crate::downloader::http::HttpDownloader;
+use crate::downloader::output::get_container_ext;
+use crate::downloader::output::get_output_base;
+use crate::downloader::subtitle::handle_subtitles;
+use crate::getopt::OptStore;
+use crate::error::Error;
+use crate::error::Result;
+use crate::i18n::gettext;
+use crate::metadata::VideoInfo;
+use crate::metadata::VideoPlayInfoType;
+use crate::settings::SettingStore;
+use crate::utils::runtime::spawn_blocking;
+use async_trait::async_trait;
+use std::clone::Clone;
+use std::fs::remove_file;
+
+/// A downloader for DASH streams. It will download video stream and audio stream, and then merge them by using ffmpeg.
+#[derive(Clone)]
+pub struct DashDownloader {
+    /// Extract Information
+    vi: VideoInfo,
+    /// Options
+    opt: OptStore,
+    /// Settings
+    se: SettingStore,
+    /// Aria2c interface
+    a2: Option<Aria2c>,
+    /// Built-in HTTP downloader
+    hd: Option<HttpDownloader>,
+    /// Ffmpeg interface
+    ff: Option<FFmpeg>,
+}
+
+impl DashDownloader {
+    pub fn new(
+        vi: &VideoInfo,
+        opt: &OptStore,
+        se: &SettingStore,
+        a2: Option<&Aria2c>,
+        hd: Option<&HttpDownloader>,
+        ff: Option<&FFmpeg>,
+    ) -> Self {
+        let a2 = a2.cloned();
+        let hd = hd.cloned();
+        let ff = ff.cloned();
+        Self {
+            vi: vi.clone(),
+            opt: opt.clone(),
+            se: se.clone(),
+            a2,
+            hd,
+            ff,
+        }
+    }
+
+    /// Download a stream
+    /// * `url` - The url of stream
+    /// * `output` - Output file name
+    async fn download_stream(&mut self, url: &str, output: &str) -> Result<()> {
+        download_file(
+            &self.vi,
+            self.a2.as_mut(),
+            self.hd.as_mut(),
+            url,
+            output,
+        )
+        .await
+    }
+}
+
+impl DashDownloader {
+    /// Download the video and handle the output.
+    async fn download_video(&mut self) -> Result<()> {
+        if self.ff.is_none() {
+            return Err(Error::Download(String::from(gettext("Ffmpeg is needed to merge DASH streams."))));
+        }
+        let base = get_output_base(&self.vi.meta, &self.opt, &self.se);
+        if base.is_none() {
+            return Err(Error::Config(String::from(gettext("Can not generate output file name."))));
+        }
+        let base = base.unwrap();
+        let ext = get_container_ext(&self.vi, &self.opt, &self.se)?;
+        let output = format!("{}.{}", base, ext);
+        if is_downloaded(output.as_str()) {
+            eprintln!("{}\"{}\"", gettext("File already downloaded: "), output);
+            return Ok(());
+        }
+        let video_url = self.vi.video_url.as_ref().unwrap().clone();
+        let video = format!("{}.video.m4s", base);
+        self.download_stream(&video_url, &video).await?;
+        let mut audio: Option<String> = None;
+        if self.vi.audio_url.is_some() {
+            let audio_url = self.vi.audio_url.as_ref().unwrap().clone();
+            let a = format!("{}.audio.m4s", base);
+            self.download_stream(&audio_url, &a).await?;
+            audio = Some(a);
+        }
+        let meta = if enable_add_metadata(&self.opt, &self.se) {
+            Some(FFMetaFile::from_video_metadata(&self.vi.meta))
+        } else {
+            None
+        };
+        let ff = self.ff.as_ref().unwrap().clone();
+        let tmp = temp_output_name(base.as_str(), ext);
+        let (v, a, t) = (video.clone(), audio.clone(), tmp.clone());
+        let r = spawn_blocking(move || {
+            let a = a.as_deref();
+            ff.merge_dash(v.as_str(), a, meta.as_ref(), t.as_str())
+        })
+        .await;
+        if !r {
+            return Err(Error::Download(String::from(gettext("Can not merge video stream and audio stream."))));
+        }
+        handle_chapters(&self.vi.meta, &self.opt, &self.se, base.as_str())?;
+        handle_subtitles(
+            &self.vi,
+            &self.opt,
+            &self.se,
+            self.ff.as_ref(),
+            base.as_str(),
+            tmp.as_str(),
+        )
+        .await?;
+        handle_cover(
+            &self.vi,
+            &self.opt,
+            &self.se,
+            self.a2.as_mut(),
+            self.hd.as_mut(),
+            self.ff.as_ref(),
+            base.as_str(),
+            tmp.as_str(),
+        )
+        .await?;
+        finish_temp_output(tmp.as_str(), output.as_str())?;
+        let _ = remove_file(video.as_str());
+        if let Some(audio) = audio {
+            let _ = remove_file(audio);
+        }
+        Ok(())
+    }
+
+}
+
+#[async_trait]
+impl Downloader for DashDownloader {
+    async fn download_async(&mut self) -> Result<()> {
+        self.download_video().await
+    }
+
+    fn typ() -> DownloaderType {
+        DownloaderType::Video
+    }
+}
+
+impl VideoDownloader for DashDownloader {
+    fn match_vi(vi: &VideoInfo) -> bool {
+        if vi.typ == VideoPlayInfoType::Dash {
+            return true;
+        }
+        false
+    }
+}
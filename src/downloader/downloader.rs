@@ -1,12 +1,31 @@
 use crate::downloader::aria2c::Aria2c;
+use crate::downloader::dash::DashDownloader;
+use crate::downloader::ffmpeg::ffmpeg::FFmpeg;
+use crate::downloader::http::HttpDownloader;
+use crate::downloader::live::LiveDownloader;
+use crate::downloader::state::state_file_name;
+use crate::downloader::segments::SegmentsDownloader;
 use crate::downloader::single::SignleUrlDownloader;
+use crate::error::Error;
+use crate::error::Result;
 use crate::getopt::OptStore;
+use crate::http_client::gen_cookie_header;
 use crate::i18n::gettext;
 use crate::metadata::ExtractInfo;
 use crate::metadata::InfoType;
 use crate::metadata::VideoInfo;
 use crate::settings::SettingStore;
+use crate::utils::headers::header_map_to_hash_map;
+use crate::utils::jobs::get_jobs;
+use crate::utils::runtime::block_on;
+use crate::utils::runtime::spawn_blocking;
+use async_trait::async_trait;
+use futures::stream;
+use json::JsonValue;
+use futures::StreamExt;
 use std::clone::Clone;
+use std::collections::HashMap;
+use std::path::Path;
 
 /// Downloader Type
 pub enum DownloaderType {
@@ -14,9 +33,16 @@ pub enum DownloaderType {
 }
 
 /// Downloader interface
-pub trait Downloader {
-    /// Perform download
-    fn download(&mut self) -> bool;
+#[async_trait]
+pub trait Downloader: Send {
+    /// Perform download.
+    /// It runs [`download_async`](#tymethod.download_async) to completion.
+    /// It can be called in or outside a tokio runtime.
+    fn download(&mut self) -> Result<()> {
+        block_on(self.download_async())
+    }
+    /// Perform download asynchronously.
+    async fn download_async(&mut self) -> Result<()>;
     fn typ() -> DownloaderType;
 }
 
@@ -26,6 +52,145 @@ pub trait VideoDownloader {
     fn match_vi(vi: &VideoInfo) -> bool;
 }
 
+/// Add headers and cookies in video information to a header list.
+/// * `vi` - Video information
+/// * `url` - URL
+/// * `headers` - Header list
+fn add_headers(vi: &VideoInfo, url: &str, headers: &mut HashMap<String, String>) {
+    if let Some(h) = &vi.headers {
+        header_map_to_hash_map(h, headers);
+    }
+    if let Some(cookies) = &vi.cookies {
+        let c = gen_cookie_header(cookies, url);
+        if !c.is_empty() {
+            headers.insert(String::from("cookie"), c);
+        }
+    }
+}
+
+/// Check whether a file is already downloaded completely.
+/// A file is unfinished if the control file of aria2c or the state file of built-in downloader exists.
+/// * `output` - The location of output file
+pub fn is_downloaded(output: &str) -> bool {
+    if !Path::new(output).exists() {
+        return false;
+    }
+    if Path::new(format!("{}.aria2", output).as_str()).exists() {
+        return false;
+    }
+    if Path::new(state_file_name(output).as_str()).exists() {
+        return false;
+    }
+    true
+}
+
+/// Get the location of temporary output file. The output is written and post-processed (metadata, subtitles, cover)
+/// in it first, so an interrupted run will not leave an unfinished output file.
+/// * `base` - The location of output file without extension
+/// * `ext` - Extension
+pub fn temp_output_name(base: &str, ext: &str) -> String {
+    format!("{}.tmp.{}", base, ext)
+}
+
+/// Rename the temporary output file to the output file.
+/// * `tmp` - The location of temporary output file
+/// * `output` - The location of output file
+pub fn finish_temp_output(tmp: &str, output: &str) -> Result<()> {
+    match std::fs::rename(tmp, output) {
+        Ok(_) => Ok(()),
+        Err(e) => Err(Error::io(tmp, e)),
+    }
+}
+
+/// Download a file. Aria2c will be used if available, otherwise built-in downloader will be used.
+/// If the file is already downloaded, it will be skipped. Unfinished file will be resumed.
+/// * `vi` - Video information which contains headers and cookies
+/// * `a2` - Aria2c interface
+/// * `hd` - Built-in HTTP downloader
+/// * `url` - URL
+/// * `output` - Output file name
+pub async fn download_file(
+    vi: &VideoInfo,
+    a2: Option<&mut Aria2c>,
+    hd: Option<&mut HttpDownloader>,
+    url: &str,
+    output: &str,
+) -> Result<()> {
+    if is_downloaded(output) {
+        eprintln!("{}\"{}\"", gettext("File already downloaded: "), output);
+        return Ok(());
+    }
+    if let Some(a2) = a2 {
+        add_headers(vi, url, &mut a2.headers);
+        if !a2.set_output(Some(&output)) {
+            return Err(Error::Config(String::from(gettext("Can not set output file name."))));
+        }
+        // Aria2c blocks the current thread until download is completed.
+        let mut a2 = a2.clone();
+        let u = String::from(url);
+        let r = spawn_blocking(move || a2.download(&u)).await;
+        if r.is_none() {
+            return Err(Error::Download(String::from(gettext("Can not run aria2c."))));
+        }
+        let code = r.unwrap();
+        if code != 0 {
+            let s = gettext("Aria2c exited with code <code> when downloading \"<url>\".")
+                .replace("<code>", format!("{}", code).as_str())
+                .replace("<url>", url);
+            return Err(Error::Download(s));
+        }
+        return Ok(());
+    }
+    if let Some(hd) = hd {
+        add_headers(vi, url, &mut hd.headers);
+        if !hd.set_output(Some(&output)) {
+            return Err(Error::Config(String::from(gettext("Can not set output file name."))));
+        }
+        return hd.download_async(url).await;
+    }
+    Err(Error::Download(String::from(gettext("No available downloader."))))
+}
+
+/// Read a boolean option or a setting in `basic` group. The option is used if both of them exist.
+/// * `opt` - Options
+/// * `se` - Settings
+/// * `key` - The name of option and setting
+/// * `default` - The value used if neither of them exists
+pub fn get_bool_option(opt: &OptStore, se: &SettingStore, key: &str, default: bool) -> bool {
+    match opt.get_option_as_bool(key) {
+        Some(b) => b,
+        None => se.get_settings_as_bool("basic", key).unwrap_or(default),
+    }
+}
+
+/// Check whether to add metadata to output file. Enabled by default.
+/// * `opt` - Options
+/// * `se` - Settings
+pub fn enable_add_metadata(opt: &OptStore, se: &SettingStore) -> bool {
+    get_bool_option(opt, se, "add-metadata", true)
+}
+
+/// Apply an option or a setting in `basic` group to aria2c or built-in downloader.
+/// The option is used if both of them exist.
+/// * `opt` - Options
+/// * `se` - Settings
+/// * `key` - The name of option and setting
+/// * `msg` - The translated message of error when the value is invalid
+/// * `set` - Setter. It returns false if the value is invalid.
+fn apply_setting<F>(opt: &OptStore, se: &SettingStore, key: &str, msg: &str, mut set: F) -> Result<()>
+where
+    F: FnMut(&JsonValue) -> bool,
+{
+    let v = match opt.get_option(key) {
+        Some(o) => Some(JsonValue::from(o)),
+        None => se.get_settings("basic", key),
+    };
+    if v.is_some() && !set(v.as_ref().unwrap()) {
+        return Err(Error::Config(format!("{}{}", gettext("Can not set settings: "), msg)));
+    }
+    Ok(())
+}
+
 /// Main downloader
 pub struct MDownloader {
     /// Settings
@@ -36,127 +201,74 @@ pub struct MDownloader {
     ei: ExtractInfo,
     /// Aria2c interface
     a2: Option<Aria2c>,
+    /// Ffmpeg interface
+    ff: Option<FFmpeg>,
+    /// Built-in HTTP downloader
+    hd: Option<HttpDownloader>,
 }
 
 impl MDownloader {
-    /// Create a new downloader
-    /// # Panic
-    /// If some settings not available, will be panic
-    pub fn new(se: &SettingStore, opt: &OptStore, ei: &ExtractInfo) -> Self {
+    /// Create a new downloader.
+    /// Return an error if the options or settings of aria2c or built-in downloader are invalid.
+    pub fn new(se: &SettingStore, opt: &OptStore, ei: &ExtractInfo) -> Result<Self> {
         let mut t = Self {
             se: se.clone(),
             opt: opt.clone(),
             ei: ei.clone(),
             a2: None,
+            ff: FFmpeg::new(None),
+            hd: HttpDownloader::new(),
         };
         if t.enable_arai2c() {
             t.a2 = Aria2c::new(None);
-            let r = t.opt.get_option_as_size("aria2c-min-split-size");
-            let r2 = t.se.get_settings("basic", "aria2c-min-split-size");
-            if r.is_some() {
-                if !t.a2.as_mut().unwrap().set_min_split_size(&r.unwrap()) {
-                    panic!(
-                        "{}{}",
-                        gettext("Can not set settings: "),
-                        gettext("aria2c-min-split-size should be 1048576(1MiB)-1073741824(1GiB).")
-                    );
-                }
-            } else if r2.is_some() {
-                if !t
-                    .a2
-                    .as_mut()
-                    .unwrap()
-                    .set_min_split_size(r2.as_ref().unwrap())
-                {
-                    panic!(
-                        "{}{}",
-                        gettext("Can not set settings: "),
-                        gettext("aria2c-min-split-size should be 1048576(1MiB)-1073741824(1GiB).")
-                    );
-                }
-            }
-            let r = t.opt.get_option_as_usize("aria2c-split");
-            let r2 = t.se.get_settings("basic", "aria2c-split");
-            if r.is_some() {
-                if !t.a2.as_mut().unwrap().set_split(r.as_ref().unwrap()) {
-                    panic!(
-                        "{}{}",
-                        gettext("Can not set settings: "),
-                        gettext("aria2c-split should be 1-*.")
-                    );
-                }
-            } else if r2.is_some() {
-                if !t.a2.as_mut().unwrap().set_split(r2.as_ref().unwrap()) {
-                    panic!(
-                        "{}{}",
-                        gettext("Can not set settings: "),
-                        gettext("aria2c-split should be 1-*.")
-                    );
-                }
-            }
-            let r = t.opt.get_option("aria2c-file-allocation");
-            let r2 = t.se.get_settings("basic", "aria2c-file-allocation");
-            if r.is_some() {
-                if !t
-                    .a2
-                    .as_mut()
-                    .unwrap()
-                    .set_file_allocation(r.as_ref().unwrap())
-                {
-                    panic!("{}{}", gettext("Can not set settings: "), gettext("The possible value of aria2c-file-allocation are none, prealloc, trunc, falloc."));
-                }
-            }
-            if r2.is_some() {
-                if !t
-                    .a2
-                    .as_mut()
-                    .unwrap()
-                    .set_file_allocation(r2.as_ref().unwrap())
-                {
-                    panic!("{}{}", gettext("Can not set settings: "), gettext("The possible value of aria2c-file-allocation are none, prealloc, trunc, falloc."));
-                }
-            }
-            let r = t
-                .opt
-                .get_option_as_usize("aria2c-max-connection-per-server");
-            let r2 =
-                t.se.get_settings("basic", "aria2c-max-connection-per-server");
-            if r.is_some() {
-                if !t
-                    .a2
-                    .as_mut()
-                    .unwrap()
-                    .set_max_connection_per_server(r.as_ref().unwrap())
-                {
-                    panic!(
-                        "{}{}",
-                        gettext("Can not set settings: "),
-                        gettext("aria2c-max-connection-per-server should be 1-*.")
-                    );
-                }
-            }
-            if r2.is_some() {
-                if !t
-                    .a2
-                    .as_mut()
-                    .unwrap()
-                    .set_max_connection_per_server(r2.as_ref().unwrap())
-                {
-                    panic!(
-                        "{}{}",
-                        gettext("Can not set settings: "),
-                        gettext("aria2c-max-connection-per-server should be 1-*.")
-                    );
-                }
+            if t.a2.is_none() {
+                eprintln!("{}", gettext("Aria2c is not available. Built-in downloader will be used."));
             }
         }
-        t
+        if let Some(a2) = &mut t.a2 {
+            apply_setting(
+                opt,
+                se,
+                "aria2c-min-split-size",
+                gettext("aria2c-min-split-size should be 1048576(1MiB)-1073741824(1GiB)."),
+                |v| a2.set_min_split_size(v),
+            )?;
+            apply_setting(opt, se, "aria2c-split", gettext("aria2c-split should be 1-*."), |v| {
+                a2.set_split(v)
+            })?;
+            apply_setting(
+                opt,
+                se,
+                "aria2c-file-allocation",
+                gettext("The possible value of aria2c-file-allocation are none, prealloc, trunc, falloc."),
+                |v| a2.set_file_allocation(v),
+            )?;
+            apply_setting(
+                opt,
+                se,
+                "aria2c-max-connection-per-server",
+                gettext("aria2c-max-connection-per-server should be 1-*."),
+                |v| a2.set_max_connection_per_server(v),
+            )?;
+        } else if let Some(hd) = &mut t.hd {
+            apply_setting(
+                opt,
+                se,
+                "aria2c-min-split-size",
+                gettext("aria2c-min-split-size should be 1048576(1MiB)-1073741824(1GiB)."),
+                |v| hd.set_min_split_size(v),
+            )?;
+            apply_setting(opt, se, "aria2c-split", gettext("aria2c-split should be 1-*."), |v| {
+                hd.set_split(v)
+            })?;
+        }
+        Ok(t)
     }
 
-    /// Preform download
+    /// Preform download asynchronously
     /// * `d` - Downloader
-    fn download(&self, d: &mut impl Downloader) -> bool {
-        d.download()
+    async fn download_async(&self, d: &mut impl Downloader) -> Result<()> {
+        d.download_async().await
     }
 
     /// Check whether to enable aria2c
@@ -172,35 +284,68 @@ impl MDownloader {
         true
     }
 
-    /// Match a suitable Video Downloader
+    /// Match a suitable Video Downloader and download asynchronously
     /// * `vi` - Video information
-    fn match_vi(&self, vi: &VideoInfo) -> bool {
+    async fn match_vi_async(&self, vi: &VideoInfo) -> Result<()> {
         if SignleUrlDownloader::match_vi(vi) {
-            return self.download(&mut SignleUrlDownloader::new(
+            return self.download_async(&mut SignleUrlDownloader::new(
+                vi,
+                &self.opt,
+                &self.se,
+                self.a2.as_ref(),
+                self.hd.as_ref(),
+                self.ff.as_ref(),
+            )).await;
+        }
+        if DashDownloader::match_vi(vi) {
+            return self.download_async(&mut DashDownloader::new(
                 vi,
                 &self.opt,
                 &self.se,
                 self.a2.as_ref(),
-            ));
+                self.hd.as_ref(),
+                self.ff.as_ref(),
+            )).await;
+        }
+        if SegmentsDownloader::match_vi(vi) {
+            return self.download_async(&mut SegmentsDownloader::new(
+                vi,
+                &self.opt,
+                &self.se,
+                self.a2.as_ref(),
+                self.hd.as_ref(),
+                self.ff.as_ref(),
+            )).await;
+        }
+        if LiveDownloader::match_vi(vi) {
+            return self.download_async(&mut LiveDownloader::new(vi, &self.opt, &self.se)).await;
         }
-        println!("{}", gettext("Can not find a suitable video downloader"));
-        false
+        Err(Error::Download(String::from(gettext("Can not find a suitable video downloader"))))
     }
 
-    /// Run downloader
-    pub fn run(&self) -> bool {
+    /// Run downloader asynchronously. At most `jobs` videos are downloaded at the same time.
+    pub async fn run_async(&self) -> Result<()> {
         if self.ei.typ == InfoType::Video {
-            return self.match_vi(self.ei.video.as_ref().unwrap());
+            return self.match_vi_async(self.ei.video.as_ref().unwrap()).await;
         } else if self.ei.typ == InfoType::VideoList {
-            for vi in self.ei.videos.as_ref().unwrap().iter() {
-                let r = self.match_vi(vi);
-                if !r {
-                    return false;
+            let jobs = get_jobs(&self.opt, &self.se);
+            let tasks = self.ei.videos.as_ref().unwrap().iter().map(|vi| {
+                let d = self.clone();
+                let vi = vi.clone();
+                tokio::spawn(async move { d.match_vi_async(&vi).await })
+            });
+            let mut re = stream::iter(tasks).buffered(jobs);
+            while let Some(r) = re.next().await {
+                match r {
+                    Ok(r) => r?,
+                    Err(e) => {
+                        return Err(Error::Download(format!("{}", e)));
+                    }
                 }
             }
-            return true;
+            return Ok(());
         }
-        false
+        Err(Error::Extract(String::from(gettext("Extract informtaion is invalid."))))
     }
 }
 
@@ -211,6 +356,8 @@ impl Clone for MDownloader {
             opt: self.opt.clone(),
             ei: self.ei.clone(),
             a2: self.a2.clone(),
+            ff: self.ff.clone(),
+            hd: self.hd.clone(),
         }
     }
 }
@@ -1,19 +1,85 @@
+extern crate chrono;
+
+use crate::metadata::VideoMetadata;
 #[cfg(test)]
 use crate::testutils::memory_file::MemoryFile;
+use chrono::SecondsFormat;
 use std::clone::Clone;
 use std::collections::HashMap;
 use std::default::Default;
 use std::error::Error;
 use std::io::Write;
 
+#[derive(Debug)]
+/// A map which keeps insertion order. So the output file is always the same.
+pub struct OrderedMap<V> {
+    list: Vec<(String, V)>,
+}
+
+impl<V> OrderedMap<V> {
+    pub fn new() -> Self {
+        Self { list: Vec::new() }
+    }
+
+    /// Insert a value. If the key already exists, the value will be replaced and the order is not changed.
+    pub fn insert<T: Into<V>>(&mut self, key: String, value: T) {
+        let value = value.into();
+        for i in self.list.iter_mut() {
+            if i.0 == key {
+                i.1 = value;
+                return;
+            }
+        }
+        self.list.push((key, value));
+    }
+
+    pub fn get(&self, key: &str) -> Option<&V> {
+        for (k, v) in self.list.iter() {
+            if k == key {
+                return Some(v);
+            }
+        }
+        None
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, (String, V)> {
+        self.list.iter()
+    }
+}
+
+impl<V> Default for OrderedMap<V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<V> From<HashMap<String, V>> for OrderedMap<V> {
+    /// Convert from a `HashMap`. The keys are sorted so the order is always the same.
+    fn from(m: HashMap<String, V>) -> Self {
+        let mut list: Vec<(String, V)> = m.into_iter().collect();
+        list.sort_by(|a, b| a.0.cmp(&b.0));
+        Self { list }
+    }
+}
+
+impl<V: Clone> Clone for OrderedMap<V> {
+    fn clone(&self) -> Self {
+        Self {
+            list: self.list.clone(),
+        }
+    }
+}
+
 #[derive(Debug)]
 /// ffmpeg metadata file
 /// See [here](https://ffmpeg.org/ffmpeg-formats.html#Metadata-1) for more information.
 pub struct FFMetaFile {
     /// Basic metadata
-    pub basic: HashMap<String, String>,
+    pub basic: OrderedMap<String>,
     /// Metadata in specify section
-    pub extra: HashMap<String, HashMap<String, String>>,
+    pub extra: OrderedMap<OrderedMap<String>>,
+    /// Chapters. Every chapter is saved as a `[CHAPTER]` section.
+    pub chapters: Vec<OrderedMap<String>>,
 }
 
 /// Escape key and value
@@ -28,29 +94,92 @@ fn escape(s: &str) -> String {
 impl FFMetaFile {
     pub fn new() -> Self {
         Self {
-            basic: HashMap::new(),
-            extra: HashMap::new(),
+            basic: OrderedMap::new(),
+            extra: OrderedMap::new(),
+            chapters: Vec::new(),
         }
     }
+
+    /// Convert video metadata to ffmpeg metadata. The keys are the keys used by mp4 container.
+    /// * `m` - Video metadata
+    pub fn from_video_metadata(m: &VideoMetadata) -> Self {
+        let mut f = Self::new();
+        if let Some(title) = &m.title {
+            f.basic.insert(String::from("title"), title.clone());
+        }
+        if let Some(comment) = m.description.as_ref().or(m.comment.as_ref()) {
+            f.basic.insert(String::from("comment"), comment.clone());
+        }
+        if let Some(author) = &m.author {
+            f.basic.insert(String::from("artist"), author.clone());
+        }
+        if let Some(album) = &m.album {
+            f.basic.insert(String::from("album"), album.clone());
+        }
+        if let Some(album_artist) = &m.album_artist {
+            f.basic.insert(String::from("album_artist"), album_artist.clone());
+        }
+        if let Some(t) = &m.track {
+            f.basic.insert(String::from("track"), format!("{}/{}", t.no(), t.total()));
+        }
+        if let Some(date) = &m.date {
+            f.basic.insert(String::from("date"), date.to_rfc3339_opts(SecondsFormat::Secs, true));
+        }
+        if !m.tags.is_empty() {
+            f.basic.insert(String::from("genre"), m.tags.join(", "));
+        }
+        if let Some(video_id) = &m.video_id {
+            f.basic.insert(
+                String::from("episode_id"),
+                video_id.clone(),
+            );
+        }
+        if let Some(lyrics) = &m.lyrics {
+            f.basic
+                .insert(String::from("lyrics"), lyrics.clone());
+        }
+        let mut keys: Vec<&String> = m.extra.keys().collect();
+        keys.sort();
+        for k in keys {
+            if f.basic.get(k).is_none() {
+                f.basic.insert(k.clone(), m.extra.get(k).unwrap().clone());
+            }
+        }
+        for c in m.chapters.iter() {
+            let mut s = OrderedMap::new();
+            s.insert(String::from("TIMEBASE"), String::from("1/1000"));
+            s.insert(String::from("START"), format!("{}", c.start));
+            s.insert(String::from("END"), format!("{}", c.end));
+            s.insert(String::from("title"), c.title.clone());
+            f.chapters.push(s);
+        }
+        f
+    }
+
     /// Save file
     pub fn save<U: Write>(&self, f: &mut U) -> Result<(), Box<dyn Error>> {
-        f.write(";FFMETADATA1\n".as_bytes())?;
-        for (k, v) in self.basic.iter() {
-            f.write(escape(k).as_bytes())?;
-            f.write("=".as_bytes())?;
-            f.write(escape(v).as_bytes())?;
-            f.write("\n".as_bytes())?;
-        }
+        f.write_all(";FFMETADATA1\n".as_bytes())?;
+        Self::write_map(f, &self.basic)?;
         for (s, v) in self.extra.iter() {
-            f.write("[".as_bytes())?;
-            f.write(s.as_bytes())?;
-            f.write("]\n".as_bytes())?;
-            for (k, v) in v.iter() {
-                f.write(escape(k).as_bytes())?;
-                f.write("=".as_bytes())?;
-                f.write(escape(v).as_bytes())?;
-                f.write("\n".as_bytes())?;
-            }
+            f.write_all("[".as_bytes())?;
+            f.write_all(s.as_bytes())?;
+            f.write_all("]\n".as_bytes())?;
+            Self::write_map(f, v)?;
+        }
+        for c in self.chapters.iter() {
+            f.write_all("[CHAPTER]\n".as_bytes())?;
+            Self::write_map(f, c)?;
+        }
+        Ok(())
+    }
+
+    /// Write key-value pairs
+    fn write_map<U: Write>(f: &mut U, m: &OrderedMap<String>) -> Result<(), Box<dyn Error>> {
+        for (k, v) in m.iter() {
+            f.write_all(escape(k).as_bytes())?;
+            f.write_all("=".as_bytes())?;
+            f.write_all(escape(v).as_bytes())?;
+            f.write_all("\n".as_bytes())?;
         }
         Ok(())
     }
@@ -61,6 +190,7 @@ impl Clone for FFMetaFile {
         Self {
             basic: self.basic.clone(),
             extra: self.extra.clone(),
+            chapters: self.chapters.clone(),
         }
     }
 }
@@ -68,8 +198,9 @@ impl Clone for FFMetaFile {
 impl Default for FFMetaFile {
     fn default() -> Self {
         Self {
-            basic: HashMap::new(),
-            extra: HashMap::new(),
+            basic: OrderedMap::new(),
+            extra: OrderedMap::new(),
+            chapters: Vec::new(),
         }
     }
 }
@@ -92,3 +223,53 @@ fn test_save() {
             .as_bytes()
     );
 }
+
+#[test]
+fn test_save_order() {
+    let mut f = MemoryFile::new();
+    let mut f2 = FFMetaFile::new();
+    f2.basic.insert(String::from("title"), "t");
+    f2.basic.insert(String::from("artist"), "a");
+    f2.basic.insert(String::from("title"), "t2");
+    let mut c = OrderedMap::new();
+    c.insert(String::from("START"), "0");
+    c.insert(String::from("END"), "10");
+    f2.chapters.push(c);
+    let mut c = OrderedMap::new();
+    c.insert(String::from("START"), "10");
+    c.insert(String::from("END"), "20");
+    f2.chapters.push(c);
+    f2.save(&mut f).unwrap();
+    assert_eq!(
+        f,
+        ";FFMETADATA1\ntitle=t2\nartist=a\n[CHAPTER]\nSTART=0\nEND=10\n[CHAPTER]\nSTART=10\nEND=20\n".as_bytes()
+    );
+}
+
+#[test]
+fn test_from_video_metadata() {
+    use crate::metadata::Chapter;
+    use crate::metadata::NoInTotal;
+    use chrono::TimeZone;
+    use chrono::Utc;
+    let mut m = VideoMetadata::default();
+    m.title = Some(String::from("t"));
+    m.description = Some(String::from("d"));
+    m.author = Some(String::from("a"));
+    m.track = NoInTotal::new(1, 2);
+    m.date = Some(Utc.timestamp_opt(0, 0).unwrap());
+    m.tags.push(String::from("x"));
+    m.tags.push(String::from("y"));
+    m.video_id = Some(String::from("BV1"));
+    m.extra.insert(String::from("part"), String::from("p1"));
+    m.chapters.push(Chapter::new(0, 1500, "c=1"));
+    m.lyrics = Some(String::from("l1\nl2"));
+    let f = FFMetaFile::from_video_metadata(&m);
+    let mut o = MemoryFile::new();
+    f.save(&mut o).unwrap();
+    assert_eq!(
+        o,
+        ";FFMETADATA1\ntitle=t\ncomment=d\nartist=a\ntrack=1/2\ndate=1970-01-01T00:00:00Z\ngenre=x, y\nepisode_id=BV1\nlyrics=l1\\\nl2\npart=p1\n[CHAPTER]\nTIMEBASE=1/1000\nSTART=0\nEND=1500\ntitle=c\\=1\n"
+            .as_bytes()
+    );
+}
@@ -0,0 +1,524 @@
+extern crate subprocess;
+
+use crate::downloader::ffmpeg::ffmetafile::FFMetaFile;
+use crate::i18n::gettext;
+use crate::utils::path::path_to_str;
+use core::time::Duration;
+use std::clone::Clone;
+use std::env::current_dir;
+use std::fs::remove_file;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use subprocess::ExitStatus;
+use subprocess::Popen;
+use subprocess::PopenConfig;
+use subprocess::Redirection;
+
+/// Test ffmpeg whether to works
+/// * `p` - The path of ffmpeg
+pub fn test_ffmpeg(p: &str) -> bool {
+    let l = vec![p, "-h"];
+    let r = Popen::create(
+        &l,
+        PopenConfig {
+            stdin: Redirection::Pipe,
+            stdout: Redirection::Pipe,
+            stderr: Redirection::Pipe,
+            ..PopenConfig::default()
+        },
+    );
+    match r {
+        Ok(_) => {}
+        Err(_) => {
+            return false;
+        }
+    }
+    let mut r = r.unwrap();
+    let _ = r.communicate(Some(""));
+    let re = r.wait_timeout(Duration::new(5, 0));
+    match re {
+        Ok(_) => {}
+        Err(_) => {
+            return false;
+        }
+    }
+    let re = re.unwrap();
+    if re.is_none() {
+        let _ = r.kill();
+        return false;
+    }
+    let re = re.unwrap();
+    if re.success() {
+        return true;
+    }
+    false
+}
+
+/// Generate the file list used by ffmpeg's concat demuxer
+/// * `files` - The locations of files
+pub fn gen_concat_list(files: &[String]) -> String {
+    let mut s = String::from("ffconcat version 1.0\n");
+    for f in files.iter() {
+        s += format!("file '{}'\n", f.replace("'", "'\\''")).as_str();
+    }
+    s
+}
+
+/// Count streams of specified type from the stream information printed by ffmpeg.
+/// * `s` - The output of ffmpeg
+/// * `typ` - Stream type. Such as `Video`, `Subtitle`
+fn count_streams(s: &str, typ: &str) -> usize {
+    let pat = format!(": {}:", typ);
+    let mut c = 0;
+    for l in s.lines() {
+        let l = l.trim();
+        if l.starts_with("Stream #0:") && l.contains(pat.as_str()) {
+            c += 1;
+        }
+    }
+    c
+}
+
+/// Get MIME type of an image from its extension
+/// * `p` - The location of image
+fn get_image_mime_type(p: &str) -> &'static str {
+    let p = p.to_lowercase();
+    if p.ends_with(".png") {
+        "image/png"
+    } else if p.ends_with(".webp") {
+        "image/webp"
+    } else if p.ends_with(".gif") {
+        "image/gif"
+    } else {
+        "image/jpeg"
+    }
+}
+
+/// Ffmpeg interface
+#[derive(Clone)]
+pub struct FFmpeg {
+    /// Executable path
+    exe: String,
+}
+
+impl FFmpeg {
+    /// Create a new interface
+    /// * `exe` - The path of executable
+    pub fn new(exe: Option<&str>) -> Option<Self> {
+        let e = exe.unwrap_or("ffmpeg");
+        if !test_ffmpeg(e) {
+            return None;
+        }
+        Some(Self {
+            exe: String::from(e),
+        })
+    }
+
+    /// Merge video stream and audio stream into one file without re-encoding.
+    /// * `video` - The location of video stream file
+    /// * `audio` - The location of audio stream file
+    /// * `meta` - Metadata which will be written to output file
+    /// * `output` - The location of output file
+    pub fn merge_dash(
+        &self,
+        video: &str,
+        audio: Option<&str>,
+        meta: Option<&FFMetaFile>,
+        output: &str,
+    ) -> bool {
+        let mut li = vec![self.exe.clone(), String::from("-y")];
+        li.push(String::from("-i"));
+        li.push(String::from(video));
+        let mut ind = 1;
+        if let Some(audio) = audio {
+            li.push(String::from("-i"));
+            li.push(String::from(audio));
+            ind += 1;
+        }
+        let meta_file = format!("{}.ffmeta", output);
+        if let Some(meta) = meta {
+            if !self.write_meta_file(meta, meta_file.as_str()) {
+                return false;
+            }
+            li.push(String::from("-i"));
+            li.push(meta_file.clone());
+            li.push(String::from("-map_metadata"));
+            li.push(format!("{}", ind));
+            if !meta.chapters.is_empty() {
+                li.push(String::from("-map_chapters"));
+                li.push(format!("{}", ind));
+            }
+        }
+        li.push(String::from("-map"));
+        li.push(String::from("0:v"));
+        if audio.is_some() {
+            li.push(String::from("-map"));
+            li.push(String::from("1:a"));
+        }
+        li.push(String::from("-c"));
+        li.push(String::from("copy"));
+        li.push(String::from(output));
+        let r = self.run(li);
+        if meta.is_some() {
+            let _ = remove_file(meta_file.as_str());
+        }
+        match r {
+            Some(r) => r == 0,
+            None => false,
+        }
+    }
+
+    /// Concatenate files into one file without re-encoding by using concat demuxer.
+    /// * `files` - The locations of files. The order is the order in output file.
+    /// * `meta` - Metadata which will be written to output file
+    /// * `output` - The location of output file
+    pub fn concat(&self, files: &[String], meta: Option<&FFMetaFile>, output: &str) -> bool {
+        let list_file = format!("{}.ffconcat", output);
+        let f = File::create(list_file.as_str());
+        match f {
+            Ok(_) => {}
+            Err(e) => {
+                eprintln!("{}\"{}\": {}", gettext("Can not create file: "), list_file, e);
+                return false;
+            }
+        }
+        let mut f = f.unwrap();
+        // Relative paths in list file are resolved from the location of list file.
+        let mut abs_files: Vec<String> = Vec::new();
+        for i in files.iter() {
+            let p = Path::new(i);
+            if p.is_absolute() {
+                abs_files.push(i.clone());
+            } else {
+                match current_dir() {
+                    Ok(d) => abs_files.push(String::from(path_to_str(d.join(p).as_path()))),
+                    Err(_) => abs_files.push(i.clone()),
+                }
+            }
+        }
+        match f.write_all(gen_concat_list(&abs_files).as_bytes()) {
+            Ok(_) => {}
+            Err(e) => {
+                eprintln!("{}\"{}\": {}", gettext("Can not write data to file: "), list_file, e);
+                return false;
+            }
+        }
+        drop(f);
+        let mut li = vec![self.exe.clone(), String::from("-y")];
+        li.push(String::from("-f"));
+        li.push(String::from("concat"));
+        li.push(String::from("-safe"));
+        li.push(String::from("0"));
+        li.push(String::from("-i"));
+        li.push(list_file.clone());
+        let meta_file = format!("{}.ffmeta", output);
+        if let Some(meta) = meta {
+            if !self.write_meta_file(meta, meta_file.as_str()) {
+                return false;
+            }
+            li.push(String::from("-i"));
+            li.push(meta_file.clone());
+            li.push(String::from("-map_metadata"));
+            li.push(String::from("1"));
+            if !meta.chapters.is_empty() {
+                li.push(String::from("-map_chapters"));
+                li.push(String::from("1"));
+            }
+        }
+        li.push(String::from("-map"));
+        li.push(String::from("0"));
+        li.push(String::from("-c"));
+        li.push(String::from("copy"));
+        li.push(String::from(output));
+        let r = self.run(li);
+        let _ = remove_file(list_file.as_str());
+        if meta.is_some() {
+            let _ = remove_file(meta_file.as_str());
+        }
+        match r {
+            Some(r) => r == 0,
+            None => false,
+        }
+    }
+
+    /// Write metadata to a file without re-encoding.
+    /// * `input` - The location of input file
+    /// * `meta` - Metadata
+    /// * `output` - The location of output file
+    pub fn add_metadata(&self, input: &str, meta: &FFMetaFile, output: &str) -> bool {
+        let meta_file = format!("{}.ffmeta", output);
+        if !self.write_meta_file(meta, meta_file.as_str()) {
+            return false;
+        }
+        let mut li = vec![self.exe.clone(), String::from("-y")];
+        li.push(String::from("-i"));
+        li.push(String::from(input));
+        li.push(String::from("-i"));
+        li.push(meta_file.clone());
+        li.push(String::from("-map_metadata"));
+        li.push(String::from("1"));
+        if !meta.chapters.is_empty() {
+            li.push(String::from("-map_chapters"));
+            li.push(String::from("1"));
+        }
+        li.push(String::from("-map"));
+        li.push(String::from("0"));
+        li.push(String::from("-c"));
+        li.push(String::from("copy"));
+        li.push(String::from(output));
+        let r = self.run(li);
+        let _ = remove_file(meta_file.as_str());
+        match r {
+            Some(r) => r == 0,
+            None => false,
+        }
+    }
+
+    /// Convert an image to another format. The format is detected from extension of output file.
+    /// * `input` - The location of input image
+    /// * `output` - The location of output image
+    pub fn convert_image(&self, input: &str, output: &str) -> bool {
+        let li = vec![
+            self.exe.clone(),
+            String::from("-y"),
+            String::from("-i"),
+            String::from(input),
+            String::from(output),
+        ];
+        match self.run(li) {
+            Some(r) => r == 0,
+            None => false,
+        }
+    }
+
+    /// Return the number of video streams in a file.
+    /// * `input` - The location of file
+    pub fn count_video_streams(&self, input: &str) -> Option<usize> {
+        self.count_streams(input, "Video")
+    }
+
+    /// Return the number of subtitle streams in a file.
+    /// * `input` - The location of file
+    pub fn count_subtitle_streams(&self, input: &str) -> Option<usize> {
+        self.count_streams(input, "Subtitle")
+    }
+
+    /// Return the number of streams of specified type in a file.
+    /// * `input` - The location of file
+    /// * `typ` - Stream type printed by ffmpeg. Such as `Video`, `Subtitle`
+    fn count_streams(&self, input: &str, typ: &str) -> Option<usize> {
+        let li = vec![
+            self.exe.clone(),
+            String::from("-hide_banner"),
+            String::from("-i"),
+            String::from(input),
+        ];
+        let r = Popen::create(
+            &li,
+            PopenConfig {
+                stdin: Redirection::Pipe,
+                stdout: Redirection::Pipe,
+                stderr: Redirection::Pipe,
+                ..PopenConfig::default()
+            },
+        );
+        match r {
+            Ok(_) => {}
+            Err(e) => {
+                eprintln!("{}", e);
+                return None;
+            }
+        }
+        let mut p = r.unwrap();
+        let re = p.communicate(Some(""));
+        let _ = p.wait();
+        match re {
+            Ok((_, err)) => err.map(|err| count_streams(err.as_str(), typ)),
+            Err(e) => {
+                eprintln!("{}", e);
+                None
+            }
+        }
+    }
+
+    /// Embed a cover image to a file without re-encoding.
+    /// In mp4 container, cover will be added as attached picture. In mkv container, cover will be added as attachment.
+    /// * `input` - The location of input file
+    /// * `cover` - The location of cover image. Should be JPEG or PNG in mp4 container.
+    /// * `output` - The location of output file
+    pub fn embed_cover(&self, input: &str, cover: &str, output: &str) -> bool {
+        let ext = match output.rfind('.') {
+            Some(i) => output[i + 1..].to_lowercase(),
+            None => String::from(""),
+        };
+        let mut li = vec![self.exe.clone(), String::from("-y")];
+        li.push(String::from("-i"));
+        li.push(String::from(input));
+        if ext == "mkv" || ext == "mka" {
+            li.push(String::from("-attach"));
+            li.push(String::from(cover));
+            li.push(String::from("-metadata:s:t"));
+            li.push(format!("mimetype={}", get_image_mime_type(cover)));
+            li.push(String::from("-metadata:s:t"));
+            li.push(format!(
+                "filename=cover.{}",
+                match cover.rfind('.') {
+                    Some(i) => &cover[i + 1..],
+                    None => "jpg",
+                }
+            ));
+            li.push(String::from("-map"));
+            li.push(String::from("0"));
+        } else {
+            let count = self.count_video_streams(input);
+            if count.is_none() {
+                return false;
+            }
+            li.push(String::from("-i"));
+            li.push(String::from(cover));
+            li.push(String::from("-map"));
+            li.push(String::from("0"));
+            li.push(String::from("-map"));
+            li.push(String::from("1"));
+            li.push(format!("-disposition:v:{}", count.unwrap()));
+            li.push(String::from("attached_pic"));
+        }
+        li.push(String::from("-c"));
+        li.push(String::from("copy"));
+        li.push(String::from(output));
+        match self.run(li) {
+            Some(r) => r == 0,
+            None => false,
+        }
+    }
+
+    /// Mux subtitles into a file without re-encoding other streams.
+    /// In mp4 container, subtitles will be converted to `mov_text`, so ASS subtitles should not be muxed into it. In mkv container, subtitles will be copied.
+    /// * `input` - The location of input file
+    /// * `subs` - Subtitles. Contains the location of subtitle file, ISO 639-2 language code and title.
+    /// * `output` - The location of output file
+    pub fn mux_subtitles(
+        &self,
+        input: &str,
+        subs: &[(String, Option<String>, Option<String>)],
+        output: &str,
+    ) -> bool {
+        let ext = match output.rfind('.') {
+            Some(i) => output[i + 1..].to_lowercase(),
+            None => String::from(""),
+        };
+        let mut li = vec![self.exe.clone(), String::from("-y")];
+        li.push(String::from("-i"));
+        li.push(String::from(input));
+        for (path, _, _) in subs.iter() {
+            li.push(String::from("-i"));
+            li.push(path.clone());
+        }
+        li.push(String::from("-map"));
+        li.push(String::from("0"));
+        for i in 0..subs.len() {
+            li.push(String::from("-map"));
+            li.push(format!("{}", i + 1));
+        }
+        li.push(String::from("-c"));
+        li.push(String::from("copy"));
+        if ext == "mkv" || ext == "mka" {
+            li.push(String::from("-c:s"));
+            li.push(String::from("copy"));
+        } else {
+            li.push(String::from("-c:s"));
+            li.push(String::from("mov_text"));
+        }
+        let count = self.count_subtitle_streams(input);
+        if count.is_none() {
+            return false;
+        }
+        let count = count.unwrap();
+        for (i, (_, lang, title)) in subs.iter().enumerate() {
+            if lang.is_some() {
+                li.push(format!("-metadata:s:s:{}", count + i));
+                li.push(format!("language={}", lang.as_ref().unwrap()));
+            }
+            if title.is_some() {
+                li.push(format!("-metadata:s:s:{}", count + i));
+                li.push(format!("title={}", title.as_ref().unwrap()));
+            }
+        }
+        li.push(String::from(output));
+        match self.run(li) {
+            Some(r) => r == 0,
+            None => false,
+        }
+    }
+
+    /// Run ffmpeg and return exit code.
+    /// * `li` - Command line
+    fn run(&self, mut li: Vec<String>) -> Option<i32> {
+        // ffmpeg reads commands from stdin. Do not let it consume the input of terminal.
+        li.insert(1, String::from("-nostdin"));
+        let r = Popen::create(&li, PopenConfig::default());
+        match r {
+            Ok(_) => {}
+            Err(e) => {
+                eprintln!("{}", e);
+                return None;
+            }
+        }
+        let mut p = r.unwrap();
+        let r = p.wait();
+        match r {
+            Ok(e) => match e {
+                ExitStatus::Exited(s) => Some(s as i32),
+                _ => None,
+            },
+            Err(e) => {
+                eprintln!("{}", e);
+                None
+            }
+        }
+    }
+
+    /// Write metadata to a file
+    /// * `meta` - Metadata
+    /// * `path` - The location of the file
+    fn write_meta_file(&self, meta: &FFMetaFile, path: &str) -> bool {
+        let f = File::create(path);
+        match f {
+            Ok(_) => {}
+            Err(e) => {
+                eprintln!("{}\"{}\": {}", gettext("Can not create file: "), path, e);
+                return false;
+            }
+        }
+        let mut f = f.unwrap();
+        match meta.save(&mut f) {
+            Ok(_) => true,
+            Err(e) => {
+                eprintln!("{}\"{}\": {}", gettext("Can not write data to file: "), path, e);
+                false
+            }
+        }
+    }
+}
+
+#[test]
+fn test_gen_concat_list() {
+    let l = vec![String::from("a.flv"), String::from("It's.flv")];
+    assert_eq!(
+        "ffconcat version 1.0\nfile 'a.flv'\nfile 'It'\\''s.flv'\n",
+        gen_concat_list(&l)
+    );
+}
+
+#[test]
+fn test_count_streams() {
+    let s = "Input #0, mov,mp4,m4a,3gp,3g2,mj2, from 'a.mp4':
+  Duration: 00:00:10.00, start: 0.000000, bitrate: 1000 kb/s
+    Stream #0:0(und): Video: h264 (High) (avc1 / 0x31637661), yuv420p, 1920x1080, 24 fps
+    Stream #0:1(und): Audio: aac (LC) (mp4a / 0x6134706D), 44100 Hz, stereo, fltp, 128 kb/s
+At least one output file must be specified";
+    assert_eq!(1, count_streams(s, "Video"));
+    assert_eq!(0, count_streams(s, "Subtitle"));
+    assert_eq!("image/png", get_image_mime_type("a.PNG"));
+}
@@ -1 +1,3 @@
 pub mod ffmetafile;
+#[allow(clippy::module_inception)]
+pub mod ffmpeg;
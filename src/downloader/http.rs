@@ -0,0 +1,570 @@
+extern crate reqwest;
+
+use crate::downloader::state::state_file_name;
+use crate::downloader::state::DownloadState;
+use crate::error::Error;
+use crate::error::Result;
+use crate::i18n::gettext;
+use crate::utils::convert::ToStr;
+use crate::utils::number::ToUsize;
+use crate::utils::path::filter_file_name;
+use crate::utils::size::format_size;
+use crate::utils::size::ToSize;
+use futures::future::join_all;
+use reqwest::header::HeaderMap;
+use reqwest::header::HeaderName;
+use reqwest::header::HeaderValue;
+use reqwest::Client;
+use reqwest::Response;
+use reqwest::StatusCode;
+use std::clone::Clone;
+use std::collections::HashMap;
+use std::fs::rename;
+use std::fs::File;
+use std::path::Path;
+use std::io::IsTerminal;
+use std::io::SeekFrom;
+use std::io::Write;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::Mutex;
+use std::time::Instant;
+use tokio::fs::OpenOptions;
+use tokio::io::AsyncSeekExt;
+use tokio::io::AsyncWriteExt;
+
+/// Get total size from `Content-Range` header. Such as `bytes 0-0/1234`
+/// * `s` - The value of header
+fn parse_content_range_total(s: &str) -> Option<usize> {
+    let i = s.rfind('/');
+    s[i? + 1..].trim().parse::<usize>().ok()
+}
+
+/// Split a file into ranges. Every range is not less than `min_split_size` bytes.
+/// * `size` - The size of file
+/// * `split` - The maximum number of ranges
+/// * `min_split_size` - The minimum size of a range
+///
+/// Returns a list of `(start, end)`. `end` is inclusive.
+fn split_ranges(size: usize, split: usize, min_split_size: usize) -> Vec<(usize, usize)> {
+    let mut r: Vec<(usize, usize)> = Vec::new();
+    if size == 0 {
+        return r;
+    }
+    let mut n = size / min_split_size;
+    if n > split {
+        n = split;
+    }
+    if n < 1 {
+        n = 1;
+    }
+    let len = size / n;
+    for i in 0..n {
+        let start = i * len;
+        let end = if i == n - 1 { size - 1 } else { start + len - 1 };
+        r.push((start, end));
+    }
+    r
+}
+
+/// Download progress shared between connections
+struct Progress {
+    /// Downloaded bytes
+    downloaded: AtomicUsize,
+    /// Total bytes
+    total: Option<usize>,
+    /// Bytes downloaded before this run
+    resumed: usize,
+    /// Start time
+    start: Instant,
+    /// The last time when progress is printed
+    last: Mutex<Instant>,
+    /// Downloaded bytes of every range
+    ranges: Vec<AtomicUsize>,
+    /// Download state
+    state: Option<DownloadState>,
+    /// The location of state file
+    state_file: Option<String>,
+}
+
+impl Progress {
+    fn new(total: Option<usize>) -> Self {
+        let now = Instant::now();
+        Self {
+            downloaded: AtomicUsize::new(0),
+            total,
+            resumed: 0,
+            start: now,
+            last: Mutex::new(now),
+            ranges: Vec::new(),
+            state: None,
+            state_file: None,
+        }
+    }
+
+    /// Create a progress which will save download state to a file.
+    /// * `state` - Download state
+    /// * `state_file` - The location of state file
+    fn with_state(state: &DownloadState, state_file: &str) -> Self {
+        let mut p = Self::new(Some(state.size));
+        let resumed = state.downloaded();
+        p.downloaded = AtomicUsize::new(resumed);
+        p.resumed = resumed;
+        for (_, _, done) in state.ranges.iter() {
+            p.ranges.push(AtomicUsize::new(*done));
+        }
+        p.state = Some(state.clone());
+        p.state_file = Some(String::from(state_file));
+        p
+    }
+
+    /// Add downloaded bytes and print progress at most once a second.
+    /// * `index` - The index of range
+    /// * `n` - Bytes
+    fn add(&self, index: usize, n: usize) {
+        self.downloaded.fetch_add(n, Ordering::Relaxed);
+        if index < self.ranges.len() {
+            self.ranges[index].fetch_add(n, Ordering::Relaxed);
+        }
+        let mut last = self.last.lock().unwrap();
+        if last.elapsed().as_secs() >= 1 {
+            *last = Instant::now();
+            self.print();
+            // The state is saved again later. A failure only affects resuming download.
+            let _ = self.save_state();
+        }
+    }
+
+    /// Save download state to state file
+    fn save_state(&self) -> Result<()> {
+        if self.state.is_none() {
+            return Ok(());
+        }
+        let mut st = self.state.as_ref().unwrap().clone();
+        for (i, r) in st.ranges.iter_mut().enumerate() {
+            r.2 = self.ranges[i].load(Ordering::Relaxed);
+        }
+        st.save(self.state_file.as_ref().unwrap())
+    }
+
+    /// Print progress to stderr. Nothing is printed if stderr is not a terminal.
+    fn print(&self) {
+        if !std::io::stderr().is_terminal() {
+            return;
+        }
+        let d = self.downloaded.load(Ordering::Relaxed);
+        let el = self.start.elapsed().as_secs_f64();
+        let speed = if el > 0.0 {
+            ((d - self.resumed) as f64 / el) as usize
+        } else {
+            0
+        };
+        let s = match self.total {
+            Some(t) if t > 0 => format!(
+                "\r{}/{} ({:.1}%) {}/s    ",
+                format_size(d),
+                format_size(t),
+                d as f64 * 100.0 / t as f64,
+                format_size(speed)
+            ),
+            _ => format!("\r{} {}/s    ", format_size(d), format_size(speed)),
+        };
+        eprint!("{}", s);
+        let _ = std::io::stderr().flush();
+    }
+
+    /// Print final progress
+    fn finish(&self) {
+        if !std::io::stderr().is_terminal() {
+            return;
+        }
+        self.print();
+        eprintln!();
+    }
+}
+
+/// Get the value of a header from response.
+/// * `r` - Response
+/// * `key` - Header name
+fn get_header(r: &Response, key: &str) -> Option<String> {
+    match r.headers().get(key) {
+        Some(v) => match v.to_str() {
+            Ok(v) => Some(String::from(v)),
+            Err(_) => None,
+        },
+        None => None,
+    }
+}
+
+/// Get the location of temporary file for a output file.
+/// The file is renamed to the output file after download is completed.
+/// * `output` - The location of output file
+fn part_file_name(output: &str) -> String {
+    format!("{}.part", output)
+}
+
+/// Rename the temporary file to the output file.
+/// * `part` - The location of temporary file
+/// * `output` - The location of output file
+fn finish_part_file(part: &str, output: &str) -> Result<()> {
+    match rename(part, output) {
+        Ok(_) => Ok(()),
+        Err(e) => Err(Error::io(part, e)),
+    }
+}
+
+/// Create an error for an unexpected HTTP status.
+/// * `url` - URL
+/// * `status` - Status code
+fn status_error(url: &str, status: StatusCode) -> Error {
+    if status.as_u16() >= 400 {
+        Error::HttpStatus {
+            url: String::from(url),
+            status: status.as_u16(),
+        }
+    } else {
+        Error::Download(format!("{}{}", gettext("Unexpected HTTP status: "), status))
+    }
+}
+
+/// Create an empty file. If the file exists, it will be truncated.
+/// * `path` - The location of file
+/// * `size` - Allocate space for the file
+fn create_file(path: &str, size: Option<usize>) -> Result<()> {
+    let f = match File::create(path) {
+        Ok(f) => f,
+        Err(e) => return Err(Error::io(path, e)),
+    };
+    if let Some(size) = size {
+        match f.set_len(size as u64) {
+            Ok(_) => {}
+            Err(e) => return Err(Error::io(path, e)),
+        }
+    }
+    Ok(())
+}
+
+/// Write response body to a file.
+/// * `r` - Response
+/// * `path` - The location of file
+/// * `start` - The start position in file
+/// * `index` - The index of range
+/// * `progress` - Download progress
+async fn write_response(
+    mut r: Response,
+    path: &str,
+    start: usize,
+    index: usize,
+    progress: &Progress,
+) -> Result<()> {
+    let mut f = match OpenOptions::new().write(true).open(path).await {
+        Ok(f) => f,
+        Err(e) => return Err(Error::io(path, e)),
+    };
+    match f.seek(SeekFrom::Start(start as u64)).await {
+        Ok(_) => {}
+        Err(e) => return Err(Error::io(path, e)),
+    }
+    while let Some(c) = r.chunk().await? {
+        match f.write_all(&c).await {
+            Ok(_) => {}
+            Err(e) => return Err(Error::io(path, e)),
+        }
+        progress.add(index, c.len());
+    }
+    match f.flush().await {
+        Ok(_) => Ok(()),
+        Err(e) => Err(Error::io(path, e)),
+    }
+}
+
+/// Download a range of file.
+/// * `client` - HTTP client
+/// * `url` - URL
+/// * `headers` - HTTP headers
+/// * `path` - The location of file
+/// * `range` - The start and end of range (inclusive)
+/// * `index` - The index of range
+/// * `progress` - Download progress
+async fn download_range(
+    client: &Client,
+    url: &str,
+    headers: &HeaderMap,
+    path: &str,
+    range: (usize, usize),
+    index: usize,
+    progress: &Progress,
+) -> Result<()> {
+    let (start, end) = range;
+    if start > end {
+        return Ok(());
+    }
+    let r = client
+        .get(url)
+        .headers(headers.clone())
+        .header("Range", format!("bytes={}-{}", start, end))
+        .send()
+        .await;
+    match r {
+        Ok(r) => {
+            if r.status() != StatusCode::PARTIAL_CONTENT {
+                return Err(status_error(url, r.status()));
+            }
+            write_response(r, path, start, index, progress).await
+        }
+        Err(e) => Err(Error::network(url, e)),
+    }
+}
+
+/// Built-in HTTP downloader. It supports downloading a file with multiple connections.
+#[derive(Clone)]
+pub struct HttpDownloader {
+    /// HTTP Client
+    client: Client,
+    /// HTTP Headers
+    pub headers: HashMap<String, String>,
+    /// Do not split less than 2*SIZE byte range.
+    min_split_size: usize,
+    /// The number of connections used when downloading a file.
+    split: usize,
+    /// Output file name
+    output: Option<String>,
+}
+
+impl HttpDownloader {
+    /// Create a new downloader
+    pub fn new() -> Option<Self> {
+        let c = Client::builder()
+            .no_gzip()
+            .no_brotli()
+            .no_deflate()
+            .build();
+        match c {
+            Ok(c) => Some(Self {
+                client: c,
+                headers: HashMap::new(),
+                min_split_size: 20971520,
+                split: 5,
+                output: None,
+            }),
+            Err(e) => {
+                eprintln!("{}", e);
+                None
+            }
+        }
+    }
+
+    /// Generate HTTP headers
+    fn gen_headers(&self) -> HeaderMap {
+        let mut h = HeaderMap::new();
+        for (k, v) in self.headers.iter() {
+            if let (Ok(k), Ok(v)) = (HeaderName::from_bytes(k.as_bytes()), HeaderValue::from_str(v.as_str())) {
+                h.insert(k, v);
+            }
+        }
+        h
+    }
+
+    /// Perfrom download asynchronously
+    /// * `url` - URL
+    pub async fn download_async(&mut self, url: &str) -> Result<()> {
+        let url = String::from(url);
+        let output = match &self.output {
+            Some(o) => o.clone(),
+            None => {
+                let path = match url.find('?') {
+                    Some(i) => &url[..i],
+                    None => url.as_str(),
+                };
+                let name = match path.rfind('/') {
+                    Some(i) => &path[i + 1..],
+                    None => path,
+                };
+                let name = filter_file_name(&String::from(name));
+                match name {
+                    Some(n) if !n.is_empty() => n,
+                    _ => String::from("index.html"),
+                }
+            }
+        };
+        let headers = self.gen_headers();
+        eprintln!("{}{}", gettext("Downloading: "), output);
+        let r = self
+            .client
+            .get(url.as_str())
+            .headers(headers.clone())
+            .header("Range", "bytes=0-0")
+            .send()
+            .await;
+        let r = match r {
+            Ok(r) => r,
+            Err(e) => return Err(Error::network(url.as_str(), e)),
+        };
+        let state_file = state_file_name(output.as_str());
+        let part = part_file_name(output.as_str());
+        if r.status() == StatusCode::PARTIAL_CONTENT {
+            let total = match get_header(&r, "Content-Range") {
+                Some(v) => parse_content_range_total(v.as_str()),
+                None => None,
+            };
+            if let Some(total) = total {
+                let etag = get_header(&r, "ETag");
+                let last_modified = get_header(&r, "Last-Modified");
+                let mut state: Option<DownloadState> = None;
+                if Path::new(part.as_str()).exists() {
+                    let st = DownloadState::load(state_file.as_str())?;
+                    if let Some(st) = st {
+                        if st.is_same_file(&etag, &last_modified, total) {
+                            eprintln!(
+                                "{}{}",
+                                gettext("Resume downloading from "),
+                                format_size(st.downloaded())
+                            );
+                            state = Some(st);
+                        } else {
+                            eprintln!("{}", gettext("Remote file has been changed. Restart downloading."));
+                        }
+                    }
+                }
+                if state.is_none() {
+                    let ranges = split_ranges(total, self.split, self.min_split_size);
+                    let st = DownloadState::new(url.as_str(), etag, last_modified, total, &ranges);
+                    st.save(state_file.as_str())?;
+                    create_file(part.as_str(), Some(total))?;
+                    state = Some(st);
+                }
+                let state = state.unwrap();
+                let progress = Progress::with_state(&state, state_file.as_str());
+                let mut tasks = Vec::new();
+                for (i, (start, end, done)) in state.ranges.iter().enumerate() {
+                    tasks.push(download_range(
+                        &self.client,
+                        url.as_str(),
+                        &headers,
+                        part.as_str(),
+                        (*start + *done, *end),
+                        i,
+                        &progress,
+                    ));
+                }
+                let re = join_all(tasks).await;
+                progress.finish();
+                for i in re.into_iter() {
+                    if i.is_err() {
+                        // Keep the original error. The state saved last time is still usable.
+                        let _ = progress.save_state();
+                        return i;
+                    }
+                }
+                finish_part_file(part.as_str(), output.as_str())?;
+                return DownloadState::remove(state_file.as_str());
+            }
+        }
+        let r = if r.status() == StatusCode::PARTIAL_CONTENT {
+            // The total size is unknown, so the file can not be downloaded by ranges.
+            // Request the whole file again, otherwise only the probed byte is saved.
+            match self.client.get(url.as_str()).headers(headers.clone()).send().await {
+                Ok(r) => r,
+                Err(e) => return Err(Error::network(url.as_str(), e)),
+            }
+        } else {
+            r
+        };
+        if !r.status().is_success() || r.status() == StatusCode::PARTIAL_CONTENT {
+            return Err(status_error(url.as_str(), r.status()));
+        }
+        // Range is not supported by server. The whole file is returned and can not be resumed.
+        DownloadState::remove(state_file.as_str())?;
+        create_file(part.as_str(), None)?;
+        let progress = Progress::new(r.content_length().map(|s| s as usize));
+        let re = write_response(r, part.as_str(), 0, 0, &progress).await;
+        progress.finish();
+        re?;
+        finish_part_file(part.as_str(), output.as_str())
+    }
+
+    /// Set settings.
+    /// * `inp` - Input object
+    pub fn set_min_split_size(&mut self, inp: &impl ToSize) -> bool {
+        let s = inp.to_size();
+        if s.is_none() {
+            return false;
+        }
+        let s = s.unwrap();
+        if (1048576..=1073741824).contains(&s) {
+            self.min_split_size = s;
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn set_output<U: ToStr>(&mut self, inp: Option<&U>) -> bool {
+        match inp {
+            Some(inp) => match inp.to_str() {
+                Some(s) => {
+                    self.output = Some(String::from(s));
+                    true
+                }
+                None => false,
+            },
+            None => {
+                self.output = None;
+                true
+            }
+        }
+    }
+
+    /// Set settings.
+    /// * `inp` - Input object
+    pub fn set_split<U: ToUsize>(&mut self, inp: &U) -> bool {
+        let s = inp.to_usize();
+        if s.is_none() {
+            return false;
+        }
+        let s = s.unwrap();
+        if s >= 1 {
+            self.split = s;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[test]
+fn test_parse_content_range_total() {
+    assert_eq!(Some(1234), parse_content_range_total("bytes 0-0/1234"));
+    assert_eq!(None, parse_content_range_total("bytes 0-0/*"));
+}
+
+#[test]
+fn test_split_ranges() {
+    let m = 1048576;
+    assert_eq!(vec![(0, m - 1)], split_ranges(m, 5, m));
+    assert_eq!(
+        vec![(0, m - 1), (m, 2 * m - 1), (2 * m, 3 * m)],
+        split_ranges(3 * m + 1, 5, m)
+    );
+    assert_eq!(5, split_ranges(100 * m, 5, m).len());
+    assert_eq!(0, split_ranges(0, 5, m).len());
+}
+
+#[tokio::test]
+async fn test_download_unknown_total() {
+    use crate::testutils::http_server::start_server;
+    let url = start_server(|head| {
+        if head.contains("range:") {
+            ("206 Partial Content", vec![("Content-Range", String::from("bytes 0-0/*"))], b"h".to_vec())
+        } else {
+            ("200 OK", vec![], b"hello world".to_vec())
+        }
+    });
+    let output = std::env::temp_dir().join(format!("bili_test_unknown_total_{}", std::process::id()));
+    let output = output.to_str().unwrap();
+    let mut d = HttpDownloader::new().unwrap();
+    assert!(d.set_output(Some(&output)));
+    d.download_async(format!("{}/a.bin", url).as_str()).await.unwrap();
+    assert_eq!(b"hello world".to_vec(), std::fs::read(output).unwrap());
+    std::fs::remove_file(output).unwrap();
+}
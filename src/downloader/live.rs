@@ -0,0 +1,905 @@
+extern crate reqwest;
+extern crate tokio;
+
+use crate::downloader::downloader::Downloader;
+use crate::downloader::downloader::DownloaderType;
+use crate::downloader::downloader::VideoDownloader;
+use crate::downloader::output::get_output_base;
+use crate::getopt::OptStore;
+use crate::http_client::gen_cookie_header;
+use crate::error::Error;
+use crate::error::Result;
+use crate::i18n::gettext;
+use crate::metadata::LiveDanmakuRecorder;
+use crate::metadata::LiveProtocol;
+use crate::metadata::LiveStatus;
+use crate::metadata::VideoInfo;
+use crate::metadata::VideoPlayInfoType;
+use crate::settings::SettingStore;
+use crate::utils::duration::parse_duration;
+use crate::utils::duration::ToDuration;
+use crate::utils::number::ToUsize;
+use crate::utils::size::format_size;
+use crate::utils::size::parse_size;
+use crate::utils::size::ToSize;
+use async_trait::async_trait;
+use reqwest::header::HeaderMap;
+use reqwest::header::HeaderValue;
+use reqwest::Client;
+use std::clone::Clone;
+use std::path::Path;
+use std::time::Duration;
+use std::time::Instant;
+use tokio::fs::File;
+use tokio::io::AsyncWriteExt;
+use tokio::time::sleep;
+use tokio::time::timeout;
+
+/// The key of settings group
+pub const LIVE_SETTINGS_KEY: &str = "LiveRecord";
+/// If no data is received in this duration, the stream is treated as interrupted.
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Options for recording live
+pub struct LiveRecordOptions {
+    /// Stop recording after this duration
+    pub duration: Option<Duration>,
+    /// Stop recording after this size of data is recorded
+    pub max_size: Option<usize>,
+    /// Split output to a new file after this duration
+    pub segment_interval: Option<Duration>,
+    /// The maximum number of reconnections without receiving any data
+    pub max_retry: usize,
+    /// The time to wait before reconnecting
+    pub retry_interval: Duration,
+}
+
+impl LiveRecordOptions {
+    /// Read options from options and settings.
+    /// * `opt` - Options
+    /// * `se` - Settings
+    ///
+    /// Return None if any value is invalid.
+    pub fn from_opt(opt: &OptStore, se: &SettingStore) -> Option<Self> {
+        let invalid = |key: &str| {
+            eprintln!("{}", gettext("The value of option \"<key>\" is invalid.").replace("<key>", key));
+        };
+        let get_duration = |key: &str| -> Result<Option<u64>, ()> {
+            let o = opt.get_option(key);
+            if let Some(o) = o {
+                let r = parse_duration(o.as_str());
+                if r.is_none() {
+                    invalid(key);
+                    return Err(());
+                }
+                return Ok(r);
+            }
+            match se.get_settings(LIVE_SETTINGS_KEY, key) {
+                Some(v) => Ok(v.to_duration()),
+                None => Ok(None),
+            }
+        };
+        let duration = get_duration("duration");
+        let segment_interval = get_duration("segment-interval");
+        let retry_interval = get_duration("live-retry-interval");
+        if duration.is_err() || segment_interval.is_err() || retry_interval.is_err() {
+            return None;
+        }
+        let mut max_size = None;
+        let o = opt.get_option("max-size");
+        if let Some(o) = o {
+            max_size = parse_size(o.as_str());
+            if max_size.is_none() {
+                invalid("max-size");
+                return None;
+            }
+        } else {
+            let s = se.get_settings(LIVE_SETTINGS_KEY, "max-size");
+            if let Some(s) = s {
+                max_size = s.to_size();
+            }
+        }
+        let mut max_retry = 10;
+        let o = opt.get_option("live-retry");
+        if let Some(o) = o {
+            let r = o.to_usize();
+            if r.is_none() {
+                invalid("live-retry");
+                return None;
+            }
+            max_retry = r.unwrap();
+        } else {
+            let s = se.get_settings(LIVE_SETTINGS_KEY, "live-retry");
+            if let Some(s) = s {
+                max_retry = s.to_usize().unwrap_or(max_retry);
+            }
+        }
+        let to_duration = |d: Option<u64>| match d {
+            Some(d) if d > 0 => Some(Duration::from_secs(d)),
+            _ => None,
+        };
+        Some(Self {
+            duration: to_duration(duration.unwrap()),
+            max_size: match max_size {
+                Some(s) if s > 0 => Some(s),
+                _ => None,
+            },
+            segment_interval: to_duration(segment_interval.unwrap()),
+            max_retry,
+            retry_interval: match retry_interval.unwrap() {
+                Some(d) => Duration::from_secs(d),
+                None => Duration::from_secs(5),
+            },
+        })
+    }
+}
+
+/// A tag in FLV stream
+#[derive(Clone)]
+pub struct FlvTag {
+    /// Tag type. 8 is audio, 9 is video, 18 is script data.
+    pub typ: u8,
+    /// Timestamp in milliseconds
+    pub timestamp: u32,
+    /// Tag data
+    pub data: Vec<u8>,
+}
+
+impl FlvTag {
+    /// Return true if it is a video key frame.
+    pub fn is_keyframe(&self) -> bool {
+        self.typ == 9 && !self.data.is_empty() && self.data[0] >> 4 == 1
+    }
+
+    /// Return true if it is a AVC/HEVC sequence header or AAC sequence header.
+    pub fn is_sequence_header(&self) -> bool {
+        if self.data.len() < 2 || self.data[1] != 0 {
+            return false;
+        }
+        if self.typ == 9 {
+            let codec = self.data[0] & 0x0f;
+            codec == 7 || codec == 12
+        } else if self.typ == 8 {
+            self.data[0] >> 4 == 10
+        } else {
+            false
+        }
+    }
+
+    /// Serialize the tag with a new timestamp.
+    /// * `timestamp` - Timestamp in milliseconds
+    pub fn to_bytes(&self, timestamp: u32) -> Vec<u8> {
+        let size = self.data.len();
+        let mut r = Vec::with_capacity(size + 15);
+        r.push(self.typ);
+        r.extend_from_slice(&(size as u32).to_be_bytes()[1..]);
+        r.extend_from_slice(&timestamp.to_be_bytes()[1..]);
+        r.push((timestamp >> 24) as u8);
+        r.extend_from_slice(&[0, 0, 0]);
+        r.extend_from_slice(&self.data);
+        r.extend_from_slice(&((size + 11) as u32).to_be_bytes());
+        r
+    }
+}
+
+/// Parse FLV stream incrementally.
+pub struct FlvReader {
+    /// Unparsed data
+    buf: Vec<u8>,
+    /// FLV header (without `PreviousTagSize0`)
+    pub header: Option<Vec<u8>>,
+}
+
+impl FlvReader {
+    pub fn new() -> Self {
+        Self {
+            buf: Vec::new(),
+            header: None,
+        }
+    }
+
+    /// Add received data.
+    pub fn push(&mut self, data: &[u8]) {
+        self.buf.extend_from_slice(data);
+    }
+
+    /// Return the next complete tag.
+    ///
+    /// Return `Ok(None)` if more data is needed. Return `Err` if the stream is not a valid FLV stream.
+    pub fn next_tag(&mut self) -> Result<Option<FlvTag>> {
+        if self.header.is_none() {
+            if self.buf.len() < 9 {
+                return Ok(None);
+            }
+            if &self.buf[0..3] != b"FLV" {
+                return Err(Error::Download(String::from(gettext("Invalid FLV stream."))));
+            }
+            let len = u32::from_be_bytes([self.buf[5], self.buf[6], self.buf[7], self.buf[8]]) as usize;
+            if len < 9 {
+                return Err(Error::Download(String::from(gettext("Invalid FLV stream."))));
+            }
+            if self.buf.len() < len + 4 {
+                return Ok(None);
+            }
+            let mut h = self.buf[0..9].to_vec();
+            h[5..9].copy_from_slice(&9u32.to_be_bytes());
+            self.header = Some(h);
+            self.buf.drain(0..len + 4);
+        }
+        if self.buf.len() < 11 {
+            return Ok(None);
+        }
+        let b = &self.buf;
+        let size = u32::from_be_bytes([0, b[1], b[2], b[3]]) as usize;
+        if self.buf.len() < size + 15 {
+            return Ok(None);
+        }
+        let tag = FlvTag {
+            typ: b[0] & 0x1f,
+            timestamp: u32::from_be_bytes([b[7], b[4], b[5], b[6]]),
+            data: b[11..11 + size].to_vec(),
+        };
+        self.buf.drain(0..size + 15);
+        Ok(Some(tag))
+    }
+}
+
+impl Default for FlvReader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A segment in HLS media playlist
+pub struct HlsSegment {
+    /// Media sequence number
+    pub seq: u64,
+    /// Duration in seconds
+    pub duration: f64,
+    /// Url
+    pub url: String,
+}
+
+/// HLS playlist
+pub struct HlsPlaylist {
+    /// The url of first variant stream if it is a master playlist
+    pub variant: Option<String>,
+    /// The url of initialization section (`EXT-X-MAP`)
+    pub map: Option<String>,
+    /// Segments
+    pub segments: Vec<HlsSegment>,
+    /// Target duration in seconds
+    pub target_duration: u64,
+    /// Whether the playlist is ended (`EXT-X-ENDLIST`)
+    pub ended: bool,
+}
+
+impl HlsPlaylist {
+    /// Return true if the media sequence is restarted. It means all segments are older than
+    /// the last written segment by more than the length of the playlist.
+    /// A playlist which is a little out of date is not treated as restarted.
+    /// * `last_seq` - The sequence number of last written segment
+    pub fn is_restarted(&self, last_seq: u64) -> bool {
+        match self.segments.last() {
+            Some(seg) => seg.seq < last_seq && last_seq - seg.seq > self.segments.len() as u64,
+            None => false,
+        }
+    }
+}
+
+/// Resolve a relative url.
+/// * `base` - The url of playlist
+/// * `url` - Relative url
+pub fn resolve_url(base: &str, url: &str) -> String {
+    if url.starts_with("http://") || url.starts_with("https://") {
+        return String::from(url);
+    }
+    let base = match base.find('?') {
+        Some(i) => &base[..i],
+        None => base,
+    };
+    if url.starts_with("//") {
+        let scheme = match base.find("://") {
+            Some(i) => &base[..i],
+            None => "https",
+        };
+        return format!("{}:{}", scheme, url);
+    }
+    if url.starts_with("/") {
+        let host_end = match base.find("://") {
+            Some(i) => match base[i + 3..].find('/') {
+                Some(j) => i + 3 + j,
+                None => base.len(),
+            },
+            None => 0,
+        };
+        return format!("{}{}", &base[..host_end], url);
+    }
+    match base.rfind('/') {
+        Some(i) => format!("{}{}", &base[..i + 1], url),
+        None => String::from(url),
+    }
+}
+
+/// Parse a HLS playlist.
+/// * `base` - The url of playlist
+/// * `s` - Playlist content
+pub fn parse_m3u8(base: &str, s: &str) -> Option<HlsPlaylist> {
+    let mut lines = s.lines().map(|l| l.trim()).filter(|l| !l.is_empty());
+    if lines.next() != Some("#EXTM3U") {
+        return None;
+    }
+    let mut p = HlsPlaylist {
+        variant: None,
+        map: None,
+        segments: Vec::new(),
+        target_duration: 1,
+        ended: false,
+    };
+    let mut seq = 0u64;
+    let mut duration = 0f64;
+    let mut is_variant = false;
+    for l in lines {
+        if l.starts_with("#EXT-X-STREAM-INF") {
+            is_variant = true;
+        } else if let Some(v) = l.strip_prefix("#EXT-X-MEDIA-SEQUENCE:") {
+            seq = v.trim().parse::<u64>().unwrap_or(0);
+        } else if let Some(v) = l.strip_prefix("#EXT-X-TARGETDURATION:") {
+            p.target_duration = v.trim().parse::<u64>().unwrap_or(1);
+        } else if let Some(v) = l.strip_prefix("#EXTINF:") {
+            let d = v.split(',').next().unwrap_or("0");
+            duration = d.trim().parse::<f64>().unwrap_or(0.0);
+        } else if l.starts_with("#EXT-X-MAP:") {
+            let i = l.find("URI=\"");
+            if let Some(i) = i {
+                let u = &l[i + 5..];
+                let u = match u.find('"') {
+                    Some(j) => &u[..j],
+                    None => u,
+                };
+                p.map = Some(resolve_url(base, u));
+            }
+        } else if l == "#EXT-X-ENDLIST" {
+            p.ended = true;
+        } else if !l.starts_with("#") {
+            if is_variant {
+                if p.variant.is_none() {
+                    p.variant = Some(resolve_url(base, l));
+                }
+                continue;
+            }
+            p.segments.push(HlsSegment {
+                seq,
+                duration,
+                url: resolve_url(base, l),
+            });
+            seq += 1;
+            duration = 0.0;
+        }
+    }
+    Some(p)
+}
+
+/// The result of a recording attempt
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum RecordResult {
+    /// The stream is interrupted. Need reconnect.
+    Interrupted,
+    /// The stream is ended or a limit is hit.
+    Finished,
+}
+
+/// A downloader for live stream.
+pub struct LiveDownloader {
+    /// Extract Information
+    vi: VideoInfo,
+    /// Options
+    opt: OptStore,
+    /// Settings
+    se: SettingStore,
+    /// HTTP client
+    client: Option<Client>,
+    /// Output file name without extension and index
+    base: String,
+    /// Recording options
+    ro: Option<LiveRecordOptions>,
+    /// The time when recording started
+    start: Instant,
+    /// The size of recorded data
+    size: usize,
+    /// The index of last output file
+    index: usize,
+    /// Current output file
+    file: Option<File>,
+    /// Whether any data is received since last reconnection
+    received: bool,
+    /// Danmaku recorder
+    danmaku: Option<Box<dyn LiveDanmakuRecorder>>,
+    /// The sequence number of last written HLS segment.
+    /// It is kept when reconnecting, so segments are not written again.
+    hls_seq: Option<u64>,
+}
+
+impl LiveDownloader {
+    pub fn new(vi: &VideoInfo, opt: &OptStore, se: &SettingStore) -> Self {
+        Self {
+            vi: vi.clone(),
+            opt: opt.clone(),
+            se: se.clone(),
+            client: None,
+            base: String::new(),
+            ro: None,
+            start: Instant::now(),
+            size: 0,
+            index: 0,
+            file: None,
+            received: false,
+            danmaku: None,
+            hls_seq: None,
+        }
+    }
+
+    /// Generate HTTP headers for a url.
+    /// * `url` - URL
+    fn gen_headers(&self, url: &str) -> HeaderMap {
+        let mut h = match &self.vi.headers {
+            Some(h) => h.clone(),
+            None => HeaderMap::new(),
+        };
+        if let Some(cookies) = &self.vi.cookies {
+            let c = gen_cookie_header(cookies, url);
+            if !c.is_empty() {
+                if let Ok(v) = HeaderValue::from_str(c.as_str()) {
+                    h.insert("cookie", v);
+                }
+            }
+        }
+        h
+    }
+
+    /// Send a GET request. Return None if failed.
+    /// * `url` - URL
+    async fn get(&self, url: &str) -> Option<reqwest::Response> {
+        let c = self.client.as_ref().unwrap();
+        let r = timeout(READ_TIMEOUT, c.get(url).headers(self.gen_headers(url)).send()).await;
+        match r {
+            Ok(Ok(r)) => {
+                if r.status().is_success() {
+                    Some(r)
+                } else {
+                    eprintln!("{}\"{}\": {}", gettext("Can not connect to live stream: "), url, r.status());
+                    None
+                }
+            }
+            Ok(Err(e)) => {
+                eprintln!("{}\"{}\": {}", gettext("Can not connect to live stream: "), url, e);
+                None
+            }
+            Err(_) => {
+                eprintln!("{}\"{}\"", gettext("Timeout when connecting to live stream: "), url);
+                None
+            }
+        }
+    }
+
+    /// Read the next chunk of response. Return None if the stream is ended or interrupted.
+    async fn read_chunk(r: &mut reqwest::Response) -> Option<Vec<u8>> {
+        match timeout(READ_TIMEOUT, r.chunk()).await {
+            Ok(Ok(Some(b))) => Some(b.to_vec()),
+            Ok(Ok(None)) => None,
+            Ok(Err(e)) => {
+                eprintln!("{}", e);
+                None
+            }
+            Err(_) => {
+                eprintln!("{}", gettext("No data received from live stream for a long time."));
+                None
+            }
+        }
+    }
+
+    /// Return true if a recording limit is hit.
+    fn limit_reached(&self) -> bool {
+        let ro = self.ro.as_ref().unwrap();
+        if ro.duration.is_some() && self.start.elapsed() >= ro.duration.unwrap() {
+            eprintln!("{}", gettext("The maximum recording duration is reached."));
+            return true;
+        }
+        if ro.max_size.is_some() && self.size >= ro.max_size.unwrap() {
+            eprintln!("{}", gettext("The maximum recording size is reached."));
+            return true;
+        }
+        false
+    }
+
+    /// Close current file and create a new output file.
+    /// * `ext` - File extension
+    async fn new_file(&mut self, ext: &str) -> bool {
+        self.close_file().await;
+        let mut path;
+        loop {
+            self.index += 1;
+            path = format!("{}.{:03}.{}", self.base, self.index, ext);
+            if !Path::new(path.as_str()).exists() {
+                break;
+            }
+        }
+        match File::create(path.as_str()).await {
+            Ok(f) => {
+                eprintln!("{}\"{}\"", gettext("Recording live to: "), path);
+                self.file = Some(f);
+                if let Some(danmaku) = &mut self.danmaku {
+                    let b = format!("{}.{:03}", self.base, self.index);
+                    danmaku.new_file(b.as_str());
+                }
+                true
+            }
+            Err(e) => {
+                eprintln!("{}\"{}\": {}", gettext("Can not create file: "), path, e);
+                false
+            }
+        }
+    }
+
+    /// Close current file.
+    async fn close_file(&mut self) {
+        if self.file.is_some() {
+            let mut f = self.file.take().unwrap();
+            let _ = f.flush().await;
+        }
+    }
+
+    /// Write data to current file.
+    async fn write(&mut self, data: &[u8]) -> bool {
+        match self.file.as_mut().unwrap().write_all(data).await {
+            Ok(_) => {
+                self.size += data.len();
+                true
+            }
+            Err(e) => {
+                eprintln!("{}{}", gettext("Can not write to file: "), e);
+                false
+            }
+        }
+    }
+
+    /// Record a FLV stream until it is interrupted or finished.
+    /// * `url` - Stream url
+    async fn record_flv(&mut self, url: &str) -> Option<RecordResult> {
+        let r = self.get(url).await;
+        if r.is_none() {
+            return Some(RecordResult::Interrupted);
+        }
+        let mut r = r.unwrap();
+        let interval = self.ro.as_ref().unwrap().segment_interval;
+        let mut reader = FlvReader::new();
+        let mut heads: Vec<FlvTag> = Vec::new();
+        let mut base_ts: Option<u32> = None;
+        loop {
+            let chunk = Self::read_chunk(&mut r).await;
+            if chunk.is_none() {
+                return Some(RecordResult::Interrupted);
+            }
+            reader.push(&chunk.unwrap());
+            loop {
+                let tag = match reader.next_tag() {
+                    Ok(Some(tag)) => tag,
+                    Ok(None) => break,
+                    Err(e) => {
+                        eprintln!("{}", e);
+                        return Some(RecordResult::Interrupted);
+                    }
+                };
+                let header = reader.header.as_ref().unwrap().clone();
+                let has_video = header[4] & 1 != 0;
+                if tag.typ == 18 || tag.is_sequence_header() {
+                    heads.retain(|t| t.typ != tag.typ);
+                    heads.push(tag.clone());
+                }
+                let split = match interval {
+                    Some(i) => {
+                        base_ts.is_some()
+                            && tag.timestamp.saturating_sub(base_ts.unwrap()) as u128 >= i.as_millis()
+                            && (tag.is_keyframe() || (!has_video && tag.typ == 8))
+                    }
+                    None => false,
+                };
+                if self.file.is_none() || split {
+                    if !self.new_file("flv").await {
+                        return None;
+                    }
+                    let mut data = header.clone();
+                    data.extend_from_slice(&[0, 0, 0, 0]);
+                    for h in heads.iter() {
+                        data.extend_from_slice(&h.to_bytes(0));
+                    }
+                    if !self.write(&data).await {
+                        return None;
+                    }
+                    base_ts = None;
+                    if tag.typ == 18 || tag.is_sequence_header() {
+                        continue;
+                    }
+                }
+                if tag.typ != 18 && base_ts.is_none() {
+                    base_ts = Some(tag.timestamp);
+                }
+                let ts = match base_ts {
+                    Some(b) if tag.typ != 18 => tag.timestamp.saturating_sub(b),
+                    _ => 0,
+                };
+                if !self.write(&tag.to_bytes(ts)).await {
+                    return None;
+                }
+                self.received = true;
+                if self.limit_reached() {
+                    return Some(RecordResult::Finished);
+                }
+            }
+        }
+    }
+
+    /// Download a HLS segment.
+    /// * `url` - Segment url
+    async fn download_hls_segment(&self, url: &str) -> Option<Vec<u8>> {
+        let r = self.get(url).await;
+        let mut r = r?;
+        let mut data = Vec::new();
+        loop {
+            match timeout(READ_TIMEOUT, r.chunk()).await {
+                Ok(Ok(Some(b))) => data.extend_from_slice(&b),
+                Ok(Ok(None)) => return Some(data),
+                Ok(Err(e)) => {
+                    eprintln!("{}", e);
+                    return None;
+                }
+                Err(_) => {
+                    eprintln!("{}", gettext("No data received from live stream for a long time."));
+                    return None;
+                }
+            }
+        }
+    }
+
+    /// Record a HLS stream until it is interrupted or finished.
+    /// * `url` - Playlist url
+    async fn record_hls(&mut self, url: &str) -> Option<RecordResult> {
+        let interval = self.ro.as_ref().unwrap().segment_interval;
+        let mut url = String::from(url);
+        let mut init: Option<(String, Vec<u8>)> = None;
+        let mut file_duration = 0f64;
+        loop {
+            let r = self.get(url.as_str()).await;
+            if r.is_none() {
+                return Some(RecordResult::Interrupted);
+            }
+            let t = timeout(READ_TIMEOUT, r.unwrap().text()).await;
+            let t = match t {
+                Ok(Ok(t)) => t,
+                _ => {
+                    eprintln!("{}", gettext("Can not get HLS playlist."));
+                    return Some(RecordResult::Interrupted);
+                }
+            };
+            let p = parse_m3u8(url.as_str(), t.as_str());
+            if p.is_none() {
+                eprintln!("{}", gettext("Invalid HLS playlist."));
+                return Some(RecordResult::Interrupted);
+            }
+            let p = p.unwrap();
+            if let Some(variant) = p.variant {
+                url = variant;
+                continue;
+            }
+            if let Some(m) = &p.map {
+                if init.is_none() || &init.as_ref().unwrap().0 != m {
+                    let d = self.download_hls_segment(m.as_str()).await;
+                    if d.is_none() {
+                        return Some(RecordResult::Interrupted);
+                    }
+                    init = Some((m.clone(), d.unwrap()));
+                    // New initialization section can not be appended to current file.
+                    self.close_file().await;
+                }
+            }
+            let ext = if init.is_some() { "mp4" } else { "ts" };
+            if self.hls_seq.is_some() && p.is_restarted(self.hls_seq.unwrap()) {
+                self.hls_seq = None;
+            }
+            for seg in p.segments.iter() {
+                if self.hls_seq.is_some() && seg.seq <= self.hls_seq.unwrap() {
+                    continue;
+                }
+                let data = self.download_hls_segment(seg.url.as_str()).await;
+                if data.is_none() {
+                    return Some(RecordResult::Interrupted);
+                }
+                let split = match interval {
+                    Some(i) => file_duration >= i.as_secs_f64(),
+                    None => false,
+                };
+                if self.file.is_none() || split {
+                    if !self.new_file(ext).await {
+                        return None;
+                    }
+                    file_duration = 0.0;
+                    if let Some(init) = &init {
+                        let d = init.1.clone();
+                        if !self.write(&d).await {
+                            return None;
+                        }
+                    }
+                }
+                if !self.write(&data.unwrap()).await {
+                    return None;
+                }
+                self.received = true;
+                file_duration += seg.duration;
+                self.hls_seq = Some(seg.seq);
+                if self.limit_reached() {
+                    return Some(RecordResult::Finished);
+                }
+            }
+            if p.ended {
+                return Some(RecordResult::Finished);
+            }
+            sleep(Duration::from_secs(std::cmp::max(1, p.target_duration / 2))).await;
+            if self.limit_reached() {
+                return Some(RecordResult::Finished);
+            }
+        }
+    }
+
+    /// Record live until it is ended or a limit is hit.
+    async fn record(&mut self) -> bool {
+        let live = self.vi.live.as_ref().unwrap().clone();
+        let mut source = live.source;
+        let mut urls = live.urls;
+        let mut retry = 0;
+        let mut index = 0;
+        loop {
+            self.received = false;
+            let url = urls[index % urls.len()].clone();
+            let r = match live.protocol {
+                LiveProtocol::Flv => self.record_flv(url.as_str()).await,
+                LiveProtocol::Hls => self.record_hls(url.as_str()).await,
+            };
+            self.close_file().await;
+            if r.is_none() {
+                return false;
+            }
+            if r.unwrap() == RecordResult::Finished || self.limit_reached() {
+                break;
+            }
+            if self.received {
+                retry = 0;
+            }
+            if let Some(source) = &mut source {
+                match source.get_status().await {
+                    LiveStatus::Ended => {
+                        eprintln!("{}", gettext("The live is ended."));
+                        break;
+                    }
+                    LiveStatus::Living(u) => {
+                        if !u.is_empty() {
+                            urls = u;
+                            index = 0;
+                        }
+                    }
+                    LiveStatus::Failed => {
+                        index += 1;
+                    }
+                }
+            } else {
+                index += 1;
+            }
+            retry += 1;
+            if retry > self.ro.as_ref().unwrap().max_retry {
+                eprintln!("{}", gettext("Too many reconnections. Stop recording."));
+                break;
+            }
+            let s = gettext("Live stream is interrupted. Reconnecting (<retry>/<max>)...")
+                .replace("<retry>", format!("{}", retry).as_str())
+                .replace("<max>", format!("{}", self.ro.as_ref().unwrap().max_retry).as_str());
+            eprintln!("{}", s);
+            sleep(self.ro.as_ref().unwrap().retry_interval).await;
+        }
+        self.size > 0
+    }
+}
+
+#[async_trait]
+impl Downloader for LiveDownloader {
+    async fn download_async(&mut self) -> Result<()> {
+        let ro = LiveRecordOptions::from_opt(&self.opt, &self.se);
+        if ro.is_none() {
+            return Err(Error::Config(String::from(gettext("Live record options are invalid."))));
+        }
+        self.ro = ro;
+        let base = get_output_base(&self.vi.meta, &self.opt, &self.se);
+        if base.is_none() {
+            return Err(Error::Config(String::from(gettext("Can not generate output file name."))));
+        }
+        self.base = base.unwrap();
+        let c = Client::builder().connect_timeout(READ_TIMEOUT).build();
+        match c {
+            Ok(c) => self.client = Some(c),
+            Err(e) => {
+                return Err(Error::Download(format!("{}", e)));
+            }
+        }
+        let live = self.vi.live.as_ref().unwrap();
+        if let Some(source) = &live.source {
+            self.danmaku = source.start_danmaku();
+        }
+        self.start = Instant::now();
+        let r = self.record().await;
+        if let Some(danmaku) = &mut self.danmaku {
+            danmaku.stop().await;
+        }
+        let s = gettext("Live recording finished. <size> recorded in <count> files.")
+            .replace("<size>", format_size(self.size).as_str())
+            .replace("<count>", format!("{}", self.index).as_str());
+        eprintln!("{}", s);
+        if !r {
+            return Err(Error::Download(String::from(gettext("Nothing is recorded from the live stream."))));
+        }
+        Ok(())
+    }
+
+    fn typ() -> DownloaderType {
+        DownloaderType::Video
+    }
+}
+
+impl VideoDownloader for LiveDownloader {
+    fn match_vi(vi: &VideoInfo) -> bool {
+        vi.typ == VideoPlayInfoType::Live
+    }
+}
+
+#[test]
+fn test_flv_reader() {
+    let tag = FlvTag {
+        typ: 9,
+        timestamp: 0x01020304,
+        data: vec![0x17, 0x00, 0x01],
+    };
+    assert!(tag.is_keyframe());
+    assert!(tag.is_sequence_header());
+    let mut data = b"FLV\x01\x05\x00\x00\x00\x09\x00\x00\x00\x00".to_vec();
+    data.extend_from_slice(&tag.to_bytes(tag.timestamp));
+    let mut r = FlvReader::new();
+    r.push(&data[..20]);
+    assert!(r.next_tag().unwrap().is_none());
+    r.push(&data[20..]);
+    let t = r.next_tag().unwrap().unwrap();
+    assert_eq!(9, t.typ);
+    assert_eq!(0x01020304, t.timestamp);
+    assert_eq!(vec![0x17, 0x00, 0x01], t.data);
+    assert!(r.next_tag().unwrap().is_none());
+    assert_eq!(5, r.header.as_ref().unwrap()[4]);
+    let mut r = FlvReader::new();
+    r.push(b"<html>.....");
+    assert!(r.next_tag().is_err());
+}
+
+#[test]
+fn test_parse_m3u8() {
+    let s = "#EXTM3U\n#EXT-X-TARGETDURATION:4\n#EXT-X-MEDIA-SEQUENCE:100\n#EXT-X-MAP:URI=\"h1.m4s\"\n#EXTINF:1.5,\n101.m4s\n#EXTINF:2.0,\n/live/102.m4s?a=1\n";
+    let p = parse_m3u8("https://a.com/live/x/index.m3u8?t=1", s).unwrap();
+    assert_eq!(Some(String::from("https://a.com/live/x/h1.m4s")), p.map);
+    assert_eq!(2, p.segments.len());
+    assert_eq!(101, p.segments[1].seq);
+    assert_eq!(1.5, p.segments[0].duration);
+    assert_eq!("https://a.com/live/102.m4s?a=1", p.segments[1].url);
+    assert_eq!(4, p.target_duration);
+    assert!(!p.ended);
+    assert!(!p.is_restarted(101));
+    assert!(!p.is_restarted(103));
+    assert!(p.is_restarted(200));
+    let s = "#EXTM3U\n#EXT-X-STREAM-INF:BANDWIDTH=1\nhttps://b.com/a.m3u8\n";
+    let p = parse_m3u8("https://a.com/index.m3u8", s).unwrap();
+    assert_eq!(Some(String::from("https://b.com/a.m3u8")), p.variant);
+    assert!(parse_m3u8("https://a.com/", "<html>").is_none());
+}
@@ -1,4 +1,13 @@
 pub mod aria2c;
+pub mod chapters;
+pub mod cover;
+pub mod dash;
 pub mod downloader;
 pub mod ffmpeg;
+pub mod http;
+pub mod live;
+pub mod output;
+pub mod segments;
 pub mod single;
+pub mod state;
+pub mod subtitle;
@@ -0,0 +1,336 @@
+extern crate chrono;
+
+use crate::error::Error;
+use crate::error::Result;
+use crate::getopt::OptStore;
+use crate::i18n::gettext;
+use crate::metadata::VideoInfo;
+use crate::metadata::VideoMetadata;
+use crate::settings::SettingStore;
+use crate::utils::path::filter_file_name;
+use chrono::Local;
+use json::JsonValue;
+use std::fmt::Write;
+use std::fs::create_dir_all;
+use std::path::Path;
+
+/// Default output file name template
+pub const DEFAULT_TEMPLATE: &str = "{title}";
+/// The maximum length (bytes) of a file name without extension.
+/// Some space is left for extensions and temporary file suffixes.
+const MAX_NAME_LENGTH: usize = 200;
+
+/// Get file extension from url. Such as `mp4`.
+/// * `url` - URL
+pub fn get_url_ext(url: &str) -> Option<&str> {
+    let path = match url.find('?') {
+        Some(i) => &url[..i],
+        None => url,
+    };
+    let name = match path.rfind('/') {
+        Some(i) => &path[i + 1..],
+        None => path,
+    };
+    match name.rfind('.') {
+        Some(i) => {
+            let ext = &name[i + 1..];
+            if !ext.is_empty() && ext.len() <= 4 {
+                Some(ext)
+            } else {
+                None
+            }
+        }
+        None => None,
+    }
+}
+
+/// Get the default extension of output file. Such as `mp4`.
+/// * `vi` - Video information
+pub fn get_default_ext(vi: &VideoInfo) -> &'static str {
+    if vi.audio_only {
+        "m4a"
+    } else {
+        "mp4"
+    }
+}
+
+/// Parse the name of output container.
+/// * `s` - Container name. Available value: mp4, mkv
+pub fn parse_container(s: &str) -> Option<&'static str> {
+    match s.to_lowercase().as_str() {
+        "mp4" => Some("mp4"),
+        "mkv" => Some("mkv"),
+        _ => None,
+    }
+}
+
+/// Check the value of `container` setting.
+/// * `value` - The value of setting
+pub fn check_container(value: &JsonValue) -> bool {
+    match value.as_str() {
+        Some(s) => parse_container(s).is_some(),
+        None => false,
+    }
+}
+
+/// Get the extension of output file which is merged by ffmpeg. The container can be set by `container` option.
+/// * `vi` - Video information
+/// * `opt` - Options
+/// * `se` - Settings
+pub fn get_container_ext(vi: &VideoInfo, opt: &OptStore, se: &SettingStore) -> Result<&'static str> {
+    let c = match opt.get_option("container") {
+        Some(c) => Some(c),
+        None => match se.get_settings("basic", "container") {
+            Some(c) => c.as_str().map(String::from),
+            None => None,
+        },
+    };
+    let c = match c {
+        Some(c) => match parse_container(c.as_str()) {
+            Some(c) => c,
+            None => {
+                let s = gettext("The value of option \"<key>\" is invalid.").replace("<key>", "container");
+                return Err(Error::Config(s));
+            }
+        },
+        None => "mp4",
+    };
+    if c == "mkv" {
+        return Ok(if vi.audio_only { "mka" } else { "mkv" });
+    }
+    Ok(get_default_ext(vi))
+}
+
+/// Get the value of a template field.
+/// * `meta` - Video metadata
+/// * `key` - Field name
+/// * `fmt` - Format specification. Only used by `date`
+fn get_field(meta: &VideoMetadata, key: &str, fmt: Option<&str>) -> Option<String> {
+    match key {
+        "title" => meta.title.clone(),
+        "author" => meta.author.clone(),
+        "album" => meta.album.clone(),
+        "album_artist" => meta.album_artist.clone(),
+        "video_id" => meta.video_id.clone(),
+        "track" => meta.track.as_ref().map(|t| format!("{}", t.no())),
+        "track_total" => meta.track.as_ref().map(|t| format!("{}", t.total())),
+        "date" => {
+            let d = meta.date.as_ref()?.with_timezone(&Local);
+            let f = fmt.unwrap_or("%Y-%m-%d");
+            let mut s = String::new();
+            match write!(s, "{}", d.format(f)) {
+                Ok(_) => Some(s),
+                Err(_) => {
+                    eprintln!("{}\"{}\"", gettext("Invalid date format: "), f);
+                    None
+                }
+            }
+        }
+        _ => meta.extra.get(key).cloned(),
+    }
+}
+
+/// Expand a template. Fields are enclosed in braces, such as `{title}` or `{date:%Y-%m-%d}`.
+/// Use `{{` and `}}` to insert braces. Unknown fields are expanded to empty string.
+/// Path separators in field values are replaced, so only separators in template create directories.
+/// * `tpl` - Template
+/// * `meta` - Video metadata
+pub fn expand_template(tpl: &str, meta: &VideoMetadata) -> String {
+    let mut r = String::new();
+    let mut chars = tpl.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '{' {
+            if chars.peek() == Some(&'{') {
+                chars.next();
+                r.push('{');
+                continue;
+            }
+            let mut field = String::new();
+            let mut closed = false;
+            for c in chars.by_ref() {
+                if c == '}' {
+                    closed = true;
+                    break;
+                }
+                field.push(c);
+            }
+            if !closed {
+                r.push('{');
+                r += field.as_str();
+                break;
+            }
+            let (key, fmt) = match field.find(':') {
+                Some(i) => (&field[..i], Some(&field[i + 1..])),
+                None => (field.as_str(), None),
+            };
+            let v = get_field(meta, key.trim(), fmt);
+            if let Some(v) = v {
+                r += v.replace("/", "_").replace("\\", "_").as_str();
+            }
+        } else if c == '}' {
+            if chars.peek() == Some(&'}') {
+                chars.next();
+            }
+            r.push('}');
+        } else {
+            r.push(c);
+        }
+    }
+    r
+}
+
+/// Truncate a string to a maximum length in bytes without breaking characters.
+/// * `s` - String
+/// * `len` - Maximum length
+fn truncate_str(s: &str, len: usize) -> &str {
+    if s.len() <= len {
+        return s;
+    }
+    let mut i = len;
+    while !s.is_char_boundary(i) {
+        i -= 1;
+    }
+    &s[..i]
+}
+
+/// Sanitise a path component.
+/// * `s` - Path component
+fn sanitise_component(s: &str) -> String {
+    let s = filter_file_name(&s).unwrap();
+    let s = String::from(truncate_str(s.trim(), MAX_NAME_LENGTH));
+    if s == "." || s == ".." {
+        return s.replace(".", "_");
+    }
+    if cfg!(windows) {
+        // Windows does not allow file names end with dot or space, and reserves some device names.
+        let s = String::from(s.trim_end_matches(['.', ' ']));
+        let stem = match s.find('.') {
+            Some(i) => &s[..i],
+            None => s.as_str(),
+        };
+        let stem = stem.to_uppercase();
+        let reserved = ["CON", "PRN", "AUX", "NUL"];
+        if reserved.contains(&stem.as_str())
+            || ((stem.starts_with("COM") || stem.starts_with("LPT"))
+                && stem.len() == 4
+                && stem.chars().nth(3).unwrap().is_ascii_digit())
+        {
+            return format!("_{}", s);
+        }
+        return s;
+    }
+    s
+}
+
+/// Split the root of a path. Such as `/`, `//` (UNC path) or `C:/`. Separators in root are normalised to `/`.
+/// * `s` - Path
+///
+/// Returns the root and remaining path.
+fn split_root(s: &str) -> (String, &str) {
+    let b = s.as_bytes();
+    let is_sep = |c: u8| c == b'/' || c == b'\\';
+    if b.len() >= 2 && b[0].is_ascii_alphabetic() && b[1] == b':' {
+        if b.len() >= 3 && is_sep(b[2]) {
+            return (format!("{}/", &s[..2]), &s[3..]);
+        }
+        return (String::from(&s[..2]), &s[2..]);
+    }
+    if b.len() >= 2 && is_sep(b[0]) && is_sep(b[1]) {
+        return (String::from("//"), &s[2..]);
+    }
+    if !b.is_empty() && is_sep(b[0]) {
+        return (String::from("/"), &s[1..]);
+    }
+    (String::new(), s)
+}
+
+/// Convert expanded template to a safe path. The root of path (such as `C:\`) is kept and
+/// other path components will be sanitised.
+/// * `s` - Expanded template
+pub fn sanitise_path(s: &str) -> String {
+    let (root, s) = split_root(s);
+    let mut r: Vec<String> = Vec::new();
+    for c in s.split(['/', '\\']) {
+        let c = sanitise_component(c);
+        if !c.is_empty() {
+            r.push(c);
+        }
+    }
+    format!("{}{}", root, r.join("/"))
+}
+
+/// Get the output file name template from options and settings.
+/// * `opt` - Options
+/// * `se` - Settings
+pub fn get_template(opt: &OptStore, se: &SettingStore) -> String {
+    let o = opt.get_option("output");
+    if let Some(o) = o {
+        return o;
+    }
+    let s = se.get_settings("basic", "output");
+    if let Some(s) = s {
+        if s.is_string() {
+            return String::from(s.as_str().unwrap());
+        }
+    }
+    String::from(DEFAULT_TEMPLATE)
+}
+
+/// Generate output location (without extension) for a video and create its parent directories.
+/// * `meta` - Video metadata
+/// * `opt` - Options
+/// * `se` - Settings
+pub fn get_output_base(meta: &VideoMetadata, opt: &OptStore, se: &SettingStore) -> Option<String> {
+    let tpl = get_template(opt, se);
+    let mut p = sanitise_path(expand_template(tpl.as_str(), meta).as_str());
+    if p.is_empty() || p.ends_with('/') {
+        let name = match &meta.video_id {
+            Some(id) => sanitise_component(id.as_str()),
+            None => String::from("video"),
+        };
+        p += name.as_str();
+    }
+    let parent = Path::new(p.as_str()).parent();
+    if let Some(parent) = parent {
+        if !parent.as_os_str().is_empty() && !parent.exists() {
+            match create_dir_all(parent) {
+                Ok(_) => {}
+                Err(e) => {
+                    eprintln!("{}\"{}\": {}", gettext("Can not create directory: "), parent.display(), e);
+                    return None;
+                }
+            }
+        }
+    }
+    Some(p)
+}
+
+#[test]
+fn test_expand_template() {
+    use crate::metadata::NoInTotal;
+    use chrono::TimeZone;
+    use chrono::Utc;
+    let mut m = VideoMetadata::default();
+    m.title = Some(String::from("A/B"));
+    m.author = Some(String::from("up"));
+    m.track = NoInTotal::new(2, 3);
+    m.date = Some(Utc.timestamp_opt(1600000000, 0).unwrap());
+    m.extra.insert(String::from("bvid"), String::from("BV1xx411c7mD"));
+    assert_eq!("up/A_B - 2", expand_template("{author}/{title} - {track}", &m));
+    assert_eq!("BV1xx411c7mD{}", expand_template("{bvid}{{}}{unknown}", &m));
+    assert_eq!("2020", expand_template("{date:%Y}", &m));
+}
+
+#[test]
+fn test_sanitise_path() {
+    assert_eq!("a/b_c", sanitise_path("a//b?c"));
+    assert_eq!("__/a", sanitise_path("../a"));
+    let long = "测".repeat(100);
+    assert_eq!(198, sanitise_path(long.as_str()).len());
+    assert_eq!("/a/b", sanitise_path("/a/b"));
+    assert_eq!("C:/Videos/a_b", sanitise_path("C:\\Videos\\a:b"));
+    assert_eq!("//server/share/a", sanitise_path("\\\\server\\share\\a"));
+    assert_eq!(Some("mkv"), parse_container("MKV"));
+    assert_eq!(None, parse_container("avi"));
+    assert_eq!(Some("flv"), get_url_ext("https://a.com/1.flv?a=b"));
+}
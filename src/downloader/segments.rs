@@ -0,0 +1,189 @@
+use crate::downloader::aria2c::Aria2c;
+use crate::downloader::chapters::handle_chapters;
+use crate::downloader::cover::handle_cover;
+use crate::downloader::downloader::download_file;
+use crate::downloader::downloader::enable_add_metadata;
+use crate::downloader::downloader::finish_temp_output;
+use crate::downloader::downloader::is_downloaded;
+use crate::downloader::downloader::temp_output_name;
+use crate::downloader::downloader::Downloader;
+use crate::downloader::downloader::DownloaderType;
+use crate::downloader::downloader::VideoDownloader;
+use crate::downloader::ffmpeg::ffmetafile::FFMetaFile;
+use crate::downloader::ffmpeg::ffmpeg::FFmpeg;
+use crate::downloader::http::HttpDownloader;
+use crate::downloader::output::get_container_ext;
+use crate::downloader::output::get_output_base;
+use crate::downloader::output::get_url_ext;
+use crate::downloader::subtitle::handle_subtitles;
+use crate::getopt::OptStore;
+use crate::error::Error;
+use crate::error::Result;
+use crate::i18n::gettext;
+use crate::metadata::VideoInfo;
+use crate::metadata::VideoPlayInfoType;
+use crate::settings::SettingStore;
+use crate::utils::runtime::spawn_blocking;
+use async_trait::async_trait;
+use std::clone::Clone;
+use std::fs::remove_file;
+
+/// Get file extension from the url of segment. If not found, `flv` will be returned.
+/// * `url` - The url of segment
+fn get_segment_ext(url: &str) -> &str {
+    match get_url_ext(url) {
+        Some(ext) => ext,
+        None => "flv",
+    }
+}
+
+/// A downloader for segmented video. It will download every segment, and then concatenate them by using ffmpeg.
+#[derive(Clone)]
+pub struct SegmentsDownloader {
+    /// Extract Information
+    vi: VideoInfo,
+    /// Options
+    opt: OptStore,
+    /// Settings
+    se: SettingStore,
+    /// Aria2c interface
+    a2: Option<Aria2c>,
+    /// Built-in HTTP downloader
+    hd: Option<HttpDownloader>,
+    /// Ffmpeg interface
+    ff: Option<FFmpeg>,
+}
+
+impl SegmentsDownloader {
+    pub fn new(
+        vi: &VideoInfo,
+        opt: &OptStore,
+        se: &SettingStore,
+        a2: Option<&Aria2c>,
+        hd: Option<&HttpDownloader>,
+        ff: Option<&FFmpeg>,
+    ) -> Self {
+        let a2 = a2.cloned();
+        let hd = hd.cloned();
+        let ff = ff.cloned();
+        Self {
+            vi: vi.clone(),
+            opt: opt.clone(),
+            se: se.clone(),
+            a2,
+            hd,
+            ff,
+        }
+    }
+
+    /// Download a segment
+    /// * `url` - The url of segment
+    /// * `output` - Output file name
+    async fn download_segment(&mut self, url: &str, output: &str) -> Result<()> {
+        download_file(
+            &self.vi,
+            self.a2.as_mut(),
+            self.hd.as_mut(),
+            url,
+            output,
+        )
+        .await
+    }
+}
+
+impl SegmentsDownloader {
+    /// Download the video and handle the output.
+    async fn download_video(&mut self) -> Result<()> {
+        if self.ff.is_none() {
+            return Err(Error::Download(String::from(gettext("Ffmpeg is needed to concatenate segments."))));
+        }
+        let base = get_output_base(&self.vi.meta, &self.opt, &self.se);
+        if base.is_none() {
+            return Err(Error::Config(String::from(gettext("Can not generate output file name."))));
+        }
+        let base = base.unwrap();
+        let ext = get_container_ext(&self.vi, &self.opt, &self.se)?;
+        let output = format!("{}.{}", base, ext);
+        if is_downloaded(output.as_str()) {
+            eprintln!("{}\"{}\"", gettext("File already downloaded: "), output);
+            return Ok(());
+        }
+        let segments = self.vi.segments.as_ref().unwrap().clone();
+        let total = segments.len();
+        let mut files: Vec<String> = Vec::new();
+        for (i, seg) in segments.iter().enumerate() {
+            let s = gettext("Downloading segment <num>/<total>.")
+                .replace("<num>", format!("{}", i + 1).as_str())
+                .replace("<total>", format!("{}", total).as_str());
+            eprintln!("{}", s);
+            let f = format!("{}.part{}.{}", base, i + 1, get_segment_ext(seg.url.as_str()));
+            self.download_segment(&seg.url, &f).await?;
+            files.push(f);
+        }
+        let meta = if enable_add_metadata(&self.opt, &self.se) {
+            Some(FFMetaFile::from_video_metadata(&self.vi.meta))
+        } else {
+            None
+        };
+        let ff = self.ff.as_ref().unwrap().clone();
+        let tmp = temp_output_name(base.as_str(), ext);
+        let (l, t) = (files.clone(), tmp.clone());
+        if !spawn_blocking(move || ff.concat(&l, meta.as_ref(), t.as_str())).await {
+            return Err(Error::Download(String::from(gettext("Can not concatenate segments."))));
+        }
+        handle_chapters(&self.vi.meta, &self.opt, &self.se, base.as_str())?;
+        handle_subtitles(
+            &self.vi,
+            &self.opt,
+            &self.se,
+            self.ff.as_ref(),
+            base.as_str(),
+            tmp.as_str(),
+        )
+        .await?;
+        handle_cover(
+            &self.vi,
+            &self.opt,
+            &self.se,
+            self.a2.as_mut(),
+            self.hd.as_mut(),
+            self.ff.as_ref(),
+            base.as_str(),
+            tmp.as_str(),
+        )
+        .await?;
+        finish_temp_output(tmp.as_str(), output.as_str())?;
+        for f in files.iter() {
+            let _ = remove_file(f);
+        }
+        Ok(())
+    }
+
+}
+
+#[async_trait]
+impl Downloader for SegmentsDownloader {
+    async fn download_async(&mut self) -> Result<()> {
+        self.download_video().await
+    }
+
+    fn typ() -> DownloaderType {
+        DownloaderType::Video
+    }
+}
+
+impl VideoDownloader for SegmentsDownloader {
+    fn match_vi(vi: &VideoInfo) -> bool {
+        if vi.typ == VideoPlayInfoType::Segments {
+            return true;
+        }
+        false
+    }
+}
+
+#[test]
+fn test_get_segment_ext() {
+    assert_eq!("flv", get_segment_ext("https://a.com/v/123-1-80.flv?e=ig8euxZM2rNcNbdlhoNvNC8BqJIzNbfq"));
+    assert_eq!("mp4", get_segment_ext("https://a.com/v/123-1-16.mp4"));
+    assert_eq!("flv", get_segment_ext("https://a.com/v/123?a=b.mp4"));
+}
@@ -1,14 +1,32 @@
 use crate::downloader::aria2c::Aria2c;
+use crate::downloader::chapters::handle_chapters;
+use crate::downloader::cover::handle_cover;
+use crate::downloader::downloader::download_file;
+use crate::downloader::downloader::enable_add_metadata;
+use crate::downloader::downloader::finish_temp_output;
+use crate::downloader::downloader::is_downloaded;
 use crate::downloader::downloader::Downloader;
 use crate::downloader::downloader::DownloaderType;
+use crate::downloader::downloader::temp_output_name;
 use crate::downloader::downloader::VideoDownloader;
+use crate::downloader::ffmpeg::ffmetafile::FFMetaFile;
+use crate::downloader::ffmpeg::ffmpeg::FFmpeg;
+use crate::downloader::http::HttpDownloader;
+use crate::downloader::output::get_default_ext;
+use crate::downloader::output::get_output_base;
+use crate::downloader::output::get_url_ext;
+use crate::downloader::subtitle::handle_subtitles;
 use crate::getopt::OptStore;
-use crate::http_client::gen_cookie_header;
+use crate::error::Error;
+use crate::error::Result;
+use crate::i18n::gettext;
 use crate::metadata::VideoInfo;
 use crate::metadata::VideoPlayInfoType;
 use crate::settings::SettingStore;
-use crate::utils::headers::header_map_to_hash_map;
+use crate::utils::runtime::spawn_blocking;
+use async_trait::async_trait;
 use std::clone::Clone;
+use std::fs::remove_file;
 
 /// A downloader for single URL.
 pub struct SignleUrlDownloader {
@@ -20,45 +38,122 @@ pub struct SignleUrlDownloader {
     se: SettingStore,
     /// Aria2c interface
     a2: Option<Aria2c>,
+    /// Built-in HTTP downloader
+    hd: Option<HttpDownloader>,
+    /// Ffmpeg interface
+    ff: Option<FFmpeg>,
 }
 
 impl SignleUrlDownloader {
-    pub fn new(vi: &VideoInfo, opt: &OptStore, se: &SettingStore, a2: Option<&Aria2c>) -> Self {
-        let a2 = if a2.is_none() {
-            None
-        } else {
-            Some(a2.unwrap().clone())
-        };
+    pub fn new(
+        vi: &VideoInfo,
+        opt: &OptStore,
+        se: &SettingStore,
+        a2: Option<&Aria2c>,
+        hd: Option<&HttpDownloader>,
+        ff: Option<&FFmpeg>,
+    ) -> Self {
+        let a2 = a2.cloned();
+        let hd = hd.cloned();
+        let ff = ff.cloned();
         Self {
             vi: vi.clone(),
             opt: opt.clone(),
             se: se.clone(),
             a2,
+            hd,
+            ff,
+        }
+    }
+
+    /// Add metadata to downloaded file by remuxing it with ffmpeg.
+    /// * `input` - The location of downloaded file
+    /// * `output` - The location of output file
+    async fn add_metadata(&self, input: &str, output: &str) -> Result<()> {
+        let ff = self.ff.as_ref().unwrap().clone();
+        let meta = FFMetaFile::from_video_metadata(&self.vi.meta);
+        let (i, o) = (String::from(input), String::from(output));
+        if !spawn_blocking(move || ff.add_metadata(i.as_str(), &meta, o.as_str())).await {
+            let _ = remove_file(output);
+            return Err(Error::Download(String::from(gettext("Can not add metadata to output file."))));
         }
+        Ok(())
     }
 }
 
-impl Downloader for SignleUrlDownloader {
-    fn download(&mut self) -> bool {
-        let url = self.vi.url.as_ref().unwrap();
-        if self.a2.is_some() {
-            let a2 = self.a2.as_mut().unwrap();
-            if self.vi.headers.is_some() {
-                header_map_to_hash_map(self.vi.headers.as_ref().unwrap(), &mut a2.headers);
-            }
-            if self.vi.cookies.is_some() {
-                let c = gen_cookie_header(self.vi.cookies.as_ref().unwrap(), url);
-                if c.len() > 0 {
-                    a2.headers.insert(String::from("cookie"), c);
-                }
+impl SignleUrlDownloader {
+    /// Download the video and handle the output.
+    async fn download_video(&mut self) -> Result<()> {
+        let url = self.vi.url.as_ref().unwrap().clone();
+        let base = get_output_base(&self.vi.meta, &self.opt, &self.se);
+        if base.is_none() {
+            return Err(Error::Config(String::from(gettext("Can not generate output file name."))));
+        }
+        let ext = match get_url_ext(url.as_str()) {
+            Some(ext) => ext,
+            None => get_default_ext(&self.vi),
+        };
+        let base = base.unwrap();
+        let output = format!("{}.{}", base, ext);
+        if is_downloaded(output.as_str()) {
+            eprintln!("{}\"{}\"", gettext("File already downloaded: "), output);
+            return Ok(());
+        }
+        // Keep the downloaded file until the output is finished, so an interrupted run can post-process it again.
+        let file = format!("{}.download.{}", base, ext);
+        download_file(
+            &self.vi,
+            self.a2.as_mut(),
+            self.hd.as_mut(),
+            url.as_str(),
+            file.as_str(),
+        )
+        .await?;
+        let tmp = temp_output_name(base.as_str(), ext);
+        let add_metadata = enable_add_metadata(&self.opt, &self.se);
+        if add_metadata && self.ff.is_some() {
+            self.add_metadata(file.as_str(), tmp.as_str()).await?;
+        } else {
+            if add_metadata {
+                eprintln!("{}", gettext("Ffmpeg is not available. Metadata will not be added."));
             }
-            if self.vi.meta.title.is_some() {
-                let output = self.vi.meta.title.as_ref().unwrap().clone() + ".mp4";
-                a2.set_output(Some(output).as_ref());
+            // Copy rather than rename, so the downloaded file is still there if post-processing is interrupted.
+            match tokio::fs::copy(file.as_str(), tmp.as_str()).await {
+                Ok(_) => {}
+                Err(e) => return Err(Error::io(file.as_str(), e)),
             }
-            a2.download(url);
         }
-        false
+        handle_chapters(&self.vi.meta, &self.opt, &self.se, base.as_str())?;
+        handle_subtitles(
+            &self.vi,
+            &self.opt,
+            &self.se,
+            self.ff.as_ref(),
+            base.as_str(),
+            tmp.as_str(),
+        )
+        .await?;
+        handle_cover(
+            &self.vi,
+            &self.opt,
+            &self.se,
+            self.a2.as_mut(),
+            self.hd.as_mut(),
+            self.ff.as_ref(),
+            base.as_str(),
+            tmp.as_str(),
+        )
+        .await?;
+        finish_temp_output(tmp.as_str(), output.as_str())?;
+        let _ = remove_file(file.as_str());
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Downloader for SignleUrlDownloader {
+    async fn download_async(&mut self) -> Result<()> {
+        self.download_video().await
     }
 
     fn typ() -> DownloaderType {
@@ -82,6 +177,8 @@ impl Clone for SignleUrlDownloader {
             opt: self.opt.clone(),
             se: self.se.clone(),
             a2: self.a2.clone(),
+            hd: self.hd.clone(),
+            ff: self.ff.clone(),
         }
     }
 }
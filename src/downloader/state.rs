@@ -0,0 +1,205 @@
+extern crate json;
+
+use crate::error::Error;
+use crate::error::Result;
+use json::JsonValue;
+use std::clone::Clone;
+use std::fs::remove_file;
+use std::fs::File;
+use std::io::Read;
+use std::io::Write;
+use std::path::Path;
+
+/// Get the location of state file for a output file.
+/// * `output` - The location of output file
+pub fn state_file_name(output: &str) -> String {
+    format!("{}.bili-state", output)
+}
+
+/// Download state of a file. It is saved next to the output file and used to resume download.
+#[derive(Clone)]
+pub struct DownloadState {
+    /// URL
+    pub url: String,
+    /// ETag returned by server
+    pub etag: Option<String>,
+    /// Last-Modified returned by server
+    pub last_modified: Option<String>,
+    /// Total size
+    pub size: usize,
+    /// Ranges. `(start, end, downloaded bytes)`, `end` is inclusive.
+    pub ranges: Vec<(usize, usize, usize)>,
+}
+
+impl DownloadState {
+    pub fn new(
+        url: &str,
+        etag: Option<String>,
+        last_modified: Option<String>,
+        size: usize,
+        ranges: &[(usize, usize)],
+    ) -> Self {
+        let mut r: Vec<(usize, usize, usize)> = Vec::new();
+        for (start, end) in ranges.iter() {
+            r.push((*start, *end, 0));
+        }
+        Self {
+            url: String::from(url),
+            etag,
+            last_modified,
+            size,
+            ranges: r,
+        }
+    }
+
+    /// Parse state from JSON object.
+    pub fn from_json(obj: &JsonValue) -> Option<Self> {
+        let url = obj["url"].as_str();
+        let size = obj["size"].as_usize();
+        if url.is_none() || size.is_none() || !obj["ranges"].is_array() {
+            return None;
+        }
+        let mut ranges: Vec<(usize, usize, usize)> = Vec::new();
+        for r in obj["ranges"].members() {
+            let start = r[0].as_usize();
+            let end = r[1].as_usize();
+            let done = r[2].as_usize();
+            if start.is_none() || end.is_none() || done.is_none() {
+                return None;
+            }
+            ranges.push((start.unwrap(), end.unwrap(), done.unwrap()));
+        }
+        let etag = obj["etag"].as_str().map(String::from);
+        let last_modified = obj["last_modified"].as_str().map(String::from);
+        Some(Self {
+            url: String::from(url.unwrap()),
+            etag,
+            last_modified,
+            size: size.unwrap(),
+            ranges,
+        })
+    }
+
+    /// Convert state to JSON object.
+    pub fn to_json(&self) -> JsonValue {
+        let mut ranges = JsonValue::new_array();
+        for (start, end, done) in self.ranges.iter() {
+            ranges.push(json::array![*start, *end, *done]).unwrap();
+        }
+        let mut obj = json::object! {
+            "url": self.url.clone(),
+            "size": self.size,
+            "ranges": ranges,
+        };
+        if let Some(etag) = &self.etag {
+            obj["etag"] = JsonValue::from(etag.clone());
+        }
+        if let Some(last_modified) = &self.last_modified {
+            obj["last_modified"] = JsonValue::from(last_modified.clone());
+        }
+        obj
+    }
+
+    /// Check whether the remote file is still the same one.
+    /// The URL is not compared because signed URLs change every time.
+    /// * `etag` - Current ETag
+    /// * `last_modified` - Current Last-Modified
+    /// * `size` - Current total size
+    pub fn is_same_file(
+        &self,
+        etag: &Option<String>,
+        last_modified: &Option<String>,
+        size: usize,
+    ) -> bool {
+        if self.size != size {
+            return false;
+        }
+        if self.etag.is_some() && etag.is_some() && self.etag != *etag {
+            return false;
+        }
+        if self.last_modified.is_some()
+            && last_modified.is_some()
+            && self.last_modified != *last_modified
+        {
+            return false;
+        }
+        true
+    }
+
+    /// Return downloaded bytes.
+    pub fn downloaded(&self) -> usize {
+        let mut s = 0;
+        for (_, _, done) in self.ranges.iter() {
+            s += done;
+        }
+        s
+    }
+
+    /// Load state from a file.
+    /// * `path` - The location of state file
+    ///
+    /// Return None if the file does not exist or is not a valid state file.
+    pub fn load(path: &str) -> Result<Option<Self>> {
+        if !Path::new(path).exists() {
+            return Ok(None);
+        }
+        let mut s = String::from("");
+        let r = match File::open(path) {
+            Ok(mut f) => f.read_to_string(&mut s),
+            Err(e) => Err(e),
+        };
+        match r {
+            Ok(_) => {}
+            Err(e) => return Err(Error::io(path, e)),
+        }
+        match json::parse(s.as_str()) {
+            Ok(obj) => Ok(Self::from_json(&obj)),
+            Err(_) => Ok(None),
+        }
+    }
+
+    /// Save state to a file.
+    /// * `path` - The location of state file
+    pub fn save(&self, path: &str) -> Result<()> {
+        let r = match File::create(path) {
+            Ok(mut f) => f.write_all(self.to_json().dump().as_bytes()),
+            Err(e) => Err(e),
+        };
+        match r {
+            Ok(_) => Ok(()),
+            Err(e) => Err(Error::io(path, e)),
+        }
+    }
+
+    /// Remove state file.
+    /// * `path` - The location of state file
+    pub fn remove(path: &str) -> Result<()> {
+        if Path::new(path).exists() {
+            match remove_file(path) {
+                Ok(_) => {}
+                Err(e) => return Err(Error::io(path, e)),
+            }
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_download_state() {
+    let mut s = DownloadState::new(
+        "https://a.com/a.mp4",
+        Some(String::from("\"abc\"")),
+        None,
+        100,
+        &vec![(0, 49), (50, 99)],
+    );
+    s.ranges[0].2 = 20;
+    let s2 = DownloadState::from_json(&s.to_json()).unwrap();
+    assert_eq!(s.ranges, s2.ranges);
+    assert_eq!(s.etag, s2.etag);
+    assert_eq!(None, s2.last_modified);
+    assert_eq!(20, s2.downloaded());
+    assert!(s2.is_same_file(&Some(String::from("\"abc\"")), &None, 100));
+    assert!(!s2.is_same_file(&Some(String::from("\"def\"")), &None, 100));
+    assert!(!s2.is_same_file(&None, &None, 101));
+}
@@ -0,0 +1,146 @@
+use crate::downloader::downloader::get_bool_option;
+use crate::downloader::ffmpeg::ffmpeg::FFmpeg;
+use crate::error::Error;
+use crate::error::Result;
+use crate::getopt::OptStore;
+use crate::i18n::gettext;
+use crate::metadata::VideoInfo;
+use crate::settings::SettingStore;
+use crate::utils::runtime::spawn_blocking;
+use std::fs::remove_file;
+use std::fs::rename;
+use std::fs::File;
+use std::io::Write;
+
+/// Check whether to mux subtitles into output file. Disabled by default.
+/// * `opt` - Options
+/// * `se` - Settings
+pub fn enable_mux_subtitles(opt: &OptStore, se: &SettingStore) -> bool {
+    get_bool_option(opt, se, "mux-subtitles", false)
+}
+
+/// Convert a language tag to ISO 639-2 code which is used by ffmpeg.
+/// * `lang` - Language tag. Such as `zh-CN`, `en-US`
+///
+/// Returns None if the language is unknown.
+pub fn to_iso639_2(lang: &str) -> Option<&'static str> {
+    let l = lang.to_lowercase();
+    // AI generated subtitles use tags such as `ai-zh`
+    let l = l.trim_start_matches("ai-");
+    let l = match l.find(['-', '_']) {
+        Some(i) => &l[..i],
+        None => l,
+    };
+    match l {
+        "zh" => Some("chi"),
+        "en" => Some("eng"),
+        "ja" => Some("jpn"),
+        "ko" => Some("kor"),
+        "fr" => Some("fre"),
+        "de" => Some("ger"),
+        "es" => Some("spa"),
+        "ru" => Some("rus"),
+        "pt" => Some("por"),
+        "it" => Some("ita"),
+        "th" => Some("tha"),
+        "vi" => Some("vie"),
+        "id" => Some("ind"),
+        "ar" => Some("ara"),
+        _ => None,
+    }
+}
+
+/// Write data to a file.
+/// * `path` - The location of file
+/// * `data` - File content
+fn write_file(path: &str, data: &[u8]) -> Result<()> {
+    let r = match File::create(path) {
+        Ok(mut f) => f.write_all(data),
+        Err(e) => Err(e),
+    };
+    match r {
+        Ok(_) => Ok(()),
+        Err(e) => Err(Error::io(path, e)),
+    }
+}
+
+/// Save subtitles and extra files next to the video and mux subtitles into the output file according to options and settings.
+/// * `vi` - Video information
+/// * `opt` - Options
+/// * `se` - Settings
+/// * `ff` - Ffmpeg interface
+/// * `base` - The location of output file without extension
+/// * `output` - The location of output file
+pub async fn handle_subtitles(
+    vi: &VideoInfo,
+    opt: &OptStore,
+    se: &SettingStore,
+    ff: Option<&FFmpeg>,
+    base: &str,
+    output: &str,
+) -> Result<()> {
+    for f in vi.extra_files.iter() {
+        let p = format!("{}.{}", base, f.suffix);
+        write_file(p.as_str(), &f.data)?;
+    }
+    let ext = match output.rfind('.') {
+        Some(i) => output[i + 1..].to_lowercase(),
+        None => String::from(""),
+    };
+    let mp4 = ext == "mp4" || ext == "m4v" || ext == "mov";
+    let mkv = ext == "mkv";
+    let mut subs: Vec<(String, Option<String>, Option<String>)> = Vec::new();
+    let mut skipped = false;
+    for s in vi.subtitles.iter() {
+        let p = format!("{}.{}.{}", base, s.suffix, s.ext);
+        write_file(p.as_str(), s.data.as_bytes())?;
+        // Styles and positions of ASS subtitles (such as danmaku) are lost when converted to mov_text.
+        if mp4 && s.ext == "ass" {
+            skipped = true;
+            continue;
+        }
+        let lang = match &s.lang {
+            Some(l) => to_iso639_2(l.as_str()).map(String::from),
+            None => None,
+        };
+        subs.push((p, lang, s.title.clone()));
+    }
+    if !enable_mux_subtitles(opt, se) {
+        return Ok(());
+    }
+    if skipped {
+        eprintln!("{}", gettext("ASS subtitles (such as danmaku) can not be muxed into mp4 container. They are kept as separate files. Use \"--container mkv\" to mux them."));
+    }
+    if subs.is_empty() {
+        return Ok(());
+    }
+    if !mp4 && !mkv {
+        eprintln!("{}", gettext("Muxing subtitles is not supported by this container."));
+        return Ok(());
+    }
+    if ff.is_none() {
+        eprintln!("{}", gettext("Ffmpeg is not available. Subtitles will not be muxed."));
+        return Ok(());
+    }
+    let ff = ff.unwrap().clone();
+    let tmp = format!("{}.subs.{}", base, ext);
+    let (i, o) = (String::from(output), tmp.clone());
+    if !spawn_blocking(move || ff.mux_subtitles(i.as_str(), &subs, o.as_str())).await {
+        let _ = remove_file(tmp.as_str());
+        return Err(Error::Download(String::from(gettext("Can not mux subtitles into output file."))));
+    }
+    match rename(tmp.as_str(), output) {
+        Ok(_) => Ok(()),
+        Err(e) => Err(Error::io(tmp.as_str(), e)),
+    }
+}
+
+#[test]
+fn test_to_iso639_2() {
+    assert_eq!(Some("chi"), to_iso639_2("zh-CN"));
+    assert_eq!(Some("chi"), to_iso639_2("zh-Hans"));
+    assert_eq!(Some("chi"), to_iso639_2("ai-zh"));
+    assert_eq!(Some("eng"), to_iso639_2("en-US"));
+    assert_eq!(Some("jpn"), to_iso639_2("ja"));
+    assert_eq!(None, to_iso639_2("xx"));
+}
@@ -0,0 +1,171 @@
+use crate::i18n::gettext;
+use std::fmt::Display;
+use std::fmt::Formatter;
+
+/// Errors of bili
+#[derive(Debug)]
+pub enum Error {
+    /// Failed to send a request or receive the response.
+    Network {
+        /// Url
+        url: String,
+        /// Error message
+        msg: String,
+    },
+    /// The server returned an error status code (>= 400).
+    HttpStatus {
+        /// Url
+        url: String,
+        /// Status code
+        status: u16,
+    },
+    /// The API returned an error code. Such as `-404` or `-403`.
+    Api {
+        /// API url
+        url: String,
+        /// Error code
+        code: i64,
+        /// Error message returned by the API
+        msg: String,
+    },
+    /// Failed to parse data (JSON, HTML and so on).
+    Parse {
+        /// Where the data comes from. Url or file path.
+        source: String,
+        /// Error message
+        msg: String,
+    },
+    /// Failed to read or write a file.
+    Io {
+        /// File path
+        path: String,
+        /// Error message
+        msg: String,
+    },
+    /// Invalid options or settings.
+    Config(String),
+    /// Failed to extract information.
+    Extract(String),
+    /// Failed to download.
+    Download(String),
+}
+
+impl Error {
+    /// Create an [`Io`](#variant.Io) error for a file.
+    /// * `path` - File path
+    /// * `e` - The underlying error
+    pub fn io<E: Display>(path: &str, e: E) -> Self {
+        Error::Io {
+            path: String::from(path),
+            msg: format!("{}", e),
+        }
+    }
+
+    /// Create a [`Network`](#variant.Network) error for a request.
+    /// * `url` - Url
+    /// * `e` - The underlying error
+    pub fn network<E: Display>(url: &str, e: E) -> Self {
+        Error::Network {
+            url: String::from(url),
+            msg: format!("{}", e),
+        }
+    }
+
+    /// Return the process exit code of the error.
+    ///
+    /// | Error | Exit code |
+    /// | --- | --- |
+    /// | [`Config`](#variant.Config) | 2 |
+    /// | [`Network`](#variant.Network) | 3 |
+    /// | [`HttpStatus`](#variant.HttpStatus) | 4 |
+    /// | [`Api`](#variant.Api) | 5 |
+    /// | [`Parse`](#variant.Parse) | 6 |
+    /// | [`Io`](#variant.Io) | 7 |
+    /// | [`Extract`](#variant.Extract) | 8 |
+    /// | [`Download`](#variant.Download) | 9 |
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::Config(_) => 2,
+            Error::Network { .. } => 3,
+            Error::HttpStatus { .. } => 4,
+            Error::Api { .. } => 5,
+            Error::Parse { .. } => 6,
+            Error::Io { .. } => 7,
+            Error::Extract(_) => 8,
+            Error::Download(_) => 9,
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Network { url, msg } => {
+                let s = gettext("Error when requesting \"<url>\": <msg>")
+                    .replace("<url>", url)
+                    .replace("<msg>", msg);
+                f.write_str(s.as_str())
+            }
+            Error::HttpStatus { url, status } => {
+                let s = gettext("Server returned status code <status> when requesting \"<url>\".")
+                    .replace("<url>", url)
+                    .replace("<status>", format!("{}", status).as_str());
+                f.write_str(s.as_str())
+            }
+            Error::Api { url, code, msg } => {
+                let s = gettext("API \"<url>\" returned error code <code>: <msg>")
+                    .replace("<url>", url)
+                    .replace("<code>", format!("{}", code).as_str())
+                    .replace("<msg>", msg);
+                f.write_str(s.as_str())
+            }
+            Error::Parse { source, msg } => {
+                let s = gettext("Can not parse data from \"<source>\": <msg>")
+                    .replace("<source>", source)
+                    .replace("<msg>", msg);
+                f.write_str(s.as_str())
+            }
+            Error::Io { path, msg } => {
+                let s = gettext("Can not access file \"<path>\": <msg>")
+                    .replace("<path>", path)
+                    .replace("<msg>", msg);
+                f.write_str(s.as_str())
+            }
+            Error::Config(msg) => f.write_str(msg),
+            Error::Extract(msg) => f.write_str(msg),
+            Error::Download(msg) => f.write_str(msg),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<reqwest::Error> for Error {
+    fn from(e: reqwest::Error) -> Self {
+        let url = match e.url() {
+            Some(u) => String::from(u.as_str()),
+            None => String::new(),
+        };
+        Error::network(url.as_str(), e)
+    }
+}
+
+/// Result with [`Error`]
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[test]
+fn test_error() {
+    let e = Error::Api {
+        url: String::from("https://api.bilibili.com/x/player/v2"),
+        code: -404,
+        msg: String::from("啥都木有"),
+    };
+    assert_eq!(5, e.exit_code());
+    let s = format!("{}", e);
+    assert!(s.contains("-404"));
+    assert!(s.contains("https://api.bilibili.com/x/player/v2"));
+    assert_eq!(2, Error::Config(String::from("a")).exit_code());
+    let e = Error::io("a.txt", "denied");
+    assert_eq!(7, e.exit_code());
+    assert!(format!("{}", e).contains("a.txt"));
+}
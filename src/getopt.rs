@@ -1,3 +1,5 @@
+use crate::error::Error;
+use crate::error::Result;
 use crate::i18n::gettext;
 use crate::opt_list::get_opt_list;
 use crate::utils::size::parse_size;
@@ -67,6 +69,7 @@ impl OptDes {
     ///
     /// # Examples
     /// ```
+    /// # use bili::getopt::OptDes;
     /// let opt = OptDes::new("help", Some("h"), "Print help message", true, false, Some("type"));
     /// ```
     ///
@@ -302,6 +305,8 @@ impl OptStore {
     /// * `default_des` - The list of basic options
     /// # Examples
     /// ```
+    /// # use bili::getopt::OptDes;
+    /// # use bili::getopt::OptStore;
     /// let mut opt = OptStore::new(vec![OptDes::new("some", Some("s"), "Description", false, false, None).unwrap(),]);
     /// ```
     pub fn new(default_des: Vec<OptDes>) -> OptStore {
@@ -320,11 +325,13 @@ impl OptStore {
     /// * `list` - Provider's options
     /// # Examples
     /// ```
+    /// # use bili::getopt::OptDes;
+    /// # use bili::getopt::OptStore;
     /// let mut opt = OptStore::default();
     /// opt.add("Provider name", vec![OptDes::new("some", Some("s"), "Description", false, false, None).unwrap(),]);
     /// ```
     /// # Notes
-    /// If this provider depend on another provider, you need use [`add_with_dependence`](#method.add_with_dependence) in function [`add_all_opts`](../providers/fn.add_all_opts.html).
+    /// If this provider depend on another provider, you need register it with its dependencies in function [`get_registry`](../providers/fn.get_registry.html).
     pub fn add(&mut self, key: &str, list: Vec<OptDes>) {
         let des = OptDesStore::from(list);
         self.out_des.insert(String::from(key), des);
@@ -336,13 +343,15 @@ impl OptStore {
     /// * `deps` - Provider's dependencies
     /// # Examples
     /// ```
+    /// # use bili::getopt::OptDes;
+    /// # use bili::getopt::OptStore;
     /// let mut opt = OptStore::default();
     /// opt.add("Provider1", vec![OptDes::new("some", Some("s"), "Description", false, false, None).unwrap(),]);
     /// opt.add_with_dependence("Provider2", vec![OptDes::new("some2", None, "Description", false, false, None).unwrap(),], vec!["Provider1"]);
     /// ```
     /// # Notes
     /// 1. The name in dependencies must be added before.
-    /// 2. Dependencies information now only used on help message. You just need use this function in function [`add_all_opts`](../providers/fn.add_all_opts.html). You can use [`add`](#method.add) in any other location.
+    /// 2. Dependencies information now only used on help message. The provider registry calls this function. You can use [`add`](#method.add) in any other location.
     pub fn add_with_dependence(
         &mut self,
         key: &str,
@@ -404,8 +413,9 @@ impl OptStore {
     /// * `key` - Option's name
     /// # Examples
     /// ```
+    /// # use bili::getopt::OptStore;
     /// let mut opt = OptStore::default();
-    /// if opt.parse_options() {
+    /// if opt.parse_options().is_ok() {
     ///     let cookies = opt.get_option("cookies");
     /// }
     /// ```
@@ -507,8 +517,9 @@ impl OptStore {
     /// * `key` - Option's name
     /// # Examples
     /// ```
+    /// # use bili::getopt::OptStore;
     /// let mut opt = OptStore::default();
-    /// if opt.parse_options() {
+    /// if opt.parse_options().is_ok() {
     ///     if opt.has_option("help") {
     ///         // Print help message
     ///     }
@@ -590,18 +601,19 @@ impl OptStore {
         None
     }
 
-    /// Parse options, if any error occured, will return false
+    /// Parse options, if any error occured, will return an error
     /// # Examples
     /// ```
-    /// let opt = OptStore::default();
-    /// if opt.parse_options() {
+    /// # use bili::getopt::OptStore;
+    /// let mut opt = OptStore::default();
+    /// if opt.parse_options().is_ok() {
     ///     // Do something
     /// }
     /// ```
     /// # Notes
     /// 1. This function will clear [`self.list`](#structfield.list), so [`get_option`](#method.get_option) and [`has_option`](#method.has_option) will not return previous result.
     /// 2. If found a non-option argument, this function will stop parse and return true.
-    pub fn parse_options(&mut self) -> bool {
+    pub fn parse_options(&mut self) -> Result<()> {
         self.list.clear();
         while self.ind < self.args.len() {
             let s = &self.args[self.ind];
@@ -612,8 +624,7 @@ impl OptStore {
                 if optdes.is_none() {
                     let s = gettext("<s> is not a vaild option.")
                         .replace("<s>", format!("--{}", name).as_str());
-                    println!("{}", s);
-                    return false;
+                    return Err(Error::Config(s));
                 }
                 let optdes = optdes.unwrap();
                 if !optdes.has_value() {
@@ -636,8 +647,7 @@ impl OptStore {
                         if self.ind >= self.args.len() {
                             let s = gettext("<option> need an argument.")
                                 .replace("<option>", format!("--{}", name).as_str());
-                            println!("{}", s);
-                            return false;
+                            return Err(Error::Config(s));
                         } else {
                             let v = &self.args[self.ind];
                             self.ind += 1;
@@ -645,8 +655,7 @@ impl OptStore {
                                 self.ind -= 1;
                                 let s = gettext("<option> need an argument.")
                                     .replace("<option>", format!("--{}", name).as_str());
-                                println!("{}", s);
-                                return false;
+                                return Err(Error::Config(s));
                             } else {
                                 self.list.push(Opt::new(name, Some(v.as_str())));
                             }
@@ -657,8 +666,7 @@ impl OptStore {
                 let opts = String::from(s.strip_prefix("-").unwrap());
                 if opts.len() == 0 {
                     let s = gettext("<s> is not a vaild option.").replace("<s>", "-");
-                    println!("{}", s);
-                    return false;
+                    return Err(Error::Config(s));
                 }
                 let mut i = 0;
                 while i < opts.len() {
@@ -668,8 +676,7 @@ impl OptStore {
                     if optdes.is_none() {
                         let s = gettext("<s> is not a vaild option.")
                             .replace("<s>", format!("-{}", opt).as_str());
-                        println!("{}", s);
-                        return false;
+                        return Err(Error::Config(s));
                     }
                     let optdes = optdes.unwrap();
                     if !optdes.has_value() {
@@ -704,16 +711,14 @@ impl OptStore {
                                     self.ind -= 1;
                                     let s = gettext("<option> need an argument.")
                                         .replace("<option>", format!("-{}", opt).as_str());
-                                    println!("{}", s);
-                                    return false;
+                                    return Err(Error::Config(s));
                                 } else {
                                     self.list.push(Opt::new(optdes.name(), Some(v)));
                                 }
                             } else {
                                 let s = gettext("<option> need an argument.")
                                     .replace("<option>", format!("-{}", opt).as_str());
-                                println!("{}", s);
-                                return false;
+                                return Err(Error::Config(s));
                             }
                         }
                     }
@@ -723,7 +728,7 @@ impl OptStore {
                 break;
             }
         }
-        return true;
+        Ok(())
     }
 
     /// Parse url from argument
@@ -1,16 +1,18 @@
-extern crate futures;
 extern crate json;
 extern crate reqwest;
 
 use crate::cookies_json::Cookie;
 use crate::cookies_json::CookiesJar;
+use crate::error::Error;
+use crate::error::Result;
 use crate::i18n::gettext;
-use futures::executor::block_on;
+use crate::utils::runtime::block_on;
 use json::JsonValue;
 use reqwest::Client;
 use reqwest::IntoUrl;
 use reqwest::RequestBuilder;
 use reqwest::Response;
+use reqwest::Url;
 use std::clone::Clone;
 use std::collections::HashMap;
 
@@ -126,7 +128,7 @@ impl CookieClient {
                     }
                 }
                 Err(e) => {
-                    println!("{}", e);
+                    eprintln!("{}", e);
                 }
             }
         }
@@ -135,23 +137,40 @@ impl CookieClient {
     /// Send GET requests with parameters
     /// * `param` - GET parameters. Should be a JSON object/array. If value in map is not a string, will dump it
     /// # Examples
-    /// ```
+    /// ```no_run
+    /// # use bili::http_client::CookieClient;
+    /// # use reqwest::Client;
     /// let c = Client::builder().build().unwrap();
-    /// let client = CookieClient::new(c, None);
+    /// let mut client = CookieClient::new(c, None);
     /// client.get_with_param("https://test.com/a", json::object!{"data": "param1"});
     /// client.get_with_param("https://test.com/a", json::object!{"daa": {"ad": "test"}});
     /// client.get_with_param("https://test.com/a", json::array![["daa", "param1"]]);
     /// ```
     /// It will GET `https://test.com/a?data=param1`, `https://test.com/a?daa=%7B%22ad%22%3A%22test%22%7D`, `https://test.com/a?daa=param1`
-    pub fn get_with_param<U: IntoUrl>(&mut self, url: U, param: JsonValue) -> Option<Response> {
-        let u = url.into_url();
-        if u.is_err() {
-            println!("{}\"{}\"", gettext("Can not parse URL: "), u.unwrap_err());
-            return None;
-        }
-        let mut u = u.unwrap();
+    pub fn get_with_param<U: IntoUrl + Send>(&mut self, url: U, param: JsonValue) -> Option<Response> {
+        block_on(self.get_with_param_async(url, param))
+    }
+
+    /// Send GET requests with parameters asynchronously.
+    /// See [`get_with_param`](#method.get_with_param) for details.
+    pub async fn get_with_param_async<U: IntoUrl + Send>(&mut self, url: U, param: JsonValue) -> Option<Response> {
+        let u = Self::gen_url_with_param(url, param)?;
+        self.get_async(u.as_str()).await
+    }
+
+    /// Append GET parameters to url.
+    /// * `url` - Url
+    /// * `param` - GET parameters. Should be a JSON object/array.
+    fn gen_url_with_param<U: IntoUrl>(url: U, param: JsonValue) -> Option<Url> {
+        let mut u = match url.into_url() {
+            Ok(u) => u,
+            Err(e) => {
+                eprintln!("{}\"{}\"", gettext("Can not parse URL: "), e);
+                return None;
+            }
+        };
         if !param.is_object() && !param.is_array() {
-            println!(
+            eprintln!(
                 "{}\"{}\"",
                 gettext("Parameters should be object or array: "),
                 param
@@ -173,11 +192,11 @@ impl CookieClient {
             } else {
                 for v in param.members() {
                     if !v.is_object() {
-                        println!("{}\"{}\"", gettext("Parameters should be array: "), v);
+                        eprintln!("{}\"{}\"", gettext("Parameters should be array: "), v);
                         return None;
                     }
                     if v.len() < 2 {
-                        println!("{}\"{}\"", gettext("Parameters need at least a value: "), v);
+                        eprintln!("{}\"{}\"", gettext("Parameters need at least a value: "), v);
                         return None;
                     }
                     let okey = &v[0];
@@ -201,18 +220,22 @@ impl CookieClient {
                 }
             }
         }
-        self.get(u.as_str())
+        Some(u)
     }
 
     /// Send GET requests
-    pub fn get<U: IntoUrl>(&mut self, url: U) -> Option<Response> {
+    pub fn get<U: IntoUrl + Send>(&mut self, url: U) -> Option<Response> {
+        block_on(self.get_async(url))
+    }
+
+    /// Send GET requests asynchronously
+    pub async fn get_async<U: IntoUrl + Send>(&mut self, url: U) -> Option<Response> {
         let r = self.aget(url);
-        let r = r.send();
-        let r = block_on(r);
+        let r = r.send().await;
         match r {
             Ok(_) => {}
             Err(e) => {
-                println!("{}{}", gettext("Error when request: "), e);
+                eprintln!("{}{}", gettext("Error when request: "), e);
                 return None;
             }
         }
@@ -223,6 +246,53 @@ impl CookieClient {
         Some(r)
     }
 
+    /// Send GET requests with parameters and return the response text.
+    /// * `url` - Url
+    /// * `param` - GET parameters. See [`get_with_param`](#method.get_with_param) for details.
+    pub async fn get_text_async(&mut self, url: &str, param: JsonValue) -> Result<String> {
+        let u = match Self::gen_url_with_param(url, param) {
+            Some(u) => u,
+            None => {
+                return Err(Error::Network {
+                    url: String::from(url),
+                    msg: String::from(gettext("Invalid url or parameters.")),
+                })
+            }
+        };
+        let r = match self.aget(u.as_str()).send().await {
+            Ok(r) => r,
+            Err(e) => return Err(Error::network(url, e)),
+        };
+        if self.set_cookie {
+            self.handle_set_cookie(&r);
+        }
+        let status = r.status().as_u16();
+        if status >= 400 {
+            return Err(Error::HttpStatus {
+                url: String::from(url),
+                status,
+            });
+        }
+        match r.text_with_charset("UTF-8").await {
+            Ok(t) => Ok(t),
+            Err(e) => Err(Error::network(url, e)),
+        }
+    }
+
+    /// Send GET requests with parameters and parse the response as JSON.
+    /// * `url` - Url
+    /// * `param` - GET parameters. See [`get_with_param`](#method.get_with_param) for details.
+    pub async fn get_json_async(&mut self, url: &str, param: JsonValue) -> Result<JsonValue> {
+        let t = self.get_text_async(url, param).await?;
+        match json::parse(t.as_str()) {
+            Ok(j) => Ok(j),
+            Err(e) => Err(Error::Parse {
+                source: String::from(url),
+                msg: format!("{}", e),
+            }),
+        }
+    }
+
     /// Get a request builder which contains cookies information.
     /// # Notes
     /// You need call `handle_set_cookie` to handle `set-cookie` headers in response.
@@ -0,0 +1,54 @@
+//! A video downloader.
+//!
+//! The command line tool `bili` is built on top of this library.
+//! Other programs can use the providers to extract information and
+//! [`MDownloader`] to download videos.
+//!
+//! ```no_run
+//! # async fn example() -> bili::Result<()> {
+//! let mut opt = bili::OptStore::default();
+//! let mut se = bili::SettingStore::new();
+//! let url = "https://www.bilibili.com/video/BV1xx411c7mD";
+//! let registry = bili::get_registry();
+//! let mut pro = registry.find(url).unwrap().create();
+//! pro.add_custom_options(&mut opt);
+//! pro.add_custom_settings(&mut se);
+//! pro.init(None, opt.clone(), se.clone())?;
+//! let info = pro.extract_async(url).await?;
+//! bili::MDownloader::new(&se, &opt, &info)?.run_async().await
+//! # }
+//! ```
+#[macro_use]
+extern crate lazy_static;
+
+pub mod cookies_json;
+pub mod downloader;
+pub mod error;
+pub mod getopt;
+pub mod http_client;
+pub mod i18n;
+pub mod metadata;
+pub mod opt_list;
+pub mod providers;
+pub mod settings;
+#[cfg(test)]
+mod testutils;
+pub mod utils;
+pub mod webdriver;
+
+pub use cookies_json::CookiesJar;
+pub use cookies_json::CookiesJson;
+pub use downloader::downloader::Downloader;
+pub use downloader::downloader::MDownloader;
+pub use error::Error;
+pub use error::Result;
+pub use getopt::OptStore;
+pub use metadata::ExtractInfo;
+pub use metadata::InfoType;
+pub use metadata::VideoInfo;
+pub use metadata::VideoMetadata;
+pub use providers::get_registry;
+pub use providers::provider_base::Provider;
+pub use providers::registry::ProviderEntry;
+pub use providers::registry::ProviderRegistry;
+pub use settings::SettingStore;
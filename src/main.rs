@@ -1,31 +1,22 @@
-#[macro_use]
-extern crate lazy_static;
+extern crate bili;
+extern crate json;
 
-mod cookies_json;
-mod downloader;
-mod getopt;
-mod http_client;
-mod i18n;
-mod metadata;
-mod opt_list;
-mod providers;
-mod settings;
-#[cfg(test)]
-mod testutils;
-mod utils;
-mod webdriver;
-
-use cookies_json::CookiesJar;
-use cookies_json::CookiesJson;
-use downloader::downloader::MDownloader;
-use getopt::ConfigCommand;
-use getopt::CookieCommand;
-use getopt::OptStore;
-use i18n::gettext;
-use providers::bilibili::normal_video::BiliNormalVideoProvider;
-use providers::provider_base::Provider;
-use providers::tiktok::video::TiktokVideoProvider;
-use settings::SettingStore;
+use bili::cookies_json::CookiesJar;
+use bili::cookies_json::CookiesJson;
+use bili::downloader::downloader::MDownloader;
+use bili::error::Error;
+use bili::error::Result;
+use bili::getopt::ConfigCommand;
+use bili::getopt::CookieCommand;
+use bili::getopt::OptStore;
+use bili::i18n::gettext;
+use bili::metadata::ExtractInfo;
+use bili::metadata::InfoType;
+use bili::opt_list;
+use bili::providers;
+use bili::providers::provider_base::Provider;
+use bili::settings::SettingStore;
+use bili::utils;
 
 struct Main {
     cookies: CookiesJson,
@@ -86,6 +77,25 @@ impl Main {
         )
     }
 
+    fn print_formats(&self, e: &ExtractInfo) {
+        let list = match e.typ {
+            InfoType::Video => vec![e.video.as_ref().unwrap()],
+            InfoType::VideoList => e.videos.as_ref().unwrap().iter().collect(),
+        };
+        for vi in list.iter() {
+            if vi.meta.title.is_some() {
+                println!("{}", vi.meta.title.as_ref().unwrap());
+            }
+            if vi.formats.len() == 0 {
+                println!("{}", gettext("No format information available."));
+                continue;
+            }
+            for f in vi.formats.iter() {
+                println!("{}", f.to_line());
+            }
+        }
+    }
+
     fn print_version(&self) {
         let v = env!("CARGO_PKG_VERSION");
         println!("bili  v{}  Copyright (C) 2021  lifegpc", v);
@@ -94,11 +104,15 @@ impl Main {
         println!("under certain conditions.");
     }
 
-    fn run(&mut self) -> i32 {
+    async fn run(&mut self) -> i32 {
         let url = self.opt.parse_url();
         if url.is_none() {
-            if !self.opt.parse_options() {
-                return 1;
+            match self.opt.parse_options() {
+                Ok(_) => {}
+                Err(e) => {
+                    eprintln!("{}", e);
+                    return e.exit_code();
+                }
             }
             if self.opt.has_option("help") {
                 providers::add_all_opts(&mut self.opt);
@@ -133,44 +147,51 @@ impl Main {
                 );
                 return 0;
             }
-            println!("{}", gettext("Url is needed."));
-            return 1;
+            let e = Error::Config(String::from(gettext("Url is needed.")));
+            eprintln!("{}", e);
+            return e.exit_code();
         }
         let url = url.unwrap();
-        if url == "config" {
-            return self.run_config();
-        }
-        if url == "cookie" {
-            return self.run_cookie();
+        let re = if url == "config" {
+            self.run_config()
+        } else if url == "cookie" {
+            self.run_cookie()
+        } else {
+            self.match_provider(url.as_str()).await
+        };
+        match re {
+            Ok(code) => code,
+            Err(e) => {
+                eprintln!("{}", e);
+                e.exit_code()
+            }
         }
-        self.match_provider(url.as_str())
     }
 
-    fn match_provider(&mut self, url: &str) -> i32 {
-        if BiliNormalVideoProvider::match_url(url) {
-            return self.run_iternal(&mut BiliNormalVideoProvider::new(), String::from(url));
-        }
-        if TiktokVideoProvider::match_url(url) {
-            return self.run_iternal(&mut TiktokVideoProvider::new(), String::from(url));
+    async fn match_provider(&mut self, url: &str) -> Result<i32> {
+        let registry = providers::get_registry();
+        let p = registry.find(url);
+        if p.is_none() {
+            return Err(Error::Config(String::from(gettext("Can not find suitable provider."))));
         }
-        println!("{}", gettext("Can not find suitable provider."));
-        return 1;
+        let mut pro = p.unwrap().create();
+        self.run_iternal(pro.as_mut(), String::from(url)).await
     }
 
-    fn run_iternal(&mut self, pro: &mut impl Provider, url: String) -> i32 {
+    async fn run_iternal(&mut self, pro: &mut dyn Provider, url: String) -> Result<i32> {
         if pro.has_custom_options() {
             pro.add_custom_options(&mut self.opt);
         }
-        if !self.opt.parse_options() {
-            return 1;
-        }
+        self.opt.parse_options()?;
         if pro.has_custom_settings() {
             pro.add_custom_settings(&mut self.se);
         }
-        if !self.se.read(self.opt.get_option("config"), false) {
-            return 1;
+        self.se.read(self.opt.get_option("config"), false)?;
+        let cookies = self.get_cookies();
+        let r = self.cookies.read(cookies.clone());
+        if r.is_err() && cookies.is_some() {
+            eprintln!("{}", r.unwrap_err());
         }
-        self.cookies.read(self.get_cookies());
         let jar = match self.opt.get_option("cookie-jar") {
             Some(s) => self.cookies.get(s.as_str()),
             None => match pro.get_default_cookie_jar_name() {
@@ -178,17 +199,16 @@ impl Main {
                 None => None,
             },
         };
-        if !pro.init(jar, self.opt.clone(), self.se.clone()) {
-            println!("{}", gettext("Can not initialize provider."));
-            return 1;
-        }
+        pro.init(jar, self.opt.clone(), self.se.clone())?;
         if pro.can_login() {
-            let p = pro.check_logined();
-            if p.is_none() {
-                println!("{}", gettext("Error occured when checking login."));
+            let p = pro.check_logined_async().await;
+            if p.is_err() {
+                let e = p.unwrap_err();
                 if pro.login_required() || self.opt.has_option("login") {
-                    return 1;
+                    return Err(e);
                 }
+                eprintln!("{}", e);
+                eprintln!("{}", gettext("Error occured when checking login."));
             } else {
                 let mut p = p.unwrap();
                 if !p && (pro.login_required() || self.opt.has_option("login")) {
@@ -197,79 +217,70 @@ impl Main {
                         None => match pro.get_default_cookie_jar_name() {
                             Some(s) => String::from(s),
                             None => {
-                                println!("{}", gettext("Name is needed for cookie jar."));
-                                return 1;
+                                return Err(Error::Config(String::from(gettext(
+                                    "Name is needed for cookie jar."
+                                ))));
                             }
                         },
                     };
                     let mut jar = CookiesJar::new();
-                    p = pro.login(&mut jar);
-                    if !p {
-                        println!("{}", gettext("Login failed."));
-                        return 1;
-                    }
+                    pro.login_async(&mut jar).await?;
+                    p = true;
                     self.cookies.add(k.as_str(), jar);
-                    if !self.cookies.save(self.get_cookies()) {
-                        return 1;
-                    }
+                    self.cookies.save(self.get_cookies())?;
                 }
                 let s = pro.logined();
                 if s != p {
-                    println!(
+                    eprintln!(
                         "{}",
                         gettext("Warn: fuction check_logined and logined return different result.")
                     );
                     p = s;
                 }
                 if p {
-                    println!("{}", gettext("Verify login successfully."));
+                    eprintln!("{}", gettext("Verify login successfully."));
                 }
             }
         } else if self.opt.has_option("login") {
             let s = gettext("<provider> don't support login.")
                 .replace("<provider>", pro.provider_name());
-            println!("{}", s);
-            return -1;
-        }
-        let e = pro.extract(url.as_str());
-        if e.is_none() {
-            println!("{}", gettext("Can not extract info."));
-            return 1;
+            return Err(Error::Config(s));
         }
-        let e = e.unwrap();
+        let e = pro.extract_async(url.as_str()).await?;
         if !e.check() {
-            println!("{}", gettext("Extract informtaion is invalid."));
-            return 1;
+            return Err(Error::Extract(String::from(gettext("Extract informtaion is invalid."))));
         }
-        let d = MDownloader::new(&self.se, &self.opt, &e);
-        d.run();
-        return 0;
+        if self.opt.has_option("dump-json") {
+            println!("{}", e.to_json().dump());
+            return Ok(0);
+        }
+        if self.opt.has_option("list-formats") {
+            self.print_formats(&e);
+            return Ok(0);
+        }
+        let d = MDownloader::new(&self.se, &self.opt, &e)?;
+        d.run_async().await?;
+        Ok(0)
     }
 
-    fn run_config(&mut self) -> i32 {
+    fn run_config(&mut self) -> Result<i32> {
         self.opt = OptStore::new(opt_list::get_config_opt_list());
         let cmd = self.opt.parse_config_command();
         if cmd.is_none() {
-            if !self.opt.parse_options() {
-                return 1;
-            }
+            self.opt.parse_options()?;
             if self.opt.has_option("help") {
                 self.print_config_basic_usage();
                 self.opt.print_help(None, false);
-                return 0;
+                return Ok(0);
             }
             self.print_config_basic_usage();
-            return 1;
+            return Err(Error::Config(String::from(gettext("No command specified."))));
         }
         let cmd = cmd.unwrap();
-        if !self.opt.parse_options() {
-            return 1;
-        }
+        self.opt.parse_options()?;
         providers::add_all_settings(&mut self.se);
         let fix_invalid = self.opt.has_option("fix") || cmd.typ == ConfigCommand::Fix;
-        if !self.se.read(self.opt.get_option("config"), fix_invalid) {
-            return 1;
-        }
+        self.se.read(self.opt.get_option("config"), fix_invalid)?;
         if cmd.typ == ConfigCommand::Add {
             let s = if self.opt.has_option("str") {
                 let j = json::JsonValue::String(cmd.list[2].clone());
@@ -277,46 +288,25 @@ impl Main {
             } else {
                 cmd.list[2].clone()
             };
-            if !self.se.add_value(
+            self.se.add_value(
                 cmd.list[0].as_str(),
                 cmd.list[1].as_str(),
                 s.as_str(),
                 self.opt.has_option("force"),
-            ) {
-                return 1;
-            }
-            if !self.se.save(self.opt.get_option("config")) {
-                return 1;
-            }
-            return 0;
+            )?;
+            self.se.save(self.opt.get_option("config"))?;
+            return Ok(0);
         }
         if cmd.typ == ConfigCommand::Delete {
-            let re = self
-                .se
-                .get_settings(cmd.list[0].as_str(), cmd.list[1].as_str());
-            match re {
-                Some(_) => {
-                    if self.se.delete(cmd.list[0].as_str(), cmd.list[1].as_str()) {
-                        if self.se.save(self.opt.get_option("config")) {
-                            return 0;
-                        }
-                        println!("{}", gettext("Can not save settings."));
-                        return 1;
-                    }
-                    println!("{}", gettext("Key not found"));
-                    return 1;
-                }
-                None => {
-                    println!("{}", gettext("Key not found."));
-                    return 1;
-                }
+            if !self.se.delete(cmd.list[0].as_str(), cmd.list[1].as_str()) {
+                return Err(Error::Config(String::from(gettext("Key not found."))));
             }
+            self.se.save(self.opt.get_option("config"))?;
+            return Ok(0);
         }
         if cmd.typ == ConfigCommand::Fix {
-            if !self.se.save(self.opt.get_option("config")) {
-                return 1;
-            }
-            return 0;
+            self.se.save(self.opt.get_option("config"))?;
+            return Ok(0);
         }
         if cmd.typ == ConfigCommand::Get {
             let re = self
@@ -325,11 +315,10 @@ impl Main {
             match re {
                 Some(obj) => {
                     println!("{}", obj.pretty(2));
-                    return 0;
+                    return Ok(0);
                 }
                 None => {
-                    println!("{}", gettext("No value found."));
-                    return 1;
+                    return Err(Error::Config(String::from(gettext("No value found."))));
                 }
             }
         }
@@ -340,41 +329,33 @@ impl Main {
             } else {
                 cmd.list[2].clone()
             };
-            if !self.se.set_value(
+            self.se.set_value(
                 cmd.list[0].as_str(),
                 cmd.list[1].as_str(),
                 s.as_str(),
                 self.opt.has_option("force"),
-            ) {
-                return 1;
-            }
-            if !self.se.save(self.opt.get_option("config")) {
-                return 1;
-            }
-            return 0;
+            )?;
+            self.se.save(self.opt.get_option("config"))?;
+            return Ok(0);
         }
-        return 0;
+        return Ok(0);
     }
 
-    fn run_cookie(&mut self) -> i32 {
+    fn run_cookie(&mut self) -> Result<i32> {
         self.opt = OptStore::new(opt_list::get_cookie_opt_list());
         let cmd = self.opt.parse_cookie_command();
         if cmd.is_none() {
-            if !self.opt.parse_options() {
-                return 1;
-            }
+            self.opt.parse_options()?;
             if self.opt.has_option("help") {
                 self.print_cookie_basic_usage();
                 self.opt.print_help(None, false);
-                return 0;
+                return Ok(0);
             }
             self.print_cookie_basic_usage();
-            return 1;
+            return Err(Error::Config(String::from(gettext("No command specified."))));
         }
         let cmd = cmd.unwrap();
-        if !self.opt.parse_options() {
-            return 1;
-        }
+        self.opt.parse_options()?;
         let mut c = CookiesJson::new();
         let co = match self.opt.get_option("cookies") {
             Some(c) => c,
@@ -386,26 +367,19 @@ impl Main {
         };
         let p = std::path::PathBuf::from(&co);
         if p.exists() {
-            if !c.read(Some(co.clone())) {
-                return 1;
-            }
+            c.read(Some(co.clone()))?;
         }
         if cmd.typ == CookieCommand::Load {
-            let j = CookiesJar::from_netscape_cookie_file(&cmd.list[1]);
-            if j.is_none() {
-                return 1;
-            }
-            c.add(&cmd.list[0], j.unwrap());
-        }
-        if !c.save(Some(co.clone())) {
-            return 1;
+            let j = CookiesJar::from_netscape_cookie_file(&cmd.list[1])?;
+            c.add(&cmd.list[0], j);
         }
-        0
+        c.save(Some(co.clone()))?;
+        Ok(0)
     }
 }
 
 #[tokio::main]
 async fn main() {
     let mut m = Main::new();
-    std::process::exit(m.run());
+    std::process::exit(m.run().await);
 }
@@ -1,8 +1,12 @@
 extern crate chrono;
+extern crate json;
 extern crate reqwest;
 
 use crate::cookies_json::CookiesJar;
+use async_trait::async_trait;
+use json::JsonValue;
 use chrono::DateTime;
+use chrono::SecondsFormat;
 use chrono::Utc;
 use reqwest::header::HeaderMap;
 use std::clone::Clone;
@@ -10,6 +14,9 @@ use std::collections::HashMap;
 use std::default::Default;
 use std::fmt::Debug;
 
+/// Headers which contain secrets. They are removed when dumping [`VideoInfo`](struct.VideoInfo.html) to JSON.
+const SECRET_HEADERS: [&str; 4] = ["authorization", "cookie", "proxy-authorization", "set-cookie"];
+
 /// Present a current number in a total value
 pub struct NoInTotal {
     /// Current value
@@ -29,6 +36,16 @@ impl NoInTotal {
             })
         }
     }
+
+    /// Return current value
+    pub fn no(&self) -> usize {
+        self._no
+    }
+
+    /// Return total value
+    pub fn total(&self) -> usize {
+        self._total
+    }
 }
 
 impl Clone for NoInTotal {
@@ -46,6 +63,27 @@ impl Debug for NoInTotal {
     }
 }
 
+#[derive(Clone, Debug)]
+/// A chapter of video
+pub struct Chapter {
+    /// Start time in milliseconds
+    pub start: usize,
+    /// End time in milliseconds
+    pub end: usize,
+    /// Chapter title
+    pub title: String,
+}
+
+impl Chapter {
+    pub fn new(start: usize, end: usize, title: &str) -> Self {
+        Self {
+            start,
+            end,
+            title: String::from(title),
+        }
+    }
+}
+
 #[derive(Debug)]
 /// Video metadata
 pub struct VideoMetadata {
@@ -75,6 +113,51 @@ pub struct VideoMetadata {
     pub comment: Option<String>,
     /// Extra metadata
     pub extra: HashMap<String, String>,
+    /// Chapters
+    pub chapters: Vec<Chapter>,
+    /// Lyrics (without timestamps)
+    pub lyrics: Option<String>,
+}
+
+impl VideoMetadata {
+    /// Convert metadata to JSON. Keys of extra metadata are sorted.
+    pub fn to_json(&self) -> JsonValue {
+        let track = match &self.track {
+            Some(t) => json::object! {"no": t.no(), "total": t.total()},
+            None => JsonValue::Null,
+        };
+        let date = match &self.date {
+            Some(d) => JsonValue::from(d.to_rfc3339_opts(SecondsFormat::Secs, true)),
+            None => JsonValue::Null,
+        };
+        let mut extra = JsonValue::new_object();
+        let mut keys: Vec<&String> = self.extra.keys().collect();
+        keys.sort();
+        for k in keys {
+            extra[k.as_str()] = JsonValue::from(self.extra.get(k).unwrap().as_str());
+        }
+        let mut chapters = JsonValue::new_array();
+        for c in self.chapters.iter() {
+            chapters
+                .push(json::object! {"start": c.start, "end": c.end, "title": c.title.as_str()})
+                .unwrap();
+        }
+        json::object! {
+            "title": self.title.clone(),
+            "description": self.description.clone(),
+            "author": self.author.clone(),
+            "album": self.album.clone(),
+            "video_id": self.video_id.clone(),
+            "track": track,
+            "tags": self.tags.clone(),
+            "album_artist": self.album_artist.clone(),
+            "date": date,
+            "comment": self.comment.clone(),
+            "extra": extra,
+            "chapters": chapters,
+            "lyrics": self.lyrics.clone(),
+        }
+    }
 }
 
 impl Clone for VideoMetadata {
@@ -91,6 +174,8 @@ impl Clone for VideoMetadata {
             date: self.date.clone(),
             comment: self.comment.clone(),
             extra: self.extra.clone(),
+            chapters: self.chapters.clone(),
+            lyrics: self.lyrics.clone(),
         }
     }
 }
@@ -104,11 +189,13 @@ impl Default for VideoMetadata {
             album: None,
             video_id: None,
             track: None,
-            tags: [].to_vec(),
+            tags: Vec::new(),
             album_artist: None,
             date: None,
             comment: None,
             extra: HashMap::new(),
+            chapters: Vec::new(),
+            lyrics: None,
         }
     }
 }
@@ -122,11 +209,290 @@ pub enum InfoType {
     VideoList,
 }
 
+impl InfoType {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Video => "video",
+            Self::VideoList => "video_list",
+        }
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq)]
 /// The video play information type used in [`VideoInfo`](struct.VideoInfo.html)
 pub enum VideoPlayInfoType {
     /// Only have a single url which point to the video.
     SignleUrl,
+    /// Video and audio are separated streams (DASH). They need to be merged after downloaded.
+    Dash,
+    /// The video is split into several segments. They need to be concatenated after downloaded.
+    Segments,
+    /// A live stream. It will be recorded until the live is ended.
+    Live,
+}
+
+impl VideoPlayInfoType {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::SignleUrl => "single_url",
+            Self::Dash => "dash",
+            Self::Segments => "segments",
+            Self::Live => "live",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+/// A segment of video. Used in [`VideoInfo`](struct.VideoInfo.html)
+pub struct VideoSegment {
+    /// The url of segment
+    pub url: String,
+    /// Segment size in bytes
+    pub size: Option<usize>,
+    /// Segment duration in milliseconds
+    pub duration: Option<usize>,
+}
+
+impl VideoSegment {
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: String::from(url),
+            size: None,
+            duration: None,
+        }
+    }
+
+    /// Convert segment to JSON.
+    pub fn to_json(&self) -> JsonValue {
+        json::object! {"url": self.url.as_str(), "size": self.size, "duration": self.duration}
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+/// The type of [`StreamFormat`](struct.StreamFormat.html)
+pub enum StreamType {
+    /// Only have video track
+    Video,
+    /// Only have audio track
+    Audio,
+    /// Have both video and audio track
+    Mixed,
+}
+
+impl StreamType {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Video => "video",
+            Self::Audio => "audio",
+            Self::Mixed => "mixed",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+/// An available stream provided by the provider. Used to list available formats.
+pub struct StreamFormat {
+    /// Stream type
+    pub typ: StreamType,
+    /// Format ID in provider
+    pub id: String,
+    /// Human readable description
+    pub description: String,
+    /// Codec name
+    pub codec: Option<String>,
+    /// Bandwidth in bits per second
+    pub bandwidth: Option<usize>,
+    /// Video width
+    pub width: Option<usize>,
+    /// Video height
+    pub height: Option<usize>,
+    /// Whether to this stream is selected to download
+    pub selected: bool,
+}
+
+impl StreamFormat {
+    pub fn new(typ: StreamType, id: &str, description: &str) -> Self {
+        Self {
+            typ,
+            id: String::from(id),
+            description: String::from(description),
+            codec: None,
+            bandwidth: None,
+            width: None,
+            height: None,
+            selected: false,
+        }
+    }
+
+    /// Return a line which can be printed in format list.
+    pub fn to_line(&self) -> String {
+        let mut s = format!(
+            "{} {:<6} {:<8} {:<10}",
+            if self.selected { "*" } else { " " },
+            self.id,
+            self.typ.name(),
+            self.description
+        );
+        if let (Some(width), Some(height)) = (self.width, self.height) {
+            s += format!(" {}x{}", width, height).as_str();
+        }
+        if let Some(codec) = &self.codec {
+            s += format!(" {}", codec).as_str();
+        }
+        if let Some(bandwidth) = self.bandwidth {
+            s += format!(" {}kbps", bandwidth / 1000).as_str();
+        }
+        s
+    }
+
+    /// Convert format to JSON.
+    pub fn to_json(&self) -> JsonValue {
+        json::object! {
+            "id": self.id.as_str(),
+            "type": self.typ.name(),
+            "description": self.description.as_str(),
+            "codec": self.codec.clone(),
+            "bandwidth": self.bandwidth,
+            "width": self.width,
+            "height": self.height,
+            "selected": self.selected,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+/// A subtitle track of video. It will be saved next to the video and can be muxed into the video.
+pub struct Subtitle {
+    /// Language tag. Such as `zh-CN`
+    pub lang: Option<String>,
+    /// Track title
+    pub title: Option<String>,
+    /// The suffix of file name. The file will be saved to `<output>.<suffix>.<ext>`
+    pub suffix: String,
+    /// File extension. Such as `ass`, `srt`
+    pub ext: String,
+    /// Subtitle content
+    pub data: String,
+}
+
+impl Subtitle {
+    pub fn new(suffix: &str, ext: &str, data: String) -> Self {
+        Self {
+            lang: None,
+            title: None,
+            suffix: String::from(suffix),
+            ext: String::from(ext),
+            data,
+        }
+    }
+
+    /// Convert subtitle to JSON.
+    pub fn to_json(&self) -> JsonValue {
+        json::object! {
+            "lang": self.lang.clone(),
+            "title": self.title.clone(),
+            "suffix": self.suffix.as_str(),
+            "ext": self.ext.as_str(),
+            "data": self.data.as_str(),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+/// An extra file which will be saved next to the video. Such as raw danmaku.
+pub struct ExtraFile {
+    /// The suffix of file name (including extension). The file will be saved to `<output>.<suffix>`
+    pub suffix: String,
+    /// File content
+    pub data: Vec<u8>,
+}
+
+impl ExtraFile {
+    pub fn new(suffix: &str, data: Vec<u8>) -> Self {
+        Self {
+            suffix: String::from(suffix),
+            data,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+/// The protocol of live stream
+pub enum LiveProtocol {
+    /// HTTP-FLV
+    Flv,
+    /// HTTP Live Streaming
+    Hls,
+}
+
+impl LiveProtocol {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Flv => "flv",
+            Self::Hls => "hls",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+/// The status of live returned by [`LiveSource`](trait.LiveSource.html)
+pub enum LiveStatus {
+    /// The live is going on. Contains the latest stream urls.
+    Living(Vec<String>),
+    /// The live is ended.
+    Ended,
+    /// Failed to get status.
+    Failed,
+}
+
+/// Record live danmaku (or other live comments) while recording the live stream.
+#[async_trait]
+pub trait LiveDanmakuRecorder: Send + Sync {
+    /// Start a new output file. Danmaku received later will be saved next to the file.
+    /// * `base` - The location of output file without extension
+    fn new_file(&mut self, base: &str);
+    /// Stop recording and wait until remaining danmaku are saved.
+    async fn stop(&mut self);
+}
+
+/// Provide the latest information of a live to live downloader.
+#[async_trait]
+pub trait LiveSource: Send + Sync {
+    /// Get the status of live. Called when the stream is interrupted.
+    async fn get_status(&mut self) -> LiveStatus;
+    /// Start recording live danmaku. Return None if not supported or disabled.
+    fn start_danmaku(&self) -> Option<Box<dyn LiveDanmakuRecorder>>;
+    fn box_clone(&self) -> Box<dyn LiveSource>;
+}
+
+/// Live stream information. Used in [`VideoInfo`](struct.VideoInfo.html)
+pub struct LiveInfo {
+    /// Stream protocol
+    pub protocol: LiveProtocol,
+    /// Stream urls. Other urls are used when the first url is not available.
+    pub urls: Vec<String>,
+    /// Used to refresh urls and record danmaku.
+    pub source: Option<Box<dyn LiveSource>>,
+}
+
+impl Clone for LiveInfo {
+    fn clone(&self) -> Self {
+        Self {
+            protocol: self.protocol,
+            urls: self.urls.clone(),
+            source: self.source.as_ref().map(|s| s.box_clone()),
+        }
+    }
+}
+
+impl Debug for LiveInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LiveInfo")
+            .field("protocol", &self.protocol)
+            .field("urls", &self.urls)
+            .field("source", &self.source.is_some())
+            .finish()
+    }
 }
 
 #[derive(Debug)]
@@ -141,21 +507,103 @@ pub struct VideoInfo {
     /// The playback url of video.
     /// Used when `typ` is [`VideoPlayInfoType::SignleUrl`](enum.VideoPlayInfoType.html#variant.SignleUrl)
     pub url: Option<String>,
+    /// The url of video stream.
+    /// Used when `typ` is [`VideoPlayInfoType::Dash`](enum.VideoPlayInfoType.html#variant.Dash)
+    pub video_url: Option<String>,
+    /// The url of audio stream. Some videos don't have audio stream.
+    /// Used when `typ` is [`VideoPlayInfoType::Dash`](enum.VideoPlayInfoType.html#variant.Dash)
+    pub audio_url: Option<String>,
+    /// The ordered segment list of video.
+    /// Used when `typ` is [`VideoPlayInfoType::Segments`](enum.VideoPlayInfoType.html#variant.Segments)
+    pub segments: Option<Vec<VideoSegment>>,
+    /// All available streams. Selected streams are marked.
+    pub formats: Vec<StreamFormat>,
+    /// Subtitles
+    pub subtitles: Vec<Subtitle>,
+    /// Extra files
+    pub extra_files: Vec<ExtraFile>,
     /// HTTP Headers
     pub headers: Option<HeaderMap>,
     /// Cookies
     pub cookies: Option<CookiesJar>,
+    /// Whether only have audio stream. If true, the default output extension is `m4a` instead of `mp4`.
+    pub audio_only: bool,
+    /// Live stream information.
+    /// Used when `typ` is [`VideoPlayInfoType::Live`](enum.VideoPlayInfoType.html#variant.Live)
+    pub live: Option<LiveInfo>,
 }
 
 impl VideoInfo {
     /// Check the information
     pub fn check(&self) -> bool {
-        if self.typ == VideoPlayInfoType::SignleUrl {
-            if self.url.is_none() {
-                return false;
+        match self.typ {
+            VideoPlayInfoType::SignleUrl => self.url.is_some(),
+            VideoPlayInfoType::Dash => self.video_url.is_some(),
+            VideoPlayInfoType::Segments => self.segments.as_ref().is_some_and(|s| !s.is_empty()),
+            VideoPlayInfoType::Live => self.live.as_ref().is_some_and(|l| !l.urls.is_empty()),
+        }
+    }
+
+    /// Convert video information to JSON.
+    ///
+    /// Cookies and headers which contain secrets (such as `Cookie`) are not included.
+    pub fn to_json(&self) -> JsonValue {
+        let segments = match &self.segments {
+            Some(segs) => {
+                let mut arr = JsonValue::new_array();
+                for seg in segs.iter() {
+                    arr.push(seg.to_json()).unwrap();
+                }
+                arr
             }
+            None => JsonValue::Null,
+        };
+        let mut formats = JsonValue::new_array();
+        for f in self.formats.iter() {
+            formats.push(f.to_json()).unwrap();
+        }
+        let mut subtitles = JsonValue::new_array();
+        for sub in self.subtitles.iter() {
+            subtitles.push(sub.to_json()).unwrap();
+        }
+        let mut extra_files = JsonValue::new_array();
+        for f in self.extra_files.iter() {
+            extra_files
+                .push(json::object! {"suffix": f.suffix.as_str(), "size": f.data.len()})
+                .unwrap();
+        }
+        let mut headers = JsonValue::new_object();
+        if let Some(h) = &self.headers {
+            let mut keys: Vec<&str> = h.keys().map(|k| k.as_str()).collect();
+            keys.sort();
+            for k in keys {
+                if SECRET_HEADERS.contains(&k) {
+                    continue;
+                }
+                if let Ok(v) = h.get(k).unwrap().to_str() {
+                    headers[k] = JsonValue::from(v);
+                }
+            }
+        }
+        let live = match &self.live {
+            Some(l) => json::object! {"protocol": l.protocol.name(), "urls": l.urls.clone()},
+            None => JsonValue::Null,
+        };
+        json::object! {
+            "type": self.typ.name(),
+            "meta": self.meta.to_json(),
+            "cover": self.cover.clone(),
+            "url": self.url.clone(),
+            "video_url": self.video_url.clone(),
+            "audio_url": self.audio_url.clone(),
+            "segments": segments,
+            "formats": formats,
+            "subtitles": subtitles,
+            "extra_files": extra_files,
+            "headers": headers,
+            "audio_only": self.audio_only,
+            "live": live,
         }
-        true
     }
 }
 
@@ -166,8 +614,16 @@ impl Clone for VideoInfo {
             cover: self.cover.clone(),
             typ: self.typ.clone(),
             url: self.url.clone(),
+            video_url: self.video_url.clone(),
+            audio_url: self.audio_url.clone(),
+            segments: self.segments.clone(),
+            formats: self.formats.clone(),
+            subtitles: self.subtitles.clone(),
+            extra_files: self.extra_files.clone(),
             headers: self.headers.clone(),
             cookies: self.cookies.clone(),
+            audio_only: self.audio_only,
+            live: self.live.clone(),
         }
     }
 }
@@ -179,8 +635,16 @@ impl Default for VideoInfo {
             cover: None,
             typ: VideoPlayInfoType::SignleUrl,
             url: None,
+            video_url: None,
+            audio_url: None,
+            segments: None,
+            formats: Vec::new(),
+            subtitles: Vec::new(),
+            extra_files: Vec::new(),
             headers: None,
             cookies: None,
+            audio_only: false,
+            live: None,
         }
     }
 }
@@ -215,6 +679,23 @@ impl ExtractInfo {
         }
         true
     }
+
+    /// Convert extracted information to JSON. See [`VideoInfo::to_json`](struct.VideoInfo.html#method.to_json).
+    ///
+    /// `videos` is null if the video (or the video list) is missing.
+    pub fn to_json(&self) -> JsonValue {
+        let videos = match self.typ {
+            InfoType::Video => match &self.video {
+                Some(v) => JsonValue::Array(vec![v.to_json()]),
+                None => JsonValue::Null,
+            },
+            InfoType::VideoList => match &self.videos {
+                Some(l) => JsonValue::Array(l.iter().map(|v| v.to_json()).collect()),
+                None => JsonValue::Null,
+            },
+        };
+        json::object! {"type": self.typ.name(), "videos": videos}
+    }
 }
 
 impl Clone for ExtractInfo {
@@ -236,3 +717,38 @@ impl Default for ExtractInfo {
         }
     }
 }
+
+#[test]
+fn test_video_info_to_json() {
+    let mut vi = VideoInfo::default();
+    vi.meta.title = Some(String::from("Title"));
+    vi.meta.extra.insert(String::from("bvid"), String::from("BV1xx411c7mD"));
+    vi.meta.extra.insert(String::from("aid"), String::from("2"));
+    vi.url = Some(String::from("https://a.com/a.mp4"));
+    let mut h = HeaderMap::new();
+    h.insert("referer", "https://www.bilibili.com/".parse().unwrap());
+    h.insert("cookie", "SESSDATA=secret".parse().unwrap());
+    vi.headers = Some(h);
+    let ei = ExtractInfo {
+        typ: InfoType::Video,
+        video: Some(vi),
+        ..Default::default()
+    };
+    let j = ei.to_json();
+    assert_eq!("video", j["type"]);
+    let v = &j["videos"][0];
+    assert_eq!("single_url", v["type"]);
+    assert_eq!("Title", v["meta"]["title"]);
+    assert!(v["meta"]["author"].is_null());
+    assert_eq!(vec!["aid", "bvid"], v["meta"]["extra"].entries().map(|(k, _)| k).collect::<Vec<&str>>());
+    assert_eq!("https://www.bilibili.com/", v["headers"]["referer"]);
+    assert!(!v["headers"].has_key("cookie"));
+    assert!(!j.dump().contains("secret"));
+}
+
+#[test]
+fn test_extract_info_to_json_without_video() {
+    let j = ExtractInfo::default().to_json();
+    assert_eq!("video", j["type"]);
+    assert!(j["videos"].is_null());
+}
@@ -2,11 +2,41 @@ use crate::downloader::aria2c::check_file_allocation;
 use crate::downloader::aria2c::check_max_connection_per_server;
 use crate::downloader::aria2c::check_min_split_size;
 use crate::downloader::aria2c::check_split;
+use crate::downloader::output::check_container;
 use crate::getopt::OptDes;
 use crate::settings::JsonValueType;
+use crate::utils::duration::ToDuration;
+use crate::utils::number::ToUsize;
+use crate::utils::size::ToSize;
+use json::JsonValue;
 use crate::settings::SettingDes;
 use crate::i18n::gettext;
 
+fn check_duration(value: &JsonValue) -> bool {
+    match value.to_duration() {
+        Some(d) => d > 0,
+        None => false,
+    }
+}
+
+fn check_size(value: &JsonValue) -> bool {
+    match value.to_size() {
+        Some(s) => s > 0,
+        None => false,
+    }
+}
+
+fn check_jobs(value: &JsonValue) -> bool {
+    match value.to_usize() {
+        Some(j) => j > 0,
+        None => false,
+    }
+}
+
+fn check_usize(value: &JsonValue) -> bool {
+    value.to_usize().is_some()
+}
+
 pub fn get_config_opt_list() -> Vec<OptDes> {
     vec![
         OptDes::new("config", Some("c"), gettext("The location of settings file. Default: bili.settings.json"), true, true, Some("path")).unwrap(),
@@ -26,31 +56,49 @@ pub fn get_cookie_opt_list() -> Vec<OptDes> {
 
 pub fn get_opt_list() -> Vec<OptDes> {
     vec![
-        OptDes::new("aria2c", None, gettext("Whether to enable arai2c."), true, true, Some("boolean")).unwrap(),
+        OptDes::new("add-metadata", None, gettext("Whether to add metadata to output file. Default: true"), true, true, Some("boolean")).unwrap(),
+        OptDes::new("aria2c", None, gettext("Whether to enable arai2c. If disabled or not available, built-in downloader will be used."), true, true, Some("boolean")).unwrap(),
         OptDes::new("aria2c-file-allocation", None, gettext("The file allocation method used by aria2c. Available value: none, prealloc, trunc, falloc."), true, true, Some("METHOD")).unwrap(),
         OptDes::new("aria2c-max-connection-per-server", None, gettext("The maximum number of connections to one server for each download when using aria2c to download."), true, true, Some("NUM")).unwrap(),
         OptDes::new("aria2c-min-split-size", None, gettext("Let aria2 does not split less than 2*SIZE byte range."), true, true, Some("SIZE")).unwrap(),
         OptDes::new("aria2c-split", None, gettext("The number of connections used when downloading a file."), true, true, Some("N")).unwrap(),
         OptDes::new("config", Some("c"), gettext("The location of settings file. Default: bili.settings.json"), true, true, Some("path")).unwrap(),
+        OptDes::new("container", None, gettext("The container of output file when streams are merged by ffmpeg. Available value: mp4, mkv. Default: mp4"), true, true, Some("container")).unwrap(),
         OptDes::new("cookies", None, gettext("The location of cookies file. Default: \"bili.cookies.json\" in executable's path."), true, true, Some("path")).unwrap(),
         OptDes::new("cookie-jar", Some("j"), gettext("The name of cookie jar which cookies will be stored."), true, true, Some("name")).unwrap(),
+        OptDes::new("dump-json", None, gettext("Print extracted information as JSON instead of downloading. Cookies and secret headers are not included."), false, false, None).unwrap(),
+        OptDes::new("embed-cover", None, gettext("Whether to embed cover to output file. Only mp4, mkv and mp3 container are supported. Default: false"), true, true, Some("boolean")).unwrap(),
         OptDes::new("help", Some("h"), gettext("Print help message"), true, false, Some("full|provider name")).unwrap(),
         OptDes::new("help-deps", None, gettext("Print all options/settings which provider depended on. Exclude basic options"), false, false, None).unwrap(),
         OptDes::new("help-settings", None, gettext("Print all settings"), true, false, Some("full|provider name")).unwrap(),
+        OptDes::new("jobs", None, gettext("The maximum number of videos extracted or downloaded at the same time. Default: 1"), true, true, Some("N")).unwrap(),
+        OptDes::new("list-formats", Some("F"), gettext("List all available formats instead of downloading."), false, false, None).unwrap(),
         OptDes::new("list-providers-only", None, gettext("List only providers name when print help message"), false, false, None).unwrap(),
         OptDes::new("login", None, gettext("If not logined, force to login."), false, false, None).unwrap(),
+        OptDes::new("mux-subtitles", None, gettext("Whether to mux subtitles into output file. Only mp4 and mkv container are supported. ASS subtitles (such as danmaku) are only muxed into mkv container. Default: false"), true, true, Some("boolean")).unwrap(),
+        OptDes::new("output", Some("o"), gettext("The output file name template (without extension). Available fields: {title}, {author}, {album}, {video_id}, {track}, {track_total}, {date:%Y-%m-%d} and extra metadata such as {bvid}. Use / to create subdirectories. Default: {title}"), true, true, Some("TEMPLATE")).unwrap(),
+        OptDes::new("save-chapters", None, gettext("Whether to save chapters to a standalone file (OGM format) next to the video. Default: false"), true, true, Some("boolean")).unwrap(),
+        OptDes::new("save-cover", None, gettext("Whether to save cover next to the video. Default: false"), true, true, Some("boolean")).unwrap(),
         OptDes::new("version", Some("V"), gettext("Print version of bili"), false, false, None).unwrap(),
     ]
 }
 
 pub fn get_settings_list() -> Vec<SettingDes> {
     vec![
-        SettingDes::new("aria2c", gettext("Whether to enable arai2c."), JsonValueType::Boolean, None).unwrap(),
+        SettingDes::new("add-metadata", gettext("Whether to add metadata to output file. Default: true"), JsonValueType::Boolean, None).unwrap(),
+        SettingDes::new("aria2c", gettext("Whether to enable arai2c. If disabled or not available, built-in downloader will be used."), JsonValueType::Boolean, None).unwrap(),
         SettingDes::new("aria2c-file-allocation", gettext("The file allocation method used by aria2c. Available value: none, prealloc, trunc, falloc."), JsonValueType::Str, Some(check_file_allocation)).unwrap(),
         SettingDes::new("aria2c-max-connection-per-server", gettext("The maximum number of connections to one server for each download when using aria2c to download."), JsonValueType::Multiple, Some(check_max_connection_per_server)).unwrap(),
         SettingDes::new("aria2c-min-split-size", gettext("Let aria2 does not split less than 2*SIZE byte range."), JsonValueType::Multiple, Some(check_min_split_size)).unwrap(),
         SettingDes::new("aria2c-split", gettext("The number of connections used when downloading a file."), JsonValueType::Multiple, Some(check_split)).unwrap(),
+        SettingDes::new("container", gettext("The container of output file when streams are merged by ffmpeg. Available value: mp4, mkv. Default: mp4"), JsonValueType::Str, Some(check_container)).unwrap(),
         SettingDes::new("cookies", gettext("The location of cookies file. Default: \"bili.cookies.json\" in executable's path."), JsonValueType::Str, None).unwrap(),
+        SettingDes::new("embed-cover", gettext("Whether to embed cover to output file. Only mp4, mkv and mp3 container are supported. Default: false"), JsonValueType::Boolean, None).unwrap(),
+        SettingDes::new("jobs", gettext("The maximum number of videos extracted or downloaded at the same time. Default: 1"), JsonValueType::Multiple, Some(check_jobs)).unwrap(),
+        SettingDes::new("mux-subtitles", gettext("Whether to mux subtitles into output file. Only mp4 and mkv container are supported. ASS subtitles (such as danmaku) are only muxed into mkv container. Default: false"), JsonValueType::Boolean, None).unwrap(),
+        SettingDes::new("output", gettext("The output file name template (without extension). Available fields: {title}, {author}, {album}, {video_id}, {track}, {track_total}, {date:%Y-%m-%d} and extra metadata such as {bvid}. Use / to create subdirectories. Default: {title}"), JsonValueType::Str, None).unwrap(),
+        SettingDes::new("save-chapters", gettext("Whether to save chapters to a standalone file (OGM format) next to the video. Default: false"), JsonValueType::Boolean, None).unwrap(),
+        SettingDes::new("save-cover", gettext("Whether to save cover next to the video. Default: false"), JsonValueType::Boolean, None).unwrap(),
     ]
 }
 
@@ -67,3 +115,23 @@ pub fn get_webdriver_settings() -> Vec<SettingDes> {
         SettingDes::new("chrome", gettext("Start browser with chromedriver"), JsonValueType::Boolean, None).unwrap(),
     ]
 }
+
+pub fn get_live_record_options() -> Vec<OptDes> {
+    vec![
+        OptDes::new("duration", None, gettext("Stop recording live after this duration. Such as 3600, 1h30m or 01:30:00."), true, true, Some("duration")).unwrap(),
+        OptDes::new("max-size", None, gettext("Stop recording live after this size of data is recorded. Such as 2GiB."), true, true, Some("size")).unwrap(),
+        OptDes::new("segment-interval", None, gettext("Split the recorded live to a new file after this duration. Such as 30m."), true, true, Some("duration")).unwrap(),
+        OptDes::new("live-retry", None, gettext("The maximum number of reconnections when the live stream is interrupted. Default: 10"), true, true, Some("count")).unwrap(),
+        OptDes::new("live-retry-interval", None, gettext("The time to wait before reconnecting. Default: 5s"), true, true, Some("duration")).unwrap(),
+    ]
+}
+
+pub fn get_live_record_settings() -> Vec<SettingDes> {
+    vec![
+        SettingDes::new("duration", gettext("Stop recording live after this duration. Such as 3600, \"1h30m\" or \"01:30:00\"."), JsonValueType::Multiple, Some(check_duration)).unwrap(),
+        SettingDes::new("max-size", gettext("Stop recording live after this size of data is recorded. Such as \"2GiB\"."), JsonValueType::Multiple, Some(check_size)).unwrap(),
+        SettingDes::new("segment-interval", gettext("Split the recorded live to a new file after this duration. Such as \"30m\"."), JsonValueType::Multiple, Some(check_duration)).unwrap(),
+        SettingDes::new("live-retry", gettext("The maximum number of reconnections when the live stream is interrupted. Default: 10"), JsonValueType::Multiple, Some(check_usize)).unwrap(),
+        SettingDes::new("live-retry-interval", gettext("The time to wait before reconnecting. Default: 5s"), JsonValueType::Multiple, Some(check_duration)).unwrap(),
+    ]
+}
@@ -0,0 +1,62 @@
+use crate::error::Error;
+use crate::error::Result;
+use crate::http_client::CookieClient;
+use crate::utils::runtime::block_on;
+use json::JsonValue;
+
+/// Check the code returned by Bilibili API.
+/// * `url` - API url
+/// * `re` - Response of the API
+///
+/// Return the response if the code is 0.
+pub fn check_api_code(url: &str, re: JsonValue) -> Result<JsonValue> {
+    let code = re["code"].as_i64();
+    if code == Some(0) {
+        return Ok(re);
+    }
+    let msg = match re["message"].as_str() {
+        Some(m) => m,
+        None => re["msg"].as_str().unwrap_or(""),
+    };
+    Err(Error::Api {
+        url: String::from(url),
+        code: code.unwrap_or(-1),
+        msg: String::from(msg),
+    })
+}
+
+/// The maximum number of parts (or episodes) whose information is requested at the same time.
+pub const MAX_PARTS_AT_SAME_TIME: usize = 4;
+
+/// Call a Bilibili API and return the response if the code is 0.
+/// * `c` - HTTP client
+/// * `url` - API url
+/// * `param` - Query parameters
+pub async fn get_api_async(c: &mut CookieClient, url: &str, param: JsonValue) -> Result<JsonValue> {
+    let re = c.get_json_async(url, param).await?;
+    check_api_code(url, re)
+}
+
+/// Call a Bilibili API and return the response if the code is 0.
+/// * `c` - HTTP client
+/// * `url` - API url
+/// * `param` - Query parameters
+pub fn get_api(c: &mut CookieClient, url: &str, param: JsonValue) -> Result<JsonValue> {
+    block_on(get_api_async(c, url, param))
+}
+
+#[test]
+fn test_check_api_code() {
+    let url = "https://api.bilibili.com/x/player/v2";
+    assert!(check_api_code(url, json::object! {"code": 0, "data": {}}).is_ok());
+    let e = check_api_code(url, json::object! {"code": -404, "message": "啥都木有"}).unwrap_err();
+    match e {
+        Error::Api { code, msg, .. } => {
+            assert_eq!(-404, code);
+            assert_eq!("啥都木有", msg);
+        }
+        _ => panic!("Unexpected error: {}", e),
+    }
+    let e = check_api_code(url, json::object! {"code": 72000000, "msg": "参数错误"}).unwrap_err();
+    assert_eq!(5, e.exit_code());
+}
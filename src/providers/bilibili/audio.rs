@@ -0,0 +1,400 @@
+extern crate chrono;
+extern crate futures;
+extern crate json;
+extern crate regex;
+
+use crate::cookies_json::CookiesJar;
+use crate::error::Error;
+use crate::error::Result;
+use crate::getopt::OptDes;
+use crate::getopt::OptStore;
+use crate::i18n::gettext;
+use crate::metadata::ExtraFile;
+use crate::metadata::ExtractInfo;
+use crate::metadata::InfoType;
+use crate::metadata::NoInTotal;
+use crate::metadata::StreamFormat;
+use crate::metadata::StreamType;
+use crate::metadata::VideoInfo;
+use crate::metadata::VideoMetadata;
+use crate::providers::bilibili::api::get_api_async;
+use crate::providers::bilibili::base::BiliBaseProvider;
+use crate::providers::provider_base::Provider;
+use crate::settings::SettingDes;
+use crate::settings::SettingStore;
+use async_trait::async_trait;
+use chrono::TimeZone;
+use chrono::Utc;
+use json::JsonValue;
+use regex::Regex;
+
+lazy_static! {
+    static ref RE: Regex = Regex::new(r"(?i)^(?:(?:https?://)?(?:www\.|m\.)?bilibili\.com/audio/)?(?P<type>au|am)(?P<id>\d+)/?(?:[?#].*)?$").unwrap();
+    static ref RE_LRC_TIME: Regex = Regex::new(r"\[\d+:\d+(?:[.:]\d+)?\]").unwrap();
+    static ref RE_LRC_TAG: Regex = Regex::new(r"^\[[a-zA-Z]+:.*\]$").unwrap();
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+/// Information from audio url
+pub enum AudioUrl {
+    /// Audio ID
+    Song(usize),
+    /// Playlist ID
+    Menu(usize),
+}
+
+/// Convert LRC lyrics to plain text by removing timestamps and ID tags.
+/// * `s` - LRC lyrics
+pub fn lrc_to_text(s: &str) -> String {
+    let mut r: Vec<String> = Vec::new();
+    for l in s.lines() {
+        let l = l.trim();
+        if RE_LRC_TAG.is_match(l) && !RE_LRC_TIME.is_match(l) {
+            continue;
+        }
+        let t = RE_LRC_TIME.replace_all(l, "");
+        r.push(String::from(t.trim()));
+    }
+    while !r.is_empty() && r[0].is_empty() {
+        r.remove(0);
+    }
+    while !r.is_empty() && r[r.len() - 1].is_empty() {
+        r.pop();
+    }
+    r.join("\n")
+}
+
+/// Convert a song's information to metadata.
+/// * `song` - Song information from API (`https://www.bilibili.com/audio/music-service-c/web/song/info`)
+pub fn gen_audio_metadata(song: &JsonValue) -> VideoMetadata {
+    let mut md = VideoMetadata::default();
+    if let Some(t) = song["title"].as_str() {
+        md.title = Some(String::from(t));
+    }
+    let uname = song["uname"].as_str();
+    let author = song["author"].as_str();
+    if let Some(author) = author.filter(|&author| !author.is_empty()).or(uname) {
+        md.author = Some(String::from(author));
+    }
+    if let Some(uname) = uname {
+        md.album_artist = Some(String::from(uname));
+        md.extra.insert(String::from("uploader"), String::from(uname));
+    }
+    let des = song["intro"].as_str();
+    if let Some(des) = des.filter(|&des| !des.is_empty()) {
+        md.description = Some(String::from(des));
+    }
+    let pt = song["passtime"].as_i64();
+    if let Some(pt) = pt.filter(|&pt| pt > 0) {
+        md.date = Utc.timestamp_opt(pt, 0).single();
+    }
+    let id = song["id"].as_usize();
+    if let Some(id) = id {
+        md.video_id = Some(format!("au{}", id));
+    }
+    let aid = song["aid"].as_usize();
+    if let Some(aid) = aid.filter(|&aid| aid > 0) {
+        md.extra.insert(String::from("aid"), format!("AV{}", aid));
+    }
+    let bvid = song["bvid"].as_str();
+    if let Some(bvid) = bvid.filter(|&bvid| !bvid.is_empty()) {
+        md.extra.insert(String::from("bvid"), String::from(bvid));
+    }
+    md
+}
+
+pub struct BiliAudioProvider {
+    base: BiliBaseProvider,
+    /// Input Url Information (Set in [`extract`](#method.extract) function)
+    url: Option<AudioUrl>,
+}
+
+impl BiliAudioProvider {
+    fn parse_url(url: &str) -> Option<AudioUrl> {
+        let caps = RE.captures(url);
+        let caps = caps?;
+        let id = caps.name("id").unwrap().as_str().parse::<usize>();
+        if id.is_err() {
+            return None;
+        }
+        let id = id.unwrap();
+        match caps.name("type").unwrap().as_str().to_lowercase().as_str() {
+            "au" => Some(AudioUrl::Song(id)),
+            _ => Some(AudioUrl::Menu(id)),
+        }
+    }
+
+    /// Get text from url.
+    /// * `url` - Url
+    /// * `param` - Query parameters
+    async fn get_text(&mut self, url: &str, param: JsonValue) -> Result<String> {
+        let c = self.base.client.as_mut().unwrap();
+        c.get_text_async(url, param).await
+    }
+
+    /// Call a music API and return the `data` field.
+    /// * `url` - API url
+    /// * `param` - Query parameters
+    async fn get_api(&mut self, url: &str, param: JsonValue) -> Result<JsonValue> {
+        let c = self.base.client.as_mut().unwrap();
+        let re = get_api_async(c, url, param).await?;
+        Ok(re["data"].clone())
+    }
+
+    /// Get the information of all songs in a playlist.
+    /// * `am` - Playlist ID
+    ///
+    /// Returns the title of playlist and the song list.
+    async fn get_menu(&mut self, am: usize) -> Result<(Option<String>, Vec<JsonValue>)> {
+        let info = self.get_api(
+            "https://www.bilibili.com/audio/music-service-c/web/menu/info",
+            json::object! {"sid": am},
+        )
+        .await?;
+        let title = info["title"].as_str().map(String::from);
+        let mut songs: Vec<JsonValue> = Vec::new();
+        let mut pn = 1;
+        loop {
+            let data = self.get_api(
+                "https://www.bilibili.com/audio/music-service-c/web/song/of-menu",
+                json::object! {"sid": am, "pn": pn, "ps": 100},
+            )
+            .await?;
+            for s in data["data"].members() {
+                songs.push(s.clone());
+            }
+            let page_count = data["pageCount"].as_usize().unwrap_or(0);
+            if data["data"].is_empty() || pn >= page_count {
+                break;
+            }
+            pn += 1;
+        }
+        Ok((title, songs))
+    }
+
+    /// Get the lyrics of a song.
+    /// * `song` - Song information
+    ///
+    /// Returns None if the song does not have lyrics or it can not be downloaded.
+    async fn get_lyrics(&mut self, song: &JsonValue) -> Option<String> {
+        let url = song["lyric"].as_str().filter(|u| !u.is_empty())?;
+        let url = match url.strip_prefix("http://") {
+            Some(u) => format!("https://{}", u),
+            None => String::from(url),
+        };
+        match self.get_text(url.as_str(), json::object! {}).await {
+            Ok(t) => Some(t),
+            Err(e) => {
+                eprintln!("{}", e);
+                eprintln!("{}", gettext("Can not get lyrics. Lyrics will not be saved."));
+                None
+            }
+        }
+    }
+
+    /// Generate the information of a song.
+    /// * `song` - Song information
+    /// * `album` - The title of playlist
+    /// * `track` - Position in playlist
+    async fn gen_audio_info(
+        &mut self,
+        song: &JsonValue,
+        album: &Option<String>,
+        track: Option<NoInTotal>,
+    ) -> Result<VideoInfo> {
+        let au = song["id"].as_usize();
+        if au.is_none() {
+            return Err(Error::Extract(String::from(gettext("Can not find audio ID."))));
+        }
+        let au = au.unwrap();
+        let data = self.get_api(
+            "https://www.bilibili.com/audio/music-service-c/web/url",
+            json::object! {"sid": au, "privilege": 2, "quality": 2},
+        )
+        .await?;
+        let url = data["cdns"][0].as_str();
+        if url.is_none() {
+            return Err(Error::Extract(String::from(gettext("Can not find audio stream."))));
+        }
+        let mut m = gen_audio_metadata(song);
+        if album.is_some() {
+            m.album = album.clone();
+        }
+        m.track = track;
+        let mut vi = VideoInfo {
+            meta: m,
+            url: Some(String::from(url.unwrap())),
+            audio_only: true,
+            ..Default::default()
+        };
+        let q = data["type"].as_i64().unwrap_or(0);
+        let mut f = StreamFormat::new(StreamType::Audio, format!("{}", q).as_str(), data["title"].as_str().unwrap_or(""));
+        f.selected = true;
+        vi.formats.push(f);
+        let lrc = self.get_lyrics(song).await;
+        if let Some(lrc) = lrc {
+            let text = lrc_to_text(lrc.as_str());
+            if !text.is_empty() {
+                vi.meta.lyrics = Some(text);
+            }
+            vi.extra_files.push(ExtraFile::new("lrc", lrc.into_bytes()));
+        }
+        let cover = song["cover"].as_str();
+        if let Some(cover) = cover.filter(|&cover| !cover.is_empty()) {
+            vi.cover = Some(String::from(cover));
+        }
+        let mut h = BiliBaseProvider::default_headers();
+        let referer = format!("https://www.bilibili.com/audio/au{}", au);
+        h.insert("referer", referer.parse().unwrap());
+        vi.headers = Some(h);
+        vi.cookies = Some(self.base.client.as_ref().unwrap().get_cookie_jar().clone());
+        Ok(vi)
+    }
+}
+
+#[async_trait]
+impl Provider for BiliAudioProvider {
+    fn new() -> BiliAudioProvider {
+        BiliAudioProvider {
+            base: BiliBaseProvider::new(),
+            url: None,
+        }
+    }
+
+    fn add_custom_options(&self, opt: &mut OptStore) {
+        self.base.add_custom_options(opt);
+    }
+
+    fn add_custom_settings(&self, store: &mut SettingStore) {
+        self.base.add_custom_settings(store);
+    }
+
+    fn can_login(&self) -> bool {
+        true
+    }
+
+    async fn check_logined_async(&mut self) -> Result<bool> {
+        self.base.check_logined_async().await
+    }
+
+    async fn extract_async(&mut self, url: &str) -> Result<ExtractInfo> {
+        let u = match Self::parse_url(url) {
+            Some(u) => u,
+            None => return Err(Error::Extract(String::from(gettext("Unsupported url.")))),
+        };
+        self.url = Some(u);
+        match u {
+            AudioUrl::Song(au) => {
+                let song = self.get_api(
+                    "https://www.bilibili.com/audio/music-service-c/web/song/info",
+                    json::object! {"sid": au},
+                )
+                .await?;
+                let vi = self.gen_audio_info(&song, &None, None).await?;
+                Ok(ExtractInfo {
+                    typ: InfoType::Video,
+                    video: Some(vi),
+                    ..Default::default()
+                })
+            }
+            AudioUrl::Menu(am) => {
+                let (title, songs) = self.get_menu(am).await?;
+                if songs.is_empty() {
+                    return Err(Error::Extract(String::from(gettext("No audio found."))));
+                }
+                let total = songs.len();
+                let mut list: Vec<VideoInfo> = Vec::new();
+                for (i, s) in songs.iter().enumerate() {
+                    match self.gen_audio_info(s, &title, NoInTotal::new(i + 1, total)).await {
+                        Ok(vi) => list.push(vi),
+                        Err(e) => {
+                            eprintln!("{}", e);
+                            let id = match s["id"].as_usize() {
+                                Some(id) => format!("au{}", id),
+                                None => format!("{}", i + 1),
+                            };
+                            let s = gettext("Can not extract audio <id>. Skipped.").replace("<id>", id.as_str());
+                            eprintln!("{}", s);
+                        }
+                    }
+                }
+                if list.is_empty() {
+                    return Err(Error::Extract(String::from(gettext("Can not extract any audio in the list."))));
+                }
+                Ok(ExtractInfo {
+                    typ: InfoType::VideoList,
+                    videos: Some(list),
+                    ..Default::default()
+                })
+            }
+        }
+    }
+
+    fn get_custom_options() -> Vec<OptDes> {
+        Vec::new()
+    }
+
+    fn get_custom_settings() -> Vec<SettingDes> {
+        Vec::new()
+    }
+
+    fn get_default_cookie_jar_name(&self) -> Option<&str> {
+        Some("bili")
+    }
+
+    fn has_custom_options(&self) -> bool {
+        true
+    }
+
+    fn has_custom_settings(&self) -> bool {
+        true
+    }
+
+    fn init(&mut self, jar: Option<&CookiesJar>, opt: OptStore, settings: SettingStore) -> Result<()> {
+        self.base.init(jar, opt, settings)
+    }
+
+    async fn login_async(&mut self, jar: &mut CookiesJar) -> Result<()> {
+        self.base.login_async(jar).await
+    }
+
+    fn logined(&self) -> bool {
+        self.base.logined()
+    }
+
+    fn match_url(url: &str) -> bool {
+        BiliAudioProvider::parse_url(url).is_some()
+    }
+
+    fn name() -> &'static str {
+        "BiliAudioProvider"
+    }
+
+    fn provider_name(&self) -> &'static str {
+        Self::name()
+    }
+}
+
+#[test]
+fn test_parse_url() {
+    assert_eq!(Some(AudioUrl::Song(1436924)), BiliAudioProvider::parse_url("https://www.bilibili.com/audio/au1436924?type=3"));
+    assert_eq!(Some(AudioUrl::Menu(29931301)), BiliAudioProvider::parse_url("https://www.bilibili.com/audio/am29931301"));
+    assert_eq!(Some(AudioUrl::Song(1)), BiliAudioProvider::parse_url("AU1"));
+    assert_eq!(None, BiliAudioProvider::parse_url("https://www.bilibili.com/video/av170001"));
+}
+
+#[test]
+fn test_audio_metadata() {
+    assert_eq!(
+        "a\nb\n\nc",
+        lrc_to_text("[ti:T]\n[ar:A]\n[00:01.00]a\n[00:02.50][01:02.50] b \n[00:03.00]\n[00:04.00]c\n")
+    );
+    let song = json::object! {"id": 1, "title": "T", "uname": "U", "author": "", "intro": "", "passtime": 1600000000, "aid": 0};
+    let m = gen_audio_metadata(&song);
+    assert_eq!(Some(String::from("T")), m.title);
+    assert_eq!(Some(String::from("U")), m.author);
+    assert_eq!(Some(String::from("au1")), m.video_id);
+    assert!(m.description.is_none());
+    assert!(m.extra.get("aid").is_none());
+    assert!(m.date.is_some());
+}
@@ -0,0 +1,586 @@
+extern crate chrono;
+extern crate futures;
+extern crate json;
+extern crate regex;
+
+use crate::cookies_json::CookiesJar;
+use crate::error::Error;
+use crate::error::Result;
+use crate::getopt::OptDes;
+use crate::getopt::OptStore;
+use crate::http_client::CookieClient;
+use crate::i18n::gettext;
+use crate::metadata::ExtractInfo;
+use crate::metadata::InfoType;
+use crate::metadata::NoInTotal;
+use crate::metadata::VideoInfo;
+use crate::metadata::VideoMetadata;
+use crate::providers::bilibili::api::get_api_async;
+use crate::providers::bilibili::api::MAX_PARTS_AT_SAME_TIME;
+use crate::providers::bilibili::base::BiliBaseProvider;
+use crate::providers::bilibili::opt_list::get_bili_bangumi_options;
+use crate::providers::bilibili::opt_list::get_bili_bangumi_settings;
+use crate::providers::bilibili::opt_list::get_bili_danmaku_options;
+use crate::providers::bilibili::opt_list::get_bili_danmaku_settings;
+use crate::providers::bilibili::opt_list::get_bili_subtitle_options;
+use crate::providers::bilibili::opt_list::get_bili_stream_options;
+use crate::providers::bilibili::opt_list::get_bili_stream_settings;
+use crate::providers::bilibili::opt_list::get_bili_subtitle_settings;
+use crate::providers::bilibili::part::Part;
+use crate::providers::bilibili::part::PartList;
+use crate::providers::bilibili::playinfo::StreamPreference;
+use crate::providers::bilibili::video_info::gen_part_video_info;
+use crate::providers::bilibili::video_info::PartSource;
+use crate::providers::provider_base::Provider;
+use crate::settings::SettingDes;
+use crate::settings::SettingStore;
+use async_trait::async_trait;
+use chrono::TimeZone;
+use chrono::Utc;
+use futures::stream;
+use futures::StreamExt;
+use json::JsonValue;
+use regex::Regex;
+use std::clone::Clone;
+
+lazy_static! {
+    static ref RE: Regex = Regex::new(r"(?i)^(?:(?:https?://)?(?:[a-z0-9-]+\.)*(?:bilibili\.com/bangumi/(?:play|media)/|b23\.tv/))?(?P<type>ep|ss|md)(?P<id>\d+)/?(?:[?#].*)?$").unwrap();
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+/// Information from bangumi url
+pub enum BangumiUrl {
+    /// Episode ID
+    Episode(usize),
+    /// Season ID
+    Season(usize),
+    /// Media ID
+    Media(usize),
+}
+
+/// The information of an episode
+#[derive(Clone, Debug, PartialEq)]
+pub struct EpisodeInfo {
+    /// Episode ID
+    pub ep_id: usize,
+    /// AV number
+    pub aid: usize,
+    /// BV number
+    pub bvid: Option<String>,
+    /// CID
+    pub cid: usize,
+    /// Episode title. Such as `1`, `正片`
+    pub title: String,
+    /// Long title
+    pub long_title: String,
+    /// The title shown on the webpage. Such as `第1话 xxx`
+    pub show_title: Option<String>,
+    /// Cover
+    pub cover: Option<String>,
+    /// Duration in seconds
+    pub duration: Option<usize>,
+    /// Publish time (Unix timestamp)
+    pub pub_time: Option<i64>,
+}
+
+impl EpisodeInfo {
+    /// Parse from an item of `episodes` in season information.
+    /// * `v` - Episode information
+    ///
+    /// Return None if ID or CID is missing.
+    pub fn from_json(v: &JsonValue) -> Option<Self> {
+        let ep_id = v["id"].as_usize();
+        let aid = v["aid"].as_usize();
+        let cid = v["cid"].as_usize();
+        if ep_id.is_none() || aid.is_none() || cid.is_none() {
+            return None;
+        }
+        let to_string = |v: &JsonValue| -> Option<String> {
+            match v.as_str() {
+                Some(s) if !s.is_empty() => Some(String::from(s)),
+                _ => None,
+            }
+        };
+        Some(Self {
+            ep_id: ep_id.unwrap(),
+            aid: aid.unwrap(),
+            bvid: to_string(&v["bvid"]),
+            cid: cid.unwrap(),
+            title: to_string(&v["title"]).unwrap_or_default(),
+            long_title: to_string(&v["long_title"]).unwrap_or_default(),
+            show_title: to_string(&v["show_title"]),
+            cover: match to_string(&v["cover"]) {
+                Some(c) if c.starts_with("//") => Some(format!("https:{}", c)),
+                c => c,
+            },
+            // Duration is in milliseconds.
+            duration: match v["duration"].as_usize() {
+                Some(d) if d > 0 => Some(d.div_ceil(1000)),
+                _ => None,
+            },
+            pub_time: match v["pub_time"].as_i64() {
+                Some(t) if t > 0 => Some(t),
+                _ => None,
+            },
+        })
+    }
+
+    /// Return the name of episode. Such as `第1话 xxx` or `1 xxx`
+    pub fn name(&self) -> String {
+        if let Some(show_title) = &self.show_title {
+            return show_title.clone();
+        }
+        if self.long_title.is_empty() {
+            return self.title.clone();
+        }
+        if self.title.is_empty() {
+            return self.long_title.clone();
+        }
+        format!("{} {}", self.title, self.long_title)
+    }
+}
+
+/// Parse episode list from season information.
+/// * `season` - Season information
+pub fn parse_episode_list(season: &JsonValue) -> Vec<EpisodeInfo> {
+    let mut r: Vec<EpisodeInfo> = Vec::new();
+    for i in season["episodes"].members() {
+        let e = EpisodeInfo::from_json(i);
+        if let Some(e) = e {
+            r.push(e);
+        }
+    }
+    r
+}
+
+/// Generate metadata of an episode.
+/// * `season` - Season information
+/// * `eps` - Episode list
+/// * `n` - Episode number (Start from 1)
+pub fn gen_episode_metadata(season: &JsonValue, eps: &[EpisodeInfo], n: usize) -> VideoMetadata {
+    let mut md = VideoMetadata::default();
+    let ep = &eps[n - 1];
+    let mut st = season["season_title"].as_str();
+    if st.is_none() || st.unwrap().is_empty() {
+        st = season["title"].as_str();
+    }
+    if let Some(st) = st {
+        md.album = Some(String::from(st));
+        md.extra.insert(String::from("season_title"), String::from(st));
+        if eps.len() == 1 {
+            md.title = Some(String::from(st));
+        } else {
+            md.title = Some(format!("{} - {}", st, ep.name()));
+        }
+    } else {
+        md.title = Some(ep.name());
+    }
+    let tr = NoInTotal::new(n, eps.len());
+    if let Some(tr) = tr {
+        md.track = Some(tr);
+    }
+    let des = season["evaluate"].as_str();
+    if let Some(des) = des.filter(|&des| !des.is_empty()) {
+        md.description = Some(String::from(des));
+    }
+    let au = season["up_info"]["uname"].as_str();
+    if let Some(au) = au {
+        md.author = Some(String::from(au));
+        md.album_artist = Some(String::from(au));
+    }
+    if let Some(pub_time) = ep.pub_time {
+        md.date = Utc.timestamp_opt(pub_time, 0).single();
+    }
+    for s in season["styles"].members() {
+        let s = s.as_str();
+        if let Some(s) = s {
+            md.tags.push(String::from(s));
+        }
+    }
+    md.video_id = Some(format!("ep{}", ep.ep_id));
+    md.extra.insert(String::from("ep_id"), format!("ep{}", ep.ep_id));
+    md.extra.insert(String::from("episode"), ep.title.clone());
+    md.extra.insert(String::from("episode_title"), ep.long_title.clone());
+    md.extra.insert(String::from("aid"), format!("AV{}", ep.aid));
+    md.extra.insert(String::from("cid"), format!("{}", ep.cid));
+    if let Some(bvid) = &ep.bvid {
+        md.extra.insert(String::from("bvid"), bvid.clone());
+    }
+    let ss = season["season_id"].as_usize();
+    if let Some(ss) = ss {
+        md.extra.insert(String::from("season_id"), format!("ss{}", ss));
+    }
+    let md_id = season["media_id"].as_usize();
+    if let Some(md_id) = md_id {
+        md.extra.insert(String::from("media_id"), format!("md{}", md_id));
+    }
+    md
+}
+
+pub struct BiliBangumiProvider {
+    base: BiliBaseProvider,
+    /// Input Url Information (Set in [`extract`](#method.extract) function)
+    url: Option<BangumiUrl>,
+    /// Season information from API (`https://api.bilibili.com/pgc/view/web/season`)
+    season: Option<JsonValue>,
+    /// Episode list
+    episodes: Vec<EpisodeInfo>,
+    /// Preferred streams
+    pref: StreamPreference,
+}
+
+impl BiliBangumiProvider {
+    fn parse_url(url: &str) -> Option<BangumiUrl> {
+        let caps = RE.captures(url);
+        let caps = caps?;
+        let id = caps.name("id").unwrap().as_str().parse::<usize>();
+        if id.is_err() {
+            return None;
+        }
+        let id = id.unwrap();
+        match caps.name("type").unwrap().as_str().to_lowercase().as_str() {
+            "ep" => Some(BangumiUrl::Episode(id)),
+            "ss" => Some(BangumiUrl::Season(id)),
+            _ => Some(BangumiUrl::Media(id)),
+        }
+    }
+
+    /// Call a PGC API and return the `result` field.
+    /// * `c` - HTTP client
+    /// * `url` - API url
+    /// * `param` - Query parameters
+    async fn get_pgc_api(c: &mut CookieClient, url: &str, param: JsonValue) -> Result<JsonValue> {
+        let re = get_api_async(c, url, param).await?;
+        let data = &re["result"];
+        if data.is_object() {
+            return Ok(data.clone());
+        }
+        Ok(re["data"].clone())
+    }
+
+    /// Get season information and episode list.
+    /// * `url` - Input Url Information
+    async fn get_season_info(&mut self, url: BangumiUrl) -> Result<()> {
+        let param = match url {
+            BangumiUrl::Episode(ep) => json::object! {"ep_id": ep},
+            BangumiUrl::Season(ss) => json::object! {"season_id": ss},
+            BangumiUrl::Media(md) => {
+                let re = Self::get_pgc_api(
+                    self.base.client.as_mut().unwrap(),
+                    "https://api.bilibili.com/pgc/review/user",
+                    json::object! {"media_id": md},
+                )
+                .await?;
+                let ss = re["media"]["season_id"].as_usize();
+                if ss.is_none() {
+                    return Err(Error::Extract(String::from(gettext("Can not find season ID."))));
+                }
+                json::object! {"season_id": ss.unwrap()}
+            }
+        };
+        let c = self.base.client.as_mut().unwrap();
+        let re = Self::get_pgc_api(c, "https://api.bilibili.com/pgc/view/web/season", param).await?;
+        self.episodes = parse_episode_list(&re);
+        self.season = Some(re);
+        if self.episodes.is_empty() {
+            return Err(Error::Extract(String::from(gettext("No episode found."))));
+        }
+        Ok(())
+    }
+
+    /// Get cid info from API (`https://api.bilibili.com/x/player/v2`)
+    /// * `c` - HTTP client
+    /// * `ep` - Episode information
+    async fn get_cid_info(c: &mut CookieClient, ep: &EpisodeInfo) -> Result<JsonValue> {
+        let re = get_api_async(
+            c,
+            "https://api.bilibili.com/x/player/v2",
+            json::object! {"aid": ep.aid, "cid": ep.cid, "ep_id": ep.ep_id},
+        )
+        .await?;
+        Ok(re["data"].clone())
+    }
+
+    /// Return the episode list which is selected by user.
+    ///
+    /// Priority: `part` option > episode in URL > `part` setting.
+    /// If nothing specified, return a list which contains all episodes.
+    /// Return an error if the `part` option is invalid or the episode in URL is not found.
+    fn get_part_list(&self) -> Result<PartList> {
+        let o = self.base.opt.as_ref().unwrap().get_option("part");
+        if let Some(o) = o {
+            let r = PartList::parse_from_str(o.as_str());
+            if r.is_none() {
+                let s = gettext("The value of option \"<key>\" is invalid.").replace("<key>", "part");
+                return Err(Error::Config(s));
+            }
+            return Ok(r.unwrap());
+        }
+        if let Some(BangumiUrl::Episode(ep)) = self.url {
+            for (i, e) in self.episodes.iter().enumerate() {
+                if e.ep_id == ep {
+                    return Ok(PartList::from(vec![Part::new(i + 1, i + 1).unwrap()]));
+                }
+            }
+            // Extras (PV, SP and so on) are only listed in sections, which are not supported.
+            let s = gettext("Episode <ep> is not a main episode of this season.")
+                .replace("<ep>", format!("ep{}", ep).as_str());
+            return Err(Error::Extract(s));
+        }
+        let se = self
+            .base
+            .se
+            .as_ref()
+            .unwrap()
+            .get_settings(self.provider_name(), "part");
+        if let Some(se) = &se {
+            let r = PartList::parse_from_json(se);
+            if let Some(r) = r {
+                return Ok(r);
+            }
+        }
+        Ok(PartList::from(vec![Part::new(0, 0).unwrap()]))
+    }
+
+    /// Return the episode numbers which need to be downloaded.
+    fn get_selected_episodes(&self) -> Result<Vec<usize>> {
+        let pl = self.get_part_list()?;
+        let le = self.episodes.len();
+        match pl.to_part_numbers(le) {
+            Ok(r) => {
+                if r.is_empty() {
+                    return Err(Error::Config(String::from(gettext("No episode is selected."))));
+                }
+                Ok(r)
+            }
+            Err(p) => {
+                let s = gettext("Episode <part> is out of range. This season only have <total> episodes.")
+                    .replace("<part>", format!("{}", p).as_str())
+                    .replace("<total>", format!("{}", le).as_str());
+                Err(Error::Config(s))
+            }
+        }
+    }
+
+    /// Get play url information from API (`https://api.bilibili.com/pgc/player/web/playurl`)
+    /// * `c` - HTTP client
+    /// * `ep` - Episode information
+    async fn get_playurl_info(&self, c: &mut CookieClient, ep: &EpisodeInfo) -> Result<JsonValue> {
+        let qn = self.pref.first_quality();
+        Self::get_pgc_api(
+            c,
+            "https://api.bilibili.com/pgc/player/web/playurl",
+            json::object! {"ep_id": ep.ep_id, "cid": ep.cid, "qn": qn, "fnval": 4048, "fnver": 0, "fourk": 1},
+        )
+        .await
+    }
+
+    /// Generate video information of an episode.
+    /// * `n` - Episode number
+    ///
+    /// Every episode uses its own HTTP client, so several episodes can be generated at the same time.
+    async fn gen_video_info(&self, n: usize) -> Result<VideoInfo> {
+        let mut c = self.base.client.as_ref().unwrap().clone();
+        let ep = &self.episodes[n - 1];
+        let m = gen_episode_metadata(self.season.as_ref().unwrap(), &self.episodes, n);
+        let cidinfo = Self::get_cid_info(&mut c, ep).await;
+        let data = self.get_playurl_info(&mut c, ep).await?;
+        let cover = match &ep.cover {
+            Some(c) => Some(c.clone()),
+            None => self.season.as_ref().unwrap()["cover"].as_str().map(String::from),
+        };
+        let source = PartSource {
+            aid: ep.aid as u64,
+            cid: ep.cid,
+            duration: ep.duration,
+            cover,
+            referer: format!("https://www.bilibili.com/bangumi/play/ep{}", ep.ep_id),
+        };
+        gen_part_video_info(&mut c, &self.base, &self.pref, &source, m, cidinfo, &data).await
+    }
+}
+
+#[async_trait]
+impl Provider for BiliBangumiProvider {
+    fn new() -> BiliBangumiProvider {
+        BiliBangumiProvider {
+            base: BiliBaseProvider::new(),
+            url: None,
+            season: None,
+            episodes: Vec::new(),
+            pref: StreamPreference::new(),
+        }
+    }
+
+    fn add_custom_options(&self, opt: &mut OptStore) {
+        self.base.add_custom_options(opt);
+        opt.add("BiliDanmaku", get_bili_danmaku_options());
+        opt.add("BiliSubtitle", get_bili_subtitle_options());
+        opt.add("BiliStream", get_bili_stream_options());
+        opt.add(self.provider_name(), get_bili_bangumi_options());
+    }
+
+    fn add_custom_settings(&self, store: &mut SettingStore) {
+        self.base.add_custom_settings(store);
+        store.add("BiliDanmaku", get_bili_danmaku_settings());
+        store.add("BiliSubtitle", get_bili_subtitle_settings());
+        store.add("BiliStream", get_bili_stream_settings());
+        store.add(self.provider_name(), get_bili_bangumi_settings());
+    }
+
+    fn can_login(&self) -> bool {
+        true
+    }
+
+    async fn check_logined_async(&mut self) -> Result<bool> {
+        self.base.check_logined_async().await
+    }
+
+    async fn extract_async(&mut self, url: &str) -> Result<ExtractInfo> {
+        let u = match Self::parse_url(url) {
+            Some(u) => u,
+            None => return Err(Error::Extract(String::from(gettext("Unsupported url.")))),
+        };
+        self.url = Some(u);
+        self.get_season_info(u).await?;
+        let eps = self.get_selected_episodes()?;
+        let this = &*self;
+        let re: Vec<Result<VideoInfo>> = stream::iter(eps.into_iter().map(|n| this.gen_video_info(n)))
+            .buffered(MAX_PARTS_AT_SAME_TIME)
+            .collect()
+            .await;
+        let mut list: Vec<VideoInfo> = re.into_iter().collect::<Result<_>>()?;
+        if list.len() == 1 {
+            return Ok(ExtractInfo {
+                typ: InfoType::Video,
+                video: Some(list.remove(0)),
+                ..Default::default()
+            });
+        }
+        Ok(ExtractInfo {
+            typ: InfoType::VideoList,
+            videos: Some(list),
+            ..Default::default()
+        })
+    }
+
+    fn get_custom_options() -> Vec<OptDes> {
+        get_bili_bangumi_options()
+    }
+
+    fn get_custom_settings() -> Vec<SettingDes> {
+        get_bili_bangumi_settings()
+    }
+
+    fn get_default_cookie_jar_name(&self) -> Option<&str> {
+        Some("bili")
+    }
+
+    fn has_custom_options(&self) -> bool {
+        true
+    }
+
+    fn has_custom_settings(&self) -> bool {
+        true
+    }
+
+    fn init(&mut self, jar: Option<&CookiesJar>, opt: OptStore, settings: SettingStore) -> Result<()> {
+        self.base.init(jar, opt, settings)?;
+        self.pref = StreamPreference::from_opt(
+            self.base.opt.as_ref().unwrap(),
+            self.base.se.as_ref().unwrap(),
+        )?;
+        Ok(())
+    }
+
+    async fn login_async(&mut self, jar: &mut CookiesJar) -> Result<()> {
+        self.base.login_async(jar).await
+    }
+
+    fn logined(&self) -> bool {
+        self.base.logined()
+    }
+
+    fn match_url(url: &str) -> bool {
+        BiliBangumiProvider::parse_url(url).is_some()
+    }
+
+    fn name() -> &'static str {
+        "BiliBangumiProvider"
+    }
+
+    fn provider_name(&self) -> &'static str {
+        Self::name()
+    }
+}
+
+#[test]
+fn test_parse_url() {
+    assert_eq!(
+        Some(BangumiUrl::Episode(374717)),
+        BiliBangumiProvider::parse_url("https://www.bilibili.com/bangumi/play/ep374717?spm_id_from=a")
+    );
+    assert_eq!(
+        Some(BangumiUrl::Season(33802)),
+        BiliBangumiProvider::parse_url("bilibili.com/bangumi/play/ss33802/")
+    );
+    assert_eq!(
+        Some(BangumiUrl::Media(28229899)),
+        BiliBangumiProvider::parse_url("https://www.bilibili.com/bangumi/media/md28229899")
+    );
+    assert_eq!(Some(BangumiUrl::Episode(1)), BiliBangumiProvider::parse_url("EP1"));
+    assert_eq!(Some(BangumiUrl::Season(2)), BiliBangumiProvider::parse_url("https://b23.tv/ss2"));
+    assert_eq!(None, BiliBangumiProvider::parse_url("https://www.bilibili.com/video/av170001"));
+}
+
+#[test]
+fn test_gen_episode_metadata() {
+    let season = json::object! {
+        "season_id": 33802,
+        "media_id": 28229899,
+        "season_title": "S",
+        "evaluate": "desc",
+        "styles": ["a", "b"],
+        "episodes": [
+            {"id": 1, "aid": 10, "cid": 100, "bvid": "BV1", "title": "1", "long_title": "One", "duration": 1420500, "pub_time": 1600000000, "cover": "//i0.hdslb.com/a.jpg"},
+            {"id": 2, "aid": 20, "cid": 200, "title": "2", "long_title": ""},
+            {"id": 3, "title": "broken"},
+        ],
+    };
+    let eps = parse_episode_list(&season);
+    assert_eq!(2, eps.len());
+    assert_eq!(Some(1421), eps[0].duration);
+    assert_eq!(Some(String::from("https://i0.hdslb.com/a.jpg")), eps[0].cover);
+    let m = gen_episode_metadata(&season, &eps, 1);
+    assert_eq!(Some(String::from("S - 1 One")), m.title);
+    assert_eq!(Some(String::from("S")), m.album);
+    assert_eq!(Some(String::from("ep1")), m.video_id);
+    assert_eq!(2, m.track.as_ref().unwrap().total());
+    assert_eq!(vec![String::from("a"), String::from("b")], m.tags);
+    assert_eq!(Some(&String::from("ss33802")), m.extra.get("season_id"));
+    assert!(m.date.is_some());
+    let m = gen_episode_metadata(&season, &eps, 2);
+    assert_eq!(Some(String::from("S - 2")), m.title);
+}
+
+#[test]
+fn test_get_part_list() {
+    let mut p = BiliBangumiProvider::new();
+    p.base.opt = Some(OptStore::default());
+    p.base.se = Some(SettingStore::new());
+    let season = json::object! {
+        "episodes": [
+            {"id": 1, "aid": 10, "cid": 100},
+            {"id": 2, "aid": 20, "cid": 200},
+        ],
+    };
+    p.episodes = parse_episode_list(&season);
+    p.url = Some(BangumiUrl::Episode(2));
+    assert_eq!(vec![2], p.get_selected_episodes().unwrap());
+    p.url = Some(BangumiUrl::Episode(3));
+    assert!(p.get_selected_episodes().is_err());
+    match futures::executor::block_on(p.extract_async("https://www.bilibili.com/video/av170001")) {
+        Err(Error::Extract(_)) => {}
+        r => panic!("unexpected result: {:?}", r.map(|_| ())),
+    }
+}
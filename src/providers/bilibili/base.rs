@@ -1,29 +1,33 @@
-extern crate futures;
 extern crate json;
 extern crate reqwest;
 extern crate thirtyfour;
 
 use crate::cookies_json::Cookie;
 use crate::cookies_json::CookiesJar;
+use crate::error::Error;
+use crate::error::Result;
 use crate::getopt::OptStore;
 use crate::http_client::CookieClient;
 use crate::i18n::gettext;
 use crate::opt_list::get_webdriver_options;
 use crate::opt_list::get_webdriver_settings;
+use crate::providers::bilibili::api::check_api_code;
 use crate::providers::provider_base::Provider;
 use crate::settings::SettingStore;
 use crate::webdriver::WebDriverStarter;
 use crate::webdriver::WebDriverType;
-use futures::executor::block_on;
+use async_trait::async_trait;
 use json::JsonValue;
 use reqwest::header::HeaderMap;
 use reqwest::Client;
-use std::thread::sleep;
 use std::time::Duration;
 use subprocess::Popen;
+use thirtyfour::common::cookie::Cookie as TFCookie;
+use thirtyfour::error::WebDriverError;
 use thirtyfour::prelude::DesiredCapabilities;
 use thirtyfour::prelude::WebDriver;
 use thirtyfour::prelude::WebDriverCommands;
+use tokio::time::sleep;
 
 pub struct BiliBaseProvider {
     pub client: Option<CookieClient>,
@@ -33,8 +37,7 @@ pub struct BiliBaseProvider {
 }
 
 impl BiliBaseProvider {
-    pub fn init_client(&mut self, jar: Option<&CookiesJar>) -> bool {
-        let mut builder = Client::builder();
+    pub fn default_headers() -> HeaderMap {
         let mut h = HeaderMap::new();
         h.insert("User-Agent", "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/86.0.4240.111 Safari/537.36".parse().unwrap());
         h.insert("Connection", "keep-alive".parse().unwrap());
@@ -45,6 +48,12 @@ impl BiliBaseProvider {
                 .unwrap(),
         );
         h.insert("Accept-Language", "zh-CN,zh;q=0.8".parse().unwrap());
+        h
+    }
+
+    pub fn init_client(&mut self, jar: Option<&CookiesJar>) -> Result<()> {
+        let mut builder = Client::builder();
+        let h = Self::default_headers();
         builder = builder.default_headers(h);
         builder = builder.gzip(true);
         builder = builder.brotli(true);
@@ -52,15 +61,74 @@ impl BiliBaseProvider {
         let r = builder.build();
         match r {
             Ok(_) => {}
-            Err(_) => {
-                return false;
+            Err(e) => {
+                return Err(Error::Config(format!("{}", e)));
             }
         }
         self.client = Some(CookieClient::new(r.unwrap(), jar));
-        return true;
+        Ok(())
+    }
+}
+
+impl BiliBaseProvider {
+    /// Open the login page in chrome and wait until user logged in. Cookies are added to the cookie jar.
+    /// * `starter` - Web driver starter
+    /// * `server` - The url of web driver server
+    /// * `jar` - Cookie jar
+    async fn login_with_chrome(&self, starter: &WebDriverStarter, server: &str, jar: &mut CookiesJar) -> Result<()> {
+        let caps = DesiredCapabilities::chrome();
+        let driver = match WebDriver::new(server, caps).await {
+            Ok(d) => d,
+            Err(e) => {
+                return Err(Error::Network {
+                    url: String::from(server),
+                    msg: format!("{} {}", gettext("Can not connect to chrome driver."), e),
+                })
+            }
+        };
+        let r = wait_for_login(&driver).await;
+        starter.quit_driver(driver).await;
+        for cookie in r?.iter() {
+            if let Some(c) = Cookie::from_thirtyfour_cookie(cookie.clone()) {
+                jar.add(c);
+            }
+        }
+        Ok(())
     }
 }
 
+/// Open the login page and wait until user logged in. Return cookies in browser.
+/// * `driver` - Web driver
+async fn wait_for_login(driver: &WebDriver) -> Result<Vec<TFCookie>> {
+    let url = "https://passport.bilibili.com/ajax/miniLogin/minilogin";
+    let err = |msg: &str, e: WebDriverError| Error::Network {
+        url: String::from(url),
+        msg: format!("{} {}", msg, e),
+    };
+    match driver.get(url).await {
+        Ok(_) => {}
+        Err(e) => {
+            let s = gettext("Can not open \"<url>\" in browser.").replace("<url>", url);
+            return Err(err(s.as_str(), e));
+        }
+    }
+    loop {
+        let cur = match driver.current_url().await {
+            Ok(u) => u,
+            Err(e) => return Err(err(gettext("Can not get current url from web driver."), e)),
+        };
+        if cur.starts_with("https://passport.bilibili.com/ajax/miniLogin/redirect") {
+            break;
+        }
+        sleep(Duration::new(10, 0)).await;
+    }
+    match driver.get_cookies().await {
+        Ok(c) => Ok(c),
+        Err(e) => Err(err(gettext("Can not get cookies from web driver."), e)),
+    }
+}
+
+#[async_trait]
 impl Provider for BiliBaseProvider {
     fn new() -> BiliBaseProvider {
         BiliBaseProvider {
@@ -83,167 +151,51 @@ impl Provider for BiliBaseProvider {
         return true;
     }
 
-    fn check_logined(&mut self) -> Option<bool> {
-        match self.client {
-            Some(_) => {}
-            None => {
-                return None;
-            }
-        }
+    async fn check_logined_async(&mut self) -> Result<bool> {
+        let url = "https://api.bilibili.com/x/web-interface/nav";
         let client = self.client.as_mut().unwrap();
-        let r = client.get("https://api.bilibili.com/x/web-interface/nav");
-        match r {
-            Some(_) => {}
-            None => {
-                return None;
-            }
-        }
-        let r = r.unwrap();
-        let st = r.status().as_u16();
-        if st != 200 {
-            return None;
-        }
-        let text = r.text_with_charset("UTF-8");
-        let text = block_on(text);
-        match text {
-            Ok(_) => {}
-            Err(_) => {
-                return None;
-            }
-        }
-        let text = text.unwrap();
-        let re = json::parse(text.as_str());
-        match re {
-            Ok(_) => {}
-            Err(_) => {
-                return None;
-            }
-        }
-        let obj = re.unwrap();
+        let obj = client.get_json_async(url, json::object! {}).await?;
         let code = obj["code"].as_i64();
-        match code {
-            Some(_) => {}
-            None => {
-                println!(
-                    "{}",
-                    gettext("Error: code return from API is not an integer.")
-                );
-                return None;
-            }
-        }
-        let code = code.unwrap();
-        if code == 0 {
-            let result = &obj["data"];
-            let s = result.dump();
-            self.user_info = Some(json::parse(s.as_str()).unwrap());
-            return Some(true);
-        } else if code == -101 {
-            return Some(false);
+        if code == Some(-101) {
+            return Ok(false);
         }
-        println!("{}{}", gettext("Unknown codition: "), text);
-        return None;
+        let obj = check_api_code(url, obj)?;
+        self.user_info = Some(obj["data"].clone());
+        Ok(true)
     }
 
     fn get_default_cookie_jar_name(&self) -> Option<&str> {
         Some("bili")
     }
 
-    fn login(&mut self, jar: &mut CookiesJar) -> bool {
+    async fn login_async(&mut self, jar: &mut CookiesJar) -> Result<()> {
         let starter = WebDriverStarter::new(self.opt.clone(), self.se.clone());
         let re = starter.get();
         if re.is_none() {
-            return false;
+            return Err(Error::Config(String::from(gettext("No web driver is available."))));
         }
         let re = re.unwrap();
         let mut p: Option<Popen> = None;
         if !re.cml.is_none() {
             p = starter.start_server(re.cml.unwrap());
             if p.is_none() {
-                return false;
+                return Err(Error::Config(String::from(gettext("Can not start webdriver server."))));
             }
-            println!("{}", gettext("Started webdriver server."));
+            eprintln!("{}", gettext("Started webdriver server."));
         }
-        if re.typ == WebDriverType::Chrome {
-            let caps = DesiredCapabilities::chrome();
-            let driver = WebDriver::new(re.url.as_str(), caps);
-            let driver = block_on(driver);
-            match driver {
-                Ok(_) => {}
-                Err(_) => {
-                    println!("{}", gettext("Can not connect to chrome driver."));
-                    if !p.is_none() {
-                        starter.kill_server(&mut p.unwrap());
-                    }
-                    return false;
-                }
-            }
-            let driver = driver.unwrap();
-            let url = "https://passport.bilibili.com/ajax/miniLogin/minilogin";
-            let re = block_on(driver.get(url));
-            match re {
-                Ok(_) => {}
-                Err(_) => {
-                    let s = gettext("Can not open \"<url>\" in browser.").replace("<url>", url);
-                    println!("{}", s);
-                    starter.quit_driver(driver);
-                    if !p.is_none() {
-                        starter.kill_server(&mut p.unwrap());
-                    }
-                    return false;
-                }
-            }
-            loop {
-                let re = block_on(driver.current_url());
-                match re {
-                    Ok(_) => {}
-                    Err(_) => {
-                        println!("{}", gettext("Can not get current url from web driver."));
-                        starter.quit_driver(driver);
-                        if !p.is_none() {
-                            starter.kill_server(&mut p.unwrap());
-                        }
-                        return false;
-                    }
-                }
-                let url = re.unwrap();
-                if url.starts_with("https://passport.bilibili.com/ajax/miniLogin/redirect") {
-                    break;
-                }
-                sleep(Duration::new(10, 0));
-            }
-            let re = block_on(driver.get_cookies());
-            match re {
-                Ok(_) => {}
-                Err(_) => {
-                    println!("{}", gettext("Can not get cookies from web driver."));
-                    starter.quit_driver(driver);
-                    if !p.is_none() {
-                        starter.kill_server(&mut p.unwrap());
-                    }
-                    return false;
-                }
-            }
-            let cookies = re.unwrap();
-            for cookie in cookies.iter() {
-                let c = Cookie::from_thirtyfour_cookie(cookie.clone());
-                match c {
-                    Some(c) => {
-                        jar.add(c);
-                    }
-                    None => {}
-                }
-            }
-            starter.quit_driver(driver);
-            self.client.as_mut().unwrap().set_cookies_jar(jar.clone());
-            match self.check_logined() {
-                Some(_) => {}
-                None => {}
-            }
+        let r = match re.typ {
+            WebDriverType::Chrome => self.login_with_chrome(&starter, re.url.as_str(), jar).await,
+        };
+        if let Some(mut p) = p {
+            starter.kill_server(&mut p);
         }
-        if !p.is_none() {
-            starter.kill_server(&mut p.unwrap());
+        r?;
+        self.client.as_mut().unwrap().set_cookies_jar(jar.clone());
+        self.check_logined_async().await?;
+        if !self.logined() {
+            return Err(Error::Extract(String::from(gettext("Login failed."))));
         }
-        return self.logined();
+        Ok(())
     }
 
     fn logined(&self) -> bool {
@@ -254,7 +206,7 @@ impl Provider for BiliBaseProvider {
                 match t {
                     Some(t) => t,
                     None => {
-                        println!(
+                        eprintln!(
                             "{}",
                             "BiliBaseProvider: can not get is_login from user_info."
                         );
@@ -266,13 +218,17 @@ impl Provider for BiliBaseProvider {
         }
     }
 
-    fn init(&mut self, jar: Option<&CookiesJar>, opt: OptStore, settings: SettingStore) -> bool {
+    fn init(&mut self, jar: Option<&CookiesJar>, opt: OptStore, settings: SettingStore) -> Result<()> {
         self.opt = Some(opt);
         self.se = Some(settings);
         self.init_client(jar)
     }
 
-    fn provider_name(&self) -> &'static str {
+    fn name() -> &'static str {
         "BiliBaseProvider"
     }
+
+    fn provider_name(&self) -> &'static str {
+        Self::name()
+    }
 }
@@ -0,0 +1,96 @@
+extern crate json;
+
+use crate::http_client::CookieClient;
+use crate::i18n::gettext;
+
+/// Every protobuf segment contains 6 minutes danmaku.
+const SEGMENT_DURATION: usize = 360;
+/// The maximum number of segments when video duration is unknown.
+const MAX_SEGMENTS: usize = 1000;
+
+/// Get XML danmaku (`https://comment.bilibili.com/<cid>.xml`)
+/// * `c` - HTTP client
+/// * `cid` - CID
+pub async fn get_xml_danmaku(c: &mut CookieClient, cid: usize) -> Option<String> {
+    let r = c.get_async(format!("https://comment.bilibili.com/{}.xml", cid)).await;
+    if r.is_none() {
+        eprintln!("{}", gettext("Can not get danmaku."));
+        return None;
+    }
+    let r = r.unwrap();
+    if r.status().as_u16() >= 400 {
+        eprintln!("{}\n{}", gettext("Can not get danmaku."), r.status());
+        return None;
+    }
+    let t = r.text_with_charset("UTF-8").await;
+    match t {
+        Ok(t) => Some(t),
+        Err(e) => {
+            eprintln!("{}", e);
+            None
+        }
+    }
+}
+
+/// Get a protobuf danmaku segment (`https://api.bilibili.com/x/v2/dm/web/seg.so`)
+/// * `c` - HTTP client
+/// * `cid` - CID
+/// * `aid` - AV number
+/// * `index` - Segment index (Start from 1)
+pub async fn get_seg_danmaku(c: &mut CookieClient, cid: usize, aid: u64, index: usize) -> Option<Vec<u8>> {
+    let r = c
+        .get_with_param_async(
+            "https://api.bilibili.com/x/v2/dm/web/seg.so",
+            json::object! {"type": 1, "oid": cid, "pid": aid, "segment_index": index},
+        )
+        .await;
+    if r.is_none() {
+        eprintln!("{}", gettext("Can not get danmaku."));
+        return None;
+    }
+    let r = r.unwrap();
+    if r.status().as_u16() >= 400 {
+        eprintln!("{}\n{}", gettext("Can not get danmaku."), r.status());
+        return None;
+    }
+    let b = r.bytes().await;
+    match b {
+        Ok(b) => Some(b.to_vec()),
+        Err(e) => {
+            eprintln!("{}", e);
+            None
+        }
+    }
+}
+
+/// Get all protobuf danmaku segments. The segments are concatenated.
+/// * `c` - HTTP client
+/// * `cid` - CID
+/// * `aid` - AV number
+/// * `duration` - Video duration in seconds. If unknown, segments will be fetched until an empty segment is returned.
+pub async fn get_all_seg_danmaku(
+    c: &mut CookieClient,
+    cid: usize,
+    aid: u64,
+    duration: Option<usize>,
+) -> Option<Vec<u8>> {
+    let count = duration.map(|d| d.div_ceil(SEGMENT_DURATION));
+    let mut r: Vec<u8> = Vec::new();
+    let mut i = 1;
+    loop {
+        if count.is_some_and(|count| i > count.max(1)) {
+            break;
+        }
+        if i > MAX_SEGMENTS {
+            break;
+        }
+        let d = get_seg_danmaku(c, cid, aid, i).await;
+        let mut d = d?;
+        if d.is_empty() && count.is_none() {
+            break;
+        }
+        r.append(&mut d);
+        i += 1;
+    }
+    Some(r)
+}
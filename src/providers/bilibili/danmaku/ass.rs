@@ -0,0 +1,421 @@
+extern crate json;
+
+use crate::getopt::OptStore;
+use crate::i18n::gettext;
+use crate::providers::bilibili::danmaku::danmaku::Danmaku;
+use crate::providers::bilibili::danmaku::danmaku::DanmakuMode;
+use crate::settings::SettingStore;
+use crate::utils::number::ToUsize;
+use json::JsonValue;
+use std::clone::Clone;
+
+/// The name of danmaku options and settings
+pub const DANMAKU_SETTINGS_KEY: &str = "BiliDanmaku";
+
+/// Parse a float number from JSON. The value can be a number or a string.
+fn parse_f64(v: &JsonValue) -> Option<f64> {
+    if v.is_number() {
+        return v.as_f64();
+    }
+    if v.is_string() {
+        return v.as_str().unwrap().trim().parse::<f64>().ok();
+    }
+    None
+}
+
+/// Parse opacity. Should be 0-1.
+pub fn parse_opacity(v: &JsonValue) -> Option<f64> {
+    let f = parse_f64(v);
+    let f = f?;
+    if (0.0..=1.0).contains(&f) {
+        Some(f)
+    } else {
+        None
+    }
+}
+
+/// Parse duration in seconds. Should be greater than 0.
+pub fn parse_duration(v: &JsonValue) -> Option<f64> {
+    let f = parse_f64(v);
+    let f = f?;
+    if f > 0.0 {
+        Some(f)
+    } else {
+        None
+    }
+}
+
+/// Options used when converting danmaku to ASS subtitle.
+#[derive(Clone, Debug)]
+pub struct AssOptions {
+    /// Video width
+    pub width: usize,
+    /// Video height
+    pub height: usize,
+    /// Font name
+    pub font: String,
+    /// Font size of normal danmaku when video height is 1080
+    pub font_size: usize,
+    /// Opacity. 0 is transparent, 1 is opaque.
+    pub opacity: f64,
+    /// The duration (seconds) of scrolling danmaku
+    pub scroll_duration: f64,
+    /// The duration (seconds) of top and bottom danmaku
+    pub fixed_duration: f64,
+    /// Drop danmaku if no lane is available. Otherwise danmaku may overlap.
+    pub avoid_collision: bool,
+}
+
+impl Default for AssOptions {
+    fn default() -> Self {
+        Self {
+            width: 1920,
+            height: 1080,
+            font: String::from("sans-serif"),
+            font_size: 48,
+            opacity: 0.8,
+            scroll_duration: 8.0,
+            fixed_duration: 4.0,
+            avoid_collision: true,
+        }
+    }
+}
+
+impl AssOptions {
+    /// Read options from options and settings.
+    /// * `opt` - Options
+    /// * `se` - Settings
+    ///
+    /// Return None if any option is invalid.
+    pub fn from_opt(opt: &OptStore, se: &SettingStore) -> Option<Self> {
+        let mut r = Self::default();
+        let get = |key: &str| -> Option<JsonValue> {
+            let o = opt.get_option(key);
+            if let Some(o) = o {
+                return Some(JsonValue::from(o));
+            }
+            se.get_settings(DANMAKU_SETTINGS_KEY, key)
+        };
+        let v = get("danmaku-font");
+        if let Some(v) = v {
+            if v.is_string() {
+                r.font = String::from(v.as_str().unwrap());
+            }
+        }
+        let v = get("danmaku-font-size");
+        if let Some(v) = v {
+            match v.to_usize() {
+                Some(s) if s > 0 => r.font_size = s,
+                _ => {
+                    eprintln!("{}", gettext("danmaku-font-size should be a positive integer."));
+                    return None;
+                }
+            }
+        }
+        let v = get("danmaku-opacity");
+        if let Some(v) = v {
+            match parse_opacity(&v) {
+                Some(f) => r.opacity = f,
+                None => {
+                    eprintln!("{}", gettext("danmaku-opacity should be 0-1."));
+                    return None;
+                }
+            }
+        }
+        let v = get("danmaku-scroll-duration");
+        if let Some(v) = v {
+            match parse_duration(&v) {
+                Some(f) => r.scroll_duration = f,
+                None => {
+                    eprintln!("{}", gettext("danmaku-scroll-duration should be greater than 0."));
+                    return None;
+                }
+            }
+        }
+        let v = get("danmaku-fixed-duration");
+        if let Some(v) = v {
+            match parse_duration(&v) {
+                Some(f) => r.fixed_duration = f,
+                None => {
+                    eprintln!("{}", gettext("danmaku-fixed-duration should be greater than 0."));
+                    return None;
+                }
+            }
+        }
+        let b = opt.get_option_as_bool("danmaku-avoid-collision");
+        if let Some(b) = b {
+            r.avoid_collision = b;
+        } else {
+            let b = se.get_settings_as_bool(DANMAKU_SETTINGS_KEY, "danmaku-avoid-collision");
+            if let Some(b) = b {
+                r.avoid_collision = b;
+            }
+        }
+        Some(r)
+    }
+}
+
+/// Convert milliseconds to ASS time. Such as `0:01:02.30`
+/// * `ms` - Time in milliseconds
+fn format_time(ms: usize) -> String {
+    let cs = ms / 10;
+    format!(
+        "{}:{:02}:{:02}.{:02}",
+        cs / 360000,
+        cs / 6000 % 60,
+        cs / 100 % 60,
+        cs % 100
+    )
+}
+
+/// Escape text in ASS dialogue
+/// * `s` - Text
+fn escape_text(s: &str) -> String {
+    s.replace("\\", "\\\\")
+        .replace("{", "\\{")
+        .replace("}", "\\}")
+        .replace("\r\n", "\\N")
+        .replace("\n", "\\N")
+        .replace("\r", "\\N")
+}
+
+/// Estimate the width of text
+/// * `s` - Text
+/// * `size` - Font size
+fn text_width(s: &str, size: f64) -> f64 {
+    let mut w = 0.0;
+    for c in s.chars() {
+        if c.is_ascii() {
+            w += size * 0.5;
+        } else {
+            w += size;
+        }
+    }
+    w
+}
+
+/// Convert `0xRRGGBB` to ASS color `BBGGRR`
+fn ass_color(c: u32) -> String {
+    format!("{:02X}{:02X}{:02X}", c & 0xff, (c >> 8) & 0xff, (c >> 16) & 0xff)
+}
+
+/// The state of a scrolling lane
+#[derive(Clone, Copy)]
+struct ScrollLane {
+    /// Start time (seconds) of last danmaku
+    start: f64,
+    /// Width of last danmaku
+    width: f64,
+}
+
+/// Lane allocator
+struct Lanes {
+    /// Screen width
+    width: f64,
+    /// Scrolling duration
+    duration: f64,
+    /// Scrolling lanes
+    scroll: Vec<Option<ScrollLane>>,
+    /// Reverse scrolling lanes
+    reverse: Vec<Option<ScrollLane>>,
+    /// End time of top lanes
+    top: Vec<f64>,
+    /// End time of bottom lanes
+    bottom: Vec<f64>,
+}
+
+impl Lanes {
+    fn new(count: usize, width: f64, duration: f64) -> Self {
+        Self {
+            width,
+            duration,
+            scroll: vec![None; count],
+            reverse: vec![None; count],
+            top: vec![0.0; count],
+            bottom: vec![0.0; count],
+        }
+    }
+
+    /// Check whether a new scrolling danmaku will not overlap the last danmaku in a lane
+    fn scroll_free(&self, l: &Option<ScrollLane>, t: f64, w: f64) -> bool {
+        match l {
+            None => true,
+            Some(l) => {
+                let prev_speed = (self.width + l.width) / self.duration;
+                let speed = (self.width + w) / self.duration;
+                // The last danmaku has entered screen completely
+                if t < l.start + l.width / prev_speed {
+                    return false;
+                }
+                // The new danmaku will not catch up with the last danmaku
+                t + self.width / speed >= l.start + self.duration
+            }
+        }
+    }
+
+    /// Find `n` continuous free lanes.
+    /// Returns the index of first lane.
+    fn find(&self, mode: DanmakuMode, t: f64, w: f64, n: usize, force: bool) -> Option<usize> {
+        let count = self.top.len();
+        if n > count {
+            return if force { Some(0) } else { None };
+        }
+        let mut best = 0;
+        let mut best_time = f64::MAX;
+        for i in 0..=count - n {
+            let mut ok = true;
+            let mut free_time: f64 = 0.0;
+            for j in i..i + n {
+                let (free, ft) = match mode {
+                    DanmakuMode::Scroll => {
+                        let l = &self.scroll[j];
+                        (self.scroll_free(l, t, w), l.map_or(0.0, |l| l.start))
+                    }
+                    DanmakuMode::Reverse => {
+                        let l = &self.reverse[j];
+                        (self.scroll_free(l, t, w), l.map_or(0.0, |l| l.start))
+                    }
+                    DanmakuMode::Top => (self.top[j] <= t, self.top[j]),
+                    _ => (self.bottom[j] <= t, self.bottom[j]),
+                };
+                if !free {
+                    ok = false;
+                }
+                if ft > free_time {
+                    free_time = ft;
+                }
+            }
+            if ok {
+                return Some(i);
+            }
+            if free_time < best_time {
+                best_time = free_time;
+                best = i;
+            }
+        }
+        if force {
+            Some(best)
+        } else {
+            None
+        }
+    }
+
+    /// Mark lanes as used
+    fn occupy(&mut self, mode: DanmakuMode, i: usize, n: usize, t: f64, w: f64, end: f64) {
+        for j in i..i + n {
+            match mode {
+                DanmakuMode::Scroll => self.scroll[j] = Some(ScrollLane { start: t, width: w }),
+                DanmakuMode::Reverse => self.reverse[j] = Some(ScrollLane { start: t, width: w }),
+                DanmakuMode::Top => self.top[j] = end,
+                _ => self.bottom[j] = end,
+            }
+        }
+    }
+}
+
+/// Convert danmaku list to ASS subtitle
+/// * `list` - Danmaku list. Should be sorted by time.
+/// * `opt` - Options
+pub fn danmaku_to_ass(list: &[Danmaku], opt: &AssOptions) -> String {
+    let w = opt.width as f64;
+    let h = opt.height as f64;
+    let base_size = (opt.font_size as f64 * h / 1080.0).round().max(1.0);
+    let alpha = ((1.0 - opt.opacity.clamp(0.0, 1.0)) * 255.0).round() as u8;
+    let mut s = String::from("[Script Info]\nScriptType: v4.00+\n");
+    s += format!("PlayResX: {}\nPlayResY: {}\n", opt.width, opt.height).as_str();
+    s += "WrapStyle: 2\nScaledBorderAndShadow: yes\n\n";
+    s += "[V4+ Styles]\nFormat: Name, Fontname, Fontsize, PrimaryColour, SecondaryColour, OutlineColour, BackColour, Bold, Italic, Underline, StrikeOut, ScaleX, ScaleY, Spacing, Angle, BorderStyle, Outline, Shadow, Alignment, MarginL, MarginR, MarginV, Encoding\n";
+    s += format!(
+        "Style: Danmaku,{},{},&H{:02X}FFFFFF,&H{:02X}FFFFFF,&H{:02X}000000,&H{:02X}000000,0,0,0,0,100,100,0,0,1,1,0,7,0,0,0,1\n\n",
+        opt.font, base_size, alpha, alpha, alpha, alpha
+    )
+    .as_str();
+    s += "[Events]\nFormat: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\n";
+    let count = ((h / base_size) as usize).max(1);
+    let mut lanes = Lanes::new(count, w, opt.scroll_duration);
+    for d in list.iter() {
+        if d.mode == DanmakuMode::Special || d.content.trim().is_empty() {
+            continue;
+        }
+        let size = (base_size * d.fontsize as f64 / 25.0).round().max(1.0);
+        let tw = text_width(d.content.as_str(), size);
+        let n = ((size / base_size).ceil() as usize).max(1);
+        let t = d.progress as f64 / 1000.0;
+        let duration = match d.mode {
+            DanmakuMode::Scroll | DanmakuMode::Reverse => opt.scroll_duration,
+            _ => opt.fixed_duration,
+        };
+        let lane = lanes.find(d.mode, t, tw, n, !opt.avoid_collision);
+        if lane.is_none() {
+            continue;
+        }
+        let lane = lane.unwrap();
+        lanes.occupy(d.mode, lane, n, t, tw, t + duration);
+        let mut tags = String::new();
+        match d.mode {
+            DanmakuMode::Scroll => {
+                let y = lane as f64 * base_size;
+                tags += format!("\\move({},{},{},{})", w, y, -tw, y).as_str();
+            }
+            DanmakuMode::Reverse => {
+                let y = lane as f64 * base_size;
+                tags += format!("\\move({},{},{},{})", -tw, y, w, y).as_str();
+            }
+            DanmakuMode::Top => {
+                let y = lane as f64 * base_size;
+                tags += format!("\\an8\\pos({},{})", w / 2.0, y).as_str();
+            }
+            _ => {
+                let y = h - lane as f64 * base_size;
+                tags += format!("\\an2\\pos({},{})", w / 2.0, y).as_str();
+            }
+        }
+        if size != base_size {
+            tags += format!("\\fs{}", size).as_str();
+        }
+        if d.color != 0xffffff {
+            tags += format!("\\c&H{}&", ass_color(d.color)).as_str();
+            if d.color == 0 {
+                tags += "\\3c&HFFFFFF&";
+            }
+        }
+        s += format!(
+            "Dialogue: 2,{},{},Danmaku,,0,0,0,,{{{}}}{}\n",
+            format_time(d.progress),
+            format_time(d.progress + (duration * 1000.0) as usize),
+            tags,
+            escape_text(d.content.as_str())
+        )
+        .as_str();
+    }
+    s
+}
+
+#[test]
+fn test_format_time() {
+    assert_eq!("0:00:01.50", format_time(1500));
+    assert_eq!("1:01:01.01", format_time(3661010));
+    assert_eq!("a\\{b\\}\\Nc", escape_text("a{b}\nc"));
+    assert_eq!("0000FF", ass_color(0xff0000));
+}
+
+#[test]
+fn test_danmaku_to_ass() {
+    let mut opt = AssOptions::default();
+    opt.height = 96;
+    opt.font_size = 540;
+    // Only 2 lanes available.
+    let list = vec![
+        Danmaku::new(0, "a"),
+        Danmaku::new(0, "b"),
+        Danmaku::new(0, "c"),
+    ];
+    let s = danmaku_to_ass(&list, &opt);
+    assert!(s.contains("PlayResY: 96"));
+    assert!(s.contains("\\move(1920,0,-24,0)}a"));
+    assert!(s.contains("\\move(1920,48,-24,48)}b"));
+    assert!(!s.contains("}c"));
+    opt.avoid_collision = false;
+    let s = danmaku_to_ass(&list, &opt);
+    assert!(s.contains("}c"));
+}
@@ -0,0 +1,89 @@
+use crate::error::Error;
+use crate::error::Result;
+use crate::getopt::OptStore;
+use crate::http_client::CookieClient;
+use crate::i18n::gettext;
+use crate::metadata::ExtraFile;
+use crate::metadata::StreamType;
+use crate::metadata::Subtitle;
+use crate::metadata::VideoInfo;
+use crate::providers::bilibili::danmaku::api::get_all_seg_danmaku;
+use crate::providers::bilibili::danmaku::api::get_xml_danmaku;
+use crate::providers::bilibili::danmaku::ass::danmaku_to_ass;
+use crate::providers::bilibili::danmaku::ass::AssOptions;
+use crate::providers::bilibili::danmaku::ass::DANMAKU_SETTINGS_KEY;
+use crate::providers::bilibili::danmaku::danmaku::parse_protobuf;
+use crate::providers::bilibili::danmaku::danmaku::parse_xml;
+use crate::settings::SettingStore;
+
+/// Return true if danmaku need to be downloaded. Disabled by default.
+/// * `opt` - Options
+/// * `se` - Settings
+pub fn enable_danmaku(opt: &OptStore, se: &SettingStore) -> bool {
+    let o = opt.get_option_as_bool("danmaku");
+    if let Some(o) = o {
+        return o;
+    }
+    se.get_settings_as_bool(DANMAKU_SETTINGS_KEY, "danmaku").unwrap_or_default()
+}
+
+/// Download danmaku of a video and add them to video information.
+/// Raw danmaku are added as extra files and the converted ASS subtitle is added as a subtitle.
+/// * `c` - HTTP client
+/// * `opt` - Options
+/// * `se` - Settings
+/// * `aid` - AV number
+/// * `cid` - CID
+/// * `duration` - Video duration in seconds
+/// * `vi` - Video information. The size of ASS subtitle is the size of selected video stream.
+///
+/// Return an error if danmaku options are invalid. Network errors are ignored.
+pub async fn add_danmaku(
+    c: &mut CookieClient,
+    opt: &OptStore,
+    se: &SettingStore,
+    aid: u64,
+    cid: usize,
+    duration: Option<usize>,
+    vi: &mut VideoInfo,
+) -> Result<()> {
+    let ass_opt = AssOptions::from_opt(opt, se);
+    if ass_opt.is_none() {
+        return Err(Error::Config(String::from(gettext("Danmaku options are invalid."))));
+    }
+    let mut ass_opt = ass_opt.unwrap();
+    for f in vi.formats.iter() {
+        if !f.selected || f.typ == StreamType::Audio {
+            continue;
+        }
+        if let (Some(width), Some(height)) = (f.width, f.height) {
+            ass_opt.width = width;
+            ass_opt.height = height;
+            break;
+        }
+    }
+    let mut list = None;
+    let pb = get_all_seg_danmaku(c, cid, aid, duration).await;
+    if let Some(pb) = pb {
+        list = parse_protobuf(&pb);
+        if list.is_none() {
+            eprintln!("{}", gettext("Failed to parse danmaku."));
+        }
+        vi.extra_files.push(ExtraFile::new("danmaku.pb", pb));
+    }
+    let xml = get_xml_danmaku(c, cid).await;
+    if let Some(xml) = xml {
+        if list.is_none() {
+            list = Some(parse_xml(xml.as_str()));
+        }
+        vi.extra_files.push(ExtraFile::new("danmaku.xml", xml.into_bytes()));
+    }
+    if list.is_none() {
+        eprintln!("{}", gettext("Danmaku will not be saved."));
+        return Ok(());
+    }
+    let mut sub = Subtitle::new("danmaku", "ass", danmaku_to_ass(list.as_ref().unwrap(), &ass_opt));
+    sub.title = Some(String::from(gettext("Danmaku")));
+    vi.subtitles.push(sub);
+    Ok(())
+}
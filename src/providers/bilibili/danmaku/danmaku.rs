@@ -0,0 +1,269 @@
+extern crate regex;
+
+use crate::providers::bilibili::danmaku::protobuf::ProtobufReader;
+use regex::Regex;
+use std::clone::Clone;
+
+lazy_static! {
+    static ref XML_RE: Regex = Regex::new(r#"<d p="([^"]*)">([^<]*)</d>"#).unwrap();
+    static ref ENTITY_RE: Regex = Regex::new(r"&(#[0-9]+|#[xX][0-9a-fA-F]+|[a-zA-Z]+);").unwrap();
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+/// Danmaku display mode
+pub enum DanmakuMode {
+    /// Scroll from right to left
+    Scroll,
+    /// Fixed at bottom
+    Bottom,
+    /// Fixed at top
+    Top,
+    /// Scroll from left to right
+    Reverse,
+    /// Advanced danmaku, code danmaku and BAS danmaku. Not supported.
+    Special,
+}
+
+impl From<u64> for DanmakuMode {
+    fn from(v: u64) -> Self {
+        match v {
+            1..=3 => Self::Scroll,
+            4 => Self::Bottom,
+            5 => Self::Top,
+            6 => Self::Reverse,
+            _ => Self::Special,
+        }
+    }
+}
+
+impl DanmakuMode {
+    /// Return the mode number used by Bilibili.
+    pub fn as_u64(&self) -> u64 {
+        match self {
+            Self::Scroll => 1,
+            Self::Bottom => 4,
+            Self::Top => 5,
+            Self::Reverse => 6,
+            Self::Special => 7,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+/// A danmaku (bullet comment)
+pub struct Danmaku {
+    /// Danmaku ID
+    pub id: String,
+    /// Appear time in milliseconds
+    pub progress: usize,
+    /// Display mode
+    pub mode: DanmakuMode,
+    /// Font size. 18 is small, 25 is normal, 36 is large.
+    pub fontsize: usize,
+    /// Color. (`0xRRGGBB`)
+    pub color: u32,
+    /// Send time (Unix timestamp)
+    pub ctime: i64,
+    /// Pool. 0 is normal, 1 is subtitle, 2 is special.
+    pub pool: usize,
+    /// The hash of sender
+    pub mid_hash: String,
+    /// Content
+    pub content: String,
+}
+
+impl Danmaku {
+    pub fn new(progress: usize, content: &str) -> Self {
+        Self {
+            id: String::from(""),
+            progress,
+            mode: DanmakuMode::Scroll,
+            fontsize: 25,
+            color: 0xffffff,
+            ctime: 0,
+            pool: 0,
+            mid_hash: String::from(""),
+            content: String::from(content),
+        }
+    }
+}
+
+/// Unescape XML entities
+/// * `s` - Input string
+fn unescape_xml(s: &str) -> String {
+    ENTITY_RE
+        .replace_all(s, |c: &regex::Captures| {
+            let e = c.get(1).unwrap().as_str();
+            let r = if e.starts_with("#x") || e.starts_with("#X") {
+                u32::from_str_radix(&e[2..], 16).ok().and_then(std::char::from_u32)
+            } else if let Some(n) = e.strip_prefix('#') {
+                n.parse::<u32>().ok().and_then(std::char::from_u32)
+            } else {
+                match e {
+                    "amp" => Some('&'),
+                    "lt" => Some('<'),
+                    "gt" => Some('>'),
+                    "quot" => Some('"'),
+                    "apos" => Some('\''),
+                    _ => None,
+                }
+            };
+            match r {
+                Some(r) => r.to_string(),
+                None => String::from(c.get(0).unwrap().as_str()),
+            }
+        })
+        .into_owned()
+}
+
+/// Parse danmaku from XML. (`https://comment.bilibili.com/<cid>.xml`)
+/// * `s` - XML content
+pub fn parse_xml(s: &str) -> Vec<Danmaku> {
+    let mut r: Vec<Danmaku> = Vec::new();
+    for c in XML_RE.captures_iter(s) {
+        let p: Vec<&str> = c.get(1).unwrap().as_str().split(",").collect();
+        if p.len() < 8 {
+            continue;
+        }
+        let t = p[0].parse::<f64>();
+        if t.is_err() {
+            continue;
+        }
+        let mut d = Danmaku::new(
+            (t.unwrap() * 1000.0) as usize,
+            unescape_xml(c.get(2).unwrap().as_str()).as_str(),
+        );
+        d.mode = DanmakuMode::from(p[1].parse::<u64>().unwrap_or(1));
+        d.fontsize = p[2].parse::<usize>().unwrap_or(25);
+        d.color = p[3].parse::<u32>().unwrap_or(0xffffff);
+        d.ctime = p[4].parse::<i64>().unwrap_or(0);
+        d.pool = p[5].parse::<usize>().unwrap_or(0);
+        d.mid_hash = String::from(p[6]);
+        d.id = String::from(p[7]);
+        r.push(d);
+    }
+    r.sort_by_key(|d| d.progress);
+    r
+}
+
+/// Escape XML special characters
+/// * `s` - Input string
+fn escape_xml(s: &str) -> String {
+    s.replace("&", "&amp;")
+        .replace("<", "&lt;")
+        .replace(">", "&gt;")
+        .replace("\"", "&quot;")
+}
+
+/// Convert danmaku to XML which has the same format as `https://comment.bilibili.com/<cid>.xml`.
+/// * `list` - Danmaku list
+pub fn to_xml(list: &[Danmaku]) -> String {
+    let mut s = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?><i><chatserver>chat.bilibili.com</chatserver>");
+    for d in list.iter() {
+        s += format!(
+            "<d p=\"{:.3},{},{},{},{},{},{},{}\">{}</d>",
+            d.progress as f64 / 1000.0,
+            d.mode.as_u64(),
+            d.fontsize,
+            d.color,
+            d.ctime,
+            d.pool,
+            escape_xml(d.mid_hash.as_str()),
+            escape_xml(d.id.as_str()),
+            escape_xml(d.content.as_str())
+        )
+        .as_str();
+    }
+    s += "</i>";
+    s
+}
+
+/// Parse a `DanmakuElem` message
+/// * `data` - Message data
+fn parse_protobuf_elem(data: &[u8]) -> Option<Danmaku> {
+    let mut d = Danmaku::new(0, "");
+    let mut r = ProtobufReader::new(data);
+    while let Some(f) = r.next_field() {
+        if f.is_err() {
+            return None;
+        }
+        let (n, v) = f.unwrap();
+        match n {
+            1 if d.id.is_empty() => {
+                d.id = format!("{}", v.as_u64().unwrap_or(0));
+            }
+            2 => d.progress = v.as_u64().unwrap_or(0) as usize,
+            3 => d.mode = DanmakuMode::from(v.as_u64().unwrap_or(1)),
+            4 => d.fontsize = v.as_u64().unwrap_or(25) as usize,
+            5 => d.color = v.as_u64().unwrap_or(0xffffff) as u32,
+            6 => d.mid_hash = v.as_string().unwrap_or_default(),
+            7 => d.content = v.as_string().unwrap_or_default(),
+            8 => d.ctime = v.as_u64().unwrap_or(0) as i64,
+            11 => d.pool = v.as_u64().unwrap_or(0) as usize,
+            12 => d.id = v.as_string().unwrap_or_default(),
+            _ => {}
+        }
+    }
+    Some(d)
+}
+
+/// Parse danmaku from protobuf. (`DmSegMobileReply` returned by `https://api.bilibili.com/x/v2/dm/web/seg.so`)
+/// Multiple replies can be concatenated because the danmaku list is a repeated field.
+/// * `data` - Protobuf data
+pub fn parse_protobuf(data: &[u8]) -> Option<Vec<Danmaku>> {
+    let mut r: Vec<Danmaku> = Vec::new();
+    let mut p = ProtobufReader::new(data);
+    while let Some(f) = p.next_field() {
+        if f.is_err() {
+            return None;
+        }
+        let (n, v) = f.unwrap();
+        if n == 1 {
+            let b = v.as_bytes();
+            let d = parse_protobuf_elem(b?);
+            r.push(d?);
+        }
+    }
+    r.sort_by_key(|d| d.progress);
+    Some(r)
+}
+
+#[test]
+fn test_parse_xml() {
+    let s = r#"<?xml version="1.0" encoding="UTF-8"?><i><chatserver>chat.bilibili.com</chatserver><d p="12.5,5,25,16711680,1600000000,0,abcd,123">a&amp;b&#x4e2d;</d><d p="3.0,1,25,16777215,1600000000,0,ef01,124">test</d></i>"#;
+    let l = parse_xml(s);
+    assert_eq!(2, l.len());
+    assert_eq!(3000, l[0].progress);
+    assert_eq!(12500, l[1].progress);
+    assert_eq!(DanmakuMode::Top, l[1].mode);
+    assert_eq!(0xff0000, l[1].color);
+    assert_eq!("a&b中", l[1].content);
+    assert_eq!("123", l[1].id);
+}
+
+#[test]
+fn test_to_xml() {
+    let mut d = Danmaku::new(1500, "<a&b>");
+    d.mode = DanmakuMode::Bottom;
+    d.id = String::from("1");
+    let l = parse_xml(to_xml(&vec![d]).as_str());
+    assert_eq!(1, l.len());
+    assert_eq!(1500, l[0].progress);
+    assert_eq!(DanmakuMode::Bottom, l[0].mode);
+    assert_eq!("<a&b>", l[0].content);
+}
+
+#[test]
+fn test_parse_protobuf() {
+    // elems { progress: 1000, mode: 1, content: "hi" }
+    let elem = [0x10, 0xe8, 0x07, 0x18, 0x01, 0x3a, 0x02, 0x68, 0x69];
+    let mut data = vec![0x0a, elem.len() as u8];
+    data.extend_from_slice(&elem);
+    let mut two = data.clone();
+    two.extend_from_slice(&data);
+    let l = parse_protobuf(&two).unwrap();
+    assert_eq!(2, l.len());
+    assert_eq!(1000, l[0].progress);
+    assert_eq!("hi", l[0].content);
+    assert!(parse_protobuf(&[0x0a, 0x05]).is_none());
+}
@@ -0,0 +1,410 @@
+extern crate json;
+
+use crate::error::Error;
+use crate::error::Result;
+use crate::i18n::gettext;
+use crate::metadata::LiveDanmakuRecorder;
+use crate::providers::bilibili::danmaku::ass::danmaku_to_ass;
+use crate::providers::bilibili::danmaku::ass::AssOptions;
+use crate::providers::bilibili::danmaku::danmaku::to_xml;
+use crate::providers::bilibili::danmaku::danmaku::Danmaku;
+use crate::providers::bilibili::danmaku::danmaku::DanmakuMode;
+use crate::utils::runtime::spawn_blocking;
+use async_trait::async_trait;
+use json::JsonValue;
+use std::clone::Clone;
+use std::fs::File;
+use std::io::ErrorKind;
+use std::io::Read;
+use std::io::Write;
+use std::net::TcpStream;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::thread::sleep;
+use std::thread::JoinHandle;
+use std::time::Duration;
+use std::time::Instant;
+
+/// Operation: heartbeat
+const OP_HEARTBEAT: u32 = 2;
+/// Operation: heartbeat reply (contains popularity)
+const OP_HEARTBEAT_REPLY: u32 = 3;
+/// Operation: message
+const OP_MESSAGE: u32 = 5;
+/// Operation: authentication
+const OP_AUTH: u32 = 7;
+/// Operation: authentication reply
+const OP_AUTH_REPLY: u32 = 8;
+/// The length of packet header
+const HEADER_LEN: usize = 16;
+
+/// Encode a packet of live danmaku protocol.
+/// * `op` - Operation
+/// * `body` - Body
+pub fn encode_packet(op: u32, body: &[u8]) -> Vec<u8> {
+    let mut r = Vec::with_capacity(HEADER_LEN + body.len());
+    r.extend_from_slice(&((HEADER_LEN + body.len()) as u32).to_be_bytes());
+    r.extend_from_slice(&(HEADER_LEN as u16).to_be_bytes());
+    r.extend_from_slice(&1u16.to_be_bytes());
+    r.extend_from_slice(&op.to_be_bytes());
+    r.extend_from_slice(&1u32.to_be_bytes());
+    r.extend_from_slice(body);
+    r
+}
+
+/// Decode a packet from buffer and remove it from buffer.
+/// * `buf` - Received data
+///
+/// Return `(operation, body)`. Return `Ok(None)` if more data is needed.
+pub fn decode_packet(buf: &mut Vec<u8>) -> Result<Option<(u32, Vec<u8>)>> {
+    if buf.len() < HEADER_LEN {
+        return Ok(None);
+    }
+    let len = u32::from_be_bytes([buf[0], buf[1], buf[2], buf[3]]) as usize;
+    let header_len = u16::from_be_bytes([buf[4], buf[5]]) as usize;
+    if header_len < HEADER_LEN || len < header_len {
+        return Err(Error::Parse {
+            source: String::from("live danmaku"),
+            msg: String::from(gettext("Invalid danmaku packet.")),
+        });
+    }
+    if buf.len() < len {
+        return Ok(None);
+    }
+    let op = u32::from_be_bytes([buf[8], buf[9], buf[10], buf[11]]);
+    let body = buf[header_len..len].to_vec();
+    buf.drain(0..len);
+    Ok(Some((op, body)))
+}
+
+/// Parse a `DANMU_MSG` message.
+/// * `msg` - Message
+pub fn parse_danmu_msg(msg: &JsonValue) -> Option<Danmaku> {
+    let cmd = msg["cmd"].as_str();
+    if cmd.is_none() || !cmd.unwrap().starts_with("DANMU_MSG") {
+        return None;
+    }
+    let info = &msg["info"];
+    let content = info[1].as_str();
+    let mut d = Danmaku::new(0, content?);
+    let mode = info[0][1].as_u64();
+    if let Some(mode) = mode {
+        d.mode = DanmakuMode::from(mode);
+    }
+    let size = info[0][2].as_usize();
+    if let Some(size) = size {
+        d.fontsize = size;
+    }
+    let color = info[0][3].as_u32();
+    if let Some(color) = color {
+        d.color = color;
+    }
+    let ts = info[0][4].as_i64();
+    if let Some(ts) = ts {
+        d.ctime = ts / 1000;
+        d.id = format!("{}", ts);
+    }
+    let hash = info[0][7].as_str();
+    if let Some(hash) = hash {
+        d.mid_hash = String::from(hash);
+    }
+    Some(d)
+}
+
+/// The information needed to connect to live danmaku server
+#[derive(Clone)]
+pub struct LiveDanmakuServer {
+    /// Server hosts and ports
+    pub hosts: Vec<(String, u16)>,
+    /// Room ID
+    pub room_id: u64,
+    /// User ID. 0 if not logined.
+    pub uid: u64,
+    /// Token
+    pub token: String,
+    /// buvid3 cookie
+    pub buvid: String,
+}
+
+impl LiveDanmakuServer {
+    /// Generate the authentication packet.
+    fn auth_packet(&self) -> Vec<u8> {
+        let body = json::object! {
+            "uid": self.uid,
+            "roomid": self.room_id,
+            "protover": 1,
+            "platform": "web",
+            "type": 2,
+            "key": self.token.as_str(),
+            "buvid": self.buvid.as_str(),
+        };
+        encode_packet(OP_AUTH, body.dump().as_bytes())
+    }
+}
+
+/// The interval of saving danmaku of current file
+const SAVE_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Danmaku of an output file
+struct DanmakuFile {
+    /// The location of output file without extension
+    base: String,
+    /// The time when the output file is created
+    start: Instant,
+    /// Received danmaku
+    list: Vec<Danmaku>,
+    /// Whether some danmaku are not saved yet
+    dirty: bool,
+}
+
+/// Write danmaku to XML and ASS files.
+/// * `base` - The location of output file without extension
+/// * `list` - Danmaku
+/// * `ass` - ASS subtitle options
+fn write_danmaku_files(base: &str, list: &[Danmaku], ass: &AssOptions) {
+    let xml = format!("{}.danmaku.xml", base);
+    let ass_file = format!("{}.danmaku.ass", base);
+    for (path, s) in [(xml, to_xml(list)), (ass_file, danmaku_to_ass(list, ass))].iter() {
+        let r = File::create(path.as_str()).and_then(|mut f| f.write_all(s.as_bytes()));
+        if let Err(e) = r {
+            eprintln!("{}\"{}\": {}", gettext("Can not write to file: "), path, e);
+        }
+    }
+}
+
+/// The state shared by recorder, receiving thread and saving thread
+#[derive(Default)]
+struct RecorderState {
+    /// Current output file
+    current: Option<DanmakuFile>,
+    /// Finished files which are not saved yet
+    finished: Vec<DanmakuFile>,
+}
+
+type SharedState = Arc<Mutex<RecorderState>>;
+
+/// Save finished files and current file. Files are written after the lock is released.
+/// * `state` - Shared state
+/// * `ass` - ASS subtitle options
+fn save_danmaku(state: &SharedState, ass: &AssOptions) {
+    let mut files: Vec<(String, Vec<Danmaku>)> = Vec::new();
+    {
+        let mut st = state.lock().unwrap();
+        for f in std::mem::take(&mut st.finished) {
+            files.push((f.base, f.list));
+        }
+        if let Some(f) = st.current.as_mut() {
+            if f.dirty {
+                f.dirty = false;
+                files.push((f.base.clone(), f.list.clone()));
+            }
+        }
+    }
+    for (base, list) in files.iter() {
+        write_danmaku_files(base, list, ass);
+    }
+}
+
+/// Save danmaku periodically until stopped. Remaining danmaku are saved before returning.
+/// * `state` - Shared state
+/// * `stop` - Stop flag
+/// * `ass` - ASS subtitle options
+fn save_danmaku_periodically(state: SharedState, stop: Arc<AtomicBool>, ass: AssOptions) {
+    let mut last = Instant::now();
+    while !stop.load(Ordering::Relaxed) {
+        sleep(Duration::from_millis(100));
+        let has_finished = !state.lock().unwrap().finished.is_empty();
+        if has_finished || last.elapsed() >= SAVE_INTERVAL {
+            save_danmaku(&state, &ass);
+            last = Instant::now();
+        }
+    }
+    save_danmaku(&state, &ass);
+}
+
+/// Connect to server and receive danmaku until stopped.
+/// * `server` - Server information
+/// * `stop` - Stop flag
+/// * `state` - Shared state. Received danmaku are added to current file.
+fn receive_danmaku(server: LiveDanmakuServer, stop: Arc<AtomicBool>, state: SharedState) {
+    let mut index = 0;
+    while !stop.load(Ordering::Relaxed) {
+        let (host, port) = &server.hosts[index % server.hosts.len()];
+        index += 1;
+        match TcpStream::connect((host.as_str(), *port)) {
+            Ok(s) => {
+                receive_from_stream(s, &server, &stop, &state);
+            }
+            Err(e) => {
+                eprintln!("{}{}", gettext("Can not connect to live danmaku server: "), e);
+            }
+        }
+        // Wait before reconnecting.
+        let t = Instant::now();
+        while !stop.load(Ordering::Relaxed) && t.elapsed() < Duration::from_secs(5) {
+            sleep(Duration::from_millis(100));
+        }
+    }
+}
+
+/// Receive danmaku from a connected stream until it is closed or stopped.
+fn receive_from_stream(mut s: TcpStream, server: &LiveDanmakuServer, stop: &Arc<AtomicBool>, state: &SharedState) {
+    if s.set_read_timeout(Some(Duration::from_secs(1))).is_err() {
+        return;
+    }
+    if s.write_all(&server.auth_packet()).is_err() {
+        return;
+    }
+    let mut last_heartbeat = Instant::now();
+    if s.write_all(&encode_packet(OP_HEARTBEAT, &[])).is_err() {
+        return;
+    }
+    let mut buf = Vec::new();
+    let mut tmp = [0u8; 4096];
+    while !stop.load(Ordering::Relaxed) {
+        if last_heartbeat.elapsed() >= Duration::from_secs(30) {
+            if s.write_all(&encode_packet(OP_HEARTBEAT, &[])).is_err() {
+                return;
+            }
+            last_heartbeat = Instant::now();
+        }
+        match s.read(&mut tmp) {
+            Ok(0) => return,
+            Ok(n) => buf.extend_from_slice(&tmp[..n]),
+            Err(e) => {
+                if e.kind() == ErrorKind::WouldBlock || e.kind() == ErrorKind::TimedOut {
+                    continue;
+                }
+                return;
+            }
+        }
+        loop {
+            let (op, body) = match decode_packet(&mut buf) {
+                Ok(Some(p)) => p,
+                Ok(None) => break,
+                Err(_) => return,
+            };
+            match op {
+                OP_MESSAGE => {
+                    let t = std::str::from_utf8(&body);
+                    if t.is_err() {
+                        continue;
+                    }
+                    let m = json::parse(t.unwrap());
+                    if m.is_err() {
+                        continue;
+                    }
+                    if let Some(mut d) = parse_danmu_msg(&m.unwrap()) {
+                        // Danmaku received before the first file is created are dropped.
+                        let mut st = state.lock().unwrap();
+                        if let Some(f) = st.current.as_mut() {
+                            d.progress = f.start.elapsed().as_millis() as usize;
+                            f.list.push(d);
+                            f.dirty = true;
+                        }
+                    }
+                }
+                OP_AUTH_REPLY | OP_HEARTBEAT_REPLY => {}
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Record live danmaku to files.
+///
+/// Danmaku are received in a thread and saved in another thread, so the async recording loop is never blocked.
+/// Danmaku of current file are saved every [`SAVE_INTERVAL`], so they survive an interruption.
+pub struct BiliLiveDanmakuRecorder {
+    /// Stop flag
+    stop: Arc<AtomicBool>,
+    /// Shared state
+    state: SharedState,
+    /// Receiving thread and saving thread
+    handles: Vec<JoinHandle<()>>,
+}
+
+impl BiliLiveDanmakuRecorder {
+    /// Start receiving danmaku.
+    /// * `server` - Server information
+    /// * `ass` - ASS subtitle options
+    pub fn start(server: &LiveDanmakuServer, ass: &AssOptions) -> Option<Self> {
+        if server.hosts.is_empty() {
+            return None;
+        }
+        let stop = Arc::new(AtomicBool::new(false));
+        let state: SharedState = Arc::new(Mutex::new(RecorderState::default()));
+        let (s, st, l) = (server.clone(), Arc::clone(&stop), Arc::clone(&state));
+        let receiver = std::thread::spawn(move || receive_danmaku(s, st, l));
+        let (st, l, a) = (Arc::clone(&stop), Arc::clone(&state), ass.clone());
+        let saver = std::thread::spawn(move || save_danmaku_periodically(l, st, a));
+        Some(Self {
+            stop,
+            state,
+            handles: vec![receiver, saver],
+        })
+    }
+}
+
+#[async_trait]
+impl LiveDanmakuRecorder for BiliLiveDanmakuRecorder {
+    fn new_file(&mut self, base: &str) {
+        let mut st = self.state.lock().unwrap();
+        let f = DanmakuFile {
+            base: String::from(base),
+            start: Instant::now(),
+            list: Vec::new(),
+            // Files are created even if no danmaku is received.
+            dirty: true,
+        };
+        if let Some(old) = st.current.replace(f) {
+            st.finished.push(old);
+        }
+    }
+
+    async fn stop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        let handles = std::mem::take(&mut self.handles);
+        spawn_blocking(move || {
+            for h in handles {
+                // A panic in the thread has been printed already.
+                let _ = h.join();
+            }
+        })
+        .await;
+    }
+}
+
+impl Drop for BiliLiveDanmakuRecorder {
+    fn drop(&mut self) {
+        // The saving thread saves remaining danmaku before it exits.
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+#[test]
+fn test_packet() {
+    let mut buf = encode_packet(OP_MESSAGE, b"{\"cmd\":\"DANMU_MSG\"}");
+    buf.extend_from_slice(&encode_packet(OP_HEARTBEAT, &[])[..10]);
+    let p = decode_packet(&mut buf).unwrap().unwrap();
+    assert_eq!(OP_MESSAGE, p.0);
+    assert_eq!(b"{\"cmd\":\"DANMU_MSG\"}".to_vec(), p.1);
+    assert!(decode_packet(&mut buf).unwrap().is_none());
+    let mut buf = vec![0u8; 16];
+    assert!(decode_packet(&mut buf).is_err());
+}
+
+#[test]
+fn test_parse_danmu_msg() {
+    let m = json::parse(r#"{"cmd":"DANMU_MSG:4:0:2:2:2:0","info":[[0,5,25,16777215,1650000000123,0,0,"abcd"],"Hello",[1,"u"]]}"#).unwrap();
+    let d = parse_danmu_msg(&m).unwrap();
+    assert_eq!("Hello", d.content);
+    assert_eq!(DanmakuMode::Top, d.mode);
+    assert_eq!(25, d.fontsize);
+    assert_eq!(0xffffff, d.color);
+    assert_eq!(1650000000, d.ctime);
+    assert_eq!("abcd", d.mid_hash);
+    assert!(parse_danmu_msg(&json::parse(r#"{"cmd":"SEND_GIFT"}"#).unwrap()).is_none());
+}
@@ -0,0 +1,7 @@
+pub mod api;
+pub mod ass;
+pub mod attach;
+#[allow(clippy::module_inception)]
+pub mod danmaku;
+pub mod live;
+pub mod protobuf;
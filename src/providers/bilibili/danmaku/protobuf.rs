@@ -0,0 +1,141 @@
+/// A field value in protobuf message
+#[derive(Debug, PartialEq)]
+pub enum ProtobufValue<'a> {
+    Varint(u64),
+    Fixed64(u64),
+    Bytes(&'a [u8]),
+    Fixed32(u32),
+}
+
+impl<'a> ProtobufValue<'a> {
+    pub fn as_u64(&self) -> Option<u64> {
+        match self {
+            Self::Varint(v) => Some(*v),
+            Self::Fixed64(v) => Some(*v),
+            Self::Fixed32(v) => Some(*v as u64),
+            Self::Bytes(_) => None,
+        }
+    }
+
+    pub fn as_bytes(&self) -> Option<&'a [u8]> {
+        match self {
+            Self::Bytes(b) => Some(b),
+            _ => None,
+        }
+    }
+
+    /// Return the value as UTF-8 string. Invalid characters are replaced.
+    pub fn as_string(&self) -> Option<String> {
+        match self {
+            Self::Bytes(b) => Some(String::from_utf8_lossy(b).into_owned()),
+            _ => None,
+        }
+    }
+}
+
+/// A minimal protobuf reader. It only splits message into fields, the schema is handled by caller.
+pub struct ProtobufReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> ProtobufReader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    /// Read a varint
+    fn read_varint(&mut self) -> Option<u64> {
+        let mut r: u64 = 0;
+        let mut shift = 0;
+        loop {
+            if self.pos >= self.data.len() || shift >= 64 {
+                return None;
+            }
+            let b = self.data[self.pos];
+            self.pos += 1;
+            r |= ((b & 0x7f) as u64) << shift;
+            if b & 0x80 == 0 {
+                return Some(r);
+            }
+            shift += 7;
+        }
+    }
+
+    /// Read `n` bytes
+    fn read_bytes(&mut self, n: usize) -> Option<&'a [u8]> {
+        let end = self.pos.checked_add(n)?;
+        if end > self.data.len() {
+            return None;
+        }
+        let r = &self.data[self.pos..end];
+        self.pos = end;
+        Some(r)
+    }
+
+    /// Read next field. Returns field number and value.
+    /// Returns `None` if reach the end. Returns `Some(Err(()))` if the data is invalid.
+    pub fn next_field(&mut self) -> Option<Result<(u64, ProtobufValue<'a>), ()>> {
+        if self.pos >= self.data.len() {
+            return None;
+        }
+        let key = self.read_varint();
+        if key.is_none() {
+            return Some(Err(()));
+        }
+        let key = key.unwrap();
+        let field = key >> 3;
+        let v = match key & 7 {
+            0 => match self.read_varint() {
+                Some(v) => ProtobufValue::Varint(v),
+                None => return Some(Err(())),
+            },
+            1 => match self.read_bytes(8) {
+                Some(b) => {
+                    let mut a = [0u8; 8];
+                    a.copy_from_slice(b);
+                    ProtobufValue::Fixed64(u64::from_le_bytes(a))
+                }
+                None => return Some(Err(())),
+            },
+            2 => {
+                let len = self.read_varint();
+                if len.is_none() {
+                    return Some(Err(()));
+                }
+                match self.read_bytes(len.unwrap() as usize) {
+                    Some(b) => ProtobufValue::Bytes(b),
+                    None => return Some(Err(())),
+                }
+            }
+            5 => match self.read_bytes(4) {
+                Some(b) => {
+                    let mut a = [0u8; 4];
+                    a.copy_from_slice(b);
+                    ProtobufValue::Fixed32(u32::from_le_bytes(a))
+                }
+                None => return Some(Err(())),
+            },
+            _ => return Some(Err(())),
+        };
+        Some(Ok((field, v)))
+    }
+}
+
+#[test]
+fn test_protobuf_reader() {
+    // field 1: varint 150, field 2: string "ab", field 3: fixed32 1
+    let data = [0x08, 0x96, 0x01, 0x12, 0x02, 0x61, 0x62, 0x1d, 0x01, 0x00, 0x00, 0x00];
+    let mut r = ProtobufReader::new(&data);
+    assert_eq!(Some(Ok((1, ProtobufValue::Varint(150)))), r.next_field());
+    let f = r.next_field().unwrap().unwrap();
+    assert_eq!(2, f.0);
+    assert_eq!(Some(String::from("ab")), f.1.as_string());
+    assert_eq!(Some(Ok((3, ProtobufValue::Fixed32(1)))), r.next_field());
+    assert_eq!(None, r.next_field());
+    let mut r = ProtobufReader::new(&[0x12, 0x05, 0x61]);
+    assert_eq!(Some(Err(())), r.next_field());
+    // field 2 with a length of u64::MAX
+    let mut r = ProtobufReader::new(&[0x12, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01, 0x61]);
+    assert_eq!(Some(Err(())), r.next_field());
+}
@@ -1,19 +1,34 @@
-extern crate futures;
 extern crate json;
 
+use crate::error::Error;
+use crate::error::Result;
 use crate::getopt::OptStore;
 use crate::http_client::CookieClient;
 use crate::i18n::gettext;
+use crate::providers::bilibili::api::get_api_async;
+use crate::providers::bilibili::interaction_graph::get_graph_formats;
+use crate::providers::bilibili::interaction_graph::InteractionGraph;
+use crate::providers::bilibili::interaction_graph::InteractionNode;
 use crate::providers::bilibili::normal_video::UrlInfo;
 use crate::providers::bilibili::part_info::PartInfo;
 use crate::providers::bilibili::part_info::PartInfoList;
+use crate::providers::bilibili::stein::state_key;
+use crate::providers::bilibili::stein::Actions;
+use crate::providers::bilibili::stein::Condition;
+use crate::providers::bilibili::stein::StoryVars;
 use crate::settings::SettingStore;
-use futures::executor::block_on;
+use crate::utils::number::ToUsize;
+use futures::future::BoxFuture;
 use json::JsonValue;
 use std::clone::Clone;
+use std::collections::HashMap;
+use std::collections::HashSet;
 use std::convert::TryFrom;
 use std::default::Default;
 
+/// The API which returns the information of a node
+const EDGE_INFO_API: &str = "https://api.bilibili.com/x/stein/edgeinfo_v2";
+
 /// The information of an edge
 #[derive(Debug, PartialEq)]
 pub struct EdgeInfo {
@@ -42,6 +57,18 @@ impl Clone for EdgeInfo {
     }
 }
 
+/// Parse a choice of question.
+/// * `v` - Choice
+fn parse_edge_info(v: &JsonValue) -> Result<EdgeInfo> {
+    match EdgeInfo::try_from(v) {
+        Ok(ei) => Ok(ei),
+        Err(e) => Err(Error::Parse {
+            source: String::from(EDGE_INFO_API),
+            msg: String::from(e),
+        }),
+    }
+}
+
 impl Default for EdgeInfo {
     fn default() -> Self {
         Self {
@@ -102,8 +129,18 @@ pub struct InteractionVideoParser {
     buvid3: Option<String>,
     /// The list of edge ID. Used to remove duplicate.
     edge_list: Vec<usize>,
+    /// The data of requested nodes. The key is edge ID.
+    node_data: HashMap<usize, JsonValue>,
+    /// Explored story states
+    states: HashSet<(usize, Vec<(String, u64)>)>,
+    /// The maximum depth and the maximum count of story states
+    limits: (usize, usize),
+    /// Whether some story states are not explored because of limits
+    limit_reached: bool,
     /// Store the result
     pub part_list: PartInfoList,
+    /// The node/edge graph
+    pub graph: InteractionGraph,
     /// Url information
     url: UrlInfo,
     /// Part count
@@ -128,7 +165,12 @@ impl InteractionVideoParser {
             graph_version,
             buvid3,
             edge_list: [].to_vec(),
+            node_data: HashMap::new(),
+            states: HashSet::new(),
+            limits: (0, 0),
+            limit_reached: false,
             part_list,
+            graph: InteractionGraph::new(url.bv.as_str(), graph_version),
             url,
             part_count,
             opt,
@@ -150,6 +192,9 @@ impl InteractionVideoParser {
             return false;
         }
         let id = id.unwrap();
+        if !self.graph.add_node(data) {
+            return false;
+        }
         for k in data["story_list"].members() {
             let eid = k["edge_id"].as_number();
             if eid.is_some() {
@@ -168,98 +213,175 @@ impl InteractionVideoParser {
         false
     }
 
-    /// Deal questions in a node
+    /// Return the data of a node. The data is requested from API only once.
+    /// * `c` - HTTP Session
+    /// * `ei` - The choice which points to the node
+    async fn get_node_data(&mut self, c: &mut CookieClient, ei: &EdgeInfo) -> Result<JsonValue> {
+        if self.node_data.contains_key(&ei.id) {
+            return Ok(self.node_data.get(&ei.id).unwrap().clone());
+        }
+        let data = self.get_edge_info(c, Some(ei.clone())).await?;
+        if !self.add_node(&data) {
+            return Err(Error::Parse {
+                source: String::from(EDGE_INFO_API),
+                msg: format!("{}{}", gettext("Can not parse node: "), ei.id),
+            });
+        }
+        self.edge_list.push(ei.id);
+        self.node_data.insert(ei.id, data.clone());
+        Ok(data)
+    }
+
+    /// Set random variables to unknown (NaN). Random variables get a new value when a node is reached,
+    /// so conditions on them are not evaluated to a fixed result and both outcomes are explored.
+    /// * `vars` - Story variables
+    fn reset_random_vars(&self, vars: &mut StoryVars) {
+        for v in self.graph.variables.iter() {
+            if v.random {
+                vars.insert(v.id.clone(), f64::NAN);
+            }
+        }
+    }
+
+    /// Explore all story states which are reachable from a node in depth-first order.
+    /// * `c` - HTTP Session
     /// * `data` - data from [`get_edge_info`](#method.get_edge_info)
-    fn deal_question(&mut self, c: &mut CookieClient, data: &JsonValue) -> bool {
-        for q in data["edges"]["questions"].members() {
-            for q2 in q["choices"].members() {
-                let ei = EdgeInfo::try_from(q2);
-                match ei {
-                    Ok(_) => {}
-                    Err(e) => {
-                        println!("{}", e);
-                        return false;
+    /// * `vars` - Story variables when reaching the node
+    /// * `depth` - The count of choices made from the first node
+    ///
+    /// The future is boxed because it is recursive.
+    fn explore<'a>(
+        &'a mut self,
+        c: &'a mut CookieClient,
+        data: &'a JsonValue,
+        vars: &'a StoryVars,
+        depth: usize,
+    ) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            for q in data["edges"]["questions"].members() {
+                for q2 in q["choices"].members() {
+                    let ei = parse_edge_info(q2)?;
+                    // Expressions which can not be parsed are treated as always true, so no branch is missed.
+                    let ok = match Condition::parse(ei.condition.as_str()) {
+                        Ok(cond) => cond.check(vars),
+                        Err(e) => {
+                            eprintln!("{}\"{}\": {}", gettext("Can not parse condition: "), ei.condition, e);
+                            true
+                        }
+                    };
+                    if !ok {
+                        continue;
                     }
-                }
-                let ei = ei.unwrap();
-                let mut it = self.edge_list.iter();
-                let re = it.find(|&&x| x == ei.id);
-                if re.is_none() {
-                    let data = self.get_edge_info(c, Some(ei.clone()));
-                    if data.is_none() {
-                        return false;
+                    let mut nvars = vars.clone();
+                    match Actions::parse(ei.native_action.as_str()) {
+                        Ok(a) => a.apply(&mut nvars),
+                        Err(e) => {
+                            eprintln!("{}\"{}\": {}", gettext("Can not parse action: "), ei.native_action, e);
+                        }
                     }
-                    let data = data.unwrap();
-                    if !self.add_node(&data) {
-                        return false;
+                    self.reset_random_vars(&mut nvars);
+                    let key = state_key(ei.id, &nvars);
+                    if self.states.contains(&key) {
+                        continue;
                     }
-                    self.edge_list.push(ei.id);
-                    if !self.deal_question(c, &data) {
-                        return false;
+                    if depth >= self.limits.0 || self.states.len() >= self.limits.1 {
+                        self.limit_reached = true;
+                        continue;
                     }
+                    self.states.insert(key);
+                    let data = self.get_node_data(c, &ei).await?;
+                    self.explore(c, &data, &nvars, depth + 1).await?;
+                }
+            }
+            Ok(())
+        })
+    }
+
+    /// Deal questions in a node
+    /// * `data` - data from [`get_edge_info`](#method.get_edge_info)
+    ///
+    /// All reachable story states are explored first. Then the nodes which are not reachable
+    /// (or not explored because of limits) are added, so the part list is still complete.
+    async fn deal_question(&mut self, c: &mut CookieClient, data: &JsonValue) -> Result<()> {
+        let root = data["edge_id"].as_usize();
+        if let Some(root) = root.filter(|&root| !self.node_data.contains_key(&root)) {
+            self.edge_list.push(root);
+            self.node_data.insert(root, data.clone());
+        }
+        let mut vars = StoryVars::new();
+        for v in self.graph.variables.iter() {
+            vars.insert(v.id.clone(), v.value);
+        }
+        self.reset_random_vars(&mut vars);
+        if let Some(root) = root {
+            self.states.insert(state_key(root, &vars));
+        }
+        self.explore(c, data, &vars, 0).await?;
+        let reachable = self.edge_list.clone();
+        let mut i = 0;
+        while i < self.edge_list.len() {
+            let id = self.edge_list[i];
+            let d = self.node_data.get(&id).unwrap().clone();
+            for q in d["edges"]["questions"].members() {
+                for q2 in q["choices"].members() {
+                    let ei = parse_edge_info(q2)?;
+                    self.get_node_data(c, &ei).await?;
                 }
             }
+            i += 1;
+        }
+        self.report_unreachable(&reachable);
+        Ok(())
+    }
+
+    /// Print the nodes which can not be reached under story conditions.
+    /// * `reachable` - The edge ID of reachable nodes
+    fn report_unreachable(&self, reachable: &[usize]) {
+        if self.limit_reached {
+            eprintln!("{}", gettext("Warning: The limit of interactive video exploration is reached. Some story states are not explored."));
+        }
+        let unreachable: Vec<&InteractionNode> = self
+            .graph
+            .nodes
+            .iter()
+            .filter(|n| !reachable.contains(&n.edge_id))
+            .collect();
+        if unreachable.is_empty() {
+            return;
+        }
+        let total = match self.part_count {
+            Some(c) => c,
+            None => self.graph.nodes.len(),
+        };
+        let s = gettext("<num> of <total> parts can not be reached under story conditions:")
+            .replace("<num>", format!("{}", unreachable.len()).as_str())
+            .replace("<total>", format!("{}", total).as_str());
+        eprintln!("{}", s);
+        for n in unreachable.iter() {
+            eprintln!("{} (CID: {})", n.title, n.cid);
         }
-        true
     }
 
     /// Get edge information from API
     /// * `c` - HTTP Session
     /// * `edge` - Edge information. If is None, means first node.
-    fn get_edge_info(&self, c: &mut CookieClient, edge: Option<EdgeInfo>) -> Option<JsonValue> {
+    async fn get_edge_info(&self, c: &mut CookieClient, edge: Option<EdgeInfo>) -> Result<JsonValue> {
         let mut param = json::object! {"bvid": self.url.bv.clone(), "graph_version": self.graph_version, "platform": "pc", "portal": 0, "screen": 0};
         if self.buvid3.is_some() {
             match param.insert("buvid3", self.buvid3.as_ref().unwrap().clone()) {
                 Ok(_) => {}
                 Err(e) => {
-                    println!("{}\n{}", gettext("Warning: "), e);
+                    eprintln!("{}\n{}", gettext("Warning: "), e);
                 }
             }
         }
         if edge.is_some() {
             let e = edge.unwrap();
-            match param.insert("edge_id", e.id) {
-                Ok(_) => {}
-                Err(e) => {
-                    println!("{}", e);
-                    return None;
-                }
-            }
-            match param.insert("choice", e.native_action.clone()) {
-                Ok(_) => {}
-                Err(e) => {
-                    println!("{}", e);
-                    return None;
-                }
-            }
-        }
-        let r = c.get_with_param("https://api.bilibili.com/x/stein/edgeinfo_v2", param);
-        if r.is_none() {
-            return None;
-        }
-        let r = r.unwrap();
-        if r.status().as_u16() > 400 {
-            println!("{}\n{}", gettext("Can not get edge info."), r.status());
-            return None;
-        }
-        let t = block_on(r.text_with_charset("UTF-8"));
-        if t.is_err() {
-            println!("{}", t.unwrap_err());
-            return None;
-        }
-        let t = t.unwrap();
-        let re = json::parse(t.as_str());
-        if re.is_err() {
-            println!("{}", re.unwrap_err());
-            return None;
-        }
-        let re = re.unwrap();
-        let code = re["code"].as_i64().unwrap();
-        if code != 0 {
-            println!("{} {}", code, re["message"].as_str().unwrap());
-            return None;
+            param["edge_id"] = JsonValue::from(e.id);
+            param["choice"] = JsonValue::from(e.native_action.clone());
         }
-        Some(re["data"].clone())
+        let re = get_api_async(c, EDGE_INFO_API, param).await?;
+        Ok(re["data"].clone())
     }
 
     /// Get settings from options and settings.
@@ -277,37 +399,79 @@ impl InteractionVideoParser {
         false
     }
 
-    /// Parse part list. Return true if Ok
+    /// Get the maximum depth and the maximum count of story states from options and settings.
+    ///
+    /// Return an error if the option is invalid.
+    fn get_limits(&self) -> Result<(usize, usize)> {
+        let mut r = [100, 10000];
+        for (i, key) in ["interaction-max-depth", "interaction-max-states"].iter().enumerate() {
+            if let Some(opt) = &self.opt {
+                let o = opt.get_option(key);
+                if let Some(o) = o {
+                    let v = o.to_usize();
+                    if v.is_none() || v.unwrap() == 0 {
+                        let s = gettext("The value of option \"<key>\" is invalid.").replace("<key>", key);
+                        return Err(Error::Config(s));
+                    }
+                    r[i] = v.unwrap();
+                    continue;
+                }
+            }
+            if let Some(settings) = &self.settings {
+                let v = settings.get_settings("BiliNormalVideoProvider", key);
+                if let Some(v) = v {
+                    r[i] = v.to_usize().unwrap_or(r[i]);
+                }
+            }
+        }
+        Ok((r[0], r[1]))
+    }
+
+    /// Whether the full graph is needed. The graph is only complete when all nodes are walked.
+    fn need_graph(&self) -> bool {
+        match get_graph_formats(self.opt.as_ref(), self.settings.as_ref()) {
+            Some(f) => !f.is_empty(),
+            None => false,
+        }
+    }
+
+    /// Parse part list.
     /// * `c` - HTTP Session
-    pub fn parse(&mut self, c: &mut CookieClient) -> bool {
-        let data = self.get_edge_info(c, None);
-        if data.is_none() {
-            return false;
+    ///
+    /// If graph export is enabled, all nodes are walked to build [`graph`](#structfield.graph).
+    pub async fn parse(&mut self, c: &mut CookieClient) -> Result<()> {
+        self.limits = self.get_limits()?;
+        let data = self.get_edge_info(c, None).await?;
+        if !self.graph.add_node(&data) {
+            return Err(Error::Parse {
+                source: String::from(EDGE_INFO_API),
+                msg: String::from(gettext("Can not parse the first node of interactive video.")),
+            });
         }
-        let data = data.unwrap();
+        let need_graph = self.need_graph();
         if self.part_count.is_some() && !self.no_use_storylist() {
             let count = self.part_count.unwrap();
             let li = self.parse_list_from_story_list(&data["story_list"]);
             if li.is_some() {
                 let li = li.unwrap();
                 if li.list.len() == count {
+                    if need_graph {
+                        self.deal_question(c, &data).await?;
+                    }
                     self.part_list = li;
-                    return true;
+                    return Ok(());
                 }
             }
         }
-        if !self.deal_question(c, &data) {
-            return false;
-        }
+        self.deal_question(c, &data).await?;
         if self.part_count.is_some() && self.part_list.list.len() != self.part_count.unwrap() {
             let s =
                 gettext("Video information say there are <total> parts, but only get <num> parts.")
                     .replace("<total>", format!("{}", self.part_count.unwrap()).as_str())
                     .replace("<num>", format!("{}", self.part_list.list.len()).as_str());
-            println!("{}", s);
-            return false;
+            return Err(Error::Extract(s));
         }
-        true
+        Ok(())
     }
 
     /// In most case, story_list in first node already include all parts.  
@@ -349,7 +513,12 @@ impl Clone for InteractionVideoParser {
             graph_version: self.graph_version.clone(),
             buvid3: self.buvid3.clone(),
             edge_list: self.edge_list.clone(),
+            node_data: self.node_data.clone(),
+            states: self.states.clone(),
+            limits: self.limits,
+            limit_reached: self.limit_reached,
             part_list: self.part_list.clone(),
+            graph: self.graph.clone(),
             url: self.url.clone(),
             part_count: self.part_count.clone(),
             opt: self.opt.clone(),
@@ -0,0 +1,373 @@
+extern crate json;
+
+use crate::getopt::OptStore;
+use crate::i18n::gettext;
+use crate::providers::bilibili::interaction::EdgeInfo;
+use crate::settings::SettingStore;
+use json::JsonValue;
+use std::clone::Clone;
+use std::convert::TryFrom;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+/// The export format of interactive video graph
+pub enum GraphFormat {
+    /// JSON
+    Json,
+    /// Graphviz DOT
+    Dot,
+}
+
+impl GraphFormat {
+    /// Parse a comma separated format list. Such as `json,dot`.
+    /// * `s` - Format list
+    pub fn parse_list(s: &str) -> Option<Vec<GraphFormat>> {
+        let mut r: Vec<GraphFormat> = Vec::new();
+        for i in s.split(',') {
+            let f = match i.trim().to_lowercase().as_str() {
+                "json" => GraphFormat::Json,
+                "dot" | "gv" => GraphFormat::Dot,
+                _ => return None,
+            };
+            if !r.contains(&f) {
+                r.push(f);
+            }
+        }
+        Some(r)
+    }
+
+    /// Return the suffix of output file.
+    pub fn suffix(&self) -> &'static str {
+        match self {
+            GraphFormat::Json => "interaction.json",
+            GraphFormat::Dot => "interaction.dot",
+        }
+    }
+}
+
+/// Return the export formats of interactive video graph.
+/// * `opt` - Options
+/// * `se` - Settings
+///
+/// Return an empty list if export is not enabled. Return None if the option is invalid.
+pub fn get_graph_formats(opt: Option<&OptStore>, se: Option<&SettingStore>) -> Option<Vec<GraphFormat>> {
+    if let Some(opt) = opt {
+        let o = opt.get_option("interaction-graph");
+        if let Some(o) = o {
+            let r = GraphFormat::parse_list(o.as_str());
+            if r.is_none() {
+                let s = gettext("The value of option \"<key>\" is invalid.").replace("<key>", "interaction-graph");
+                eprintln!("{}", s);
+            }
+            return r;
+        }
+    }
+    if let Some(se) = se {
+        let s = se.get_settings("BiliNormalVideoProvider", "interaction-graph");
+        if let Some(s) = s {
+            let r = GraphFormat::parse_list(s.as_str().unwrap_or(""));
+            if r.is_some() {
+                return r;
+            }
+        }
+    }
+    Some(Vec::new())
+}
+
+/// A story variable of interactive video
+#[derive(Clone, Debug, PartialEq)]
+pub struct InteractionVariable {
+    /// Variable ID. Used in conditions and actions.
+    pub id: String,
+    /// Display name
+    pub name: String,
+    /// Initial value
+    pub value: f64,
+    /// Whether the variable is shown to user
+    pub is_show: bool,
+    /// Whether the variable is a random variable (`type` is 2).
+    /// It gets a random value every time a node is reached, so the initial value is meaningless.
+    pub random: bool,
+}
+
+impl InteractionVariable {
+    /// Parse from an item of `hidden_vars`.
+    pub fn from_json(v: &JsonValue) -> Option<Self> {
+        let id = match v["id_v2"].as_str() {
+            Some(id) => id,
+            None => v["id"].as_str().unwrap_or(""),
+        };
+        if id.is_empty() {
+            return None;
+        }
+        Some(Self {
+            id: String::from(id),
+            name: String::from(v["name"].as_str().unwrap_or("")),
+            value: v["value"].as_f64().unwrap_or(0.0),
+            is_show: v["is_show"].as_u64().unwrap_or(0) != 0,
+            random: v["type"].as_u64() == Some(2),
+        })
+    }
+}
+
+/// A question (choice point) at the end of a node
+#[derive(Clone, Debug, PartialEq)]
+pub struct InteractionQuestion {
+    /// Question ID
+    pub id: usize,
+    /// Question type. 0 means jump automatically, 1 means choices, 2 means choices on the video.
+    pub typ: i64,
+    /// Question title
+    pub title: String,
+    /// Choices. Every choice points to a node.
+    pub choices: Vec<EdgeInfo>,
+}
+
+/// A node of interactive video. Every node plays a part.
+#[derive(Clone, Debug, PartialEq)]
+pub struct InteractionNode {
+    /// Edge ID (Node ID)
+    pub edge_id: usize,
+    /// CID of the part
+    pub cid: usize,
+    /// Node title
+    pub title: String,
+    /// Questions shown after the part is played
+    pub questions: Vec<InteractionQuestion>,
+}
+
+impl InteractionNode {
+    /// Parse from data of API (`https://api.bilibili.com/x/stein/edgeinfo_v2`)
+    pub fn from_json(data: &JsonValue) -> Option<Self> {
+        let title = data["title"].as_str()?;
+        let edge_id = data["edge_id"].as_usize()?;
+        let mut cid = None;
+        for k in data["story_list"].members() {
+            if k["edge_id"].as_usize() == Some(edge_id) {
+                cid = k["cid"].as_usize();
+                break;
+            }
+        }
+        let cid = cid?;
+        let mut questions: Vec<InteractionQuestion> = Vec::new();
+        for q in data["edges"]["questions"].members() {
+            let mut choices: Vec<EdgeInfo> = Vec::new();
+            for c in q["choices"].members() {
+                match EdgeInfo::try_from(c) {
+                    Ok(e) => choices.push(e),
+                    Err(e) => {
+                        eprintln!("{}", e);
+                        return None;
+                    }
+                }
+            }
+            questions.push(InteractionQuestion {
+                id: q["id"].as_usize().unwrap_or(0),
+                typ: q["type"].as_i64().unwrap_or(0),
+                title: String::from(q["title"].as_str().unwrap_or("")),
+                choices,
+            });
+        }
+        Some(Self {
+            edge_id,
+            cid,
+            title: String::from(title),
+            questions,
+        })
+    }
+}
+
+/// The node/edge graph of interactive video
+#[derive(Clone, Debug, PartialEq)]
+pub struct InteractionGraph {
+    /// BV number
+    pub bvid: String,
+    /// Graph version
+    pub graph_version: usize,
+    /// Story variables
+    pub variables: Vec<InteractionVariable>,
+    /// Nodes. The first node is the start node.
+    pub nodes: Vec<InteractionNode>,
+}
+
+/// Escape a string in DOT quoted string.
+fn escape_dot(s: &str) -> String {
+    s.replace("\\", "\\\\").replace("\"", "\\\"").replace("\n", "\\n")
+}
+
+impl InteractionGraph {
+    pub fn new(bvid: &str, graph_version: usize) -> Self {
+        Self {
+            bvid: String::from(bvid),
+            graph_version,
+            variables: Vec::new(),
+            nodes: Vec::new(),
+        }
+    }
+
+    /// Add a node. If the node is the first node, the story variables are also read.
+    /// * `data` - data from API (`https://api.bilibili.com/x/stein/edgeinfo_v2`)
+    ///
+    /// Return false if data is invalid.
+    pub fn add_node(&mut self, data: &JsonValue) -> bool {
+        let n = InteractionNode::from_json(data);
+        if n.is_none() {
+            return false;
+        }
+        let n = n.unwrap();
+        if self.get_node(n.edge_id).is_some() {
+            return true;
+        }
+        if self.nodes.is_empty() {
+            for v in data["hidden_vars"].members() {
+                let v = InteractionVariable::from_json(v);
+                if let Some(v) = v {
+                    self.variables.push(v);
+                }
+            }
+        }
+        self.nodes.push(n);
+        true
+    }
+
+    /// Return the node with the edge ID.
+    /// * `edge_id` - Edge ID
+    pub fn get_node(&self, edge_id: usize) -> Option<&InteractionNode> {
+        self.nodes.iter().find(|n| n.edge_id == edge_id)
+    }
+
+    /// Export to JSON.
+    pub fn to_json(&self) -> JsonValue {
+        let mut vars = JsonValue::new_array();
+        for v in self.variables.iter() {
+            vars.push(json::object! {
+                "id": v.id.as_str(),
+                "name": v.name.as_str(),
+                "value": v.value,
+                "is_show": v.is_show,
+                "random": v.random,
+            })
+            .unwrap();
+        }
+        let mut nodes = JsonValue::new_array();
+        for n in self.nodes.iter() {
+            let mut questions = JsonValue::new_array();
+            for q in n.questions.iter() {
+                let mut choices = JsonValue::new_array();
+                for c in q.choices.iter() {
+                    choices
+                        .push(json::object! {
+                            "edge_id": c.id,
+                            "cid": c.cid,
+                            "option": c.option.as_str(),
+                            "condition": c.condition.as_str(),
+                            "native_action": c.native_action.as_str(),
+                            "is_default": c.is_default,
+                        })
+                        .unwrap();
+                }
+                questions
+                    .push(json::object! {"id": q.id, "type": q.typ, "title": q.title.as_str(), "choices": choices})
+                    .unwrap();
+            }
+            nodes
+                .push(json::object! {"edge_id": n.edge_id, "cid": n.cid, "title": n.title.as_str(), "questions": questions})
+                .unwrap();
+        }
+        let root = match self.nodes.first() {
+            Some(n) => JsonValue::from(n.edge_id),
+            None => JsonValue::Null,
+        };
+        json::object! {
+            "bvid": self.bvid.as_str(),
+            "graph_version": self.graph_version,
+            "root": root,
+            "variables": vars,
+            "nodes": nodes,
+        }
+    }
+
+    /// Export to Graphviz DOT.
+    ///
+    /// Default choices are drawn in bold. Conditions are appended to the choice labels.
+    pub fn to_dot(&self) -> String {
+        let mut s = format!("digraph \"{}\" {{\n", escape_dot(self.bvid.as_str()));
+        for (i, n) in self.nodes.iter().enumerate() {
+            let shape = if i == 0 { ", shape=doublecircle" } else { "" };
+            s += format!(
+                "    e{} [label=\"{}\\ncid: {}\"{}];\n",
+                n.edge_id,
+                escape_dot(n.title.as_str()),
+                n.cid,
+                shape
+            )
+            .as_str();
+        }
+        for n in self.nodes.iter() {
+            for q in n.questions.iter() {
+                for c in q.choices.iter() {
+                    let mut label = c.option.clone();
+                    if !c.condition.is_empty() {
+                        label += format!("\n[{}]", c.condition).as_str();
+                    }
+                    let style = if c.is_default { ", style=bold" } else { "" };
+                    s += format!(
+                        "    e{} -> e{} [label=\"{}\"{}];\n",
+                        n.edge_id,
+                        c.id,
+                        escape_dot(label.as_str()),
+                        style
+                    )
+                    .as_str();
+                }
+            }
+        }
+        s += "}\n";
+        s
+    }
+
+    /// Export to a format.
+    /// * `f` - Format
+    pub fn export(&self, f: GraphFormat) -> String {
+        match f {
+            GraphFormat::Json => self.to_json().pretty(2),
+            GraphFormat::Dot => self.to_dot(),
+        }
+    }
+}
+
+#[test]
+fn test_interaction_graph() {
+    assert_eq!(Some(vec![GraphFormat::Json, GraphFormat::Dot]), GraphFormat::parse_list("json, DOT,json"));
+    assert_eq!(None, GraphFormat::parse_list("svg"));
+    let mut g = InteractionGraph::new("BV1xx411c7mD", 3);
+    let root = json::object! {
+        "title": "开始",
+        "edge_id": 1,
+        "story_list": [{"edge_id": 1, "cid": 100}, {"edge_id": 2, "cid": 200}],
+        "hidden_vars": [
+            {"id_v2": "$a", "name": "好感", "value": 5, "is_show": 1, "type": 1},
+            {"id_v2": "$r", "name": "随机值", "value": 0, "is_show": 0, "type": 2}
+        ],
+        "edges": {"questions": [{"id": 9, "type": 1, "title": "", "choices": [
+            {"id": 2, "cid": 200, "option": "A \"去\"", "condition": "$a>=5", "native_action": "$a=$a+1", "is_default": 1},
+            {"id": 3, "cid": 300, "option": "B", "condition": "", "native_action": "", "is_default": 0}
+        ]}]}
+    };
+    assert!(g.add_node(&root));
+    assert!(g.add_node(&json::object! {"title": "A", "edge_id": 2, "story_list": [{"edge_id": 2, "cid": 200}]}));
+    assert!(!g.add_node(&json::object! {"title": "C", "edge_id": 4, "story_list": []}));
+    assert_eq!(2, g.nodes.len());
+    assert_eq!(2, g.variables.len());
+    assert_eq!(5.0, g.variables[0].value);
+    assert!(!g.variables[0].random);
+    assert!(g.variables[1].random);
+    let j = g.to_json();
+    assert_eq!(1, j["root"].as_usize().unwrap());
+    assert_eq!(Some(true), j["variables"][1]["random"].as_bool());
+    assert_eq!(200, j["nodes"][0]["questions"][0]["choices"][0]["cid"].as_usize().unwrap());
+    assert_eq!("$a>=5", j["nodes"][0]["questions"][0]["choices"][0]["condition"].as_str().unwrap());
+    let d = g.to_dot();
+    assert!(d.contains("e1 [label=\"开始\\ncid: 100\", shape=doublecircle];"));
+    assert!(d.contains("e1 -> e2 [label=\"A \\\"去\\\"\\n[$a>=5]\", style=bold];"));
+    assert!(d.contains("e1 -> e3 [label=\"B\"];"));
+}
@@ -0,0 +1,408 @@
+extern crate futures;
+extern crate json;
+extern crate regex;
+
+use crate::cookies_json::CookiesJar;
+use crate::error::Error;
+use crate::error::Result;
+use crate::getopt::OptDes;
+use crate::getopt::OptStore;
+use crate::i18n::gettext;
+use crate::metadata::ExtractInfo;
+use crate::metadata::InfoType;
+use crate::metadata::NoInTotal;
+use crate::metadata::VideoInfo;
+use crate::providers::bilibili::api::get_api_async;
+use crate::providers::bilibili::base::BiliBaseProvider;
+use crate::providers::bilibili::normal_video::extract_videos_by_id;
+use crate::providers::bilibili::normal_video::BiliNormalVideoProvider;
+use crate::providers::provider_base::Provider;
+use crate::settings::SettingDes;
+use crate::settings::SettingStore;
+use crate::utils::jobs::get_jobs;
+use async_trait::async_trait;
+use json::JsonValue;
+use regex::Regex;
+use std::clone::Clone;
+
+lazy_static! {
+    static ref RE_FAV: Regex = Regex::new(r"(?i)^(?:https?://)?space\.bilibili\.com/\d+/favlist\?(?:.*&)?fid=(?P<fid>\d+)(?:[&#].*)?$").unwrap();
+    static ref RE_FAV2: Regex = Regex::new(r"(?i)^(?:https?://)?(?:www\.)?bilibili\.com/medialist/(?:detail|play)/ml(?P<fid>\d+)/?(?:[?#].*)?$").unwrap();
+    static ref RE_CHANNEL: Regex = Regex::new(r"(?i)^(?:https?://)?space\.bilibili\.com/(?P<mid>\d+)/channel/(?P<type>collectiondetail|seriesdetail)\?(?:.*&)?sid=(?P<sid>\d+)(?:[&#].*)?$").unwrap();
+    static ref RE_LISTS: Regex = Regex::new(r"(?i)^(?:https?://)?space\.bilibili\.com/(?P<mid>\d+)/lists/(?P<sid>\d+)\?(?:.*&)?type=(?P<type>season|series)(?:[&#].*)?$").unwrap();
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+/// Information from list url
+pub enum ListUrl {
+    /// Favorites folder ID
+    Favlist(usize),
+    /// Collection (UGC season). Uploader's ID and season ID
+    Collection(usize, usize),
+    /// Series. Uploader's ID and series ID
+    Series(usize, usize),
+}
+
+/// A video in the list
+#[derive(Clone, Debug, PartialEq)]
+pub struct ListVideo {
+    /// BV number
+    pub bvid: String,
+    /// Title
+    pub title: String,
+}
+
+/// Parse videos from a list of video information.
+/// * `list` - Such as `medias` of favorites folder or `archives` of collection
+///
+/// Items which are not video (such as audio in favorites folder) are skipped.
+pub fn parse_list_videos(list: &JsonValue) -> Vec<ListVideo> {
+    let mut r: Vec<ListVideo> = Vec::new();
+    for i in list.members() {
+        if i["type"].as_usize().is_some_and(|typ| typ != 2) {
+            continue;
+        }
+        let bvid = i["bvid"].as_str();
+        if bvid.is_none() {
+            continue;
+        }
+        r.push(ListVideo {
+            bvid: String::from(bvid.unwrap()),
+            title: String::from(i["title"].as_str().unwrap_or("")),
+        });
+    }
+    r
+}
+
+/// Set the position in list as metadata.
+/// * `vi` - Video information
+/// * `album` - The name of list
+/// * `no` - Position in list (Start from 1)
+/// * `total` - The count of videos in list
+pub fn set_list_metadata(vi: &mut VideoInfo, album: &Option<String>, no: usize, total: usize) {
+    if album.is_some() {
+        vi.meta.album = album.clone();
+    }
+    let tr = NoInTotal::new(no, total);
+    if let Some(tr) = tr {
+        vi.meta.track = Some(tr);
+    }
+}
+
+pub struct BiliListProvider {
+    base: BiliBaseProvider,
+    /// Input Url Information (Set in [`extract`](#method.extract) function)
+    url: Option<ListUrl>,
+    /// The name of list
+    title: Option<String>,
+}
+
+impl BiliListProvider {
+    fn parse_url(url: &str) -> Option<ListUrl> {
+        for re in [&*RE_FAV, &*RE_FAV2].iter() {
+            let caps = re.captures(url);
+            if let Some(caps) = caps {
+                return match caps.name("fid").unwrap().as_str().parse::<usize>() {
+                    Ok(fid) => Some(ListUrl::Favlist(fid)),
+                    Err(_) => None,
+                };
+            }
+        }
+        for re in [&*RE_CHANNEL, &*RE_LISTS].iter() {
+            let caps = re.captures(url);
+            if let Some(caps) = caps {
+                let mid = caps.name("mid").unwrap().as_str().parse::<usize>();
+                let sid = caps.name("sid").unwrap().as_str().parse::<usize>();
+                if mid.is_err() || sid.is_err() {
+                    return None;
+                }
+                let mid = mid.unwrap();
+                let sid = sid.unwrap();
+                return match caps.name("type").unwrap().as_str().to_lowercase().as_str() {
+                    "collectiondetail" | "season" => Some(ListUrl::Collection(mid, sid)),
+                    _ => Some(ListUrl::Series(mid, sid)),
+                };
+            }
+        }
+        None
+    }
+
+    /// Get all videos in the list.
+    /// * `url` - Url
+    async fn get_list(&mut self, url: &str) -> Result<Vec<ListVideo>> {
+        let u = match Self::parse_url(url) {
+            Some(u) => u,
+            None => return Err(Error::Extract(String::from(gettext("Unsupported url.")))),
+        };
+        self.url = Some(u);
+        let list = match u {
+            ListUrl::Favlist(fid) => self.get_favlist(fid).await,
+            ListUrl::Collection(mid, sid) => self.get_collection(mid, sid).await,
+            ListUrl::Series(mid, sid) => self.get_series(mid, sid).await,
+        }?;
+        if list.is_empty() {
+            return Err(Error::Extract(String::from(gettext("No video is selected."))));
+        }
+        Ok(list)
+    }
+
+    /// Generate extract information from extracted videos.
+    /// * `list` - Videos in the list
+    /// * `results` - Extracted videos in the same order as `list`
+    fn gen_extract_info(&self, list: &[ListVideo], results: Vec<Result<Vec<VideoInfo>>>) -> Result<ExtractInfo> {
+        let mut videos: Vec<VideoInfo> = Vec::new();
+        let total = list.len();
+        for (i, (v, r)) in list.iter().zip(results).enumerate() {
+            match r {
+                Ok(r) => {
+                    for mut vi in r {
+                        set_list_metadata(&mut vi, &self.title, i + 1, total);
+                        videos.push(vi);
+                    }
+                }
+                Err(e) => {
+                    eprintln!("{}", e);
+                    let s = gettext("Can not extract video <id>. Skipped.").replace("<id>", v.bvid.as_str());
+                    eprintln!("{}", s);
+                }
+            }
+        }
+        if videos.is_empty() {
+            return Err(Error::Extract(String::from(gettext("Can not extract any video in the list."))));
+        }
+        Ok(ExtractInfo {
+            typ: InfoType::VideoList,
+            videos: Some(videos),
+            ..Default::default()
+        })
+    }
+
+    /// Call an API and return the `data` field.
+    /// * `url` - API url
+    /// * `param` - Query parameters
+    async fn get_api(&mut self, url: &str, param: JsonValue) -> Result<JsonValue> {
+        let c = self.base.client.as_mut().unwrap();
+        match get_api_async(c, url, param).await {
+            Ok(re) => Ok(re["data"].clone()),
+            Err(e) => {
+                if let Error::Api { code: -403, .. } = e {
+                    if !self.base.logined() {
+                        eprintln!("{}", gettext("This list may be private. Please login and try again."));
+                    }
+                }
+                Err(e)
+            }
+        }
+    }
+
+    /// Get all videos in a favorites folder.
+    /// * `fid` - Favorites folder ID
+    async fn get_favlist(&mut self, fid: usize) -> Result<Vec<ListVideo>> {
+        let mut r: Vec<ListVideo> = Vec::new();
+        let mut pn = 1;
+        loop {
+            let data = self.get_api(
+                "https://api.bilibili.com/x/v3/fav/resource/list",
+                json::object! {"media_id": fid, "pn": pn, "ps": 20, "platform": "web"},
+            )
+            .await?;
+            if pn == 1 {
+                let t = data["info"]["title"].as_str();
+                if let Some(t) = t {
+                    self.title = Some(String::from(t));
+                }
+            }
+            let mut list = parse_list_videos(&data["medias"]);
+            r.append(&mut list);
+            if !data["has_more"].as_bool().unwrap_or(false) {
+                break;
+            }
+            pn += 1;
+        }
+        Ok(r)
+    }
+
+    /// Get all videos in a collection.
+    /// * `mid` - Uploader's ID
+    /// * `sid` - Season ID
+    async fn get_collection(&mut self, mid: usize, sid: usize) -> Result<Vec<ListVideo>> {
+        let mut r: Vec<ListVideo> = Vec::new();
+        let mut pn = 1;
+        loop {
+            let data = self.get_api(
+                "https://api.bilibili.com/x/polymer/web-space/seasons_archives_list",
+                json::object! {"mid": mid, "season_id": sid, "page_num": pn, "page_size": 30, "sort_reverse": false},
+            )
+            .await?;
+            if pn == 1 {
+                let t = data["meta"]["name"].as_str();
+                if let Some(t) = t {
+                    self.title = Some(String::from(t));
+                }
+            }
+            let mut list = parse_list_videos(&data["archives"]);
+            let le = list.len();
+            r.append(&mut list);
+            let total = data["page"]["total"].as_usize();
+            if le == 0 || total.is_none() || pn * 30 >= total.unwrap() {
+                break;
+            }
+            pn += 1;
+        }
+        Ok(r)
+    }
+
+    /// Get all videos in a series.
+    /// * `mid` - Uploader's ID
+    /// * `sid` - Series ID
+    async fn get_series(&mut self, mid: usize, sid: usize) -> Result<Vec<ListVideo>> {
+        let meta = self.get_api(
+            "https://api.bilibili.com/x/series/series",
+            json::object! {"series_id": sid},
+        )
+        .await?;
+        let t = meta["meta"]["name"].as_str();
+        if let Some(t) = t {
+            self.title = Some(String::from(t));
+        }
+        let mut r: Vec<ListVideo> = Vec::new();
+        let mut pn = 1;
+        loop {
+            let data = self.get_api(
+                "https://api.bilibili.com/x/series/archives",
+                json::object! {"mid": mid, "series_id": sid, "pn": pn, "ps": 30, "sort": "asc"},
+            )
+            .await?;
+            let mut list = parse_list_videos(&data["archives"]);
+            let le = list.len();
+            r.append(&mut list);
+            let total = data["page"]["total"].as_usize();
+            if le == 0 || total.is_none() || pn * 30 >= total.unwrap() {
+                break;
+            }
+            pn += 1;
+        }
+        Ok(r)
+    }
+}
+
+#[async_trait]
+impl Provider for BiliListProvider {
+    fn new() -> BiliListProvider {
+        BiliListProvider {
+            base: BiliBaseProvider::new(),
+            url: None,
+            title: None,
+        }
+    }
+
+    fn add_custom_options(&self, opt: &mut OptStore) {
+        BiliNormalVideoProvider::new().add_custom_options(opt);
+    }
+
+    fn add_custom_settings(&self, store: &mut SettingStore) {
+        BiliNormalVideoProvider::new().add_custom_settings(store);
+    }
+
+    fn can_login(&self) -> bool {
+        true
+    }
+
+    async fn check_logined_async(&mut self) -> Result<bool> {
+        self.base.check_logined_async().await
+    }
+
+    async fn extract_async(&mut self, url: &str) -> Result<ExtractInfo> {
+        let list = self.get_list(url).await?;
+        let videos = list.iter().map(|v| (v.bvid.clone(), v.title.clone())).collect();
+        let jobs = get_jobs(self.base.opt.as_ref().unwrap(), self.base.se.as_ref().unwrap());
+        let results = extract_videos_by_id(&self.base, videos, jobs).await;
+        self.gen_extract_info(&list, results)
+    }
+
+    fn get_custom_options() -> Vec<OptDes> {
+        Vec::new()
+    }
+
+    fn get_custom_settings() -> Vec<SettingDes> {
+        Vec::new()
+    }
+
+    fn get_default_cookie_jar_name(&self) -> Option<&str> {
+        Some("bili")
+    }
+
+    fn has_custom_options(&self) -> bool {
+        true
+    }
+
+    fn has_custom_settings(&self) -> bool {
+        true
+    }
+
+    fn init(&mut self, jar: Option<&CookiesJar>, opt: OptStore, settings: SettingStore) -> Result<()> {
+        self.base.init(jar, opt, settings)
+    }
+
+    async fn login_async(&mut self, jar: &mut CookiesJar) -> Result<()> {
+        self.base.login_async(jar).await
+    }
+
+    fn logined(&self) -> bool {
+        self.base.logined()
+    }
+
+    fn match_url(url: &str) -> bool {
+        BiliListProvider::parse_url(url).is_some()
+    }
+
+    fn name() -> &'static str {
+        "BiliListProvider"
+    }
+
+    fn provider_name(&self) -> &'static str {
+        Self::name()
+    }
+}
+
+#[test]
+fn test_parse_url() {
+    assert_eq!(
+        Some(ListUrl::Favlist(1052622027)),
+        BiliListProvider::parse_url("https://space.bilibili.com/2/favlist?fid=1052622027&ftype=create")
+    );
+    assert_eq!(
+        Some(ListUrl::Favlist(1052622027)),
+        BiliListProvider::parse_url("https://www.bilibili.com/medialist/detail/ml1052622027")
+    );
+    assert_eq!(
+        Some(ListUrl::Collection(2, 358)),
+        BiliListProvider::parse_url("https://space.bilibili.com/2/channel/collectiondetail?sid=358")
+    );
+    assert_eq!(
+        Some(ListUrl::Series(2, 1000)),
+        BiliListProvider::parse_url("space.bilibili.com/2/channel/seriesdetail?sid=1000&ctype=0")
+    );
+    assert_eq!(
+        Some(ListUrl::Collection(2, 358)),
+        BiliListProvider::parse_url("https://space.bilibili.com/2/lists/358?type=season")
+    );
+    assert_eq!(None, BiliListProvider::parse_url("https://space.bilibili.com/2"));
+}
+
+#[test]
+fn test_list_metadata() {
+    let data = json::array![
+        {"type": 2, "bvid": "BV1xx411c7mD", "title": "a"},
+        {"type": 12, "bvid": "", "title": "audio"},
+        {"bvid": "BV1GJ411x7h7", "title": "b"},
+    ];
+    let l = parse_list_videos(&data);
+    assert_eq!(2, l.len());
+    assert_eq!("BV1GJ411x7h7", l[1].bvid);
+    let mut vi = VideoInfo::default();
+    set_list_metadata(&mut vi, &Some(String::from("Fav")), 2, 3);
+    assert_eq!(Some(String::from("Fav")), vi.meta.album);
+    assert_eq!(2, vi.meta.track.as_ref().unwrap().no());
+    assert_eq!(3, vi.meta.track.as_ref().unwrap().total());
+}
@@ -0,0 +1,535 @@
+extern crate chrono;
+extern crate futures;
+extern crate json;
+extern crate regex;
+
+use crate::cookies_json::CookiesJar;
+use crate::error::Error;
+use crate::error::Result;
+use crate::getopt::OptDes;
+use crate::getopt::OptStore;
+use crate::http_client::CookieClient;
+use crate::i18n::gettext;
+use crate::metadata::ExtractInfo;
+use crate::metadata::InfoType;
+use crate::metadata::LiveDanmakuRecorder;
+use crate::metadata::LiveInfo;
+use crate::metadata::LiveProtocol;
+use crate::metadata::LiveSource;
+use crate::metadata::LiveStatus;
+use crate::metadata::VideoInfo;
+use crate::metadata::VideoMetadata;
+use crate::metadata::VideoPlayInfoType;
+use crate::opt_list::get_live_record_options;
+use crate::opt_list::get_live_record_settings;
+use crate::providers::bilibili::api::get_api_async;
+use crate::providers::bilibili::base::BiliBaseProvider;
+use crate::providers::bilibili::danmaku::ass::AssOptions;
+use crate::providers::bilibili::danmaku::attach::enable_danmaku;
+use crate::providers::bilibili::danmaku::live::BiliLiveDanmakuRecorder;
+use crate::providers::bilibili::danmaku::live::LiveDanmakuServer;
+use crate::providers::bilibili::opt_list::get_bili_danmaku_options;
+use crate::providers::bilibili::opt_list::get_bili_danmaku_settings;
+use crate::providers::bilibili::opt_list::get_bili_live_options;
+use crate::providers::bilibili::opt_list::get_bili_live_settings;
+use crate::providers::provider_base::Provider;
+use crate::settings::SettingDes;
+use crate::settings::SettingStore;
+use crate::utils::number::ToUsize;
+use async_trait::async_trait;
+use chrono::TimeZone;
+use chrono::Utc;
+use json::JsonValue;
+use regex::Regex;
+use std::clone::Clone;
+
+lazy_static! {
+    static ref RE: Regex = Regex::new(r"(?i)^(?:https?://)?live\.bilibili\.com/(?:h5/|blanc/)?(?P<room>\d+)/?(?:[?#].*)?$").unwrap();
+}
+
+/// Parse live protocol from string.
+/// * `s` - `flv` or `hls`
+pub fn parse_live_protocol(s: &str) -> Option<LiveProtocol> {
+    match s.to_lowercase().as_str() {
+        "flv" => Some(LiveProtocol::Flv),
+        "hls" => Some(LiveProtocol::Hls),
+        _ => None,
+    }
+}
+
+/// Call a live API and return the `data` field.
+/// * `c` - HTTP client
+/// * `url` - API url
+/// * `param` - Query parameters
+async fn get_live_api(c: &mut CookieClient, url: &str, param: JsonValue) -> Result<JsonValue> {
+    let re = get_api_async(c, url, param).await?;
+    Ok(re["data"].clone())
+}
+
+/// Get stream urls from play information.
+/// * `playurl` - `playurl_info.playurl` from API (`https://api.live.bilibili.com/xlive/web-room/v2/index/getRoomPlayInfo`)
+/// * `prefer` - Preferred protocol. Other protocol is used if preferred protocol is not available.
+pub fn parse_live_urls(playurl: &JsonValue, prefer: LiveProtocol) -> Option<(LiveProtocol, Vec<String>)> {
+    let order = match prefer {
+        LiveProtocol::Flv => [LiveProtocol::Flv, LiveProtocol::Hls],
+        LiveProtocol::Hls => [LiveProtocol::Hls, LiveProtocol::Flv],
+    };
+    for protocol in order.iter() {
+        let (name, formats): (&str, &[&str]) = match protocol {
+            LiveProtocol::Flv => ("http_stream", &["flv"]),
+            LiveProtocol::Hls => ("http_hls", &["ts", "fmp4"]),
+        };
+        for stream in playurl["stream"].members() {
+            if stream["protocol_name"].as_str() != Some(name) {
+                continue;
+            }
+            for fname in formats.iter() {
+                for format in stream["format"].members() {
+                    if format["format_name"].as_str() != Some(fname) {
+                        continue;
+                    }
+                    let mut codec = None;
+                    for c in format["codec"].members() {
+                        if c["codec_name"].as_str() == Some("avc") {
+                            codec = Some(c);
+                            break;
+                        }
+                        if codec.is_none() {
+                            codec = Some(c);
+                        }
+                    }
+                    if codec.is_none() {
+                        continue;
+                    }
+                    let codec = codec.unwrap();
+                    let base_url = codec["base_url"].as_str();
+                    if base_url.is_none() {
+                        continue;
+                    }
+                    let mut urls: Vec<String> = Vec::new();
+                    for u in codec["url_info"].members() {
+                        let host = u["host"].as_str();
+                        if host.is_none() {
+                            continue;
+                        }
+                        urls.push(format!("{}{}{}", host.unwrap(), base_url.unwrap(), u["extra"].as_str().unwrap_or("")));
+                    }
+                    if !urls.is_empty() {
+                        return Some((*protocol, urls));
+                    }
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Generate metadata for a live room.
+/// * `room_id` - Room ID
+/// * `init` - Data from API (`https://api.live.bilibili.com/room/v1/Room/room_init`)
+/// * `info` - Data from API (`https://api.live.bilibili.com/room/v1/Room/get_info`)
+/// * `uname` - The name of streamer
+pub fn gen_live_metadata(room_id: u64, init: &JsonValue, info: &JsonValue, uname: &Option<String>) -> VideoMetadata {
+    let mut md = VideoMetadata::default();
+    let t = info["title"].as_str();
+    if let Some(t) = t {
+        md.title = Some(String::from(t));
+    }
+    if uname.is_some() {
+        md.author = uname.clone();
+    }
+    let des = info["description"].as_str();
+    if let Some(des) = des.filter(|&des| !des.is_empty()) {
+        md.description = Some(String::from(des));
+    }
+    let lt = init["live_time"].as_i64();
+    if let Some(lt) = lt.filter(|&lt| lt > 0) {
+        md.date = Utc.timestamp_opt(lt, 0).single();
+    }
+    md.video_id = Some(format!("live{}", room_id));
+    for k in ["parent_area_name", "area_name"].iter() {
+        let a = info[*k].as_str();
+        if let Some(a) = a.filter(|&a| !a.is_empty()) {
+            md.tags.push(String::from(a));
+        }
+    }
+    let tags = info["tags"].as_str();
+    if let Some(tags) = tags {
+        for t in tags.split(',') {
+            let t = t.trim();
+            if !t.is_empty() {
+                md.tags.push(String::from(t));
+            }
+        }
+    }
+    md.extra.insert(String::from("room_id"), format!("{}", room_id));
+    let uid = init["uid"].as_u64();
+    if let Some(uid) = uid {
+        md.extra.insert(String::from("uid"), format!("{}", uid));
+    }
+    md
+}
+
+/// Used to refresh stream urls and record danmaku when recording live.
+pub struct BiliLiveSource {
+    /// HTTP client
+    client: CookieClient,
+    /// Room ID
+    room_id: u64,
+    /// Quality number
+    qn: usize,
+    /// Preferred protocol
+    protocol: LiveProtocol,
+    /// Danmaku server and ASS options. None if danmaku is disabled.
+    danmaku: Option<(LiveDanmakuServer, AssOptions)>,
+}
+
+impl BiliLiveSource {
+    /// Get stream urls.
+    async fn get_urls(&mut self) -> Option<(LiveProtocol, Vec<String>)> {
+        let data = get_live_api(
+            &mut self.client,
+            "https://api.live.bilibili.com/xlive/web-room/v2/index/getRoomPlayInfo",
+            json::object! {"room_id": self.room_id, "protocol": "0,1", "format": "0,1,2", "codec": "0,1", "qn": self.qn, "platform": "web", "ptype": 8},
+        )
+        .await;
+        match data {
+            Ok(data) => parse_live_urls(&data["playurl_info"]["playurl"], self.protocol),
+            Err(e) => {
+                eprintln!("{}", e);
+                None
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl LiveSource for BiliLiveSource {
+    async fn get_status(&mut self) -> LiveStatus {
+        let init = get_live_api(
+            &mut self.client,
+            "https://api.live.bilibili.com/room/v1/Room/room_init",
+            json::object! {"id": self.room_id},
+        )
+        .await;
+        let init = match init {
+            Ok(init) => init,
+            Err(e) => {
+                eprintln!("{}", e);
+                return LiveStatus::Failed;
+            }
+        };
+        if init["live_status"].as_u64() != Some(1) {
+            return LiveStatus::Ended;
+        }
+        match self.get_urls().await {
+            Some((p, urls)) if p == self.protocol => LiveStatus::Living(urls),
+            _ => LiveStatus::Failed,
+        }
+    }
+
+    fn start_danmaku(&self) -> Option<Box<dyn LiveDanmakuRecorder>> {
+        let (server, ass) = self.danmaku.as_ref()?;
+        match BiliLiveDanmakuRecorder::start(server, ass) {
+            Some(r) => Some(Box::new(r)),
+            None => None,
+        }
+    }
+
+    fn box_clone(&self) -> Box<dyn LiveSource> {
+        Box::new(Self {
+            client: self.client.clone(),
+            room_id: self.room_id,
+            qn: self.qn,
+            protocol: self.protocol,
+            danmaku: self.danmaku.clone(),
+        })
+    }
+}
+
+pub struct BiliLiveProvider {
+    base: BiliBaseProvider,
+}
+
+impl BiliLiveProvider {
+    fn parse_url(url: &str) -> Option<u64> {
+        let caps = RE.captures(url);
+        caps?.name("room").unwrap().as_str().parse::<u64>().ok()
+    }
+
+    /// Return preferred protocol and quality number.
+    fn get_stream_preference(&self) -> Option<(LiveProtocol, usize)> {
+        let opt = self.base.opt.as_ref().unwrap();
+        let se = self.base.se.as_ref().unwrap();
+        let mut protocol = LiveProtocol::Flv;
+        let o = opt.get_option("live-protocol");
+        if let Some(o) = o {
+            let p = parse_live_protocol(o.as_str());
+            if p.is_none() {
+                eprintln!("{}", gettext("The value of option \"<key>\" is invalid.").replace("<key>", "live-protocol"));
+                return None;
+            }
+            protocol = p.unwrap();
+        } else {
+            let s = se.get_settings(self.provider_name(), "live-protocol");
+            if let Some(s) = s {
+                let p = parse_live_protocol(s.as_str().unwrap_or(""));
+                if let Some(p) = p {
+                    protocol = p;
+                }
+            }
+        }
+        let mut qn = 10000;
+        let o = opt.get_option("live-quality");
+        if let Some(o) = o {
+            let q = o.to_usize();
+            if q.is_none() {
+                eprintln!("{}", gettext("The value of option \"<key>\" is invalid.").replace("<key>", "live-quality"));
+                return None;
+            }
+            qn = q.unwrap();
+        } else {
+            let s = se.get_settings(self.provider_name(), "live-quality");
+            if let Some(s) = s {
+                qn = s.to_usize().unwrap_or(qn);
+            }
+        }
+        Some((protocol, qn))
+    }
+
+    /// Get the information of live danmaku server.
+    /// * `room_id` - Room ID
+    async fn get_danmaku_server(&mut self, room_id: u64) -> Result<LiveDanmakuServer> {
+        let c = self.base.client.as_mut().unwrap();
+        let data = get_live_api(
+            c,
+            "https://api.live.bilibili.com/xlive/web-room/v1/index/getDanmuInfo",
+            json::object! {"id": room_id, "type": 0},
+        )
+        .await?;
+        let mut hosts: Vec<(String, u16)> = Vec::new();
+        for h in data["host_list"].members() {
+            if let (Some(host), Some(port)) = (h["host"].as_str(), h["port"].as_u16()) {
+                hosts.push((String::from(host), port));
+            }
+        }
+        if hosts.is_empty() {
+            hosts.push((String::from("broadcastlv.chat.bilibili.com"), 2243));
+        }
+        let uid = match c.get_cookie("DedeUserID") {
+            Some(u) => u.parse::<u64>().unwrap_or(0),
+            None => 0,
+        };
+        Ok(LiveDanmakuServer {
+            hosts,
+            room_id,
+            uid,
+            token: String::from(data["token"].as_str().unwrap_or("")),
+            buvid: c.get_cookie("buvid3").unwrap_or(String::from("")),
+        })
+    }
+}
+
+#[async_trait]
+impl Provider for BiliLiveProvider {
+    fn new() -> BiliLiveProvider {
+        BiliLiveProvider {
+            base: BiliBaseProvider::new(),
+        }
+    }
+
+    fn add_custom_options(&self, opt: &mut OptStore) {
+        self.base.add_custom_options(opt);
+        opt.add("BiliDanmaku", get_bili_danmaku_options());
+        opt.add("LiveRecord", get_live_record_options());
+        opt.add(self.provider_name(), get_bili_live_options());
+    }
+
+    fn add_custom_settings(&self, store: &mut SettingStore) {
+        self.base.add_custom_settings(store);
+        store.add("BiliDanmaku", get_bili_danmaku_settings());
+        store.add("LiveRecord", get_live_record_settings());
+        store.add(self.provider_name(), get_bili_live_settings());
+    }
+
+    fn can_login(&self) -> bool {
+        true
+    }
+
+    async fn check_logined_async(&mut self) -> Result<bool> {
+        self.base.check_logined_async().await
+    }
+
+    async fn extract_async(&mut self, url: &str) -> Result<ExtractInfo> {
+        let room = match Self::parse_url(url) {
+            Some(room) => room,
+            None => return Err(Error::Extract(String::from(gettext("Unsupported url.")))),
+        };
+        let pref = self.get_stream_preference();
+        if pref.is_none() {
+            return Err(Error::Config(String::from(gettext("Stream preference is invalid."))));
+        }
+        let (protocol, qn) = pref.unwrap();
+        let c = self.base.client.as_mut().unwrap();
+        let init = get_live_api(
+            c,
+            "https://api.live.bilibili.com/room/v1/Room/room_init",
+            json::object! {"id": room},
+        )
+        .await?;
+        let room_id = init["room_id"].as_u64();
+        if room_id.is_none() {
+            return Err(Error::Extract(String::from(gettext("Can not find live room ID."))));
+        }
+        let room_id = room_id.unwrap();
+        if init["live_status"].as_u64() != Some(1) {
+            return Err(Error::Extract(String::from(gettext("The live room is not streaming now."))));
+        }
+        let info = get_live_api(
+            c,
+            "https://api.live.bilibili.com/room/v1/Room/get_info",
+            json::object! {"room_id": room_id},
+        )
+        .await?;
+        let anchor = get_live_api(
+            c,
+            "https://api.live.bilibili.com/live_user/v1/UserInfo/get_anchor_in_room",
+            json::object! {"roomid": room_id},
+        )
+        .await;
+        let uname = match &anchor {
+            Ok(a) => a["info"]["uname"].as_str().map(String::from),
+            Err(_) => None,
+        };
+        let opt = self.base.opt.as_ref().unwrap().clone();
+        let se = self.base.se.as_ref().unwrap().clone();
+        let mut danmaku = None;
+        if enable_danmaku(&opt, &se) {
+            let ass = AssOptions::from_opt(&opt, &se);
+            if ass.is_none() {
+                return Err(Error::Config(String::from(gettext("Danmaku options are invalid."))));
+            }
+            match self.get_danmaku_server(room_id).await {
+                Ok(server) => {
+                    danmaku = Some((server, ass.unwrap()));
+                }
+                Err(e) => {
+                    eprintln!("{}", e);
+                    eprintln!("{}", gettext("Can not get live danmaku server. Danmaku will not be recorded."));
+                }
+            }
+        }
+        let mut source = BiliLiveSource {
+            client: self.base.client.as_ref().unwrap().clone(),
+            room_id,
+            qn,
+            protocol,
+            danmaku,
+        };
+        let urls = source.get_urls().await;
+        if urls.is_none() {
+            return Err(Error::Extract(String::from(gettext("Can not find live stream."))));
+        }
+        let (protocol, urls) = urls.unwrap();
+        // Keep the protocol when refreshing urls.
+        source.protocol = protocol;
+        let mut vi = VideoInfo {
+            meta: gen_live_metadata(room_id, &init, &info, &uname),
+            typ: VideoPlayInfoType::Live,
+            live: Some(LiveInfo {
+                protocol,
+                urls,
+                source: Some(Box::new(source)),
+            }),
+            ..Default::default()
+        };
+        let cover = info["user_cover"].as_str();
+        if let Some(cover) = cover.filter(|&cover| !cover.is_empty()) {
+            vi.cover = Some(String::from(cover));
+        }
+        let mut h = BiliBaseProvider::default_headers();
+        let referer = format!("https://live.bilibili.com/{}", room_id);
+        h.insert("referer", referer.parse().unwrap());
+        vi.headers = Some(h);
+        vi.cookies = Some(self.base.client.as_ref().unwrap().get_cookie_jar().clone());
+        Ok(ExtractInfo {
+            typ: InfoType::Video,
+            video: Some(vi),
+            ..Default::default()
+        })
+    }
+
+    fn get_custom_options() -> Vec<OptDes> {
+        get_bili_live_options()
+    }
+
+    fn get_custom_settings() -> Vec<SettingDes> {
+        get_bili_live_settings()
+    }
+
+    fn get_default_cookie_jar_name(&self) -> Option<&str> {
+        Some("bili")
+    }
+
+    fn has_custom_options(&self) -> bool {
+        true
+    }
+
+    fn has_custom_settings(&self) -> bool {
+        true
+    }
+
+    fn init(&mut self, jar: Option<&CookiesJar>, opt: OptStore, settings: SettingStore) -> Result<()> {
+        self.base.init(jar, opt, settings)
+    }
+
+    async fn login_async(&mut self, jar: &mut CookiesJar) -> Result<()> {
+        self.base.login_async(jar).await
+    }
+
+    fn logined(&self) -> bool {
+        self.base.logined()
+    }
+
+    fn match_url(url: &str) -> bool {
+        BiliLiveProvider::parse_url(url).is_some()
+    }
+
+    fn name() -> &'static str {
+        "BiliLiveProvider"
+    }
+
+    fn provider_name(&self) -> &'static str {
+        Self::name()
+    }
+}
+
+#[test]
+fn test_parse_url() {
+    assert_eq!(Some(22637261), BiliLiveProvider::parse_url("https://live.bilibili.com/22637261?from=search"));
+    assert_eq!(Some(1), BiliLiveProvider::parse_url("live.bilibili.com/h5/1"));
+    assert_eq!(None, BiliLiveProvider::parse_url("https://www.bilibili.com/video/av170001"));
+    assert_eq!(Some(LiveProtocol::Hls), parse_live_protocol("HLS"));
+}
+
+#[test]
+fn test_parse_live_urls() {
+    let playurl = json::object! {
+        "stream": [
+            {"protocol_name": "http_stream", "format": [{"format_name": "flv", "codec": [
+                {"codec_name": "hevc", "base_url": "/h.flv?", "url_info": [{"host": "https://a.com", "extra": "x=1"}]},
+                {"codec_name": "avc", "base_url": "/a.flv?", "url_info": [{"host": "https://a.com", "extra": "x=1"}, {"host": "https://b.com", "extra": "x=2"}]}
+            ]}]},
+            {"protocol_name": "http_hls", "format": [{"format_name": "fmp4", "codec": [
+                {"codec_name": "avc", "base_url": "/a.m3u8?", "url_info": [{"host": "https://c.com", "extra": ""}]}
+            ]}]}
+        ]
+    };
+    let (p, urls) = parse_live_urls(&playurl, LiveProtocol::Flv).unwrap();
+    assert_eq!(LiveProtocol::Flv, p);
+    assert_eq!(vec!["https://a.com/a.flv?x=1", "https://b.com/a.flv?x=2"], urls);
+    let (p, urls) = parse_live_urls(&playurl, LiveProtocol::Hls).unwrap();
+    assert_eq!(LiveProtocol::Hls, p);
+    assert_eq!(vec!["https://c.com/a.m3u8?"], urls);
+    assert!(parse_live_urls(&json::object! {}, LiveProtocol::Flv).is_none());
+}
@@ -1,8 +1,22 @@
+pub mod api;
+pub mod audio;
+pub mod bangumi;
 pub mod base;
+pub mod danmaku;
 pub mod interaction;
+pub mod interaction_graph;
+pub mod list;
+pub mod live;
 pub mod normal_video;
 pub mod opt_list;
 pub mod parser;
 pub mod part;
 pub mod part_info;
+pub mod playinfo;
+pub mod quality;
+pub mod space;
+pub mod stein;
+pub mod subtitle;
 pub mod util;
+pub mod video_info;
+pub mod view_point;
@@ -1,33 +1,56 @@
 extern crate chrono;
-extern crate futures;
 extern crate json;
 extern crate regex;
 
 use crate::cookies_json::CookiesJar;
+use crate::error::Error;
+use crate::error::Result;
 use crate::getopt::OptDes;
 use crate::getopt::OptStore;
+use crate::http_client::CookieClient;
 use crate::i18n::gettext;
+use crate::metadata::ExtraFile;
 use crate::metadata::ExtractInfo;
+use crate::metadata::InfoType;
 use crate::metadata::NoInTotal;
+use crate::metadata::VideoInfo;
 use crate::metadata::VideoMetadata;
+use crate::providers::bilibili::api::get_api_async;
+use crate::providers::bilibili::api::MAX_PARTS_AT_SAME_TIME;
 use crate::providers::bilibili::base::BiliBaseProvider;
 use crate::providers::bilibili::interaction::InteractionVideoParser;
+use crate::providers::bilibili::interaction_graph::get_graph_formats;
+use crate::providers::bilibili::interaction_graph::InteractionGraph;
+use crate::providers::bilibili::opt_list::get_bili_danmaku_options;
+use crate::providers::bilibili::opt_list::get_bili_danmaku_settings;
 use crate::providers::bilibili::opt_list::get_bili_normal_video_options;
 use crate::providers::bilibili::opt_list::get_bili_normal_video_settings;
+use crate::providers::bilibili::opt_list::get_bili_subtitle_options;
+use crate::providers::bilibili::opt_list::get_bili_stream_options;
+use crate::providers::bilibili::opt_list::get_bili_stream_settings;
+use crate::providers::bilibili::opt_list::get_bili_subtitle_settings;
 use crate::providers::bilibili::parser::HTMLDataInJS;
+use crate::providers::bilibili::part::Part;
+use crate::providers::bilibili::part::PartList;
 use crate::providers::bilibili::part_info::PartInfoList;
+use crate::providers::bilibili::playinfo::StreamPreference;
 use crate::providers::bilibili::util;
+use crate::providers::bilibili::video_info::gen_part_video_info;
+use crate::providers::bilibili::video_info::PartSource;
 use crate::providers::provider_base::Provider;
 use crate::settings::SettingDes;
 use crate::settings::SettingStore;
+use async_trait::async_trait;
 use chrono::TimeZone;
 use chrono::Utc;
-use futures::executor::block_on;
+use futures::stream;
+use futures::StreamExt;
 use json::JsonValue;
 use regex::Regex;
 use std::clone::Clone;
 use std::collections::HashMap;
 use std::convert::TryFrom;
+use tokio::task::JoinError;
 
 lazy_static! {
     static ref RE: Regex = Regex::new(r"(?i)^(av(?P<av>\d+)|(?P<bv>bv[a-z0-9]{9,10}))$").unwrap();
@@ -89,49 +112,36 @@ pub struct BiliNormalVideoProvider {
     cidinfo: HashMap<usize, JsonValue>,
     /// Input Url Information (Set in [`basic_info`](#method.basic_info) function)
     url: Option<UrlInfo>,
+    /// Preferred streams
+    pref: StreamPreference,
+    /// The node/edge graph of interactive video
+    interaction_graph: Option<InteractionGraph>,
 }
 
 impl BiliNormalVideoProvider {
     /// Extract basic information
     /// * `url` - Input url
-    /// return true if `videoinfo` is ok
-    fn basic_info(&mut self, url: UrlInfo) -> bool {
+    async fn basic_info(&mut self, url: UrlInfo) -> Result<()> {
         const PLAYERINFO: &str = "window.__playinfo__";
         const INITIAL: &str = "window.__INITIAL_STATE__";
         self.url = Some(url.clone());
         let link = format!("https://www.bilibili.com/video/{}", url.bv);
         {
             let c = self.base.client.as_mut().unwrap();
-            let r = c.get(link);
-            match r {
-                Some(_) => {}
-                None => return false,
-            }
-            let r = r.unwrap();
-            if r.status().as_u16() >= 400 {
-                println!(
-                    "{}\"{}\"",
-                    gettext("Error when geting the webpage: "),
-                    r.status().as_str()
-                );
-                return false;
-            }
-            let t = block_on(r.text_with_charset("UTF-8"));
-            match t {
-                Ok(_) => {}
-                Err(e) => {
-                    println!("{}\"{}\"", gettext("Error when geting the webpage: "), e);
-                    return false;
-                }
-            }
-            let t = t.unwrap();
+            let t = c.get_text_async(link.as_str(), json::object! {}).await?;
             let mut js = HTMLDataInJS::new();
             if !js.parse(t.as_str(), vec![PLAYERINFO, INITIAL]) {
-                return false;
+                return Err(Error::Parse {
+                    source: link,
+                    msg: String::from(gettext("Can not find video information in the webpage.")),
+                });
             }
             let data = js.maps.get(INITIAL);
             if data.is_none() {
-                return false;
+                return Err(Error::Parse {
+                    source: link,
+                    msg: String::from(gettext("Can not find video information in the webpage.")),
+                });
             }
             let data = data.unwrap();
             let dat = &data[..data.len() - 122];
@@ -139,8 +149,10 @@ impl BiliNormalVideoProvider {
             match data {
                 Ok(_) => {}
                 Err(e) => {
-                    println!("{}\"{}\"", gettext("Can not parse as JSON: "), e);
-                    return false;
+                    return Err(Error::Parse {
+                        source: link,
+                        msg: format!("{}", e),
+                    });
                 }
             }
             self.videoinfo = Some(data.unwrap());
@@ -153,66 +165,38 @@ impl BiliNormalVideoProvider {
                         self.playinfo = Some(pinfo);
                     }
                     Err(e) => {
-                        println!("{}\"{}\"", gettext("Can not parse as JSON: "), e);
+                        eprintln!("{}\"{}\"", gettext("Can not parse as JSON: "), e);
                     }
                 }
             }
             let pages = &self.videoinfo.as_ref().unwrap()["videoData"]["pages"];
-            let pl = PartInfoList::try_from(pages);
-            if pl.is_err() {
-                let re = c.get_with_param(
-                    "https://api.bilibili.com/x/player/pagelist",
-                    json::object! {"bvid": url.bv.clone(), "jsonp": "jsonp"},
-                );
-                if re.is_none() {
-                    println!("{}", gettext("Can not get page list."));
-                    return false;
-                }
-                let re = re.unwrap();
-                if re.status().as_u16() >= 400 {
-                    println!("{}\n{}", gettext("Can not get page list."), re.status());
-                    return false;
-                }
-                let t = block_on(re.text_with_charset("UTF-8"));
-                if t.is_err() {
-                    println!("{}", t.unwrap_err());
-                    return false;
-                }
-                let t = t.unwrap();
-                let pages = json::parse(t.as_str());
-                if pages.is_err() {
-                    println!("{}", pages.unwrap_err());
-                    return false;
-                }
-                let pages = pages.unwrap();
-                let code = pages["code"].as_i64().unwrap();
-                if code != 0 {
-                    println!("{} {}", code, pages["message"].as_str().unwrap());
-                    return false;
-                }
-                let pages = &pages["data"];
-                let pl = PartInfoList::try_from(pages);
-                if pl.is_err() {
-                    println!("{}", pl.unwrap_err());
-                    return false;
+            let pl = match PartInfoList::try_from(pages) {
+                Ok(pl) => pl,
+                Err(_) => {
+                    let api = "https://api.bilibili.com/x/player/pagelist";
+                    let pages = get_api_async(c, api, json::object! {"bvid": url.bv.clone(), "jsonp": "jsonp"}).await?;
+                    match PartInfoList::try_from(&pages["data"]) {
+                        Ok(pl) => pl,
+                        Err(e) => {
+                            return Err(Error::Parse {
+                                source: String::from(api),
+                                msg: String::from(e),
+                            })
+                        }
+                    }
                 }
-                self.partinfo = Some(pl.unwrap());
-            } else {
-                self.partinfo = Some(pl.unwrap());
-            }
+            };
+            self.partinfo = Some(pl);
         }
         let fcid = self.partinfo.as_ref().unwrap().first_cid();
         if fcid.is_none() {
-            println!("{}", gettext("Can not find CID."));
-            return false;
+            return Err(Error::Extract(String::from(gettext("Can not find CID."))));
         }
         let fcid = fcid.unwrap();
-        if !self.get_cid_info(fcid) {
-            return false;
-        }
+        self.get_cid_info(fcid).await?;
         let interaction = self.is_interaction_video();
         if interaction.is_none() {
-            return false;
+            return Err(Error::Extract(String::from(gettext("Can not find CID."))));
         }
         let interaction = interaction.unwrap();
         if interaction {
@@ -230,54 +214,170 @@ impl BiliNormalVideoProvider {
                 self.base.opt.clone(),
                 self.base.se.clone(),
             );
-            if !parser.parse(self.base.client.as_mut().unwrap()) {
-                return false;
-            }
+            parser.parse(self.base.client.as_mut().unwrap()).await?;
             self.partinfo = Some(parser.part_list);
+            self.interaction_graph = Some(parser.graph);
         }
-        true
+        Ok(())
+    }
+
+    /// Get cid info from API (`https://api.bilibili.com/x/player/v2`)
+    /// * `c` - HTTP client
+    /// * `url` - Input Url Information
+    /// * `cid` - CID
+    async fn fetch_cid_info(c: &mut CookieClient, url: &UrlInfo, cid: usize) -> Result<JsonValue> {
+        let re = get_api_async(
+            c,
+            "https://api.bilibili.com/x/player/v2",
+            json::object! {"aid": url.av, "bvid": url.bv.clone(), "cid": cid},
+        )
+        .await?;
+        Ok(re["data"].clone())
     }
 
     /// Get cid info from API (`https://api.bilibili.com/x/player/v2`) and write to [`cidinfo`](#structfield.cidinfo) if success
     /// * cid - CID
-    ///
-    /// Return true if successed.
-    fn get_cid_info(&mut self, cid: usize) -> bool {
+    async fn get_cid_info(&mut self, cid: usize) -> Result<()> {
         let c = self.base.client.as_mut().unwrap();
-        let url = self.url.as_ref().unwrap().clone();
-        let r = c.get_with_param(
-            "https://api.bilibili.com/x/player/v2",
-            json::object! {"aid": url.av, "bvid": url.bv, "cid": cid},
-        );
-        if r.is_none() {
-            println!("{}", gettext("Can not get part info."));
-            return false;
-        }
-        let r = r.unwrap();
-        if r.status().as_u16() > 400 {
-            println!("{}\n{}", gettext("Can not get part info."), r.status());
-            return false;
-        }
-        let t = block_on(r.text_with_charset("UTF-8"));
-        if t.is_err() {
-            println!("{}", t.unwrap_err());
-            return false;
-        }
-        let t = t.unwrap();
-        let re = json::parse(t.as_str());
-        if re.is_err() {
-            println!("{}", re.unwrap_err());
-            return false;
-        }
-        let re = re.unwrap();
-        let code = re["code"].as_i64().unwrap();
-        if code != 0 {
-            println!("{} {}", code, re["message"].as_str().unwrap());
-            return false;
-        }
-        let data = &re["data"];
-        self.cidinfo.insert(cid, data.clone());
-        true
+        let re = Self::fetch_cid_info(c, self.url.as_ref().unwrap(), cid).await?;
+        self.cidinfo.insert(cid, re);
+        Ok(())
+    }
+
+    /// Return cid info of a part. Use the one in [`cidinfo`](#structfield.cidinfo) if exists.
+    /// * `c` - HTTP client
+    /// * `cid` - CID
+    async fn get_part_cid_info(&self, c: &mut CookieClient, cid: usize) -> Result<JsonValue> {
+        match self.cidinfo.get(&cid) {
+            Some(info) => Ok(info.clone()),
+            None => Self::fetch_cid_info(c, self.url.as_ref().unwrap(), cid).await,
+        }
+    }
+
+    /// Return play url information of a part.
+    /// * `c` - HTTP client
+    /// * `cid` - CID
+    ///
+    /// The information extracted from HTML (`window.__playinfo__`) only belongs to first part,
+    /// other parts will get it from API (`https://api.bilibili.com/x/player/playurl`).
+    async fn get_part_playinfo(&self, c: &mut CookieClient, cid: usize) -> Result<JsonValue> {
+        let fcid = self.partinfo.as_ref().unwrap().first_cid();
+        if fcid == Some(cid) {
+            if let Some(playinfo) = &self.playinfo {
+                let data = &playinfo["data"];
+                if data["dash"].is_object() || data["durl"].is_array() {
+                    return Ok(data.clone());
+                }
+            }
+        }
+        let qn = self.pref.first_quality();
+        let url = self.url.as_ref().unwrap();
+        let re = get_api_async(
+            c,
+            "https://api.bilibili.com/x/player/playurl",
+            json::object! {"avid": url.av, "bvid": url.bv.clone(), "cid": cid, "qn": qn, "fnval": 4048, "fnver": 0, "fourk": 1},
+        )
+        .await?;
+        Ok(re["data"].clone())
+    }
+
+    /// Generate video information of a part.
+    /// * `p` - Part number
+    ///
+    /// Every part uses its own HTTP client, so several parts can be generated at the same time.
+    async fn gen_video_info(&self, p: usize) -> Result<VideoInfo> {
+        let mut c = self.base.client.as_ref().unwrap().clone();
+        let m = self.gen_video_metadata(Some(p));
+        if m.is_none() {
+            return Err(Error::Extract(String::from(gettext("Can not generate video metadata."))));
+        }
+        let m = m.unwrap();
+        let part = &self.partinfo.as_ref().unwrap()[p - 1];
+        let cidinfo = self.get_part_cid_info(&mut c, part.cid).await;
+        let data = self.get_part_playinfo(&mut c, part.cid).await?;
+        let cover = self.videoinfo.as_ref().unwrap()["videoData"]["pic"]
+            .as_str()
+            .map(|pic| {
+                if pic.starts_with("//") {
+                    format!("https:{}", pic)
+                } else {
+                    String::from(pic)
+                }
+            });
+        let url = self.url.as_ref().unwrap();
+        let source = PartSource {
+            aid: url.av as u64,
+            cid: part.cid,
+            duration: part.duration,
+            cover,
+            referer: format!("https://www.bilibili.com/video/{}", url.bv),
+        };
+        gen_part_video_info(&mut c, &self.base, &self.pref, &source, m, cidinfo, &data).await
+    }
+
+    /// Return the part list which is selected by user.
+    ///
+    /// Priority: `part` option > part number in URL > `part` setting.
+    /// If nothing specified, return a list which contains all parts.
+    /// Return an error if the `part` option is invalid.
+    fn get_part_list(&self) -> Result<PartList> {
+        let o = self.base.opt.as_ref().unwrap().get_option("part");
+        if let Some(o) = o {
+            let r = PartList::parse_from_str(o.as_str());
+            if r.is_none() {
+                let s = gettext("The value of option \"<key>\" is invalid.").replace("<key>", "part");
+                return Err(Error::Config(s));
+            }
+            return Ok(r.unwrap());
+        }
+        let p = self.url.as_ref().unwrap().part;
+        if let Some(p) = p {
+            if p == 0 {
+                return Err(self.part_out_of_range(p));
+            }
+            let pa = Part::new(p, p);
+            if let Some(pa) = pa {
+                return Ok(PartList::from(vec![pa]));
+            }
+        }
+        let se = self
+            .base
+            .se
+            .as_ref()
+            .unwrap()
+            .get_settings("BiliNormalVideoProvider", "part");
+        if let Some(se) = &se {
+            let r = PartList::parse_from_json(se);
+            if let Some(r) = r {
+                return Ok(r);
+            }
+        }
+        Ok(PartList::from(vec![Part::new(0, 0).unwrap()]))
+    }
+
+    /// Return the part numbers which need to be downloaded.
+    fn get_selected_parts(&self) -> Result<Vec<usize>> {
+        let pl = self.get_part_list()?;
+        let le = self.partinfo.as_ref().unwrap().list.len();
+        match pl.to_part_numbers(le) {
+            Ok(r) => {
+                if r.is_empty() {
+                    return Err(Error::Config(String::from(gettext("No part is selected."))));
+                }
+                Ok(r)
+            }
+            Err(p) => Err(self.part_out_of_range(p)),
+        }
+    }
+
+    /// Generate the error returned when a part number is out of range.
+    /// * `p` - Part number or part
+    fn part_out_of_range<T: std::fmt::Display>(&self, p: T) -> Error {
+        let le = self.partinfo.as_ref().unwrap().list.len();
+        let s = gettext("Part <part> is out of range. This video only have <total> parts.")
+            .replace("<part>", format!("{}", p).as_str())
+            .replace("<total>", format!("{}", le).as_str());
+        Error::Config(s)
     }
 
     /// Generate video metadata.
@@ -288,7 +388,6 @@ impl BiliNormalVideoProvider {
             return None;
         }
         let vi = self.videoinfo.as_ref().unwrap();
-        println!("{}", vi.pretty(2));
         let c = self.part_count();
         let n = match p {
             Some(p) => p,
@@ -352,7 +451,6 @@ impl BiliNormalVideoProvider {
             md.extra
                 .insert(String::from("aid"), format!("AV{}", aid.unwrap()));
         }
-        println!("{:?}", md);
         Some(md)
     }
 
@@ -393,7 +491,7 @@ impl BiliNormalVideoProvider {
                             return Some(UrlInfo::from_av(av, None));
                         }
                         Err(_) => {
-                            println!("{}", gettext("AV number is too big."));
+                            eprintln!("{}", gettext("AV number is too big."));
                             return None;
                         }
                     }
@@ -425,7 +523,7 @@ impl BiliNormalVideoProvider {
                                 return Some(UrlInfo::from_av(av, p));
                             }
                             Err(_) => {
-                                println!("{}", gettext("AV number is too big."));
+                                eprintln!("{}", gettext("AV number is too big."));
                                 return None;
                             }
                         }
@@ -451,6 +549,73 @@ impl BiliNormalVideoProvider {
     }
 }
 
+/// Create a new [`BiliNormalVideoProvider`] which shares the cookies, options and settings of `base`.
+/// * `base` - An initialized base provider
+fn new_provider_from_base(base: &BiliBaseProvider) -> Result<BiliNormalVideoProvider> {
+    let mut p = BiliNormalVideoProvider::new();
+    let jar = base.client.as_ref().unwrap().get_cookie_jar().clone();
+    p.init(
+        Some(&jar),
+        base.opt.as_ref().unwrap().clone(),
+        base.se.as_ref().unwrap().clone(),
+    )?;
+    Ok(p)
+}
+
+/// Convert extracted information to a list of videos.
+/// * `e` - Extracted information
+fn extract_info_to_videos(e: ExtractInfo) -> Vec<VideoInfo> {
+    match e.typ {
+        InfoType::Video => vec![e.video.unwrap()],
+        InfoType::VideoList => e.videos.unwrap(),
+    }
+}
+
+/// Print the progress of extracting videos.
+/// * `index` - Index of the video (starts from 0)
+/// * `total` - Total count of videos
+/// * `title` - Title of the video
+pub fn print_extract_progress(index: usize, total: usize, title: &str) {
+    let s = gettext("Extracting video <index>/<total>: <title>")
+        .replace("<index>", format!("{}", index + 1).as_str())
+        .replace("<total>", format!("{}", total).as_str())
+        .replace("<title>", title);
+    eprintln!("{}", s);
+}
+
+/// Extract several videos at the same time. Every video is extracted through a new [`BiliNormalVideoProvider`] which shares the cookies, options and settings of `base`.
+/// * `base` - An initialized base provider
+/// * `videos` - AV number or BV number and title of videos
+/// * `jobs` - The maximum number of videos extracted at the same time
+///
+/// Return all parts of every video in the same order as `videos`.
+pub async fn extract_videos_by_id(
+    base: &BiliBaseProvider,
+    videos: Vec<(String, String)>,
+    jobs: usize,
+) -> Vec<Result<Vec<VideoInfo>>> {
+    let total = videos.len();
+    let tasks = videos.into_iter().enumerate().map(|(i, (id, title))| {
+        print_extract_progress(i, total, title.as_str());
+        let p = new_provider_from_base(base);
+        tokio::spawn(async move {
+            let mut p = p?;
+            Ok(extract_info_to_videos(p.extract_async(id.as_str()).await?))
+        })
+    });
+    let re: Vec<Result<Result<Vec<VideoInfo>>, JoinError>> =
+        stream::iter(tasks).buffered(jobs).collect().await;
+    let mut r = Vec::new();
+    for i in re {
+        match i {
+            Ok(v) => r.push(v),
+            Err(e) => r.push(Err(Error::Extract(format!("{}", e)))),
+        }
+    }
+    r
+}
+
+#[async_trait]
 impl Provider for BiliNormalVideoProvider {
     fn new() -> BiliNormalVideoProvider {
         BiliNormalVideoProvider {
@@ -460,16 +625,24 @@ impl Provider for BiliNormalVideoProvider {
             partinfo: None,
             cidinfo: HashMap::new(),
             url: None,
+            pref: StreamPreference::new(),
+            interaction_graph: None,
         }
     }
 
     fn add_custom_options(&self, opt: &mut OptStore) {
         self.base.add_custom_options(opt);
+        opt.add("BiliDanmaku", get_bili_danmaku_options());
+        opt.add("BiliSubtitle", get_bili_subtitle_options());
+        opt.add("BiliStream", get_bili_stream_options());
         opt.add(self.provider_name(), get_bili_normal_video_options());
     }
 
     fn add_custom_settings(&self, store: &mut SettingStore) {
         self.base.add_custom_settings(store);
+        store.add("BiliDanmaku", get_bili_danmaku_settings());
+        store.add("BiliSubtitle", get_bili_subtitle_settings());
+        store.add("BiliStream", get_bili_stream_settings());
         store.add(self.provider_name(), get_bili_normal_video_settings());
     }
 
@@ -477,20 +650,47 @@ impl Provider for BiliNormalVideoProvider {
         true
     }
 
-    fn check_logined(&mut self) -> Option<bool> {
-        self.base.check_logined()
+    async fn check_logined_async(&mut self) -> Result<bool> {
+        self.base.check_logined_async().await
     }
 
-    fn extract(&mut self, url: &str) -> Option<ExtractInfo> {
-        let u = Self::parse_url(url).unwrap();
-        if !self.basic_info(u) {
-            return None;
-        }
-        let re = self.gen_video_metadata(None);
-        if re.is_none() {
-            return None;
+    async fn extract_async(&mut self, url: &str) -> Result<ExtractInfo> {
+        let u = match Self::parse_url(url) {
+            Some(u) => u,
+            None => return Err(Error::Extract(String::from(gettext("Unsupported url.")))),
+        };
+        let graph_formats = get_graph_formats(self.base.opt.as_ref(), self.base.se.as_ref());
+        if graph_formats.is_none() {
+            let s = gettext("The value of option \"<key>\" is invalid.").replace("<key>", "interaction-graph");
+            return Err(Error::Config(s));
+        }
+        let graph_formats = graph_formats.unwrap();
+        self.basic_info(u).await?;
+        let parts = self.get_selected_parts()?;
+        let this = &*self;
+        let re: Vec<Result<VideoInfo>> = stream::iter(parts.into_iter().map(|p| this.gen_video_info(p)))
+            .buffered(MAX_PARTS_AT_SAME_TIME)
+            .collect()
+            .await;
+        let mut list: Vec<VideoInfo> = re.into_iter().collect::<Result<_>>()?;
+        // The graph is shared by all parts, so it is only saved with the first part.
+        if let (Some(g), Some(first)) = (self.interaction_graph.as_ref(), list.first_mut()) {
+            for f in graph_formats.iter() {
+                first.extra_files.push(ExtraFile::new(f.suffix(), g.export(*f).into_bytes()));
+            }
         }
-        None
+        if list.len() == 1 {
+            return Ok(ExtractInfo {
+                typ: InfoType::Video,
+                video: Some(list.remove(0)),
+                ..Default::default()
+            });
+        }
+        Ok(ExtractInfo {
+            typ: InfoType::VideoList,
+            videos: Some(list),
+            ..Default::default()
+        })
     }
 
     fn get_custom_options() -> Vec<OptDes> {
@@ -513,12 +713,17 @@ impl Provider for BiliNormalVideoProvider {
         true
     }
 
-    fn init(&mut self, jar: Option<&CookiesJar>, opt: OptStore, settings: SettingStore) -> bool {
-        self.base.init(jar, opt, settings)
+    fn init(&mut self, jar: Option<&CookiesJar>, opt: OptStore, settings: SettingStore) -> Result<()> {
+        self.base.init(jar, opt, settings)?;
+        self.pref = StreamPreference::from_opt(
+            self.base.opt.as_ref().unwrap(),
+            self.base.se.as_ref().unwrap(),
+        )?;
+        Ok(())
     }
 
-    fn login(&mut self, jar: &mut CookiesJar) -> bool {
-        self.base.login(jar)
+    async fn login_async(&mut self, jar: &mut CookiesJar) -> Result<()> {
+        self.base.login_async(jar).await
     }
 
     fn logined(&self) -> bool {
@@ -534,9 +739,13 @@ impl Provider for BiliNormalVideoProvider {
         }
     }
 
-    fn provider_name(&self) -> &'static str {
+    fn name() -> &'static str {
         "BiliNormalVideoProvider"
     }
+
+    fn provider_name(&self) -> &'static str {
+        Self::name()
+    }
 }
 
 #[test]
@@ -591,3 +800,21 @@ fn test_parse_url() {
         BiliNormalVideoProvider::parse_url("https://b23.tv/av170001?test3&p=2&d")
     );
 }
+
+#[test]
+fn test_part_zero_in_url() {
+    let mut p = BiliNormalVideoProvider::new();
+    p.base.opt = Some(OptStore::default());
+    p.base.se = Some(SettingStore::new());
+    p.url = BiliNormalVideoProvider::parse_url("https://www.bilibili.com/video/av170001?p=0");
+    p.partinfo = Some(PartInfoList::from(vec![
+        crate::providers::bilibili::part_info::PartInfo::new(1, 1, "a"),
+        crate::providers::bilibili::part_info::PartInfo::new(2, 2, "b"),
+    ]));
+    match p.get_selected_parts() {
+        Err(Error::Config(_)) => {}
+        r => panic!("unexpected result: {:?}", r),
+    }
+    p.url = BiliNormalVideoProvider::parse_url("https://www.bilibili.com/video/av170001?p=2");
+    assert_eq!(vec![2], p.get_selected_parts().unwrap());
+}
@@ -2,9 +2,20 @@ extern crate json;
 
 use crate::getopt::OptDes;
 use crate::i18n::gettext;
+use crate::providers::bilibili::danmaku::ass::parse_duration;
+use crate::providers::bilibili::danmaku::ass::parse_opacity;
+use crate::providers::bilibili::interaction_graph::GraphFormat;
+use crate::providers::bilibili::live::parse_live_protocol;
 use crate::providers::bilibili::part::PartList;
+use crate::providers::bilibili::quality::parse_audio_quality_list_from_json;
+use crate::providers::bilibili::quality::parse_quality_list_from_json;
+use crate::providers::bilibili::quality::parse_video_codec_list_from_json;
+use crate::providers::bilibili::space::SpaceOrder;
+use crate::providers::bilibili::subtitle::parse_lang_list_from_json;
+use crate::providers::bilibili::subtitle::SubtitleFormat;
 use crate::settings::JsonValueType;
 use crate::settings::SettingDes;
+use crate::utils::number::ToUsize;
 use json::JsonValue;
 
 fn check_part(value: &JsonValue) -> bool {
@@ -15,10 +26,114 @@ fn check_part(value: &JsonValue) -> bool {
     }
 }
 
+fn check_quality(value: &JsonValue) -> bool {
+    parse_quality_list_from_json(value).is_some()
+}
+
+fn check_video_codec(value: &JsonValue) -> bool {
+    parse_video_codec_list_from_json(value).is_some()
+}
+
+fn check_audio_quality(value: &JsonValue) -> bool {
+    parse_audio_quality_list_from_json(value).is_some()
+}
+
+fn check_interaction_graph(value: &JsonValue) -> bool {
+    match value.as_str() {
+        Some(s) => GraphFormat::parse_list(s).is_some(),
+        None => false,
+    }
+}
+
+fn check_danmaku_font_size(value: &JsonValue) -> bool {
+    match value.to_usize() {
+        Some(s) => s > 0,
+        None => false,
+    }
+}
+
+fn check_danmaku_opacity(value: &JsonValue) -> bool {
+    parse_opacity(value).is_some()
+}
+
+fn check_danmaku_duration(value: &JsonValue) -> bool {
+    parse_duration(value).is_some()
+}
+
+fn check_sub_lang(value: &JsonValue) -> bool {
+    parse_lang_list_from_json(value).is_some()
+}
+
+fn check_sub_format(value: &JsonValue) -> bool {
+    match value.as_str() {
+        Some(s) => SubtitleFormat::parse(s).is_some(),
+        None => false,
+    }
+}
+
+pub fn get_bili_danmaku_options() -> Vec<OptDes> {
+    vec![
+        OptDes::new("danmaku", None, gettext("Whether to download danmaku. Raw danmaku (XML and protobuf) will be saved and converted to ASS subtitle."), true, true, Some("boolean")).unwrap(),
+        OptDes::new("danmaku-font", None, gettext("The font used in danmaku ASS subtitle. Default: sans-serif"), true, true, Some("font")).unwrap(),
+        OptDes::new("danmaku-font-size", None, gettext("The font size of normal danmaku when video height is 1080. Default: 48"), true, true, Some("size")).unwrap(),
+        OptDes::new("danmaku-opacity", None, gettext("The opacity of danmaku. 0 is transparent, 1 is opaque. Default: 0.8"), true, true, Some("opacity")).unwrap(),
+        OptDes::new("danmaku-scroll-duration", None, gettext("The duration of scrolling danmaku in seconds. Default: 8"), true, true, Some("seconds")).unwrap(),
+        OptDes::new("danmaku-fixed-duration", None, gettext("The duration of top and bottom danmaku in seconds. Default: 4"), true, true, Some("seconds")).unwrap(),
+        OptDes::new("danmaku-avoid-collision", None, gettext("Whether to drop danmaku which will overlap other danmaku. Default: true"), true, true, Some("boolean")).unwrap(),
+    ]
+}
+
+pub fn get_bili_danmaku_settings() -> Vec<SettingDes> {
+    vec![
+        SettingDes::new("danmaku", gettext("Whether to download danmaku. Raw danmaku (XML and protobuf) will be saved and converted to ASS subtitle."), JsonValueType::Boolean, None).unwrap(),
+        SettingDes::new("danmaku-font", gettext("The font used in danmaku ASS subtitle. Default: sans-serif"), JsonValueType::Str, None).unwrap(),
+        SettingDes::new("danmaku-font-size", gettext("The font size of normal danmaku when video height is 1080. Default: 48"), JsonValueType::Multiple, Some(check_danmaku_font_size)).unwrap(),
+        SettingDes::new("danmaku-opacity", gettext("The opacity of danmaku. 0 is transparent, 1 is opaque. Default: 0.8"), JsonValueType::Multiple, Some(check_danmaku_opacity)).unwrap(),
+        SettingDes::new("danmaku-scroll-duration", gettext("The duration of scrolling danmaku in seconds. Default: 8"), JsonValueType::Multiple, Some(check_danmaku_duration)).unwrap(),
+        SettingDes::new("danmaku-fixed-duration", gettext("The duration of top and bottom danmaku in seconds. Default: 4"), JsonValueType::Multiple, Some(check_danmaku_duration)).unwrap(),
+        SettingDes::new("danmaku-avoid-collision", gettext("Whether to drop danmaku which will overlap other danmaku. Default: true"), JsonValueType::Boolean, None).unwrap(),
+    ]
+}
+
+pub fn get_bili_subtitle_options() -> Vec<OptDes> {
+    vec![
+        OptDes::new("subtitles", None, gettext("Whether to download CC subtitles."), true, true, Some("boolean")).unwrap(),
+        OptDes::new("sub-lang", None, gettext("The languages of CC subtitles which will be downloaded, separated by comma. Such as zh-CN,en-US. \"all\" means all languages. Implies --subtitles. Default: all"), true, true, Some("languages")).unwrap(),
+        OptDes::new("sub-format", None, gettext("The format of CC subtitles. Available value: srt, vtt, ass. Default: srt"), true, true, Some("format")).unwrap(),
+    ]
+}
+
+pub fn get_bili_subtitle_settings() -> Vec<SettingDes> {
+    vec![
+        SettingDes::new("subtitles", gettext("Whether to download CC subtitles."), JsonValueType::Boolean, None).unwrap(),
+        SettingDes::new("sub-lang", gettext("The languages of CC subtitles which will be downloaded. Such as [\"zh-CN\", \"en-US\"]. \"all\" means all languages. Default: all"), JsonValueType::Multiple, Some(check_sub_lang)).unwrap(),
+        SettingDes::new("sub-format", gettext("The format of CC subtitles. Available value: srt, vtt, ass. Default: srt"), JsonValueType::Str, Some(check_sub_format)).unwrap(),
+    ]
+}
+
+pub fn get_bili_stream_options() -> Vec<OptDes> {
+    vec![
+        OptDes::new("quality", Some("q"), gettext("The preferred video quality list. Available value: 8K, DOLBY, HDR, 4K, 1080P60, 1080P+, 1080P, 720P60, 720P, 480P, 360P, 240P or quality ID. Such as 1080P+,1080P."), true, true, Some("quality")).unwrap(),
+        OptDes::new("video-codec", None, gettext("The preferred video codec list. Available value: AVC, HEVC, AV1."), true, true, Some("codec")).unwrap(),
+        OptDes::new("audio-quality", None, gettext("The preferred audio quality list. Available value: HIRES, DOLBY, 192K, 132K, 64K or quality ID."), true, true, Some("quality")).unwrap(),
+    ]
+}
+
+pub fn get_bili_stream_settings() -> Vec<SettingDes> {
+    vec![
+        SettingDes::new("quality", gettext("The preferred video quality list. Available value: 8K, DOLBY, HDR, 4K, 1080P60, 1080P+, 1080P, 720P60, 720P, 480P, 360P, 240P or quality ID. Such as \"1080P+,1080P\" or [112, 80]."), JsonValueType::Multiple, Some(check_quality)).unwrap(),
+        SettingDes::new("video-codec", gettext("The preferred video codec list. Available value: AVC, HEVC, AV1."), JsonValueType::Multiple, Some(check_video_codec)).unwrap(),
+        SettingDes::new("audio-quality", gettext("The preferred audio quality list. Available value: HIRES, DOLBY, 192K, 132K, 64K or quality ID."), JsonValueType::Multiple, Some(check_audio_quality)).unwrap(),
+    ]
+}
+
 pub fn get_bili_normal_video_options() -> Vec<OptDes> {
     vec![
         OptDes::new("part", Some("p"), gettext("The video part number of a page."), true, true, Some("part number")).unwrap(),
         OptDes::new("no-use-storylist", None, gettext("Don't use story list when parsing interactive video. (Warning: This will slow the speed.)"), false, false, None).unwrap(),
+        OptDes::new("interaction-max-depth", None, gettext("The maximum count of choices made when exploring interactive video. Default: 100"), true, true, Some("count")).unwrap(),
+        OptDes::new("interaction-max-states", None, gettext("The maximum count of story states explored in interactive video. Default: 10000"), true, true, Some("count")).unwrap(),
+        OptDes::new("interaction-graph", None, gettext("Export the node graph of interactive video. Available value: json, dot. Such as json,dot. (Warning: This will slow the speed.)"), true, true, Some("format")).unwrap(),
     ]
 }
 
@@ -26,5 +141,79 @@ pub fn get_bili_normal_video_settings() -> Vec<SettingDes> {
     vec![
         SettingDes::new("part", gettext("The video part number of a page.\nExample: \n2\tSelect part 2\n\"2-34\"\tSelect from part 2 to part 34.\n\"3, 5-10\" or [3, \"5-10\"]\tSelect part 3 and from part 5 to part 10.\n\"3-\"\tSelect from part 3 to last part.\n\"-10\"\tSelect from first part to part 10.\n\"-\"\tSelect all parts."), JsonValueType::Multiple, Some(check_part)).unwrap(),
         SettingDes::new("no-use-storylist", gettext("Don't use story list when parsing interactive video. (Warning: This will slow the speed.)"), JsonValueType::Boolean, None).unwrap(),
+        SettingDes::new("interaction-max-depth", gettext("The maximum count of choices made when exploring interactive video. Default: 100"), JsonValueType::Multiple, Some(check_max_count)).unwrap(),
+        SettingDes::new("interaction-max-states", gettext("The maximum count of story states explored in interactive video. Default: 10000"), JsonValueType::Multiple, Some(check_max_count)).unwrap(),
+        SettingDes::new("interaction-graph", gettext("Export the node graph of interactive video. Available value: json, dot. Such as \"json,dot\". (Warning: This will slow the speed.)"), JsonValueType::Str, Some(check_interaction_graph)).unwrap(),
+    ]
+}
+
+pub fn get_bili_bangumi_options() -> Vec<OptDes> {
+    vec![
+        OptDes::new("part", Some("p"), gettext("The episode numbers of a season."), true, true, Some("episode number")).unwrap(),
+    ]
+}
+
+pub fn get_bili_bangumi_settings() -> Vec<SettingDes> {
+    vec![
+        SettingDes::new("part", gettext("The episode numbers of a season. The syntax is same as part number of normal video.\nExample: \n2\tSelect episode 2\n\"3-\"\tSelect from episode 3 to last episode."), JsonValueType::Multiple, Some(check_part)).unwrap(),
+    ]
+}
+
+fn check_space_order(value: &JsonValue) -> bool {
+    match value.as_str() {
+        Some(s) => SpaceOrder::parse(s).is_some(),
+        None => false,
+    }
+}
+
+fn check_max_count(value: &JsonValue) -> bool {
+    match value.to_usize() {
+        Some(s) => s > 0,
+        None => false,
+    }
+}
+
+pub fn get_bili_space_options() -> Vec<OptDes> {
+    vec![
+        OptDes::new("order", None, gettext("The order of the uploader's video list. Available value: pubdate, click, stow. Default: pubdate"), true, true, Some("order")).unwrap(),
+        OptDes::new("keyword", None, gettext("Only download the uploader's videos which match the keyword."), true, true, Some("keyword")).unwrap(),
+        OptDes::new("date-after", None, gettext("Only download videos published on or after this date. Such as 2021-09-01."), true, true, Some("date")).unwrap(),
+        OptDes::new("date-before", None, gettext("Only download videos published on or before this date. Such as 2021-09-30."), true, true, Some("date")).unwrap(),
+        OptDes::new("max-count", None, gettext("The maximum count of videos to download."), true, true, Some("count")).unwrap(),
+    ]
+}
+
+pub fn get_bili_space_settings() -> Vec<SettingDes> {
+    vec![
+        SettingDes::new("order", gettext("The order of the uploader's video list. Available value: pubdate, click, stow. Default: pubdate"), JsonValueType::Str, Some(check_space_order)).unwrap(),
+        SettingDes::new("max-count", gettext("The maximum count of videos to download."), JsonValueType::Multiple, Some(check_max_count)).unwrap(),
+    ]
+}
+
+fn check_live_protocol(value: &JsonValue) -> bool {
+    match value.as_str() {
+        Some(s) => parse_live_protocol(s).is_some(),
+        None => false,
+    }
+}
+
+fn check_live_quality(value: &JsonValue) -> bool {
+    match value.to_usize() {
+        Some(s) => s > 0,
+        None => false,
+    }
+}
+
+pub fn get_bili_live_options() -> Vec<OptDes> {
+    vec![
+        OptDes::new("live-protocol", None, gettext("The protocol of live stream. Available value: flv, hls. Default: flv"), true, true, Some("protocol")).unwrap(),
+        OptDes::new("live-quality", None, gettext("The quality number (qn) of live stream. Such as 10000 (original), 400 (blu-ray), 250 (ultra high). Default: 10000"), true, true, Some("qn")).unwrap(),
+    ]
+}
+
+pub fn get_bili_live_settings() -> Vec<SettingDes> {
+    vec![
+        SettingDes::new("live-protocol", gettext("The protocol of live stream. Available value: flv, hls. Default: flv"), JsonValueType::Str, Some(check_live_protocol)).unwrap(),
+        SettingDes::new("live-quality", gettext("The quality number (qn) of live stream. Such as 10000 (original), 400 (blu-ray), 250 (ultra high). Default: 10000"), JsonValueType::Multiple, Some(check_live_quality)).unwrap(),
     ]
 }
@@ -72,11 +72,12 @@ impl HTMLDataInJS {
     /// * `keys` - Keys.
     /// # Examples
     /// ```
+    /// # use bili::providers::bilibili::parser::HTMLDataInJS;
     /// let html = "<script>key=value</script>";
-    /// let a = HTMLDataInJS::new();
+    /// let mut a = HTMLDataInJS::new();
     /// if a.parse(html, vec!["key"]) {
     ///     let s = a.maps.get("key").unwrap();
-    ///     println!("{}", s); // value
+    ///     eprintln!("{}", s); // value
     /// }
     /// ```
     pub fn parse(&mut self, html: &str, keys: Vec<&str>) -> bool {
@@ -84,7 +85,7 @@ impl HTMLDataInJS {
         match doc {
             Ok(_) => {}
             Err(e) => {
-                println!("{}\"{}\"", gettext("Can not parse HTML: "), e);
+                eprintln!("{}\"{}\"", gettext("Can not parse HTML: "), e);
                 return false;
             }
         }
@@ -6,6 +6,7 @@ use json::JsonValue;
 use regex::Regex;
 use std::clone::Clone;
 use std::convert::From;
+use std::fmt::Display;
 
 lazy_static! {
     static ref RE: Regex =
@@ -87,6 +88,22 @@ impl Part {
     }
 }
 
+impl Display for Part {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.start != 0 && self.start == self.end {
+            return f.write_str(&format!("{}", self.start));
+        }
+        if self.start != 0 {
+            f.write_str(&format!("{}", self.start))?;
+        }
+        f.write_str("-")?;
+        if self.end != 0 {
+            f.write_str(&format!("{}", self.end))?;
+        }
+        Ok(())
+    }
+}
+
 impl Clone for Part {
     fn clone(&self) -> Part {
         Part {
@@ -151,6 +168,28 @@ impl PartList {
         None
     }
 
+    /// Resolve the list to part numbers.
+    /// * `count` - The count of parts
+    ///
+    /// Open-ended ranges will be resolved by using `count`. Duplicate part numbers are removed.
+    /// If a part is out of range, the part will be returned as error.
+    pub fn to_part_numbers(&self, count: usize) -> Result<Vec<usize>, Part> {
+        let mut r: Vec<usize> = [].to_vec();
+        for p in self.list.iter() {
+            let start = if p.start == 0 { 1 } else { p.start };
+            let end = if p.end == 0 { count } else { p.end };
+            if start > count || end > count {
+                return Err(p.clone());
+            }
+            for i in start..end + 1 {
+                if !r.contains(&i) {
+                    r.push(i);
+                }
+            }
+        }
+        Ok(r)
+    }
+
     /// Parse part number list from string
     /// * `s` - input string. such as `1-34,36`
     pub fn parse_from_str(s: &str) -> Option<PartList> {
@@ -234,3 +273,26 @@ fn test_part_list_parse_from_json() {
         PartList::parse_from_json(&json::parse("[3, \"5-\"]").unwrap())
     )
 }
+
+#[test]
+fn test_part_display() {
+    assert_eq!("3", format!("{}", Part::new(3, 3).unwrap()));
+    assert_eq!("3-", format!("{}", Part::new(3, 0).unwrap()));
+    assert_eq!("-5", format!("{}", Part::new(0, 5).unwrap()));
+    assert_eq!("-", format!("{}", Part::new(0, 0).unwrap()));
+    assert_eq!("2-5", format!("{}", Part::new(2, 5).unwrap()));
+}
+
+#[test]
+fn test_part_list_to_part_numbers() {
+    let l = PartList::parse_from_str("3, 1-2, 2-").unwrap();
+    assert_eq!(Ok(vec![3, 1, 2, 4]), l.to_part_numbers(4));
+    let l = PartList::parse_from_str("-").unwrap();
+    assert_eq!(Ok(vec![1, 2, 3]), l.to_part_numbers(3));
+    let l = PartList::parse_from_str("-2").unwrap();
+    assert_eq!(Ok(vec![1, 2]), l.to_part_numbers(3));
+    let l = PartList::parse_from_str("1, 3-5").unwrap();
+    assert_eq!(Err(Part::new(3, 5).unwrap()), l.to_part_numbers(4));
+    let l = PartList::parse_from_str("5-").unwrap();
+    assert_eq!(Err(Part::new(5, 0).unwrap()), l.to_part_numbers(4));
+}
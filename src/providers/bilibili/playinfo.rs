@@ -0,0 +1,342 @@
+extern crate json;
+
+use crate::error::Error;
+use crate::error::Result;
+use crate::getopt::OptStore;
+use crate::i18n::gettext;
+use crate::metadata::StreamFormat;
+use crate::metadata::StreamType;
+use crate::metadata::VideoInfo;
+use crate::metadata::VideoPlayInfoType;
+use crate::metadata::VideoSegment;
+use crate::providers::bilibili::quality;
+use crate::settings::SettingStore;
+use crate::utils::json::jv_multikey_value;
+use json::JsonValue;
+use std::clone::Clone;
+
+/// The name of settings which contains `quality`, `video-codec` and `audio-quality`
+pub const STREAM_SETTINGS_KEY: &str = "BiliStream";
+
+/// Preferred streams
+#[derive(Clone, Debug)]
+pub struct StreamPreference {
+    /// Preferred video quality list
+    pub quality: Vec<usize>,
+    /// Preferred video codec list
+    pub video_codec: Vec<usize>,
+    /// Preferred audio quality list
+    pub audio_quality: Vec<usize>,
+}
+
+impl Default for StreamPreference {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StreamPreference {
+    pub fn new() -> Self {
+        Self {
+            quality: Vec::new(),
+            video_codec: Vec::new(),
+            audio_quality: Vec::new(),
+        }
+    }
+
+    /// Read preferences from options and settings.
+    /// * `opt` - Options
+    /// * `se` - Settings
+    ///
+    /// Return an error if any option is invalid.
+    pub fn from_opt(opt: &OptStore, se: &SettingStore) -> Result<Self> {
+        let q = get_preference(
+            opt,
+            se,
+            "quality",
+            quality::parse_quality_list,
+            quality::parse_quality_list_from_json,
+        )?;
+        let c = get_preference(
+            opt,
+            se,
+            "video-codec",
+            quality::parse_video_codec_list,
+            quality::parse_video_codec_list_from_json,
+        )?;
+        let a = get_preference(
+            opt,
+            se,
+            "audio-quality",
+            quality::parse_audio_quality_list,
+            quality::parse_audio_quality_list_from_json,
+        )?;
+        Ok(Self {
+            quality: q,
+            video_codec: c,
+            audio_quality: a,
+        })
+    }
+
+    /// Return the most preferred quality which is used when requesting play url. Default: 127 (8K)
+    pub fn first_quality(&self) -> usize {
+        if !self.quality.is_empty() {
+            self.quality[0]
+        } else {
+            127
+        }
+    }
+}
+
+/// Get a preference list from options and settings.
+/// * `opt` - Options
+/// * `se` - Settings
+/// * `key` - Option's name and setting's key
+/// * `parse` - Function to parse option
+/// * `parse_json` - Function to parse setting
+///
+/// Return an error if option is invalid.
+fn get_preference(
+    opt: &OptStore,
+    se: &SettingStore,
+    key: &str,
+    parse: fn(&str) -> Option<Vec<usize>>,
+    parse_json: fn(&JsonValue) -> Option<Vec<usize>>,
+) -> Result<Vec<usize>> {
+    let o = opt.get_option(key);
+    if let Some(o) = &o {
+        return match parse(o.as_str()) {
+            Some(r) => Ok(r),
+            None => Err(Error::Config(
+                gettext("The value of option \"<key>\" is invalid.").replace("<key>", key),
+            )),
+        };
+    }
+    let se = se.get_settings(STREAM_SETTINGS_KEY, key);
+    if let Some(se) = &se {
+        let r = parse_json(se);
+        if let Some(r) = r {
+            return Ok(r);
+        }
+    }
+    Ok(Vec::new())
+}
+
+/// Create an error which means the playback url is not found.
+fn no_playback_url() -> Error {
+    Error::Extract(String::from(gettext("Can not get playback url from play url information.")))
+}
+
+/// Extract playback url from play url information.
+/// * `data` - Play url information
+/// * `pref` - Preferred streams
+/// * `vi` - Video information
+pub fn extract_playinfo(data: &JsonValue, pref: &StreamPreference, vi: &mut VideoInfo) -> Result<()> {
+    let dash = &data["dash"];
+    if dash.is_object() {
+        return extract_dash_playinfo(data, pref, vi);
+    }
+    let durl = &data["durl"];
+    if !durl.is_array() || durl.is_empty() {
+        return Err(no_playback_url());
+    }
+    // Only the stream of returned quality is available, so other qualities are just listed.
+    let q = data["quality"].as_usize();
+    for (i, aq) in data["accept_quality"].members().enumerate() {
+        let id = aq.as_usize();
+        if id.is_none() {
+            continue;
+        }
+        let id = id.unwrap();
+        let desc = match data["accept_description"][i].as_str() {
+            Some(d) => String::from(d),
+            None => quality::quality_name(id),
+        };
+        let mut f = StreamFormat::new(StreamType::Mixed, format!("{}", id).as_str(), desc.as_str());
+        f.selected = q == Some(id);
+        vi.formats.push(f);
+    }
+    if let Some(q) = q {
+        check_quality_fallback(data, pref, q);
+    }
+    if durl.len() > 1 {
+        return extract_segments_playinfo(durl, vi);
+    }
+    let u = durl[0]["url"].as_str();
+    if u.is_none() {
+        return Err(no_playback_url());
+    }
+    vi.typ = VideoPlayInfoType::SignleUrl;
+    vi.url = Some(String::from(u.unwrap()));
+    Ok(())
+}
+
+/// Print a warning if the selected quality is not the most preferred quality.
+/// * `data` - Play url information
+/// * `pref` - Preferred streams
+/// * `q` - Selected quality
+fn check_quality_fallback(data: &JsonValue, pref: &StreamPreference, q: usize) {
+    if pref.quality.is_empty() || pref.quality[0] == q {
+        return;
+    }
+    let p = pref.quality[0];
+    let mut reason: Option<&str> = None;
+    for f in data["support_formats"].members() {
+        if f["quality"].as_usize() == Some(p) {
+            if f["need_vip"].as_bool() == Some(true) {
+                reason = Some(gettext("Quality <quality> needs VIP."));
+            } else if f["need_login"].as_bool() == Some(true) {
+                reason = Some(gettext("Quality <quality> needs login."));
+            }
+        }
+    }
+    let reason = match reason {
+        Some(r) => r,
+        None => gettext("Quality <quality> is not available."),
+    };
+    eprintln!(
+        "{}",
+        reason.replace("<quality>", quality::quality_name(p).as_str())
+    );
+    eprintln!(
+        "{}",
+        gettext("Fallback to quality <quality>.")
+            .replace("<quality>", quality::quality_name(q).as_str())
+    );
+}
+
+/// Extract stream urls from DASH information.
+/// * `data` - Play url information
+/// * `pref` - Preferred streams
+/// * `vi` - Video information
+fn extract_dash_playinfo(data: &JsonValue, pref: &StreamPreference, vi: &mut VideoInfo) -> Result<()> {
+    let dash = &data["dash"];
+    let mut qualities: Vec<usize> = Vec::new();
+    for v in dash["video"].members() {
+        let id = v["id"].as_usize();
+        if let Some(id) = id.filter(|&id| !qualities.contains(&id)) {
+            qualities.push(id);
+        }
+    }
+    let q = quality::select_quality(&qualities, &pref.quality);
+    if q.is_none() {
+        return Err(Error::Extract(String::from(gettext("Can not find video stream."))));
+    }
+    let q = q.unwrap();
+    check_quality_fallback(data, pref, q);
+    let mut codecs: Vec<usize> = Vec::new();
+    for v in dash["video"].members() {
+        if v["id"].as_usize() == Some(q) {
+            let c = v["codecid"].as_usize();
+            if let Some(c) = c.filter(|&c| !codecs.contains(&c)) {
+                codecs.push(c);
+            }
+        }
+    }
+    let codec = quality::select_video_codec(&codecs, &pref.video_codec);
+    let mut video: Option<&JsonValue> = None;
+    for v in dash["video"].members() {
+        if v["id"].as_usize() != Some(q) || v["codecid"].as_usize() != codec {
+            continue;
+        }
+        if video.is_none() || v["bandwidth"].as_usize() > video.unwrap()["bandwidth"].as_usize()
+        {
+            video = Some(v);
+        }
+    }
+    if video.is_none() {
+        return Err(Error::Extract(String::from(gettext("Can not find video stream."))));
+    }
+    let video = video.unwrap();
+    let u = jv_multikey_value(video, vec!["baseUrl", "base_url"]);
+    if u.is_none() || !u.unwrap().is_string() {
+        return Err(Error::Extract(String::from(gettext("Can not find video stream."))));
+    }
+    vi.video_url = Some(String::from(u.unwrap().as_str().unwrap()));
+    let mut audios: Vec<&JsonValue> = Vec::new();
+    for a in dash["audio"].members() {
+        audios.push(a);
+    }
+    for a in dash["dolby"]["audio"].members() {
+        audios.push(a);
+    }
+    if dash["flac"]["audio"].is_object() {
+        audios.push(&dash["flac"]["audio"]);
+    }
+    let mut aqs: Vec<usize> = Vec::new();
+    for a in audios.iter() {
+        let id = a["id"].as_usize();
+        if let Some(id) = id.filter(|&id| !aqs.contains(&id)) {
+            aqs.push(id);
+        }
+    }
+    let aq = quality::select_audio_quality(&aqs, &pref.audio_quality);
+    let mut audio: Option<&JsonValue> = None;
+    if aq.is_some() {
+        for a in audios.iter() {
+            if a["id"].as_usize() == aq {
+                audio = Some(a);
+                break;
+            }
+        }
+    }
+    if let Some(audio) = audio {
+        if let Some(u) = jv_multikey_value(audio, vec!["baseUrl", "base_url"]).and_then(|u| u.as_str()) {
+            vi.audio_url = Some(String::from(u));
+        }
+    }
+    for v in dash["video"].members() {
+        let id = v["id"].as_usize().unwrap_or(0);
+        let mut f = StreamFormat::new(
+            StreamType::Video,
+            format!("{}", id).as_str(),
+            quality::quality_name(id).as_str(),
+        );
+        let c = v["codecid"].as_usize();
+        f.codec = c.map(quality::video_codec_name);
+        f.bandwidth = v["bandwidth"].as_usize();
+        f.width = v["width"].as_usize();
+        f.height = v["height"].as_usize();
+        f.selected = std::ptr::eq(v, video);
+        vi.formats.push(f);
+    }
+    for a in audios.iter() {
+        let id = a["id"].as_usize().unwrap_or(0);
+        let mut f = StreamFormat::new(
+            StreamType::Audio,
+            format!("{}", id).as_str(),
+            quality::audio_quality_name(id).as_str(),
+        );
+        f.codec = a["codecs"].as_str().map(String::from);
+        f.bandwidth = a["bandwidth"].as_usize();
+        f.selected = audio.is_some() && std::ptr::eq(*a, audio.unwrap());
+        vi.formats.push(f);
+    }
+    vi.typ = VideoPlayInfoType::Dash;
+    Ok(())
+}
+
+/// Extract segment list from `durl` in play url information.
+/// * `durl` - Segment list
+/// * `vi` - Video information
+fn extract_segments_playinfo(durl: &JsonValue, vi: &mut VideoInfo) -> Result<()> {
+    let mut list: Vec<(usize, VideoSegment)> = Vec::new();
+    for (i, d) in durl.members().enumerate() {
+        let u = d["url"].as_str();
+        if u.is_none() {
+            return Err(no_playback_url());
+        }
+        let mut seg = VideoSegment::new(u.unwrap());
+        seg.size = d["size"].as_usize();
+        seg.duration = d["length"].as_usize();
+        let order = match d["order"].as_usize() {
+            Some(o) => o,
+            None => i + 1,
+        };
+        list.push((order, seg));
+    }
+    list.sort_by_key(|k| k.0);
+    vi.segments = Some(list.into_iter().map(|k| k.1).collect());
+    vi.typ = VideoPlayInfoType::Segments;
+    Ok(())
+}
@@ -0,0 +1,244 @@
+extern crate json;
+
+use crate::providers::bilibili::util;
+use json::JsonValue;
+
+lazy_static! {
+    /// Video quality ID and its name
+    static ref QUALITY: Vec<(usize, &'static str)> = vec![
+        (127, "8K"),
+        (126, "DOLBY"),
+        (125, "HDR"),
+        (120, "4K"),
+        (116, "1080P60"),
+        (112, "1080P+"),
+        (80, "1080P"),
+        (74, "720P60"),
+        (64, "720P"),
+        (32, "480P"),
+        (16, "360P"),
+        (6, "240P"),
+    ];
+    /// Video codec ID and its name
+    static ref VIDEO_CODEC: Vec<(usize, &'static str)> = vec![(7, "AVC"), (12, "HEVC"), (13, "AV1")];
+    /// Audio quality ID and its name
+    static ref AUDIO_QUALITY: Vec<(usize, &'static str)> = vec![
+        (30251, "HIRES"),
+        (30250, "DOLBY"),
+        (30280, "192K"),
+        (30232, "132K"),
+        (30216, "64K"),
+    ];
+}
+
+/// Find ID by name or ID in a table.
+fn parse_id(table: &[(usize, &'static str)], s: &str) -> Option<usize> {
+    let s = s.trim();
+    let n = util::atou(s);
+    if let Some(n) = n {
+        for (id, _) in table.iter() {
+            if *id == n {
+                return Some(n);
+            }
+        }
+        return None;
+    }
+    let s = s.to_uppercase();
+    for (id, name) in table.iter() {
+        if *name == s {
+            return Some(*id);
+        }
+    }
+    None
+}
+
+/// Parse a preference list (such as `1080P,720P` or `80,64`)
+fn parse_id_list(table: &[(usize, &'static str)], s: &str) -> Option<Vec<usize>> {
+    let mut r: Vec<usize> = Vec::new();
+    for i in s.split(",") {
+        let id = parse_id(table, i);
+        r.push(id?);
+    }
+    if !r.is_empty() {
+        return Some(r);
+    }
+    None
+}
+
+/// Parse a preference list from JSON. The value can be a number, a string or an array.
+fn parse_id_list_from_json(table: &[(usize, &'static str)], v: &JsonValue) -> Option<Vec<usize>> {
+    if v.is_number() {
+        let n = v.as_usize();
+        return parse_id_list(table, format!("{}", n?).as_str());
+    } else if v.is_string() {
+        return parse_id_list(table, v.as_str().unwrap());
+    } else if v.is_array() {
+        let mut r: Vec<usize> = Vec::new();
+        for i in v.members() {
+            let l = parse_id_list_from_json(table, i);
+            r.append(&mut l?);
+        }
+        if !r.is_empty() {
+            return Some(r);
+        }
+    }
+    None
+}
+
+/// Parse video quality preference list.
+/// * `s` - Such as `1080P,720P` or `80,64`
+pub fn parse_quality_list(s: &str) -> Option<Vec<usize>> {
+    parse_id_list(&QUALITY, s)
+}
+
+/// Parse video codec preference list.
+/// * `s` - Such as `hevc,avc` or `12,7`
+pub fn parse_video_codec_list(s: &str) -> Option<Vec<usize>> {
+    parse_id_list(&VIDEO_CODEC, s)
+}
+
+/// Parse audio quality preference list.
+/// * `s` - Such as `hires,192k` or `30251,30280`
+pub fn parse_audio_quality_list(s: &str) -> Option<Vec<usize>> {
+    parse_id_list(&AUDIO_QUALITY, s)
+}
+
+pub fn parse_quality_list_from_json(v: &JsonValue) -> Option<Vec<usize>> {
+    parse_id_list_from_json(&QUALITY, v)
+}
+
+pub fn parse_video_codec_list_from_json(v: &JsonValue) -> Option<Vec<usize>> {
+    parse_id_list_from_json(&VIDEO_CODEC, v)
+}
+
+pub fn parse_audio_quality_list_from_json(v: &JsonValue) -> Option<Vec<usize>> {
+    parse_id_list_from_json(&AUDIO_QUALITY, v)
+}
+
+/// Return the name of a video quality.
+pub fn quality_name(id: usize) -> String {
+    for (i, name) in QUALITY.iter() {
+        if *i == id {
+            return String::from(*name);
+        }
+    }
+    format!("{}", id)
+}
+
+/// Return the name of a video codec.
+pub fn video_codec_name(id: usize) -> String {
+    for (i, name) in VIDEO_CODEC.iter() {
+        if *i == id {
+            return String::from(*name);
+        }
+    }
+    format!("{}", id)
+}
+
+/// Return the name of an audio quality.
+pub fn audio_quality_name(id: usize) -> String {
+    for (i, name) in AUDIO_QUALITY.iter() {
+        if *i == id {
+            return String::from(*name);
+        }
+    }
+    format!("{}", id)
+}
+
+/// Return the rank of ID in a table. Smaller is better. Unknown ID is the worst.
+fn rank(table: &[(usize, &'static str)], id: usize) -> usize {
+    for (i, (t, _)) in table.iter().enumerate() {
+        if *t == id {
+            return i;
+        }
+    }
+    table.len()
+}
+
+/// Select an ID from available list by using preference list.
+/// * `table` - The table which define the order of ID (Best first)
+/// * `available` - Available IDs
+/// * `prefer` - Preference list
+///
+/// The first available ID in preference list will be selected.
+/// If none of them are available, the best ID which is worse than first preferred ID will be selected.
+/// If still not found, the worst available ID (the closest one to first preferred ID) will be selected.
+/// If no preference, the best available ID will be selected.
+fn select_id(table: &[(usize, &'static str)], available: &[usize], prefer: &[usize]) -> Option<usize> {
+    if available.is_empty() {
+        return None;
+    }
+    for p in prefer.iter() {
+        if available.contains(p) {
+            return Some(*p);
+        }
+    }
+    let mut list = available.to_vec();
+    list.sort_by_key(|k| rank(table, *k));
+    if !prefer.is_empty() {
+        let r = rank(table, prefer[0]);
+        for i in list.iter() {
+            if rank(table, *i) > r {
+                return Some(*i);
+            }
+        }
+        return Some(list[list.len() - 1]);
+    }
+    Some(list[0])
+}
+
+/// Select a video quality.
+/// * `available` - Available qualities
+/// * `prefer` - Preference list
+pub fn select_quality(available: &[usize], prefer: &[usize]) -> Option<usize> {
+    select_id(&QUALITY, available, prefer)
+}
+
+/// Select a video codec. If no preference, AVC is preferred because it has the best compatibility.
+/// * `available` - Available codecs
+/// * `prefer` - Preference list
+pub fn select_video_codec(available: &[usize], prefer: &[usize]) -> Option<usize> {
+    if prefer.is_empty() {
+        return select_id(&VIDEO_CODEC, available, &[7, 12, 13]);
+    }
+    select_id(&VIDEO_CODEC, available, prefer)
+}
+
+/// Select an audio quality. If no preference, the best audio except Dolby and Hi-Res will be selected.
+/// * `available` - Available audio qualities
+/// * `prefer` - Preference list
+pub fn select_audio_quality(available: &[usize], prefer: &[usize]) -> Option<usize> {
+    if prefer.is_empty() {
+        return select_id(&AUDIO_QUALITY, available, &[30280, 30232, 30216]);
+    }
+    select_id(&AUDIO_QUALITY, available, prefer)
+}
+
+#[test]
+fn test_parse_quality_list() {
+    assert_eq!(Some(vec![80, 64]), parse_quality_list("1080p, 720P"));
+    assert_eq!(Some(vec![120]), parse_quality_list("120"));
+    assert_eq!(None, parse_quality_list("121"));
+    assert_eq!(Some(vec![13, 7]), parse_video_codec_list("av1,AVC"));
+    assert_eq!(Some(vec![30251, 30280]), parse_audio_quality_list("hires,192k"));
+    assert_eq!(
+        Some(vec![116, 80]),
+        parse_quality_list_from_json(&json::array![116, "1080P"])
+    );
+}
+
+#[test]
+fn test_select_quality() {
+    let a = vec![80, 64, 32, 16];
+    assert_eq!(Some(64), select_quality(&a, &vec![64]));
+    assert_eq!(Some(80), select_quality(&a, &vec![120, 80]));
+    assert_eq!(Some(80), select_quality(&a, &vec![116]));
+    assert_eq!(Some(64), select_quality(&a, &vec![74]));
+    assert_eq!(Some(16), select_quality(&a, &vec![6]));
+    assert_eq!(Some(80), select_quality(&a, &vec![]));
+    assert_eq!(None, select_quality(&vec![], &vec![80]));
+    assert_eq!(Some(7), select_video_codec(&vec![12, 7, 13], &vec![]));
+    assert_eq!(Some(13), select_video_codec(&vec![12, 7, 13], &vec![13]));
+    assert_eq!(Some(30280), select_audio_quality(&vec![30216, 30280, 30250], &vec![]));
+    assert_eq!(Some(30250), select_audio_quality(&vec![30216, 30280, 30250], &vec![30250]));
+}
@@ -0,0 +1,456 @@
+extern crate chrono;
+extern crate futures;
+extern crate json;
+extern crate regex;
+
+use crate::cookies_json::CookiesJar;
+use crate::error::Error;
+use crate::error::Result;
+use crate::getopt::OptDes;
+use crate::getopt::OptStore;
+use crate::i18n::gettext;
+use crate::metadata::ExtractInfo;
+use crate::metadata::InfoType;
+use crate::metadata::VideoInfo;
+use crate::providers::bilibili::api::get_api_async;
+use crate::providers::bilibili::base::BiliBaseProvider;
+use crate::providers::bilibili::normal_video::extract_videos_by_id;
+use crate::providers::bilibili::normal_video::BiliNormalVideoProvider;
+use crate::providers::bilibili::opt_list::get_bili_space_options;
+use crate::providers::bilibili::opt_list::get_bili_space_settings;
+use crate::providers::provider_base::Provider;
+use crate::settings::SettingDes;
+use crate::settings::SettingStore;
+use crate::utils::jobs::get_jobs;
+use crate::utils::number::ToUsize;
+use async_trait::async_trait;
+use chrono::FixedOffset;
+use chrono::NaiveDate;
+use chrono::TimeZone;
+use json::JsonValue;
+use regex::Regex;
+use std::clone::Clone;
+
+lazy_static! {
+    static ref RE: Regex = Regex::new(r"(?i)^(?:https?://)?space\.bilibili\.com/(?P<mid>\d+)/?(?:[/?#].*)?$").unwrap();
+}
+
+/// The count of videos per page when requesting the video list.
+const PAGE_SIZE: usize = 50;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+/// The order of the uploader's video list
+pub enum SpaceOrder {
+    /// Latest published first
+    Pubdate,
+    /// Most viewed first
+    Click,
+    /// Most favorited first
+    Stow,
+}
+
+impl SpaceOrder {
+    /// Parse from string
+    /// * `s` - Such as `pubdate`, `click`, `stow`
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "pubdate" | "date" => Some(Self::Pubdate),
+            "click" | "view" => Some(Self::Click),
+            "stow" | "favorite" => Some(Self::Stow),
+            _ => None,
+        }
+    }
+
+    /// Return the value used by API
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Pubdate => "pubdate",
+            Self::Click => "click",
+            Self::Stow => "stow",
+        }
+    }
+}
+
+/// Parse a date (in UTC+8, the timezone of Bilibili) to Unix timestamp.
+/// * `s` - Date string. Such as `2021-09-01` or `20210901`
+/// * `end_of_day` - Return the last second of the day instead of the first second.
+pub fn parse_date(s: &str, end_of_day: bool) -> Option<i64> {
+    let s = s.trim();
+    let mut d = NaiveDate::parse_from_str(s, "%Y-%m-%d");
+    if d.is_err() {
+        d = NaiveDate::parse_from_str(s, "%Y%m%d");
+    }
+    if d.is_err() {
+        return None;
+    }
+    let d = d.unwrap();
+    let t = if end_of_day {
+        d.and_hms_opt(23, 59, 59)
+    } else {
+        d.and_hms_opt(0, 0, 0)
+    };
+    let tz = FixedOffset::east_opt(8 * 3600).unwrap();
+    tz.from_local_datetime(&t?).single().map(|t| t.timestamp())
+}
+
+/// A video in the uploader's video list
+#[derive(Clone, Debug, PartialEq)]
+pub struct SpaceVideo {
+    /// BV number
+    pub bvid: String,
+    /// Title
+    pub title: String,
+    /// Publish time (Unix timestamp)
+    pub created: i64,
+}
+
+/// Parse the video list from the `data` of API (`https://api.bilibili.com/x/space/arc/search`)
+/// * `data` - Data
+pub fn parse_video_list(data: &JsonValue) -> Vec<SpaceVideo> {
+    let mut r: Vec<SpaceVideo> = Vec::new();
+    for i in data["list"]["vlist"].members() {
+        let bvid = i["bvid"].as_str();
+        if bvid.is_none() {
+            continue;
+        }
+        r.push(SpaceVideo {
+            bvid: String::from(bvid.unwrap()),
+            title: String::from(i["title"].as_str().unwrap_or("")),
+            created: i["created"].as_i64().unwrap_or(0),
+        });
+    }
+    r
+}
+
+/// Filters applied to the uploader's video list
+pub struct SpaceFilter {
+    /// The order of the list
+    pub order: SpaceOrder,
+    /// Search keyword
+    pub keyword: Option<String>,
+    /// Only keep videos published after this time (included)
+    pub after: Option<i64>,
+    /// Only keep videos published before this time (included)
+    pub before: Option<i64>,
+    /// The maximum count of videos
+    pub max_count: Option<usize>,
+}
+
+impl SpaceFilter {
+    /// Create a filter which keeps all videos.
+    pub fn new() -> Self {
+        Self {
+            order: SpaceOrder::Pubdate,
+            keyword: None,
+            after: None,
+            before: None,
+            max_count: None,
+        }
+    }
+
+    /// Read filters from options and settings.
+    /// * `opt` - Options
+    /// * `se` - Settings
+    /// * `settings_key` - The key of settings group
+    ///
+    /// Return an error if any value is invalid.
+    pub fn from_opt(opt: &OptStore, se: &SettingStore, settings_key: &str) -> Result<Self> {
+        let mut f = Self::new();
+        let o = opt.get_option("order");
+        if let Some(o) = o {
+            let r = SpaceOrder::parse(o.as_str());
+            if r.is_none() {
+                return Err(Error::Config(gettext("The value of option \"<key>\" is invalid.").replace("<key>", "order")));
+            }
+            f.order = r.unwrap();
+        } else {
+            let s = se.get_settings(settings_key, "order");
+            if let Some(s) = s {
+                let r = SpaceOrder::parse(s.as_str().unwrap_or(""));
+                if let Some(r) = r {
+                    f.order = r;
+                }
+            }
+        }
+        let o = opt.get_option("keyword");
+        if let Some(o) = o {
+            if !o.is_empty() {
+                f.keyword = Some(o);
+            }
+        }
+        for (key, end_of_day) in [("date-after", false), ("date-before", true)].iter() {
+            let o = opt.get_option(key);
+            if let Some(o) = o {
+                let r = parse_date(o.as_str(), *end_of_day);
+                if r.is_none() {
+                    return Err(Error::Config(gettext("The value of option \"<key>\" is invalid.").replace("<key>", key)));
+                }
+                if *end_of_day {
+                    f.before = r;
+                } else {
+                    f.after = r;
+                }
+            }
+        }
+        let o = opt.get_option("max-count");
+        if let Some(o) = o {
+            let r = o.parse::<usize>();
+            if r.is_err() || r.as_ref().unwrap() == &0 {
+                return Err(Error::Config(gettext("The value of option \"<key>\" is invalid.").replace("<key>", "max-count")));
+            }
+            f.max_count = Some(r.unwrap());
+        } else {
+            let s = se.get_settings(settings_key, "max-count");
+            if let Some(s) = s {
+                f.max_count = s.to_usize();
+            }
+        }
+        Ok(f)
+    }
+
+    /// Return true if the video is published in the date range.
+    /// * `created` - Publish time (Unix timestamp)
+    pub fn matches(&self, created: i64) -> bool {
+        if self.after.is_some() && created < self.after.unwrap() {
+            return false;
+        }
+        if self.before.is_some() && created > self.before.unwrap() {
+            return false;
+        }
+        true
+    }
+
+    /// Return true if no more video can match the filter after this video.
+    /// * `created` - Publish time (Unix timestamp) of current video
+    ///
+    /// Only works when the list is ordered by publish time.
+    pub fn is_end(&self, created: i64) -> bool {
+        self.order == SpaceOrder::Pubdate && self.after.is_some() && created < self.after.unwrap()
+    }
+}
+
+impl Default for SpaceFilter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub struct BiliSpaceProvider {
+    base: BiliBaseProvider,
+    /// The uploader's ID (Set in [`extract`](#method.extract) function)
+    mid: Option<usize>,
+    /// Filters
+    filter: SpaceFilter,
+}
+
+impl BiliSpaceProvider {
+    fn parse_url(url: &str) -> Option<usize> {
+        let caps = RE.captures(url);
+        caps?.name("mid").unwrap().as_str().parse::<usize>().ok()
+    }
+
+    /// Get all videos of the uploader which match the filters.
+    /// * `url` - Url
+    async fn get_list(&mut self, url: &str) -> Result<Vec<SpaceVideo>> {
+        self.mid = Self::parse_url(url);
+        if self.mid.is_none() {
+            return Err(Error::Extract(String::from(gettext("Unsupported url."))));
+        }
+        let list = self.get_video_list().await?;
+        if list.is_empty() {
+            return Err(Error::Extract(String::from(gettext("No video is selected."))));
+        }
+        Ok(list)
+    }
+
+    /// Generate extract information from extracted videos.
+    /// * `list` - Videos of the uploader
+    /// * `results` - Extracted videos in the same order as `list`
+    fn gen_extract_info(&self, list: &[SpaceVideo], results: Vec<Result<Vec<VideoInfo>>>) -> Result<ExtractInfo> {
+        let mut videos: Vec<VideoInfo> = Vec::new();
+        for (v, r) in list.iter().zip(results) {
+            match r {
+                Ok(mut r) => videos.append(&mut r),
+                Err(e) => {
+                    eprintln!("{}", e);
+                    let s = gettext("Can not extract video <id>. Skipped.").replace("<id>", v.bvid.as_str());
+                    eprintln!("{}", s);
+                }
+            }
+        }
+        if videos.is_empty() {
+            return Err(Error::Extract(String::from(gettext("Can not extract any video of the uploader."))));
+        }
+        Ok(ExtractInfo {
+            typ: InfoType::VideoList,
+            videos: Some(videos),
+            ..Default::default()
+        })
+    }
+
+    /// Get a page of the video list from API (`https://api.bilibili.com/x/space/arc/search`)
+    /// * `pn` - Page number (Start from 1)
+    async fn get_page(&mut self, pn: usize) -> Result<JsonValue> {
+        let mut param = json::object! {
+            "mid": self.mid.unwrap(),
+            "ps": PAGE_SIZE,
+            "tid": 0,
+            "pn": pn,
+            "order": self.filter.order.as_str(),
+            "jsonp": "jsonp",
+        };
+        if let Some(keyword) = &self.filter.keyword {
+            param["keyword"] = JsonValue::from(keyword.as_str());
+        }
+        let c = self.base.client.as_mut().unwrap();
+        let re = get_api_async(c, "https://api.bilibili.com/x/space/arc/search", param).await?;
+        Ok(re["data"].clone())
+    }
+
+    /// Page through the video list and return the videos which match the filters.
+    async fn get_video_list(&mut self) -> Result<Vec<SpaceVideo>> {
+        let mut r: Vec<SpaceVideo> = Vec::new();
+        let mut pn = 1;
+        loop {
+            let data = self.get_page(pn).await?;
+            let list = parse_video_list(&data);
+            if list.is_empty() {
+                break;
+            }
+            for v in list.iter() {
+                if self.filter.is_end(v.created) {
+                    return Ok(r);
+                }
+                if !self.filter.matches(v.created) {
+                    continue;
+                }
+                r.push(v.clone());
+                if self.filter.max_count.is_some() && r.len() >= self.filter.max_count.unwrap() {
+                    return Ok(r);
+                }
+            }
+            let count = data["page"]["count"].as_usize();
+            if count.is_none() || pn * PAGE_SIZE >= count.unwrap() {
+                break;
+            }
+            pn += 1;
+        }
+        Ok(r)
+    }
+}
+
+#[async_trait]
+impl Provider for BiliSpaceProvider {
+    fn new() -> BiliSpaceProvider {
+        BiliSpaceProvider {
+            base: BiliBaseProvider::new(),
+            mid: None,
+            filter: SpaceFilter::new(),
+        }
+    }
+
+    fn add_custom_options(&self, opt: &mut OptStore) {
+        BiliNormalVideoProvider::new().add_custom_options(opt);
+        opt.add(self.provider_name(), get_bili_space_options());
+    }
+
+    fn add_custom_settings(&self, store: &mut SettingStore) {
+        BiliNormalVideoProvider::new().add_custom_settings(store);
+        store.add(self.provider_name(), get_bili_space_settings());
+    }
+
+    fn can_login(&self) -> bool {
+        true
+    }
+
+    async fn check_logined_async(&mut self) -> Result<bool> {
+        self.base.check_logined_async().await
+    }
+
+    async fn extract_async(&mut self, url: &str) -> Result<ExtractInfo> {
+        let list = self.get_list(url).await?;
+        let videos = list.iter().map(|v| (v.bvid.clone(), v.title.clone())).collect();
+        let jobs = get_jobs(self.base.opt.as_ref().unwrap(), self.base.se.as_ref().unwrap());
+        let results = extract_videos_by_id(&self.base, videos, jobs).await;
+        self.gen_extract_info(&list, results)
+    }
+
+    fn get_custom_options() -> Vec<OptDes> {
+        get_bili_space_options()
+    }
+
+    fn get_custom_settings() -> Vec<SettingDes> {
+        get_bili_space_settings()
+    }
+
+    fn get_default_cookie_jar_name(&self) -> Option<&str> {
+        Some("bili")
+    }
+
+    fn has_custom_options(&self) -> bool {
+        true
+    }
+
+    fn has_custom_settings(&self) -> bool {
+        true
+    }
+
+    fn init(&mut self, jar: Option<&CookiesJar>, opt: OptStore, settings: SettingStore) -> Result<()> {
+        self.base.init(jar, opt, settings)?;
+        self.filter = SpaceFilter::from_opt(
+            self.base.opt.as_ref().unwrap(),
+            self.base.se.as_ref().unwrap(),
+            self.provider_name(),
+        )?;
+        Ok(())
+    }
+
+    async fn login_async(&mut self, jar: &mut CookiesJar) -> Result<()> {
+        self.base.login_async(jar).await
+    }
+
+    fn logined(&self) -> bool {
+        self.base.logined()
+    }
+
+    fn match_url(url: &str) -> bool {
+        BiliSpaceProvider::parse_url(url).is_some()
+    }
+
+    fn name() -> &'static str {
+        "BiliSpaceProvider"
+    }
+
+    fn provider_name(&self) -> &'static str {
+        Self::name()
+    }
+}
+
+#[test]
+fn test_parse_url() {
+    assert_eq!(Some(2), BiliSpaceProvider::parse_url("https://space.bilibili.com/2"));
+    assert_eq!(Some(2), BiliSpaceProvider::parse_url("space.bilibili.com/2/video?tid=0"));
+    assert_eq!(Some(546195), BiliSpaceProvider::parse_url("https://space.bilibili.com/546195?spm_id_from=a"));
+    assert_eq!(None, BiliSpaceProvider::parse_url("https://www.bilibili.com/video/av170001"));
+}
+
+#[test]
+fn test_space_filter() {
+    assert_eq!(Some(1630425600), parse_date("2021-09-01", false));
+    assert_eq!(Some(1630511999), parse_date("20210901", true));
+    assert_eq!(None, parse_date("2021-13-01", false));
+    assert_eq!(Some(SpaceOrder::Click), SpaceOrder::parse("Click"));
+    let mut f = SpaceFilter::new();
+    f.after = parse_date("2021-09-01", false);
+    f.before = parse_date("2021-09-30", true);
+    assert!(f.matches(1630425600));
+    assert!(!f.matches(1630425599));
+    assert!(!f.matches(1633017600));
+    assert!(f.is_end(1630425599));
+    f.order = SpaceOrder::Stow;
+    assert!(!f.is_end(1630425599));
+    let data = json::object! {"list": {"vlist": [{"bvid": "BV1xx411c7mD", "title": "a", "created": 1}, {"title": "b"}]}};
+    let l = parse_video_list(&data);
+    assert_eq!(1, l.len());
+    assert_eq!("BV1xx411c7mD", l[0].bvid);
+}
@@ -0,0 +1,389 @@
+use std::clone::Clone;
+use std::collections::BTreeMap;
+
+/// A token of stein expression
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    /// Number
+    Num(f64),
+    /// Variable. Such as `$Av3o`.
+    Var(String),
+    /// Operator
+    Op(&'static str),
+    /// `(`
+    LParen,
+    /// `)`
+    RParen,
+}
+
+/// Operators. Longer operators must be placed before shorter ones.
+const OPS: [&str; 15] = [
+    "&&", "||", "==", "!=", "<=", ">=", "<", ">", "=", "+", "-", "*", "/", "%", "!",
+];
+
+/// Split an expression to tokens.
+/// * `s` - Expression
+fn tokenize(s: &str) -> Result<Vec<Token>, String> {
+    let c: Vec<char> = s.chars().collect();
+    let mut r: Vec<Token> = Vec::new();
+    let mut i = 0;
+    'outer: while i < c.len() {
+        let ch = c[i];
+        if ch.is_whitespace() {
+            i += 1;
+            continue;
+        }
+        if ch == '(' {
+            r.push(Token::LParen);
+            i += 1;
+            continue;
+        }
+        if ch == ')' {
+            r.push(Token::RParen);
+            i += 1;
+            continue;
+        }
+        if ch.is_ascii_digit() || ch == '.' {
+            let start = i;
+            while i < c.len() && (c[i].is_ascii_digit() || c[i] == '.') {
+                i += 1;
+            }
+            let n: String = c[start..i].iter().collect();
+            match n.parse::<f64>() {
+                Ok(n) => r.push(Token::Num(n)),
+                Err(_) => return Err(format!("Invalid number: {}", n)),
+            }
+            continue;
+        }
+        if ch == '$' || ch == '_' || ch.is_alphabetic() {
+            let start = i;
+            i += 1;
+            while i < c.len() && (c[i] == '_' || c[i].is_alphanumeric()) {
+                i += 1;
+            }
+            r.push(Token::Var(c[start..i].iter().collect()));
+            continue;
+        }
+        for op in OPS.iter() {
+            let oc: Vec<char> = op.chars().collect();
+            if c.len() >= i + oc.len() && c[i..i + oc.len()] == oc[..] {
+                r.push(Token::Op(op));
+                i += oc.len();
+                continue 'outer;
+            }
+        }
+        return Err(format!("Unexpected character: {}", ch));
+    }
+    Ok(r)
+}
+
+/// A parsed stein expression
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expr {
+    /// Number
+    Num(f64),
+    /// Variable
+    Var(String),
+    /// Unary operation
+    Unary(&'static str, Box<Expr>),
+    /// Binary operation
+    Binary(&'static str, Box<Expr>, Box<Expr>),
+}
+
+/// Variables of a story state. Unknown variables are treated as 0.
+/// The value of random variables is not known when exploring, so it is NaN.
+pub type StoryVars = BTreeMap<String, f64>;
+
+/// Convert a boolean to number.
+fn b(v: bool) -> f64 {
+    if v {
+        1.0
+    } else {
+        0.0
+    }
+}
+
+impl Expr {
+    /// Parse an expression.
+    /// * `s` - Expression. Such as `$a>=2&&$b<3`.
+    pub fn parse(s: &str) -> Result<Expr, String> {
+        let tokens = tokenize(s)?;
+        let mut p = ExprParser { tokens, pos: 0 };
+        let e = p.parse_or()?;
+        if p.pos < p.tokens.len() {
+            return Err(format!("Unexpected token: {:?}", p.tokens[p.pos]));
+        }
+        Ok(e)
+    }
+
+    /// Evaluate the expression. Comparisons and logical operations return 1 or 0.
+    /// If the result depends on a NaN (random) value, NaN is returned.
+    /// * `vars` - Variables
+    pub fn eval(&self, vars: &StoryVars) -> f64 {
+        match self {
+            Expr::Num(n) => *n,
+            Expr::Var(v) => *vars.get(v).unwrap_or(&0.0),
+            Expr::Unary(op, e) => {
+                let v = e.eval(vars);
+                match *op {
+                    "-" => -v,
+                    _ if v.is_nan() => v,
+                    _ => b(v == 0.0),
+                }
+            }
+            Expr::Binary(op, l, r) => {
+                let l = l.eval(vars);
+                // Short-circuit is not needed because expressions have no side effects.
+                let r = r.eval(vars);
+                match *op {
+                    // A known false (true) side decides the result even if the other side is unknown.
+                    "&&" if l == 0.0 || r == 0.0 => 0.0,
+                    "||" if (!l.is_nan() && l != 0.0) || (!r.is_nan() && r != 0.0) => 1.0,
+                    _ if l.is_nan() || r.is_nan() => f64::NAN,
+                    "&&" => 1.0,
+                    "||" => 0.0,
+                    "==" | "=" => b(l == r),
+                    "!=" => b(l != r),
+                    "<" => b(l < r),
+                    "<=" => b(l <= r),
+                    ">" => b(l > r),
+                    ">=" => b(l >= r),
+                    "+" => l + r,
+                    "-" => l - r,
+                    "*" => l * r,
+                    "/" => {
+                        if r == 0.0 {
+                            0.0
+                        } else {
+                            l / r
+                        }
+                    }
+                    _ => {
+                        if r == 0.0 {
+                            0.0
+                        } else {
+                            l % r
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// A recursive descent parser of stein expression
+struct ExprParser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl ExprParser {
+    /// Consume the next token if it is one of the operators.
+    fn eat_op(&mut self, ops: &[&'static str]) -> Option<&'static str> {
+        if self.pos < self.tokens.len() {
+            match &self.tokens[self.pos] {
+                Token::Op(o) if ops.contains(o) => {
+                    self.pos += 1;
+                    return Some(o);
+                }
+                _ => {}
+            }
+        }
+        None
+    }
+
+    fn parse_binary(
+        &mut self,
+        ops: &[&'static str],
+        next: fn(&mut ExprParser) -> Result<Expr, String>,
+    ) -> Result<Expr, String> {
+        let mut l = next(self)?;
+        loop {
+            let op = self.eat_op(ops);
+            if op.is_none() {
+                return Ok(l);
+            }
+            let r = next(self)?;
+            l = Expr::Binary(op.unwrap(), Box::new(l), Box::new(r));
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, String> {
+        self.parse_binary(&["||"], ExprParser::parse_and)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, String> {
+        self.parse_binary(&["&&"], ExprParser::parse_cmp)
+    }
+
+    fn parse_cmp(&mut self) -> Result<Expr, String> {
+        let l = self.parse_add()?;
+        let op = self.eat_op(&["==", "=", "!=", "<", "<=", ">", ">="]);
+        if op.is_none() {
+            return Ok(l);
+        }
+        let r = self.parse_add()?;
+        Ok(Expr::Binary(op.unwrap(), Box::new(l), Box::new(r)))
+    }
+
+    fn parse_add(&mut self) -> Result<Expr, String> {
+        self.parse_binary(&["+", "-"], ExprParser::parse_mul)
+    }
+
+    fn parse_mul(&mut self) -> Result<Expr, String> {
+        self.parse_binary(&["*", "/", "%"], ExprParser::parse_unary)
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, String> {
+        let op = self.eat_op(&["-", "!"]);
+        if let Some(op) = op {
+            let e = self.parse_unary()?;
+            return Ok(Expr::Unary(op, Box::new(e)));
+        }
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, String> {
+        if self.pos >= self.tokens.len() {
+            return Err(String::from("Unexpected end of expression."));
+        }
+        let t = self.tokens[self.pos].clone();
+        self.pos += 1;
+        match t {
+            Token::Num(n) => Ok(Expr::Num(n)),
+            Token::Var(v) => Ok(Expr::Var(v)),
+            Token::LParen => {
+                let e = self.parse_or()?;
+                if self.pos < self.tokens.len() && self.tokens[self.pos] == Token::RParen {
+                    self.pos += 1;
+                    Ok(e)
+                } else {
+                    Err(String::from("Missing \")\"."))
+                }
+            }
+            _ => Err(format!("Unexpected token: {:?}", t)),
+        }
+    }
+}
+
+/// A condition of choice
+pub struct Condition {
+    /// None means always true
+    expr: Option<Expr>,
+}
+
+impl Condition {
+    /// Parse a condition. Empty condition is always true.
+    /// * `s` - Condition. Such as `$a>=2&&$b<3`.
+    pub fn parse(s: &str) -> Result<Self, String> {
+        if s.trim().is_empty() {
+            return Ok(Self { expr: None });
+        }
+        Ok(Self {
+            expr: Some(Expr::parse(s)?),
+        })
+    }
+
+    /// Check whether the condition is satisfied.
+    /// If the result depends on random variables, the condition may be satisfied, so true is returned.
+    /// * `vars` - Variables
+    pub fn check(&self, vars: &StoryVars) -> bool {
+        match &self.expr {
+            Some(e) => e.eval(vars) != 0.0,
+            None => true,
+        }
+    }
+}
+
+/// Actions run when a choice is chosen
+pub struct Actions {
+    /// Variable and its new value
+    list: Vec<(String, Expr)>,
+}
+
+impl Actions {
+    /// Parse actions. Actions are separated by `;`.
+    /// * `s` - Actions. Such as `$a=$a+1;$b=0`.
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let mut list: Vec<(String, Expr)> = Vec::new();
+        for a in s.split(';') {
+            let a = a.trim();
+            if a.is_empty() {
+                continue;
+            }
+            let i = a.find('=');
+            if i.is_none() {
+                return Err(format!("Invalid action: {}", a));
+            }
+            let i = i.unwrap();
+            let name = a[..i].trim();
+            let t = tokenize(name)?;
+            if t.len() != 1 {
+                return Err(format!("Invalid action: {}", a));
+            }
+            match &t[0] {
+                Token::Var(_) => {}
+                _ => return Err(format!("Invalid action: {}", a)),
+            }
+            list.push((String::from(name), Expr::parse(&a[i + 1..])?));
+        }
+        Ok(Self { list })
+    }
+
+    /// Apply actions to variables. Actions are run in order.
+    /// * `vars` - Variables
+    pub fn apply(&self, vars: &mut StoryVars) {
+        for (k, e) in self.list.iter() {
+            let v = e.eval(vars);
+            vars.insert(k.clone(), v);
+        }
+    }
+}
+
+/// Generate a key of story state which can be used to remove duplicate states.
+/// * `edge_id` - Edge ID
+/// * `vars` - Variables
+pub fn state_key(edge_id: usize, vars: &StoryVars) -> (usize, Vec<(String, u64)>) {
+    let bits = |v: &f64| if v.is_nan() { f64::NAN.to_bits() } else { v.to_bits() };
+    (edge_id, vars.iter().map(|(k, v)| (k.clone(), bits(v))).collect())
+}
+
+#[test]
+fn test_stein_expression() {
+    let mut vars = StoryVars::new();
+    vars.insert(String::from("$a"), 3.0);
+    assert!(Condition::parse("").unwrap().check(&vars));
+    assert!(Condition::parse("$a>=3&&$b<1").unwrap().check(&vars));
+    assert!(!Condition::parse("$a>3 || $b = 1").unwrap().check(&vars));
+    assert!(Condition::parse("!($a==2) && -$a+1*2 == -1").unwrap().check(&vars));
+    assert!(Condition::parse("$a>=").is_err());
+    assert!(Condition::parse("($a").is_err());
+    let a = Actions::parse("$a=$a+1; $b = $a*2;").unwrap();
+    a.apply(&mut vars);
+    assert_eq!(Some(&4.0), vars.get("$a"));
+    assert_eq!(Some(&8.0), vars.get("$b"));
+    assert!(Actions::parse("1=2").is_err());
+    let k = state_key(1, &vars);
+    assert_eq!(k, state_key(1, &vars.clone()));
+}
+
+#[test]
+fn test_stein_random_variable() {
+    let mut vars = StoryVars::new();
+    vars.insert(String::from("$a"), 3.0);
+    vars.insert(String::from("$r"), f64::NAN);
+    // Both outcomes of a random variable are possible.
+    assert!(Condition::parse("$r<=50").unwrap().check(&vars));
+    assert!(Condition::parse("$r>50").unwrap().check(&vars));
+    assert!(Condition::parse("!($r>50)").unwrap().check(&vars));
+    assert!(Condition::parse("$r<=50 || $a==1").unwrap().check(&vars));
+    // Known conditions still work.
+    assert!(!Condition::parse("$r<=50 && $a==1").unwrap().check(&vars));
+    assert!(Condition::parse("$r<=50 || $a==3").unwrap().check(&vars));
+    let a = Actions::parse("$b=$r+1").unwrap();
+    a.apply(&mut vars);
+    assert!(vars.get("$b").unwrap().is_nan());
+    let mut vars2 = vars.clone();
+    vars2.insert(String::from("$b"), -f64::NAN);
+    assert_eq!(state_key(1, &vars), state_key(1, &vars2));
+}
@@ -0,0 +1,434 @@
+extern crate json;
+
+use crate::error::Error;
+use crate::error::Result;
+use crate::getopt::OptStore;
+use crate::http_client::CookieClient;
+use crate::i18n::gettext;
+use crate::metadata::Subtitle;
+use crate::metadata::VideoInfo;
+use crate::settings::SettingStore;
+use json::JsonValue;
+use std::clone::Clone;
+
+/// The name of subtitle options and settings
+pub const SUBTITLE_SETTINGS_KEY: &str = "BiliSubtitle";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+/// The output format of subtitles
+pub enum SubtitleFormat {
+    Srt,
+    Vtt,
+    Ass,
+}
+
+impl SubtitleFormat {
+    /// Parse format from string. Such as `srt`, `vtt`, `webvtt`, `ass`.
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "srt" => Some(Self::Srt),
+            "vtt" | "webvtt" => Some(Self::Vtt),
+            "ass" => Some(Self::Ass),
+            _ => None,
+        }
+    }
+
+    /// File extension
+    pub fn ext(&self) -> &'static str {
+        match self {
+            Self::Srt => "srt",
+            Self::Vtt => "vtt",
+            Self::Ass => "ass",
+        }
+    }
+}
+
+/// A line of BCC subtitle
+#[derive(Clone, Debug)]
+pub struct BccLine {
+    /// Start time in milliseconds
+    pub from: usize,
+    /// End time in milliseconds
+    pub to: usize,
+    /// Content
+    pub content: String,
+}
+
+/// Convert seconds in JSON to milliseconds
+fn to_ms(v: &JsonValue) -> Option<usize> {
+    let f = v.as_f64();
+    let f = f?;
+    if f < 0.0 {
+        return None;
+    }
+    Some((f * 1000.0).round() as usize)
+}
+
+/// Parse BCC subtitle (The JSON subtitle format used by bilibili)
+/// * `s` - BCC JSON content
+pub fn parse_bcc(s: &str) -> Option<Vec<BccLine>> {
+    let obj = json::parse(s);
+    if obj.is_err() {
+        return None;
+    }
+    let obj = obj.unwrap();
+    let body = &obj["body"];
+    if !body.is_array() {
+        return None;
+    }
+    let mut r: Vec<BccLine> = Vec::new();
+    for i in body.members() {
+        let from = to_ms(&i["from"]);
+        let to = to_ms(&i["to"]);
+        let content = i["content"].as_str();
+        if from.is_none() || to.is_none() || content.is_none() {
+            return None;
+        }
+        r.push(BccLine {
+            from: from.unwrap(),
+            to: to.unwrap(),
+            content: String::from(content.unwrap()),
+        });
+    }
+    r.sort_by_key(|l| l.from);
+    Some(r)
+}
+
+/// Format time as `hh:mm:ss<sep>mmm`
+/// * `ms` - Time in milliseconds
+/// * `sep` - Separator between seconds and milliseconds
+fn format_time(ms: usize, sep: char) -> String {
+    format!(
+        "{:02}:{:02}:{:02}{}{:03}",
+        ms / 3600000,
+        ms / 60000 % 60,
+        ms / 1000 % 60,
+        sep,
+        ms % 1000
+    )
+}
+
+/// Format time as ASS time `h:mm:ss.cc`
+/// * `ms` - Time in milliseconds
+fn format_ass_time(ms: usize) -> String {
+    let cs = ms / 10;
+    format!(
+        "{}:{:02}:{:02}.{:02}",
+        cs / 360000,
+        cs / 6000 % 60,
+        cs / 100 % 60,
+        cs % 100
+    )
+}
+
+/// Convert subtitle lines to SubRip format
+/// * `lines` - Subtitle lines
+pub fn bcc_to_srt(lines: &[BccLine]) -> String {
+    let mut s = String::new();
+    for (i, l) in lines.iter().enumerate() {
+        s += format!(
+            "{}\n{} --> {}\n{}\n\n",
+            i + 1,
+            format_time(l.from, ','),
+            format_time(l.to, ','),
+            l.content.trim()
+        )
+        .as_str();
+    }
+    s
+}
+
+/// Convert subtitle lines to WebVTT format
+/// * `lines` - Subtitle lines
+pub fn bcc_to_vtt(lines: &[BccLine]) -> String {
+    let mut s = String::from("WEBVTT\n\n");
+    for l in lines.iter() {
+        s += format!(
+            "{} --> {}\n{}\n\n",
+            format_time(l.from, '.'),
+            format_time(l.to, '.'),
+            l.content
+                .trim()
+                .replace("&", "&amp;")
+                .replace("<", "&lt;")
+                .replace(">", "&gt;")
+        )
+        .as_str();
+    }
+    s
+}
+
+/// Convert subtitle lines to ASS format
+/// * `lines` - Subtitle lines
+pub fn bcc_to_ass(lines: &[BccLine]) -> String {
+    let mut s = String::from("[Script Info]\nScriptType: v4.00+\nPlayResX: 1920\nPlayResY: 1080\nScaledBorderAndShadow: yes\n\n");
+    s += "[V4+ Styles]\nFormat: Name, Fontname, Fontsize, PrimaryColour, SecondaryColour, OutlineColour, BackColour, Bold, Italic, Underline, StrikeOut, ScaleX, ScaleY, Spacing, Angle, BorderStyle, Outline, Shadow, Alignment, MarginL, MarginR, MarginV, Encoding\n";
+    s += "Style: Default,sans-serif,64,&H00FFFFFF,&H00FFFFFF,&H00000000,&H80000000,0,0,0,0,100,100,0,0,1,3,0,2,40,40,40,1\n\n";
+    s += "[Events]\nFormat: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\n";
+    for l in lines.iter() {
+        s += format!(
+            "Dialogue: 0,{},{},Default,,0,0,0,,{}\n",
+            format_ass_time(l.from),
+            format_ass_time(l.to),
+            l.content
+                .trim()
+                .replace("\\", "\\\\")
+                .replace("{", "\\{")
+                .replace("}", "\\}")
+                .replace("\r\n", "\\N")
+                .replace("\n", "\\N")
+        )
+        .as_str();
+    }
+    s
+}
+
+/// Convert subtitle lines to specified format
+/// * `lines` - Subtitle lines
+/// * `fmt` - Output format
+pub fn convert_bcc(lines: &[BccLine], fmt: SubtitleFormat) -> String {
+    match fmt {
+        SubtitleFormat::Srt => bcc_to_srt(lines),
+        SubtitleFormat::Vtt => bcc_to_vtt(lines),
+        SubtitleFormat::Ass => bcc_to_ass(lines),
+    }
+}
+
+/// An available subtitle in `x/player/v2` API
+#[derive(Clone, Debug)]
+pub struct SubtitleInfo {
+    /// Language tag. Such as `zh-CN`, `ai-zh`
+    pub lan: String,
+    /// Language name. Such as `中文（中国）`
+    pub lan_doc: Option<String>,
+    /// The URL of BCC JSON
+    pub url: String,
+}
+
+/// Get available subtitles from the data of `x/player/v2` API
+/// * `data` - The `data` field of API response
+pub fn get_subtitle_list(data: &JsonValue) -> Vec<SubtitleInfo> {
+    let mut r: Vec<SubtitleInfo> = Vec::new();
+    for i in data["subtitle"]["subtitles"].members() {
+        let lan = i["lan"].as_str();
+        let url = i["subtitle_url"].as_str();
+        if lan.is_none() || url.is_none() {
+            continue;
+        }
+        let url = url.unwrap();
+        if url.is_empty() {
+            continue;
+        }
+        r.push(SubtitleInfo {
+            lan: String::from(lan.unwrap()),
+            lan_doc: i["lan_doc"].as_str().map(String::from),
+            url: if url.starts_with("//") {
+                format!("https:{}", url)
+            } else {
+                String::from(url)
+            },
+        });
+    }
+    r
+}
+
+/// Parse language list. Languages are separated by comma.
+/// * `s` - Language list. Such as `zh-CN,en-US`. `all` means all languages.
+///
+/// Returns an empty list if all languages are selected.
+pub fn parse_lang_list(s: &str) -> Vec<String> {
+    let mut r: Vec<String> = Vec::new();
+    for l in s.split(",") {
+        let l = l.trim().to_lowercase();
+        if l == "all" {
+            return Vec::new();
+        }
+        if !l.is_empty() {
+            r.push(l);
+        }
+    }
+    r
+}
+
+/// Parse language list from JSON. The value can be a string or an array of strings.
+/// * `v` - JSON value
+pub fn parse_lang_list_from_json(v: &JsonValue) -> Option<Vec<String>> {
+    if v.is_string() {
+        return Some(parse_lang_list(v.as_str().unwrap()));
+    }
+    if v.is_array() {
+        let mut r: Vec<String> = Vec::new();
+        for i in v.members() {
+            if !i.is_string() {
+                return None;
+            }
+            let l = parse_lang_list(i.as_str().unwrap());
+            if l.is_empty() {
+                return Some(l);
+            }
+            r.extend(l);
+        }
+        return Some(r);
+    }
+    None
+}
+
+/// Check whether a language is selected.
+/// * `langs` - Selected languages. An empty list means all languages.
+/// * `lan` - Language tag. A tag such as `zh-CN` is matched by `zh-cn` and `zh`.
+pub fn is_lang_selected(langs: &[String], lan: &str) -> bool {
+    if langs.is_empty() {
+        return true;
+    }
+    let lan = lan.to_lowercase();
+    for l in langs.iter() {
+        if lan == *l || lan.starts_with(format!("{}-", l).as_str()) {
+            return true;
+        }
+    }
+    false
+}
+
+/// Options used when downloading subtitles
+#[derive(Clone, Debug)]
+pub struct SubtitleOptions {
+    /// Selected languages. An empty list means all languages.
+    pub langs: Vec<String>,
+    /// Output format
+    pub format: SubtitleFormat,
+}
+
+impl SubtitleOptions {
+    /// Read options from options and settings.
+    /// * `opt` - Options
+    /// * `se` - Settings
+    ///
+    /// Return None if subtitles are disabled. Return an error if any option is invalid.
+    pub fn from_opt(opt: &OptStore, se: &SettingStore) -> Result<Option<Self>> {
+        let mut enabled = false;
+        let b = opt.get_option_as_bool("subtitles");
+        if let Some(b) = b {
+            enabled = b;
+        } else {
+            let b = se.get_settings_as_bool(SUBTITLE_SETTINGS_KEY, "subtitles");
+            if let Some(b) = b {
+                enabled = b;
+            }
+        }
+        let mut langs: Vec<String> = Vec::new();
+        let o = opt.get_option("sub-lang");
+        if let Some(o) = o {
+            // Specifying languages implies downloading subtitles.
+            enabled = true;
+            langs = parse_lang_list(o.as_str());
+        } else {
+            let v = se.get_settings(SUBTITLE_SETTINGS_KEY, "sub-lang");
+            if let Some(v) = v {
+                let l = parse_lang_list_from_json(&v);
+                if let Some(l) = l {
+                    langs = l;
+                }
+            }
+        }
+        if !enabled {
+            return Ok(None);
+        }
+        let mut format = SubtitleFormat::Srt;
+        let o = opt.get_option("sub-format");
+        if let Some(o) = o {
+            let f = SubtitleFormat::parse(o.as_str());
+            if f.is_none() {
+                return Err(Error::Config(
+                    gettext("The value of option \"<key>\" is invalid.").replace("<key>", "sub-format"),
+                ));
+            }
+            format = f.unwrap();
+        } else {
+            let v = se.get_settings(SUBTITLE_SETTINGS_KEY, "sub-format");
+            if let Some(v) = v {
+                if v.is_string() {
+                    let f = SubtitleFormat::parse(v.as_str().unwrap());
+                    if let Some(f) = f {
+                        format = f;
+                    }
+                }
+            }
+        }
+        Ok(Some(Self { langs, format }))
+    }
+}
+
+/// Download BCC subtitle
+/// * `c` - HTTP client
+/// * `url` - The URL of BCC JSON
+pub async fn get_bcc_subtitle(c: &mut CookieClient, url: &str) -> Result<Vec<BccLine>> {
+    let t = c.get_text_async(url, json::object! {}).await?;
+    match parse_bcc(t.as_str()) {
+        Some(r) => Ok(r),
+        None => Err(Error::Parse {
+            source: String::from(url),
+            msg: String::from(gettext("Failed to parse subtitle.")),
+        }),
+    }
+}
+
+/// Download CC subtitles of a video and add them to video information.
+/// * `c` - HTTP client
+/// * `data` - The `data` field of `x/player/v2` API
+/// * `sub_opt` - Subtitle options
+/// * `vi` - Video information
+pub async fn add_subtitles(
+    c: &mut CookieClient,
+    data: &JsonValue,
+    sub_opt: &SubtitleOptions,
+    vi: &mut VideoInfo,
+) -> Result<()> {
+    let list = get_subtitle_list(data);
+    if list.is_empty() {
+        eprintln!("{}", gettext("No subtitles available."));
+        return Ok(());
+    }
+    for i in list.iter() {
+        if !is_lang_selected(&sub_opt.langs, i.lan.as_str()) {
+            continue;
+        }
+        let lines = get_bcc_subtitle(c, i.url.as_str()).await?;
+        let data = convert_bcc(&lines, sub_opt.format);
+        let mut sub = Subtitle::new(i.lan.as_str(), sub_opt.format.ext(), data);
+        sub.lang = Some(i.lan.clone());
+        sub.title = i.lan_doc.clone();
+        vi.subtitles.push(sub);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_bcc_convert() {
+    let s = r#"{"font_size":0.4,"body":[{"from":61.5,"to":63.25,"location":2,"content":"b<i>\n{c}"},{"from":0.1,"to":1.0,"location":2,"content":"a"}]}"#;
+    let l = parse_bcc(s).unwrap();
+    assert_eq!(2, l.len());
+    assert_eq!(100, l[0].from);
+    assert_eq!(
+        "1\n00:00:00,100 --> 00:00:01,000\na\n\n2\n00:01:01,500 --> 00:01:03,250\nb<i>\n{c}\n\n",
+        bcc_to_srt(&l)
+    );
+    assert!(bcc_to_vtt(&l).starts_with("WEBVTT\n\n00:00:00.100 --> 00:00:01.000\na\n\n"));
+    assert!(bcc_to_vtt(&l).contains("b&lt;i&gt;\n{c}"));
+    assert!(bcc_to_ass(&l).contains("Dialogue: 0,0:01:01.50,0:01:03.25,Default,,0,0,0,,b<i>\\N\\{c\\}\n"));
+}
+
+#[test]
+fn test_lang_list() {
+    let l = parse_lang_list("zh, en-US");
+    assert!(is_lang_selected(&l, "zh-CN"));
+    assert!(is_lang_selected(&l, "en-us"));
+    assert!(!is_lang_selected(&l, "ai-zh"));
+    assert!(!is_lang_selected(&l, "ja"));
+    assert_eq!(0, parse_lang_list("zh,all").len());
+    assert!(is_lang_selected(&parse_lang_list("all"), "ja"));
+    assert_eq!(
+        Some(vec![String::from("ja"), String::from("en")]),
+        parse_lang_list_from_json(&json::array!["ja", "en"])
+    );
+    assert_eq!(Some(SubtitleFormat::Vtt), SubtitleFormat::parse("WebVTT"));
+}
@@ -0,0 +1,77 @@
+extern crate json;
+
+use crate::error::Result;
+use crate::http_client::CookieClient;
+use crate::metadata::VideoInfo;
+use crate::metadata::VideoMetadata;
+use crate::providers::bilibili::base::BiliBaseProvider;
+use crate::providers::bilibili::danmaku::attach::add_danmaku;
+use crate::providers::bilibili::danmaku::attach::enable_danmaku;
+use crate::providers::bilibili::playinfo::extract_playinfo;
+use crate::providers::bilibili::playinfo::StreamPreference;
+use crate::providers::bilibili::subtitle::add_subtitles;
+use crate::providers::bilibili::subtitle::SubtitleOptions;
+use crate::providers::bilibili::view_point::parse_view_points;
+use json::JsonValue;
+
+/// A part of a normal video or an episode of a bangumi.
+pub struct PartSource {
+    /// AV number
+    pub aid: u64,
+    /// CID
+    pub cid: usize,
+    /// Duration in seconds
+    pub duration: Option<usize>,
+    /// The url of cover
+    pub cover: Option<String>,
+    /// The referer used when downloading streams
+    pub referer: String,
+}
+
+/// Generate video information of a part.
+/// * `c` - HTTP client used by this part
+/// * `base` - An initialized base provider
+/// * `pref` - Preferred streams
+/// * `part` - The part
+/// * `meta` - Video metadata
+/// * `cidinfo` - The `data` field of `x/player/v2` API
+/// * `playinfo` - Play url information
+///
+/// Chapters are skipped if `cidinfo` is an error. The error is only returned when subtitles are needed.
+pub async fn gen_part_video_info(
+    c: &mut CookieClient,
+    base: &BiliBaseProvider,
+    pref: &StreamPreference,
+    part: &PartSource,
+    mut meta: VideoMetadata,
+    cidinfo: Result<JsonValue>,
+    playinfo: &JsonValue,
+) -> Result<VideoInfo> {
+    match &cidinfo {
+        Ok(info) => {
+            meta.chapters = parse_view_points(info, part.duration);
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+        }
+    }
+    let mut vi = VideoInfo {
+        meta,
+        ..Default::default()
+    };
+    extract_playinfo(playinfo, pref, &mut vi)?;
+    vi.cover = part.cover.clone();
+    let opt = base.opt.as_ref().unwrap();
+    let se = base.se.as_ref().unwrap();
+    if enable_danmaku(opt, se) {
+        add_danmaku(c, opt, se, part.aid, part.cid, part.duration, &mut vi).await?;
+    }
+    if let Some(sub_opt) = SubtitleOptions::from_opt(opt, se)? {
+        add_subtitles(c, &cidinfo?, &sub_opt, &mut vi).await?;
+    }
+    let mut h = BiliBaseProvider::default_headers();
+    h.insert("referer", part.referer.parse().unwrap());
+    vi.headers = Some(h);
+    vi.cookies = Some(c.get_cookie_jar().clone());
+    Ok(vi)
+}
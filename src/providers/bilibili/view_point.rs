@@ -0,0 +1,50 @@
+extern crate json;
+
+use crate::metadata::Chapter;
+use json::JsonValue;
+
+/// Convert view points (chapter markers) to chapters.
+/// * `data` - The `data` field of `x/player/v2` API
+/// * `duration` - Video duration in seconds. Used to fix the end time of the last chapter.
+pub fn parse_view_points(data: &JsonValue, duration: Option<usize>) -> Vec<Chapter> {
+    let mut r: Vec<Chapter> = Vec::new();
+    for i in data["view_points"].members() {
+        // Type 2 is chapter. Other types are used by highlights.
+        if i["type"].as_u64() != Some(2) {
+            continue;
+        }
+        let from = i["from"].as_u64();
+        let to = i["to"].as_u64();
+        let content = i["content"].as_str();
+        if from.is_none() || to.is_none() || content.is_none() {
+            continue;
+        }
+        let from = from.unwrap() as usize;
+        let mut to = to.unwrap() as usize;
+        if let Some(duration) = duration.filter(|&duration| to > duration) {
+            to = duration;
+        }
+        if to <= from {
+            continue;
+        }
+        r.push(Chapter::new(from * 1000, to * 1000, content.unwrap().trim()));
+    }
+    r.sort_by_key(|c| c.start);
+    r
+}
+
+#[test]
+fn test_parse_view_points() {
+    let data = json::object! {"view_points": [
+        {"type": 2, "from": 30, "to": 100, "content": "b"},
+        {"type": 2, "from": 0, "to": 30, "content": " a "},
+        {"type": 1, "from": 5, "to": 6, "content": "highlight"},
+        {"type": 2, "from": 100, "to": 100, "content": "empty"},
+    ]};
+    let l = parse_view_points(&data, Some(90));
+    assert_eq!(2, l.len());
+    assert_eq!("a", l[0].title);
+    assert_eq!(30000, l[1].start);
+    assert_eq!(90000, l[1].end);
+    assert_eq!(0, parse_view_points(&json::object! {}, None).len());
+}
@@ -1,28 +1,66 @@
 pub mod bilibili;
 pub mod provider_base;
+pub mod registry;
 pub mod tiktok;
 
 use crate::getopt::OptStore;
+use crate::opt_list::get_live_record_options;
+use crate::opt_list::get_live_record_settings;
 use crate::opt_list::get_webdriver_options;
 use crate::opt_list::get_webdriver_settings;
 use crate::settings::SettingStore;
+use bilibili::audio::BiliAudioProvider;
+use bilibili::bangumi::BiliBangumiProvider;
+use bilibili::opt_list::get_bili_danmaku_options;
+use bilibili::opt_list::get_bili_danmaku_settings;
+use bilibili::opt_list::get_bili_subtitle_options;
+use bilibili::opt_list::get_bili_subtitle_settings;
+use bilibili::list::BiliListProvider;
+use bilibili::live::BiliLiveProvider;
 use bilibili::normal_video::BiliNormalVideoProvider;
-use provider_base::Provider;
+use bilibili::space::BiliSpaceProvider;
+use registry::ProviderRegistry;
+use tiktok::video::TiktokVideoProvider;
+
+/// Return a registry which contains all supported providers.
+pub fn get_registry() -> ProviderRegistry {
+    let mut r = ProviderRegistry::new();
+    r.register_group("WebDriver", get_webdriver_options, get_webdriver_settings);
+    r.register_group("BiliDanmaku", get_bili_danmaku_options, get_bili_danmaku_settings);
+    r.register_group("BiliSubtitle", get_bili_subtitle_options, get_bili_subtitle_settings);
+    r.register_group("LiveRecord", get_live_record_options, get_live_record_settings);
+    r.register::<BiliNormalVideoProvider>(0, vec!["WebDriver", "BiliDanmaku", "BiliSubtitle"]);
+    r.register::<BiliBangumiProvider>(0, vec!["WebDriver", "BiliDanmaku", "BiliSubtitle"]);
+    r.register::<BiliAudioProvider>(0, vec!["WebDriver"]);
+    r.register::<BiliLiveProvider>(0, vec!["WebDriver", "BiliDanmaku", "LiveRecord"]);
+    // Favorites urls also match the url of user space.
+    r.register::<BiliListProvider>(1, vec!["BiliNormalVideoProvider"]);
+    r.register::<BiliSpaceProvider>(0, vec!["BiliNormalVideoProvider"]);
+    r.register::<TiktokVideoProvider>(0, vec![]);
+    r
+}
 
 pub fn add_all_opts(opt: &mut OptStore) {
-    opt.add("WebDriver", get_webdriver_options());
-    opt.add_with_dependence(
-        "BiliNormalVideoProvider",
-        BiliNormalVideoProvider::get_custom_options(),
-        vec!["WebDriver"],
-    );
+    get_registry().add_all_opts(opt);
 }
 
 pub fn add_all_settings(store: &mut SettingStore) {
-    store.add("WebDriver", get_webdriver_settings());
-    store.add_with_dependence(
-        "BiliNormalVideoProvider",
-        BiliNormalVideoProvider::get_custom_settings(),
-        vec!["WebDriver"],
-    );
+    get_registry().add_all_settings(store);
+}
+
+#[test]
+fn test_registry() {
+    let r = get_registry();
+    let find = |url: &str| match r.find(url) {
+        Some(p) => Some(p.name),
+        None => None,
+    };
+    assert_eq!(Some("BiliListProvider"), find("https://space.bilibili.com/1/favlist?fid=2"));
+    assert_eq!(Some("BiliSpaceProvider"), find("https://space.bilibili.com/1"));
+    assert_eq!(Some("BiliNormalVideoProvider"), find("BV1xx411c7mD"));
+    assert_eq!(Some("BiliLiveProvider"), find("https://live.bilibili.com/1"));
+    assert_eq!(None, find("https://example.com/"));
+    assert_eq!("BiliListProvider", r.providers()[0].name);
+    let mut opt = OptStore::default();
+    r.add_all_opts(&mut opt);
 }
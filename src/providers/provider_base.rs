@@ -1,28 +1,55 @@
 use crate::cookies_json::CookiesJar;
+use crate::error::Error;
+use crate::error::Result;
 use crate::getopt::OptDes;
 use crate::getopt::OptStore;
+use crate::i18n::gettext;
 use crate::metadata::ExtractInfo;
 use crate::settings::SettingDes;
 use crate::settings::SettingStore;
+use crate::utils::runtime::block_on;
+use async_trait::async_trait;
 
-pub trait Provider {
-    fn new() -> Self;
+#[async_trait]
+pub trait Provider: Send {
+    fn new() -> Self
+    where
+        Self: Sized;
     fn add_custom_options(&self, _opt: &mut OptStore) {}
     fn add_custom_settings(&self, _store: &mut SettingStore) {}
     fn can_login(&self) -> bool {
         false
     }
-    fn check_logined(&mut self) -> Option<bool> {
-        Some(false)
+    /// Check whether the cookies are logined.
+    /// It runs [`check_logined_async`](#method.check_logined_async) to completion.
+    /// It can be called in or outside a tokio runtime.
+    fn check_logined(&mut self) -> Result<bool> {
+        block_on(self.check_logined_async())
     }
-    /// Extract infomation from provider
-    fn extract(&mut self, _url: &str) -> Option<ExtractInfo> {
-        None
+    /// Asynchronous version of [`check_logined`](#method.check_logined).
+    async fn check_logined_async(&mut self) -> Result<bool> {
+        Ok(false)
+    }
+    /// Extract infomation from provider.
+    /// It runs [`extract_async`](#method.extract_async) to completion.
+    /// It can be called in or outside a tokio runtime.
+    fn extract(&mut self, url: &str) -> Result<ExtractInfo> {
+        block_on(self.extract_async(url))
+    }
+    /// Asynchronous version of [`extract`](#method.extract).
+    async fn extract_async(&mut self, _url: &str) -> Result<ExtractInfo> {
+        Err(Error::Extract(String::from(gettext("The provider can not extract information."))))
     }
-    fn get_custom_options() -> Vec<OptDes> {
+    fn get_custom_options() -> Vec<OptDes>
+    where
+        Self: Sized,
+    {
         [].to_vec()
     }
-    fn get_custom_settings() -> Vec<SettingDes> {
+    fn get_custom_settings() -> Vec<SettingDes>
+    where
+        Self: Sized,
+    {
         [].to_vec()
     }
     fn get_default_cookie_jar_name(&self) -> Option<&str> {
@@ -34,11 +61,18 @@ pub trait Provider {
     fn has_custom_settings(&self) -> bool {
         false
     }
-    fn init(&mut self, _jar: Option<&CookiesJar>, _opt: OptStore, _settings: SettingStore) -> bool {
-        false
+    fn init(&mut self, _jar: Option<&CookiesJar>, _opt: OptStore, _settings: SettingStore) -> Result<()> {
+        Err(Error::Config(String::from(gettext("Can not initialize provider."))))
     }
-    fn login(&mut self, _jar: &mut CookiesJar) -> bool {
-        false
+    /// Login and save cookies to the cookie jar.
+    /// It runs [`login_async`](#method.login_async) to completion.
+    /// It can be called in or outside a tokio runtime.
+    fn login(&mut self, jar: &mut CookiesJar) -> Result<()> {
+        block_on(self.login_async(jar))
+    }
+    /// Asynchronous version of [`login`](#method.login).
+    async fn login_async(&mut self, _jar: &mut CookiesJar) -> Result<()> {
+        Err(Error::Config(String::from(gettext("The provider does not support login."))))
     }
     fn logined(&self) -> bool {
         false
@@ -46,8 +80,16 @@ pub trait Provider {
     fn login_required(&self) -> bool {
         false
     }
-    fn match_url(_url: &str) -> bool {
+    fn match_url(_url: &str) -> bool
+    where
+        Self: Sized,
+    {
         false
     }
+    /// Return the name of the provider. It is the same as [`provider_name`](#tymethod.provider_name)
+    /// but can be used without creating the provider.
+    fn name() -> &'static str
+    where
+        Self: Sized;
     fn provider_name(&self) -> &'static str;
 }
@@ -0,0 +1,133 @@
+use crate::getopt::OptDes;
+use crate::getopt::OptStore;
+use crate::providers::provider_base::Provider;
+use crate::settings::SettingDes;
+use crate::settings::SettingStore;
+
+/// Create a provider as a trait object.
+fn create_provider<T: Provider + 'static>() -> Box<dyn Provider> {
+    Box::new(T::new())
+}
+
+/// A group of options and settings which is shared by several providers. Such as `WebDriver`.
+struct OptionGroup {
+    /// Group name
+    name: &'static str,
+    /// Options
+    options: fn() -> Vec<OptDes>,
+    /// Settings
+    settings: fn() -> Vec<SettingDes>,
+}
+
+/// A registered provider
+pub struct ProviderEntry {
+    /// Provider's name
+    pub name: &'static str,
+    /// Providers with higher priority are matched first.
+    pub priority: i32,
+    /// The name of providers or groups which this provider depends on
+    pub deps: Vec<&'static str>,
+    /// Check whether the provider supports the url
+    match_url: fn(&str) -> bool,
+    /// Create the provider
+    create: fn() -> Box<dyn Provider>,
+    /// Provider's options
+    options: fn() -> Vec<OptDes>,
+    /// Provider's settings
+    settings: fn() -> Vec<SettingDes>,
+}
+
+impl ProviderEntry {
+    /// Return true if the provider supports the url.
+    /// * `url` - Url
+    pub fn match_url(&self, url: &str) -> bool {
+        (self.match_url)(url)
+    }
+
+    /// Create a new provider.
+    pub fn create(&self) -> Box<dyn Provider> {
+        (self.create)()
+    }
+}
+
+/// A list of all supported providers.
+pub struct ProviderRegistry {
+    /// Shared groups
+    groups: Vec<OptionGroup>,
+    /// Providers (in registration order)
+    list: Vec<ProviderEntry>,
+}
+
+impl Default for ProviderRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ProviderRegistry {
+    pub fn new() -> Self {
+        Self {
+            groups: Vec::new(),
+            list: Vec::new(),
+        }
+    }
+
+    /// Register a group of options and settings which is shared by several providers.
+    /// * `name` - Group name
+    /// * `options` - Function which returns options
+    /// * `settings` - Function which returns settings
+    pub fn register_group(&mut self, name: &'static str, options: fn() -> Vec<OptDes>, settings: fn() -> Vec<SettingDes>) {
+        self.groups.push(OptionGroup { name, options, settings });
+    }
+
+    /// Register a provider.
+    /// * `priority` - Providers with higher priority are matched first. Providers with the same priority are matched in registration order.
+    /// * `deps` - The name of providers or groups which this provider depends on. They must be registered before.
+    pub fn register<T: Provider + 'static>(&mut self, priority: i32, deps: Vec<&'static str>) {
+        self.list.push(ProviderEntry {
+            name: T::name(),
+            priority,
+            deps,
+            match_url: T::match_url,
+            create: create_provider::<T>,
+            options: T::get_custom_options,
+            settings: T::get_custom_settings,
+        });
+    }
+
+    /// Return all providers in matching order.
+    pub fn providers(&self) -> Vec<&ProviderEntry> {
+        let mut r: Vec<&ProviderEntry> = self.list.iter().collect();
+        // Stable sort keeps registration order for providers with the same priority.
+        r.sort_by_key(|p| std::cmp::Reverse(p.priority));
+        r
+    }
+
+    /// Find the provider which supports the url.
+    /// * `url` - Url
+    pub fn find(&self, url: &str) -> Option<&ProviderEntry> {
+        self.providers().into_iter().find(|p| p.match_url(url))
+    }
+
+    /// Add options of all groups and providers. Used in help message.
+    /// * `opt` - Options store
+    pub fn add_all_opts(&self, opt: &mut OptStore) {
+        for g in self.groups.iter() {
+            opt.add(g.name, (g.options)());
+        }
+        for p in self.list.iter() {
+            opt.add_with_dependence(p.name, (p.options)(), p.deps.clone());
+        }
+    }
+
+    /// Add settings of all groups and providers. Used in help message and config command.
+    /// * `store` - Settings store
+    pub fn add_all_settings(&self, store: &mut SettingStore) {
+        for g in self.groups.iter() {
+            store.add(g.name, (g.settings)());
+        }
+        for p in self.list.iter() {
+            store.add_with_dependence(p.name, (p.settings)(), p.deps.clone());
+        }
+    }
+}
@@ -2,6 +2,8 @@ extern crate regex;
 extern crate reqwest;
 
 use crate::cookies_json::CookiesJar;
+use crate::error::Error;
+use crate::error::Result;
 use crate::http_client::CookieClient;
 use crate::providers::provider_base::Provider;
 use regex::Regex;
@@ -50,7 +52,7 @@ impl TiktokBaseProvider {
         None
     }
 
-    pub fn init_client(&mut self, jar: Option<&CookiesJar>) -> bool {
+    pub fn init_client(&mut self, jar: Option<&CookiesJar>) -> Result<()> {
         let mut builder = Client::builder();
         let h = Self::default_headers();
         builder = builder.default_headers(h);
@@ -60,15 +62,15 @@ impl TiktokBaseProvider {
         let r = builder.build();
         match r {
             Ok(_) => {}
-            Err(_) => {
-                return false;
+            Err(e) => {
+                return Err(Error::Config(format!("{}", e)));
             }
         }
         let mut cli = CookieClient::new(r.unwrap(), jar);
         cli.enable_set_cookie();
         cli.get("https://www.tiktok.com");
         self.client = Some(cli);
-        return true;
+        Ok(())
     }
 }
 
@@ -77,7 +79,11 @@ impl Provider for TiktokBaseProvider {
         Self { client: None }
     }
 
-    fn provider_name(&self) -> &'static str {
+    fn name() -> &'static str {
         "TiktokBaseProvider"
     }
+
+    fn provider_name(&self) -> &'static str {
+        Self::name()
+    }
 }
@@ -1,9 +1,10 @@
 extern crate chrono;
-extern crate futures;
 extern crate json;
 extern crate regex;
 
 use crate::cookies_json::CookiesJar;
+use crate::error::Error;
+use crate::error::Result;
 use crate::getopt::OptStore;
 use crate::i18n::gettext;
 use crate::utils::json::jv_multikey_value;
@@ -14,9 +15,9 @@ use crate::metadata::VideoMetadata;
 use crate::providers::provider_base::Provider;
 use crate::providers::tiktok::base::TiktokBaseProvider;
 use crate::settings::SettingStore;
+use async_trait::async_trait;
 use chrono::TimeZone;
 use chrono::Utc;
-use futures::executor::block_on;
 use json::JsonValue;
 use regex::Regex;
 
@@ -37,9 +38,9 @@ pub struct TiktokVideoProvider {
 
 impl TiktokVideoProvider {
     /// Get video information
-    fn get_info(&mut self) -> bool {
+    async fn get_info(&mut self) -> Result<()> {
         if self.video_id.is_none() {
-            return false;
+            return Err(Error::Extract(String::from(gettext("Can not find video ID."))));
         }
         let url = if self.user_name.is_none() {
             format!(
@@ -54,42 +55,45 @@ impl TiktokVideoProvider {
             )
         };
         let c = self.base.client.as_mut().unwrap();
-        let r = c.get(url);
+        let r = c.get_async(url.as_str()).await;
         if r.is_none() {
-            return false;
+            return Err(Error::Network {
+                url,
+                msg: String::from(gettext("Can not get video page.")),
+            });
         }
         let r = r.unwrap();
         if r.url().as_str() == "https://www.tiktok.com/hk/notfound" {
-            println!("{}", gettext("Hong Kong was blocked by tiktok."));
-            return false;
+            return Err(Error::Extract(String::from(gettext("Hong Kong was blocked by tiktok."))));
         }
         if r.status().as_u16() >= 400 {
-            println!("{}{}", gettext("Can not get video page: "), r.status());
-            return false;
-        }
-        let t = block_on(r.text_with_charset("UTF8"));
-        if t.is_err() {
-            println!("{}{}", gettext("Can not get video page: "), t.unwrap_err());
-            return false;
+            return Err(Error::HttpStatus {
+                url,
+                status: r.status().as_u16(),
+            });
         }
-        let t = t.unwrap();
+        let t = match r.text_with_charset("UTF8").await {
+            Ok(t) => t,
+            Err(e) => return Err(Error::network(url.as_str(), e)),
+        };
         let re = self.base.extract_info(t.as_str());
         if re.is_none() {
-            println!("{}", gettext("Can not get video information from page."));
-            return false;
+            return Err(Error::Parse {
+                source: url,
+                msg: String::from(gettext("Can not get video information from page.")),
+            });
         }
         let re = re.unwrap();
-        let j = json::parse(re.as_str());
-        if j.is_err() {
-            println!(
-                "{}{}",
-                gettext("Can not parse video infomation: "),
-                j.unwrap_err()
-            );
-            return false;
+        match json::parse(re.as_str()) {
+            Ok(j) => self.video_info = Some(j),
+            Err(e) => {
+                return Err(Error::Parse {
+                    source: url,
+                    msg: format!("{}", e),
+                })
+            }
         }
-        self.video_info = Some(j.unwrap());
-        true
+        Ok(())
     }
 
     /// Generate metadata
@@ -100,7 +104,7 @@ impl TiktokVideoProvider {
         let vi = self.video_info.as_ref().unwrap();
         let props = &vi["props"]["pageProps"];
         if !props.is_object() {
-            println!(
+            eprintln!(
                 "{}",
                 gettext("Can not get metadata from video information.")
             );
@@ -172,7 +176,7 @@ impl TiktokVideoProvider {
         let vi = self.video_info.as_ref().unwrap();
         let v = &vi["props"]["pageProps"]["itemInfo"]["itemStruct"]["video"];
         if !v.is_object() {
-            println!(
+            eprintln!(
                 "{}",
                 gettext("Can not get playback url from video information.")
             );
@@ -187,7 +191,7 @@ impl TiktokVideoProvider {
         }
         let url = jv_multikey_value(v, vec!["downloadAddr", "playAddr"]);
         if url.is_none() {
-            println!(
+            eprintln!(
                 "{}",
                 gettext("Can not get playback url from video information.")
             );
@@ -195,7 +199,7 @@ impl TiktokVideoProvider {
         }
         let u = url.unwrap().as_str();
         if u.is_none() {
-            println!(
+            eprintln!(
                 "{}",
                 gettext("Can not get playback url from video information.")
             );
@@ -206,6 +210,7 @@ impl TiktokVideoProvider {
     }
 }
 
+#[async_trait]
 impl Provider for TiktokVideoProvider {
     fn new() -> Self {
         Self {
@@ -216,11 +221,11 @@ impl Provider for TiktokVideoProvider {
         }
     }
 
-    fn extract(&mut self, url: &str) -> Option<ExtractInfo> {
+    async fn extract_async(&mut self, url: &str) -> Result<ExtractInfo> {
         let r = RE.captures(url);
         let r2 = RE2.captures(url);
         if r.is_none() && r2.is_none() {
-            return None;
+            return Err(Error::Extract(String::from(gettext("Unsupported url."))));
         }
         if r.is_some() {
             let r = r.unwrap();
@@ -233,12 +238,10 @@ impl Provider for TiktokVideoProvider {
             let vid = r2.name("id").unwrap();
             self.video_id = Some(String::from(vid.as_str()));
         }
-        if !self.get_info() {
-            return None;
-        }
+        self.get_info().await?;
         let m = self.gen_metadata();
         if m.is_none() {
-            return None;
+            return Err(Error::Extract(String::from(gettext("Can not generate video metadata."))));
         }
         let m = m.unwrap();
         let mut vi = VideoInfo {
@@ -246,7 +249,7 @@ impl Provider for TiktokVideoProvider {
             ..Default::default()
         };
         if !self.extract_playinfo(&mut vi) {
-            return None;
+            return Err(Error::Extract(String::from(gettext("Can not get playback url from video information."))));
         }
         let mut h = TiktokBaseProvider::default_headers();
         h.insert("referer", "https://www.tiktok.com/".parse().unwrap());
@@ -257,14 +260,11 @@ impl Provider for TiktokVideoProvider {
             video: Some(vi),
             ..Default::default()
         };
-        Some(ei)
+        Ok(ei)
     }
 
-    fn init(&mut self, jar: Option<&CookiesJar>, _opt: OptStore, _settings: SettingStore) -> bool {
-        if !self.base.init_client(jar) {
-            return false;
-        }
-        true
+    fn init(&mut self, jar: Option<&CookiesJar>, _opt: OptStore, _settings: SettingStore) -> Result<()> {
+        self.base.init_client(jar)
     }
 
     fn match_url(url: &str) -> bool {
@@ -273,7 +273,11 @@ impl Provider for TiktokVideoProvider {
         r.is_some() || r2.is_some()
     }
 
-    fn provider_name(&self) -> &'static str {
+    fn name() -> &'static str {
         "TiktokVideoProvider"
     }
+
+    fn provider_name(&self) -> &'static str {
+        Self::name()
+    }
 }
@@ -1,5 +1,7 @@
 extern crate json;
 
+use crate::error::Error;
+use crate::error::Result;
 use crate::i18n::gettext;
 use crate::opt_list::get_settings_list;
 use crate::utils::path::get_exe_path;
@@ -13,6 +15,7 @@ use std::fs::File;
 use std::io::Read;
 use std::io::Write;
 use std::path::Path;
+use std::path::PathBuf;
 
 #[derive(Clone, Copy, PartialEq)]
 pub enum JsonValueType {
@@ -251,7 +254,7 @@ impl SettingJar {
             match v.insert(val.name().as_str(), val.value()) {
                 Ok(_) => {}
                 Err(_) => {
-                    println!("{}", gettext("Can not insert setting to JSON object."));
+                    eprintln!("{}", gettext("Can not insert setting to JSON object."));
                     return None;
                 }
             }
@@ -290,15 +293,14 @@ impl SettingStore {
             .insert(String::from(des_key), SettingDesStore::new(list));
     }
 
-    pub fn add_value(&mut self, map_key: &str, key: &str, value: &str, force: bool) -> bool {
+    pub fn add_value(&mut self, map_key: &str, key: &str, value: &str, force: bool) -> Result<()> {
         let obj = json::parse(value);
         match obj {
             Ok(_) => {}
             Err(_) => {
                 let s =
                     gettext("\"<value>\" is not a vaild JSON object.").replace("<value>", value);
-                println!("{}", s);
-                return false;
+                return Err(Error::Config(s));
             }
         }
         let obj = obj.unwrap();
@@ -317,8 +319,7 @@ impl SettingStore {
                 };
                 let s = gettext("Unknown key.\nPlease use \"<command>\" to see available key.")
                     .replace("<command>", t.as_str());
-                println!("{}", s);
-                return false;
+                return Err(Error::Config(s));
             }
             let re = re.unwrap();
             if !re {
@@ -329,8 +330,7 @@ impl SettingStore {
                 };
                 let s = gettext("Invalid value.\nPlease use \"<cmd>\" to see more information.")
                     .replace("<cmd>", t.as_str());
-                println!("{}", s);
-                return false;
+                return Err(Error::Config(s));
             }
             if !self.maps.contains_key(map_key) {
                 self.maps.insert(String::from(map_key), SettingJar::new());
@@ -338,18 +338,15 @@ impl SettingStore {
             let jar = self.maps.get_mut(map_key);
             let jar = jar.unwrap();
             if jar.settings.contains_key(key) && !force {
-                println!(
-                    "{}",
+                return Err(Error::Config(
                     gettext("Already have this key in settings, please use \"<command>\".")
-                        .replace("<command>", "bili config set <provider> <key> <value>")
-                );
-                return false;
+                        .replace("<command>", "bili config set <provider> <key> <value>"),
+                ));
             }
             jar.add(key, obj.clone());
-            return true;
+            return Ok(());
         }
-        println!("{}", gettext("Unknown provider name.\nPlease use \"<command>\" to see all available name.\nNOTE: you can always use \"basic\".").replace("<command>", "bili --help-settings --list-providers-only"));
-        return false;
+        Err(Error::Config(gettext("Unknown provider name.\nPlease use \"<command>\" to see all available name.\nNOTE: you can always use \"basic\".").replace("<command>", "bili --help-settings --list-providers-only")))
     }
 
     pub fn add_with_dependence(
@@ -483,107 +480,76 @@ impl SettingStore {
         }
     }
 
-    pub fn read(&mut self, file_name: Option<String>, fix_invalid: bool) -> bool {
+    pub fn read(&mut self, file_name: Option<String>, fix_invalid: bool) -> Result<()> {
         self.maps.clear();
-        match file_name {
-            Some(f) => {
-                let re = self.read_internal(Path::new(f.as_str()), fix_invalid);
-                if !re {
-                    println!(
-                        "{}\"{}\"",
-                        gettext("Can not load custom settings file: "),
-                        f
-                    );
-                    self.maps.clear();
-                }
-                re
-            }
+        let path = match file_name {
+            Some(f) => PathBuf::from(f),
             None => {
                 let re = get_exe_path();
-                match re {
-                    Some(pb) => {
-                        let mut tpb = pb;
-                        tpb.push("bili.settings.json");
-                        let r = self.read_internal(tpb.as_path(), fix_invalid);
-                        if !r {
-                            self.maps.clear();
-                        }
-                        r
-                    }
-                    None => false,
+                if re.is_none() {
+                    return Err(Error::Config(String::from(gettext("Can not get the path of settings file."))));
                 }
+                let mut tpb = re.unwrap();
+                tpb.push("bili.settings.json");
+                tpb
             }
+        };
+        let re = self.read_internal(path.as_path(), fix_invalid);
+        if re.is_err() {
+            self.maps.clear();
         }
+        re
     }
 
-    fn read_internal(&mut self, path: &Path, fix_invalid: bool) -> bool {
+    fn read_internal(&mut self, path: &Path, fix_invalid: bool) -> Result<()> {
+        let io_err = |msg: &str| Error::Io {
+            path: String::from(path_to_str(path)),
+            msg: String::from(msg),
+        };
         if !path.exists() {
-            return false;
-        }
-        let re = File::open(path);
-        match re {
-            Ok(_) => {}
-            Err(_) => {
-                println!(
-                    "{}\"{}\"",
-                    gettext("Can not open settings file: "),
-                    path_to_str(path)
-                );
-                return false;
-            }
+            return Err(io_err(gettext("Settings file not found.")));
         }
-        let mut f = re.unwrap();
+        let mut f = match File::open(path) {
+            Ok(f) => f,
+            Err(e) => return Err(io_err(format!("{}", e).as_str())),
+        };
         let mut s = String::from("");
         let r = f.read_to_string(&mut s);
         match r {
             Ok(le) => {
                 if le == 0 {
                     if !fix_invalid {
-                        println!(
-                            "{}\"{}\"",
-                            gettext("Settings file is empty: "),
-                            path_to_str(path)
-                        );
-                        return false;
+                        return Err(io_err(gettext("Settings file is empty.")));
                     }
-                    return true;
+                    return Ok(());
                 }
             }
-            Err(_) => {
-                println!(
-                    "{}\"{}\"",
-                    gettext("Can not read from settings file: "),
-                    path_to_str(path)
-                );
-                return false;
+            Err(e) => {
+                return Err(io_err(format!("{}", e).as_str()));
             }
         }
         let re = json::parse(s.as_str());
         match re {
             Ok(_) => {}
-            Err(_) => {
+            Err(e) => {
                 if !fix_invalid {
-                    println!(
-                        "{}\"{}\"",
-                        gettext("Can not parse settings file: "),
-                        path_to_str(path)
-                    );
-                    return false;
+                    return Err(Error::Parse {
+                        source: String::from(path_to_str(path)),
+                        msg: format!("{}", e),
+                    });
                 }
-                return true;
+                return Ok(());
             }
         }
         let obj = re.unwrap();
         if obj.is_object() == false {
             if !fix_invalid {
-                println!(
-                    "{}\"{}\"",
-                    gettext("Unknown settings file: "),
-                    path_to_str(path)
-                );
-                return false;
+                return Err(Error::Parse {
+                    source: String::from(path_to_str(path)),
+                    msg: String::from(gettext("Unknown settings file.")),
+                });
             }
-            return true;
+            return Ok(());
         }
         for (key, o) in obj.entries() {
             let mut jar = SettingJar::new();
@@ -591,10 +557,9 @@ impl SettingStore {
                 if !fix_invalid {
                     let s = gettext("Key \"<key>\" in settings file is not a object.")
                         .replace("<key>", key);
-                    println!("{}", s);
-                    return false;
+                    return Err(Error::Config(s));
                 }
-                return true;
+                return Ok(());
             }
             for (key2, o) in o.entries() {
                 let re = self.check_valid(key, key2, o.clone());
@@ -603,8 +568,7 @@ impl SettingStore {
                         if !re {
                             if !fix_invalid {
                                 let s = gettext("\"<key>\" is invalid, you can use \"bili config fix\" to remove all invalid value.").replace("<key>", format!("{}.{}", key, key2).as_str());
-                                println!("{}", s);
-                                return false;
+                                return Err(Error::Config(s));
                             }
                         } else {
                             jar.add(key2, o.clone());
@@ -617,92 +581,65 @@ impl SettingStore {
             }
             self.maps.insert(String::from(key), jar);
         }
-        return true;
+        Ok(())
     }
 
-    pub fn save(&self, file_name: Option<String>) -> bool {
+    pub fn save(&self, file_name: Option<String>) -> Result<()> {
         let s = self.to_str();
-        match s {
-            Some(_) => {}
-            None => {
-                return false;
-            }
+        if s.is_none() {
+            return Err(Error::Config(String::from(gettext("Can not convert settings to JSON."))));
         }
         let s = s.unwrap();
         match file_name {
-            Some(f) => {
-                let re = self.save_internal(s, Path::new(f.as_str()));
-                re
-            }
-            None => {
-                let re = get_exe_path();
-                match re {
-                    Some(pb) => {
-                        let mut tpb = pb;
-                        tpb.push("bili.settings.json");
-                        let r = self.save_internal(s, tpb.as_path());
-                        r
-                    }
-                    None => false,
+            Some(f) => self.save_internal(s, Path::new(f.as_str())),
+            None => match get_exe_path() {
+                Some(pb) => {
+                    let mut tpb = pb;
+                    tpb.push("bili.settings.json");
+                    self.save_internal(s, tpb.as_path())
                 }
-            }
+                None => Err(Error::Config(String::from(gettext("Can not get the path of settings file.")))),
+            },
         }
     }
 
-    fn save_internal(&self, s: String, path: &Path) -> bool {
+    fn save_internal(&self, s: String, path: &Path) -> Result<()> {
+        let p = path_to_str(path);
         if path.exists() {
             match remove_file(path) {
                 Ok(_) => {}
-                Err(_) => {
-                    println!(
-                        "{}\"{}\"",
-                        gettext("Can not remove file: "),
-                        path_to_str(path)
-                    );
-                    return false;
+                Err(e) => {
+                    return Err(Error::io(p, e));
                 }
             }
         }
-        let r = File::create(path);
-        match r {
-            Ok(_) => {}
-            Err(_) => {
-                println!(
-                    "{}\"{}\"",
-                    gettext("Can not save to settings file: "),
-                    path_to_str(path)
-                );
-                return false;
+        let mut f = match File::create(path) {
+            Ok(f) => f,
+            Err(e) => {
+                return Err(Error::io(p, e));
             }
-        }
-        let mut f = r.unwrap();
-        match f.write(s.as_bytes()) {
+        };
+        match f.write_all(s.as_bytes()) {
             Ok(_) => {}
-            Err(_) => {
-                println!(
-                    "{}\"{}\"",
-                    gettext("Can not write data to settings file: "),
-                    path_to_str(path)
-                );
-                return false;
+            Err(e) => {
+                return Err(Error::io(p, e));
             }
         }
         match f.flush() {
             Ok(_) => {}
             Err(_) => {}
         }
-        return true;
+        Ok(())
     }
 
-    pub fn set_value(&mut self, map_key: &str, key: &str, value: &str, force: bool) -> bool {
+    pub fn set_value(&mut self, map_key: &str, key: &str, value: &str, force: bool) -> Result<()> {
         let obj = json::parse(value);
         match obj {
             Ok(_) => {}
             Err(_) => {
                 let s =
                     gettext("\"<value>\" is not a vaild JSON object.").replace("<value>", value);
-                println!("{}", s);
-                return false;
+                return Err(Error::Config(s));
             }
         }
         let obj = obj.unwrap();
@@ -721,8 +658,7 @@ impl SettingStore {
                 };
                 let s = gettext("Unknown key.\nPlease use \"<command>\" to see available key.")
                     .replace("<command>", t.as_str());
-                println!("{}", s);
-                return false;
+                return Err(Error::Config(s));
             }
             let re = re.unwrap();
             if !re {
@@ -733,37 +669,31 @@ impl SettingStore {
                 };
                 let s = gettext("Invalid value.\nPlease use \"<cmd>\" to see more information.")
                     .replace("<cmd>", t.as_str());
-                println!("{}", s);
-                return false;
+                return Err(Error::Config(s));
             }
             if !self.maps.contains_key(map_key) {
                 if force {
                     self.maps.insert(String::from(map_key), SettingJar::new());
                 } else {
-                    println!(
-                        "{}",
+                    return Err(Error::Config(
                         gettext("Current settings file don't have this setting, please use <cmd>")
-                            .replace("<cmd>", "bili config add <provider> <key> <value>")
-                    );
-                    return false;
+                            .replace("<cmd>", "bili config add <provider> <key> <value>"),
+                    ));
                 }
             }
             let jar = self.maps.get_mut(map_key).unwrap();
             if !jar.settings.contains_key(key) {
                 if !force {
-                    println!(
-                        "{}",
+                    return Err(Error::Config(
                         gettext("Current settings file don't have this setting, please use <cmd>")
-                            .replace("<cmd>", "bili config add <provider> <key> <value>")
-                    );
-                    return false;
+                            .replace("<cmd>", "bili config add <provider> <key> <value>"),
+                    ));
                 }
             }
             jar.add(key, obj);
-            return true;
+            return Ok(());
         }
-        println!("{}", gettext("Unknown provider name.\nPlease use \"<command>\" to see all available name.\nNOTE: you can always use \"basic\".").replace("<command>", "bili --help-settings --list-providers-only"));
-        return false;
+        Err(Error::Config(gettext("Unknown provider name.\nPlease use \"<command>\" to see all available name.\nNOTE: you can always use \"basic\".").replace("<command>", "bili --help-settings --list-providers-only")))
     }
 
     pub fn to_json(&self) -> Option<JsonValue> {
@@ -777,7 +707,7 @@ impl SettingStore {
             match v.insert(key, obj) {
                 Ok(_) => {}
                 Err(_) => {
-                    println!("{}", gettext("Can not insert settings jar to JSON object."));
+                    eprintln!("{}", gettext("Can not insert settings jar to JSON object."));
                     return None;
                 }
             }
@@ -0,0 +1,43 @@
+use std::io::Read;
+use std::io::Write;
+use std::net::TcpListener;
+use std::thread;
+
+/// A simple HTTP server for tests. It runs in background until the test process exits.
+/// Every request is passed to `handler` and the returned data is sent as response.
+/// * `handler` - Receive the head of request (lowercased) and return the status line, headers and body.
+///
+/// Returns the base url of server. Such as `http://127.0.0.1:1234`.
+pub fn start_server<F>(handler: F) -> String
+where
+    F: Fn(&str) -> (&'static str, Vec<(&'static str, String)>, Vec<u8>) + Send + 'static,
+{
+    let l = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = format!("http://{}", l.local_addr().unwrap());
+    thread::spawn(move || {
+        for s in l.incoming() {
+            let mut s = match s {
+                Ok(s) => s,
+                Err(_) => continue,
+            };
+            let mut head = Vec::new();
+            let mut buf = [0u8; 1024];
+            while !head.ends_with(b"\r\n\r\n") {
+                match s.read(&mut buf) {
+                    Ok(0) | Err(_) => break,
+                    Ok(n) => head.extend_from_slice(&buf[..n]),
+                }
+            }
+            let head = String::from_utf8_lossy(&head).to_lowercase();
+            let (status, headers, body) = handler(head.as_str());
+            let mut r = format!("HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n", status, body.len());
+            for (k, v) in headers.iter() {
+                r += format!("{}: {}\r\n", k, v).as_str();
+            }
+            r += "\r\n";
+            let _ = s.write_all(r.as_bytes());
+            let _ = s.write_all(&body);
+        }
+    });
+    addr
+}
@@ -0,0 +1,71 @@
+extern crate json;
+extern crate regex;
+
+use json::JsonValue;
+use regex::Regex;
+
+lazy_static! {
+    static ref RE: Regex = Regex::new(r"(?i)^(?:(\d+)h)?(?:(\d+)m)?(?:(\d+)s?)?$").unwrap();
+    static ref RE2: Regex = Regex::new(r"^(?:(\d+):)?(\d+):(\d+)$").unwrap();
+}
+
+/// Convert a duration to seconds.
+/// * `s` - Input string. Such as `3600`, `90m`, `1h30m`, `1h30m15s`, `01:30:00`, `30:00`
+pub fn parse_duration(s: &str) -> Option<u64> {
+    let s = s.trim();
+    if s.is_empty() {
+        return None;
+    }
+    let mut r = RE.captures(s);
+    if r.is_none() {
+        r = RE2.captures(s);
+    }
+    let r = r?;
+    let mut total: u64 = 0;
+    for (i, m) in [3600, 60, 1].iter().enumerate() {
+        let v = r.get(i + 1);
+        if let Some(v) = v {
+            let v = v.as_str().parse::<u64>();
+            if v.is_err() {
+                return None;
+            }
+            total += v.unwrap() * m;
+        }
+    }
+    Some(total)
+}
+
+/// Convert a value to seconds.
+pub trait ToDuration {
+    /// Convert a value to seconds.
+    fn to_duration(&self) -> Option<u64>;
+}
+
+impl ToDuration for str {
+    fn to_duration(&self) -> Option<u64> {
+        parse_duration(self)
+    }
+}
+
+impl ToDuration for JsonValue {
+    fn to_duration(&self) -> Option<u64> {
+        if self.is_number() {
+            self.as_u64()
+        } else if self.is_string() {
+            parse_duration(self.as_str().unwrap())
+        } else {
+            None
+        }
+    }
+}
+
+#[test]
+fn test_parse_duration() {
+    assert_eq!(Some(3600), parse_duration("3600"));
+    assert_eq!(Some(5400), parse_duration("90m"));
+    assert_eq!(Some(5415), parse_duration("1h30m15s"));
+    assert_eq!(Some(5400), parse_duration("01:30:00"));
+    assert_eq!(Some(1800), parse_duration("30:00"));
+    assert_eq!(None, parse_duration("1x"));
+    assert_eq!(None, parse_duration(""));
+}
@@ -15,7 +15,7 @@ pub fn header_map_to_hash_map(hm: &HeaderMap, map: &mut HashMap<String, String>)
                 map.insert(String::from(k), String::from(v));
             }
             Err(e) => {
-                println!("{}", e);
+                eprintln!("{}", e);
             }
         }
     }
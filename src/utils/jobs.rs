@@ -0,0 +1,31 @@
+use crate::getopt::OptStore;
+use crate::i18n::gettext;
+use crate::settings::SettingStore;
+use crate::utils::number::ToUsize;
+
+/// Return the maximum number of videos extracted or downloaded at the same time.
+/// * `opt` - Options
+/// * `se` - Settings
+///
+/// Invalid value is ignored and 1 is used.
+pub fn get_jobs(opt: &OptStore, se: &SettingStore) -> usize {
+    let o = opt.get_option("jobs");
+    if let Some(o) = o {
+        match o.to_usize() {
+            Some(j) if j > 0 => return j,
+            _ => {
+                let s = gettext("The value of option \"<key>\" is invalid.").replace("<key>", "jobs");
+                eprintln!("{}", s);
+                return 1;
+            }
+        }
+    }
+    let s = se.get_settings("basic", "jobs");
+    if let Some(s) = s {
+        match s.to_usize() {
+            Some(j) if j > 0 => return j,
+            _ => {}
+        }
+    }
+    1
+}
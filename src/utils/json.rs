@@ -7,8 +7,9 @@ use json::JsonValue;
 /// * `keys` - Keys of object key
 /// # Examples
 /// ```
+/// # use bili::utils::json::jv_multikey_value;
 /// let j = json::object! {"a": "b", "d": null};
-/// let v = jv_multikey_value(j, vec!["d", "a"]);
+/// let v = jv_multikey_value(&j, vec!["d", "a"]);
 /// assert_eq!(v.unwrap(), "b");
 /// ```
 pub fn jv_multikey_value<'a>(obj: &'a JsonValue, keys: Vec<&'a str>) -> Option<&'a JsonValue> {
@@ -1,6 +1,9 @@
 pub mod convert;
+pub mod duration;
 pub mod headers;
+pub mod jobs;
 pub mod number;
 pub mod path;
 pub mod json;
+pub mod runtime;
 pub mod size;
@@ -0,0 +1,48 @@
+use std::future::Future;
+use tokio::runtime::Builder;
+use tokio::runtime::Handle;
+use tokio::runtime::Runtime;
+
+lazy_static! {
+    /// The runtime used by synchronous wrappers of asynchronous functions.
+    static ref RUNTIME: Runtime = Builder::new_multi_thread().enable_all().build().unwrap();
+}
+
+/// Run a future to completion in synchronous code.
+///
+/// If the caller is not in a tokio runtime, the future is run in a shared runtime.
+/// Otherwise, the future is run in the shared runtime on another thread, so the current runtime
+/// (even a `current_thread` runtime) will not panic or deadlock.
+/// * `fut` - Future
+pub fn block_on<F>(fut: F) -> F::Output
+where
+    F: Future + Send,
+    F::Output: Send,
+{
+    if Handle::try_current().is_err() {
+        return RUNTIME.block_on(fut);
+    }
+    std::thread::scope(|s| s.spawn(|| RUNTIME.block_on(fut)).join().unwrap())
+}
+
+/// Run a blocking function (such as running an external program) on a thread where blocking is acceptable,
+/// so other tasks in the runtime are not blocked.
+/// * `f` - Function
+pub async fn spawn_blocking<F, R>(f: F) -> R
+where
+    F: FnOnce() -> R + Send + 'static,
+    R: Send + 'static,
+{
+    match tokio::task::spawn_blocking(f).await {
+        Ok(r) => r,
+        Err(e) => std::panic::resume_unwind(e.into_panic()),
+    }
+}
+
+#[test]
+fn test_block_on() {
+    assert_eq!(1, block_on(async { 1 }));
+    let rt = Builder::new_current_thread().enable_all().build().unwrap();
+    assert_eq!(2, rt.block_on(async { block_on(async { 2 }) }));
+    assert_eq!(3, rt.block_on(async { spawn_blocking(|| 3).await }));
+}
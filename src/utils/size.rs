@@ -56,6 +56,23 @@ pub fn parse_size(s: &str) -> Option<usize> {
     None
 }
 
+/// Convert bytes to a human readable string. Such as `1.50MiB`
+/// * `s` - Size in bytes
+pub fn format_size(s: usize) -> String {
+    let units = ["B", "KiB", "MiB", "GiB", "TiB", "PiB", "EiB"];
+    let mut v = s as f64;
+    let mut i = 0;
+    while v >= 1024.0 && i < units.len() - 1 {
+        v /= 1024.0;
+        i += 1;
+    }
+    if i == 0 {
+        format!("{}{}", s, units[0])
+    } else {
+        format!("{:.2}{}", v, units[i])
+    }
+}
+
 pub trait ToSize {
     /// Convert multiple-byte units to bytes
     fn to_size(&self) -> Option<usize>;
@@ -98,3 +115,10 @@ fn test_parse_size() {
     assert_eq!(Some(1000), parse_size("1K"));
     assert_eq!(Some(2048), parse_size("2KiB"));
 }
+
+#[test]
+fn test_format_size() {
+    assert_eq!("123B", format_size(123));
+    assert_eq!("1.00KiB", format_size(1024));
+    assert_eq!("1.50MiB", format_size(1572864));
+}
@@ -1,4 +1,3 @@
-extern crate futures;
 extern crate subprocess;
 extern crate thirtyfour;
 
@@ -6,7 +5,6 @@ use crate::getopt::OptStore;
 use crate::i18n::gettext;
 use crate::settings::SettingStore;
 use core::time::Duration;
-use futures::executor::block_on;
 use std::clone::Clone;
 use std::net::TcpListener;
 use subprocess::Popen;
@@ -67,17 +65,17 @@ impl WebDriverStarter {
             }
             let li = self.get_executable(pb);
             for v in li.iter() {
-                println!("{}\"{}\"", gettext("Found working driver: "), v);
+                eprintln!("{}\"{}\"", gettext("Found working driver: "), v);
                 let port = self.get_port();
                 match port {
                     Some(_) => {}
                     None => {
-                        println!("{}", gettext("Can not get a working port."));
+                        eprintln!("{}", gettext("Can not get a working port."));
                         return None;
                     }
                 }
                 let port = port.unwrap();
-                println!("{}{}", gettext("Found working port: "), port);
+                eprintln!("{}{}", gettext("Found working port: "), port);
                 let cml = self.get_command_line(pb, v.clone(), port);
                 let url = format!("http://127.0.0.1:{}", port);
                 return Some(WebDriverUrlResult::new(url.as_str(), pb, Some(cml)));
@@ -96,17 +94,17 @@ impl WebDriverStarter {
         let li = self.get_executable(WebDriverType::Chrome);
         for v in li.iter() {
             if self.test_executable(v.clone()) {
-                println!("{}\"{}\"", gettext("Found working driver: "), v);
+                eprintln!("{}\"{}\"", gettext("Found working driver: "), v);
                 let port = self.get_port();
                 match port {
                     Some(_) => {}
                     None => {
-                        println!("{}", gettext("Can not get a working port."));
+                        eprintln!("{}", gettext("Can not get a working port."));
                         return None;
                     }
                 }
                 let port = port.unwrap();
-                println!("{}{}", gettext("Found working port: "), port);
+                eprintln!("{}{}", gettext("Found working port: "), port);
                 let cml = self.get_command_line(WebDriverType::Chrome, v.clone(), port);
                 let url = format!("http://127.0.0.1:{}", port);
                 return Some(WebDriverUrlResult::new(
@@ -200,11 +198,11 @@ impl WebDriverStarter {
         }
     }
 
-    pub fn quit_driver(&self, driver: GenericWebDriver<ReqwestDriverAsync>) {
-        match block_on(driver.quit()) {
+    pub async fn quit_driver(&self, driver: GenericWebDriver<ReqwestDriverAsync>) {
+        match driver.quit().await {
             Ok(_) => {}
             Err(_) => {
-                println!("{}", gettext("Can not close browser. Please close it."));
+                eprintln!("{}", gettext("Can not close browser. Please close it."));
             }
         }
     }
@@ -214,7 +212,7 @@ impl WebDriverStarter {
         match r {
             Ok(r) => Some(r),
             Err(_) => {
-                println!(
+                eprintln!(
                     "{}{:?}",
                     gettext("Can not start server with command line: "),
                     cml
@@ -0,0 +1,23 @@
+use std::process::Command;
+
+/// Run bili with `LANG=C` and return exit code, stdout and stderr.
+fn run_bili(args: &[&str]) -> (Option<i32>, String, String) {
+    let out = Command::new(env!("CARGO_BIN_EXE_bili"))
+        .args(args)
+        .env("LANG", "C")
+        .output()
+        .unwrap();
+    (
+        out.status.code(),
+        String::from_utf8(out.stdout).unwrap(),
+        String::from_utf8(out.stderr).unwrap(),
+    )
+}
+
+#[test]
+fn test_dump_json_errors_to_stderr() {
+    let (code, stdout, stderr) = run_bili(&["https://example.com/", "--dump-json"]);
+    assert_eq!(Some(2), code);
+    assert_eq!("", stdout);
+    assert_eq!("Can not find suitable provider.\n", stderr);
+}